@@ -1,3 +1,4 @@
+use crate::entities::security_event_entity::{SecurityEventEntity, SecurityEventType};
 use crate::models::response::{ResponseModel, ResponseStatus};
 use crate::services::cascade::CascadeResult;
 use crate::utils::auth::{extract_user_from_token, validate_admin_role, validate_user_owns_data};
@@ -85,7 +86,7 @@ pub async fn hard_remove_data(
   token: String,
   visibility: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _user_id =
+  let user_id =
     extract_user_from_token(&token, &state.config.config_helper.jwt_secret).map_err(|e| e)?;
   let use_json = visibility.as_deref() == Some("private") || visibility.is_none();
   let result = if use_json {
@@ -93,15 +94,31 @@ pub async fn hard_remove_data(
       .data
       .cascade_service
       .permanent_delete_cascade_json(&table, &id)
-      .await?
+      .await
   } else {
     state
       .data
       .cascade_service
       .permanent_delete_cascade_mongo(&table, &id)
-      .await?
-  };
-  Ok(success_response(result))
+      .await
+  }
+  .map(success_response);
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::AdminHardDelete)
+        .user(Some(&user_id))
+        .actor(Some(&user_id))
+        .target(&table, &id)
+        .details(serde_json::json!({ "visibility": visibility })),
+      &result,
+    )
+    .await;
+  if result.is_ok() {
+    let _ = state.data.attachment_service.collect_garbage().await;
+  }
+  result
 }
 #[tauri::command]
 pub async fn batch_soft_delete_cascade(
@@ -158,7 +175,7 @@ pub async fn batch_hard_delete_cascade(
   token: String,
   visibility: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _user_id =
+  let user_id =
     extract_user_from_token(&token, &state.config.config_helper.jwt_secret).map_err(|e| e)?;
   let mut results: Vec<CascadeResult> = Vec::new();
   let mut all_failed = true;
@@ -182,20 +199,40 @@ pub async fn batch_hard_delete_cascade(
       results.push(result);
     }
   }
-  if results.is_empty() || all_failed {
-    return Err(err_response_formatted(
+  let response = if results.is_empty() || all_failed {
+    Err(err_response_formatted(
       "All batch hard delete operations failed",
       "",
-    ));
+    ))
+  } else {
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: format!("{} records processed", results.len()),
+      data: serde_json::json!(results
+        .into_iter()
+        .map(|r| serde_json::to_value(r).unwrap_or_default())
+        .collect::<Vec<_>>()),
+    })
+  };
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::AdminHardDelete)
+        .user(Some(&user_id))
+        .actor(Some(&user_id))
+        .details(serde_json::json!({
+          "table": table,
+          "ids": ids,
+          "visibility": visibility
+        })),
+      &response,
+    )
+    .await;
+  if response.is_ok() {
+    let _ = state.data.attachment_service.collect_garbage().await;
   }
-  Ok(ResponseModel {
-    status: ResponseStatus::Success,
-    message: format!("{} records processed", results.len()),
-    data: serde_json::json!(results
-      .into_iter()
-      .map(|r| serde_json::to_value(r).unwrap_or_default())
-      .collect::<Vec<_>>()),
-  })
+  response
 }
 #[tauri::command]
 pub async fn batch_restore_cascade(
@@ -1,6 +1,8 @@
+use crate::entities::security_event_entity::{SecurityEventEntity, SecurityEventType};
 use crate::models::response::ResponseModel;
 use crate::repositories::data_provider::DataProvider;
-use crate::utils::auth::{extract_user_from_token, validate_admin_role};
+use crate::utils::access_token::is_access_token;
+use crate::utils::auth::{extract_user_for_command, validate_admin_role};
 use crate::utils::response_helper::{err_response, success_response};
 use crate::AppState;
 use nosql_orm::prelude::DatabaseProvider;
@@ -10,11 +12,17 @@ use tauri::State;
 fn extract_user_id(
   state: &AppState,
   token: &Option<String>,
+  command: &str,
 ) -> Result<Option<String>, ResponseModel> {
-  let user_id = token
-    .as_ref()
-    .and_then(|t| extract_user_from_token(t, &state.config.config_helper.jwt_secret).ok());
-  Ok(user_id)
+  let Some(token) = token.as_deref() else {
+    return Ok(None);
+  };
+  match extract_user_for_command(token, &state.config.config_helper.jwt_secret, command) {
+    Ok(user_id) => Ok(Some(user_id)),
+    // An access token lacking the scope must not fall through to the unfiltered view
+    Err(e) if is_access_token(token) => Err(e),
+    Err(_) => Ok(None),
+  }
 }
 fn get_json_provider(state: &AppState) -> DataProvider {
   DataProvider::Json(Arc::new(state.config.json_provider.clone()))
@@ -39,7 +47,7 @@ pub async fn get_all_archive_data(
   state: State<'_, AppState>,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = extract_user_id(&state, &token)?;
+  let user_id = extract_user_id(&state, &token, "get_all_archive_data")?;
   let provider = get_json_provider(&state);
   // If no token provided (admin viewing all archive), return all data without user filter
   if user_id.is_none() {
@@ -179,7 +187,7 @@ pub async fn get_all_archive_paginated(
   skip: u64,
   limit: u64,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = extract_user_id(&state, &token)?;
+  let user_id = extract_user_id(&state, &token, "get_all_archive_paginated")?;
   let provider = get_json_provider(&state);
   // If no token provided (admin viewing all archive), return all data without user filter
   if user_id.is_none() {
@@ -462,7 +470,7 @@ pub async fn soft_delete(
   todo_id: Option<String>,
   visibility: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = extract_user_id(&state, &token)?;
+  let user_id = extract_user_id(&state, &token, "soft_delete")?;
   let is_global_admin = validate_admin_role(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
@@ -570,10 +578,37 @@ pub async fn permanent_delete(
   id: String,
   visibility: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _user_id = extract_user_id(&state, &token)?;
-  state
+  let user_id = extract_user_id(&state, &token, "permanent_delete")?;
+  let is_admin = validate_admin_role(
+    token.as_deref().unwrap_or(""),
+    &state.config.config_helper.jwt_secret,
+    &state.config.json_provider,
+    state.config.mongodb_provider.as_ref(),
+  )
+  .await
+  .is_ok();
+  let event_type = if is_admin {
+    SecurityEventType::AdminHardDelete
+  } else {
+    SecurityEventType::HardDelete
+  };
+  let event = SecurityEventEntity::new(event_type)
+    .user(user_id.as_deref())
+    .actor(user_id.as_deref())
+    .target(&table, &id)
+    .details(serde_json::json!({ "visibility": visibility }));
+  let result = state
     .system
     .manage_db_service
     .permanently_delete_record(table, id, visibility)
-    .await
+    .await;
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(event, &result)
+    .await;
+  if result.is_ok() {
+    let _ = state.data.attachment_service.collect_garbage().await;
+  }
+  result
 }
@@ -0,0 +1,81 @@
+use crate::models::response::ResponseModel;
+use crate::utils::access_token::TokenScope;
+use crate::utils::auth::extract_user_with_scope;
+use crate::AppState;
+use tauri::State;
+fn attachment_user(state: &AppState, token: &str) -> Result<String, ResponseModel> {
+  extract_user_with_scope(
+    token,
+    &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
+  )
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn upload_attachment(
+  state: State<'_, AppState>,
+  token: String,
+  owner_type: String,
+  owner_id: String,
+  file_name: String,
+  mime_type: Option<String>,
+  data: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = attachment_user(&state, &token)?;
+  state
+    .data
+    .attachment_service
+    .upload(
+      &user_id,
+      &owner_type,
+      &owner_id,
+      &file_name,
+      mime_type.as_deref(),
+      &data,
+    )
+    .await
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn download_attachment(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+  thumbnail: Option<bool>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = attachment_user(&state, &token)?;
+  state
+    .data
+    .attachment_service
+    .download(&user_id, &id, thumbnail.unwrap_or(false))
+    .await
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_attachments(
+  state: State<'_, AppState>,
+  token: String,
+  owner_type: String,
+  owner_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = attachment_user(&state, &token)?;
+  state
+    .data
+    .attachment_service
+    .list(&user_id, &owner_type, &owner_id)
+    .await
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn delete_attachment(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = attachment_user(&state, &token)?;
+  state.data.attachment_service.delete(&user_id, &id).await
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_attachment_usage(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = attachment_user(&state, &token)?;
+  state.data.attachment_service.usage(&user_id).await
+}
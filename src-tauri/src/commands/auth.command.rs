@@ -1,29 +1,121 @@
 use crate::entities::{
-  login_form_entity::LoginForm, password_reset::PasswordReset, signup_form_entity::SignupForm,
+  login_form_entity::LoginForm,
+  password_reset::PasswordReset,
+  security_event_entity::{SecurityEventEntity, SecurityEventType},
+  signup_form_entity::SignupForm,
 };
 use crate::models::response::ResponseModel;
+use crate::services::auth::auth_passkey::{AuthenticationCredential, RegistrationCredential};
+use crate::utils::access_token::TokenScope;
+use crate::utils::signed_in;
 use crate::AppState;
 use tauri::State;
+async fn audit_for_username(
+  state: &AppState,
+  event_type: SecurityEventType,
+  username: &str,
+  details: serde_json::Value,
+  result: &Result<ResponseModel, ResponseModel>,
+) {
+  let audit = &state.auth.security_audit_service;
+  let user_id = audit.resolve_user_id("username", username).await;
+  audit
+    .record_outcome(
+      SecurityEventEntity::new(event_type)
+        .user(user_id.as_deref())
+        .details(details),
+      result,
+    )
+    .await;
+}
+fn user_from_result_token(
+  state: &AppState,
+  result: &Result<ResponseModel, ResponseModel>,
+) -> Option<String> {
+  let token = result.as_ref().ok()?.data.get("token")?.as_str()?;
+  crate::utils::auth::extract_user_from_token(token, &state.config.config_helper.jwt_secret).ok()
+}
+/// Remembers who signed in on this device once a sign-in flow handed out a session token.
+fn sign_in_from_result(
+  state: &AppState,
+  result: &Result<ResponseModel, ResponseModel>,
+) -> Option<String> {
+  let user_id = user_from_result_token(state, result)?;
+  remember_signed_in(state, &user_id);
+  Some(user_id)
+}
+/// The overdue sweep skips while nobody is signed in, so a newly signed-in user gets one
+/// straight away instead of waiting for the next interval.
+fn remember_signed_in(state: &AppState, user_id: &str) {
+  if signed_in::set_signed_in_user(user_id) {
+    let overdue_service = state.data.overdue_service.clone();
+    tauri::async_runtime::spawn(async move {
+      overdue_service.sweep().await;
+    });
+  }
+}
 #[tauri::command]
 pub async fn check_token(
   state: State<'_, AppState>,
   token: String,
 ) -> Result<ResponseModel, ResponseModel> {
-  state.auth.auth_service.check_token(token).await
+  let result = state.auth.auth_service.check_token(token).await;
+  if let Some(user_id) = result
+    .as_ref()
+    .ok()
+    .and_then(|r| r.data.get("id"))
+    .and_then(|v| v.as_str())
+  {
+    remember_signed_in(&state, user_id);
+  }
+  result
+}
+#[tauri::command]
+pub async fn logout() -> Result<ResponseModel, ResponseModel> {
+  signed_in::clear_signed_in_user();
+  Ok(success_response("Signed out"))
 }
 #[tauri::command]
 pub async fn login(
   state: State<'_, AppState>,
   login_form: LoginForm,
 ) -> Result<ResponseModel, ResponseModel> {
-  state.auth.auth_service.login(login_form).await
+  let username = login_form.username.clone();
+  let result = state.auth.auth_service.login(login_form).await;
+  sign_in_from_result(&state, &result);
+  let event_type = if result.is_ok() {
+    SecurityEventType::Login
+  } else {
+    SecurityEventType::LoginFailed
+  };
+  audit_for_username(
+    &state,
+    event_type,
+    &username,
+    serde_json::json!({ "method": "password", "username": username }),
+    &result,
+  )
+  .await;
+  result
 }
 #[tauri::command]
 pub async fn register(
   state: State<'_, AppState>,
   signup_form: SignupForm,
 ) -> Result<ResponseModel, ResponseModel> {
-  state.auth.auth_service.register(signup_form).await
+  let username = signup_form.username.clone();
+  let email = signup_form.email.clone();
+  let result = state.auth.auth_service.register(signup_form).await;
+  sign_in_from_result(&state, &result);
+  audit_for_username(
+    &state,
+    SecurityEventType::Register,
+    &username,
+    serde_json::json!({ "username": username, "email": email }),
+    &result,
+  )
+  .await;
+  result
 }
 #[tauri::command]
 pub async fn request_password_reset(
@@ -49,7 +141,19 @@ pub async fn reset_password(
   state: State<'_, AppState>,
   reset_data: PasswordReset,
 ) -> Result<ResponseModel, ResponseModel> {
-  state.auth.auth_service.reset_password(reset_data).await
+  let email = reset_data.email.clone();
+  let result = state.auth.auth_service.reset_password(reset_data).await;
+  let audit = &state.auth.security_audit_service;
+  let user_id = audit.resolve_user_id("email", &email).await;
+  audit
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::PasswordReset)
+        .user(user_id.as_deref())
+        .details(serde_json::json!({ "email": email })),
+      &result,
+    )
+    .await;
+  result
 }
 #[tauri::command]
 pub async fn change_password(
@@ -57,11 +161,23 @@ pub async fn change_password(
   token: String,
   new_password: String,
 ) -> Result<ResponseModel, ResponseModel> {
-  state
+  let result = state
     .auth
     .auth_service
     .change_password(&token, &state.config.config_helper.jwt_secret, new_password)
-    .await
+    .await;
+  let user_id =
+    crate::utils::auth::extract_user_from_token(&token, &state.config.config_helper.jwt_secret)
+      .ok();
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::PasswordChanged).user(user_id.as_deref()),
+      &result,
+    )
+    .await;
+  result
 }
 #[tauri::command]
 pub async fn setup_totp(
@@ -76,7 +192,16 @@ pub async fn enable_totp(
   username: String,
   code: String,
 ) -> Result<ResponseModel, ResponseModel> {
-  state.auth.totp_service.enable_totp(&username, &code).await
+  let result = state.auth.totp_service.enable_totp(&username, &code).await;
+  audit_for_username(
+    &state,
+    SecurityEventType::TotpEnabled,
+    &username,
+    serde_json::Value::Null,
+    &result,
+  )
+  .await;
+  result
 }
 #[tauri::command]
 pub async fn verify_login_totp(
@@ -84,11 +209,26 @@ pub async fn verify_login_totp(
   username: String,
   code: String,
 ) -> Result<ResponseModel, ResponseModel> {
-  state
+  let result = state
     .auth
     .totp_service
     .verify_login_totp(&username, &code)
-    .await
+    .await;
+  sign_in_from_result(&state, &result);
+  let event_type = if result.is_ok() {
+    SecurityEventType::Login
+  } else {
+    SecurityEventType::LoginFailed
+  };
+  audit_for_username(
+    &state,
+    event_type,
+    &username,
+    serde_json::json!({ "method": "totp", "username": username }),
+    &result,
+  )
+  .await;
+  result
 }
 #[tauri::command]
 pub async fn disable_totp(
@@ -96,7 +236,16 @@ pub async fn disable_totp(
   username: String,
   code: String,
 ) -> Result<ResponseModel, ResponseModel> {
-  state.auth.totp_service.disable_totp(&username, &code).await
+  let result = state.auth.totp_service.disable_totp(&username, &code).await;
+  audit_for_username(
+    &state,
+    SecurityEventType::TotpDisabled,
+    &username,
+    serde_json::Value::Null,
+    &result,
+  )
+  .await;
+  result
 }
 #[tauri::command]
 pub async fn use_recovery_code(
@@ -104,11 +253,42 @@ pub async fn use_recovery_code(
   username: String,
   code: String,
 ) -> Result<ResponseModel, ResponseModel> {
-  state
+  let result = state
     .auth
     .totp_service
     .use_recovery_code(&username, &code)
-    .await
+    .await;
+  audit_for_username(
+    &state,
+    SecurityEventType::RecoveryCodeUsed,
+    &username,
+    serde_json::Value::Null,
+    &result,
+  )
+  .await;
+  result
+}
+#[tauri::command]
+pub async fn regenerate_recovery_codes(
+  state: State<'_, AppState>,
+  username: String,
+  password: String,
+  code: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let result = state
+    .auth
+    .totp_service
+    .regenerate_recovery_codes(&username, &password, &code)
+    .await;
+  audit_for_username(
+    &state,
+    SecurityEventType::RecoveryCodesRegenerated,
+    &username,
+    serde_json::Value::Null,
+    &result,
+  )
+  .await;
+  result
 }
 #[tauri::command]
 pub async fn get_user_security_status(
@@ -122,15 +302,319 @@ pub async fn get_user_security_status(
     &username,
   )
   .await?;
+  let remaining =
+    crate::services::auth::auth_totp::AuthTotpService::usable_recovery_codes(&user.recovery_codes);
   Ok(ResponseModel {
     status: ResponseStatus::Success,
     message: "Security status retrieved".to_string(),
     data: serde_json::json!({
       "totp_enabled": user.totp_enabled,
       "qr_login_enabled": user.qr_login_enabled,
+      "recovery_codes_remaining": remaining,
+      "recovery_codes_need_regeneration": user.totp_enabled
+        && remaining < user.recovery_codes.len(),
     }),
   })
 }
+#[tauri::command(rename_all = "snake_case")]
+pub async fn passkey_registration_start(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  state
+    .auth
+    .passkey_service
+    .start_registration(&user_id)
+    .await
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn passkey_registration_finish(
+  state: State<'_, AppState>,
+  token: String,
+  challenge_id: String,
+  name: String,
+  credential: RegistrationCredential,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  let credential_id = credential.id.clone();
+  let result = state
+    .auth
+    .passkey_service
+    .finish_registration(&user_id, &challenge_id, &name, credential)
+    .await;
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::PasskeyAdded)
+        .user(Some(&user_id))
+        .details(serde_json::json!({ "name": name, "credential_id": credential_id })),
+      &result,
+    )
+    .await;
+  result
+}
+#[tauri::command]
+pub async fn passkey_login_start(
+  state: State<'_, AppState>,
+  username: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  state
+    .auth
+    .passkey_service
+    .start_authentication(username.as_deref())
+    .await
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn passkey_login_finish(
+  state: State<'_, AppState>,
+  challenge_id: String,
+  credential: AuthenticationCredential,
+  remember: Option<bool>,
+) -> Result<ResponseModel, ResponseModel> {
+  let credential_id = credential.id.clone();
+  let result = state
+    .auth
+    .passkey_service
+    .finish_authentication(&challenge_id, credential, remember.unwrap_or(false))
+    .await;
+  let (event_type, user_id) = match sign_in_from_result(&state, &result) {
+    Some(user_id) => (SecurityEventType::Login, Some(user_id)),
+    None => (SecurityEventType::LoginFailed, None),
+  };
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(event_type)
+        .user(user_id.as_deref())
+        .details(serde_json::json!({ "method": "passkey", "credential_id": credential_id })),
+      &result,
+    )
+    .await;
+  result
+}
+#[tauri::command]
+pub async fn passkey_list(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  state.auth.passkey_service.list_passkeys(&user_id).await
+}
+#[tauri::command]
+pub async fn passkey_rename(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+  name: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  state
+    .auth
+    .passkey_service
+    .rename_passkey(&user_id, &id, &name)
+    .await
+}
+#[tauri::command]
+pub async fn passkey_remove(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  let result = state
+    .auth
+    .passkey_service
+    .remove_passkey(&user_id, &id)
+    .await;
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::PasskeyRemoved)
+        .user(Some(&user_id))
+        .target("passkeys", &id),
+      &result,
+    )
+    .await;
+  result
+}
+fn extract_session_user(state: &AppState, token: &str) -> Result<String, ResponseModel> {
+  crate::utils::auth::extract_session_user(token, &state.config.config_helper.jwt_secret)
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn access_token_create(
+  state: State<'_, AppState>,
+  token: String,
+  name: String,
+  scopes: Vec<TokenScope>,
+  expires_in_days: Option<i64>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  let profile_id =
+    crate::utils::auth::extract_profile_from_token(&token, &state.config.config_helper.jwt_secret)
+      .ok();
+  let result = state
+    .auth
+    .access_token_service
+    .create_token(
+      &user_id,
+      profile_id.as_deref(),
+      &name,
+      scopes.clone(),
+      expires_in_days,
+    )
+    .await;
+  let token_id = result
+    .as_ref()
+    .ok()
+    .and_then(|r| r.data.get("id"))
+    .and_then(|v| v.as_str())
+    .map(|s| s.to_string());
+  let mut event = SecurityEventEntity::new(SecurityEventType::AccessTokenCreated)
+    .user(Some(&user_id))
+    .details(serde_json::json!({ "name": name, "scopes": scopes }));
+  if let Some(token_id) = token_id {
+    event = event.target("personal_access_tokens", &token_id);
+  }
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(event, &result)
+    .await;
+  result
+}
+#[tauri::command]
+pub async fn access_token_list(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  state.auth.access_token_service.list_tokens(&user_id).await
+}
+#[tauri::command]
+pub async fn access_token_revoke(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  let result = state
+    .auth
+    .access_token_service
+    .revoke_token(&user_id, &id)
+    .await;
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::AccessTokenRevoked)
+        .user(Some(&user_id))
+        .target("personal_access_tokens", &id),
+      &result,
+    )
+    .await;
+  result
+}
+#[tauri::command]
+pub async fn oauth_providers(state: State<'_, AppState>) -> Result<ResponseModel, ResponseModel> {
+  Ok(state.auth.oauth_service.list_providers())
+}
+#[tauri::command]
+pub async fn oauth_login_start(
+  state: State<'_, AppState>,
+  provider: String,
+) -> Result<ResponseModel, ResponseModel> {
+  state.auth.oauth_service.start(&provider, None).await
+}
+#[tauri::command]
+pub async fn oauth_link_start(
+  state: State<'_, AppState>,
+  token: String,
+  provider: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  state
+    .auth
+    .oauth_service
+    .start(&provider, Some(&user_id))
+    .await
+}
+#[tauri::command(rename_all = "snake_case")]
+pub async fn oauth_finish(
+  state: State<'_, AppState>,
+  oauth_state: String,
+  code: String,
+  remember: Option<bool>,
+) -> Result<ResponseModel, ResponseModel> {
+  let result = state
+    .auth
+    .oauth_service
+    .finish(&oauth_state, &code, remember.unwrap_or(false))
+    .await;
+  let linked_user = result
+    .as_ref()
+    .ok()
+    .and_then(|r| r.data.get("user_id"))
+    .and_then(|v| v.as_str())
+    .map(|s| s.to_string());
+  let (event_type, user_id) = match (sign_in_from_result(&state, &result), linked_user) {
+    (Some(user_id), _) => (SecurityEventType::Login, Some(user_id)),
+    (None, Some(user_id)) => (SecurityEventType::OauthLinked, Some(user_id)),
+    (None, None) => (SecurityEventType::LoginFailed, None),
+  };
+  let provider = result
+    .as_ref()
+    .ok()
+    .and_then(|r| r.data.get("provider"))
+    .cloned()
+    .unwrap_or(serde_json::Value::Null);
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(event_type)
+        .user(user_id.as_deref())
+        .details(serde_json::json!({ "method": "oauth", "provider": provider })),
+      &result,
+    )
+    .await;
+  result
+}
+#[tauri::command]
+pub async fn oauth_identities(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  state.auth.oauth_service.list_identities(&user_id).await
+}
+#[tauri::command]
+pub async fn oauth_unlink(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_session_user(&state, &token)?;
+  let result = state
+    .auth
+    .oauth_service
+    .unlink_identity(&user_id, &id)
+    .await;
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::OauthUnlinked)
+        .user(Some(&user_id))
+        .target("oauth_identities", &id),
+      &result,
+    )
+    .await;
+  result
+}
 #[tauri::command]
 pub async fn qr_generate(
   state: State<'_, AppState>,
@@ -160,11 +644,20 @@ pub async fn qr_approve(
   token: String,
   username: String,
 ) -> Result<ResponseModel, ResponseModel> {
-  state
+  let result = state
     .auth
     .qr_auth_service
     .approve_qr_token(&token, &username)
-    .await
+    .await;
+  audit_for_username(
+    &state,
+    SecurityEventType::QrApproved,
+    &username,
+    serde_json::Value::Null,
+    &result,
+  )
+  .await;
+  result
 }
 #[tauri::command]
 pub async fn qr_status(
@@ -190,7 +683,9 @@ pub async fn qr_login_complete(
   state: State<'_, AppState>,
   token: String,
 ) -> Result<ResponseModel, ResponseModel> {
-  state.auth.qr_auth_service.complete_qr_login(&token).await
+  let result = state.auth.qr_auth_service.complete_qr_login(&token).await;
+  sign_in_from_result(&state, &result);
+  result
 }
 #[tauri::command]
 pub async fn initialize_user_data(
@@ -1,6 +1,371 @@
 use crate::crud_route;
+use crate::entities::chat_entity::ChatTaskRef;
+use crate::models::response::ResponseModel;
+use crate::services::chat_presence_service::PresenceStatus;
+use crate::services::chat_service::ChatSearchQuery;
+use crate::utils::access_token::TokenScope;
+use crate::utils::auth::extract_user_with_scope;
+use crate::utils::chat_search;
+use crate::utils::response_helper::{err_response, success_response};
+use crate::AppState;
+use serde_json::json;
+use tauri::State;
 crud_route!(get_chat, "chats", "get");
 crud_route!(get_chats, "chats", "getAll");
 crud_route!(create_chat, "chats", "create");
 crud_route!(update_chat, "chats", "update");
 crud_route!(delete_chat, "chats", "delete");
+#[tauri::command]
+pub async fn chat_realtime_endpoint(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  chat_user(&state, &token)?;
+  let url = state
+    .chat
+    .realtime_url
+    .clone()
+    .map_err(|e| err_response(&e))?;
+  Ok(success_response(json!({ "url": url })))
+}
+fn chat_user(state: &AppState, token: &str) -> Result<String, ResponseModel> {
+  extract_user_with_scope(
+    token,
+    &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
+  )
+}
+#[tauri::command]
+pub async fn presence_heartbeat(
+  state: State<'_, AppState>,
+  token: String,
+  status: Option<PresenceStatus>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  state.chat.presence_service.heartbeat(&user_id, status);
+  Ok(success_response(
+    json!({ "user_id": user_id, "status": status }),
+  ))
+}
+#[tauri::command]
+pub async fn chat_typing(
+  state: State<'_, AppState>,
+  token: String,
+  room_id: String,
+  typing: bool,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  state
+    .chat
+    .presence_service
+    .update_typing(&user_id, &room_id, typing)
+    .await
+}
+#[tauri::command]
+pub async fn get_room_presence(
+  state: State<'_, AppState>,
+  token: String,
+  room_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  state
+    .chat
+    .presence_service
+    .get_room_presence(&user_id, &room_id)
+    .await
+}
+#[tauri::command]
+pub async fn get_group_presence(
+  state: State<'_, AppState>,
+  token: String,
+  group_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  state
+    .chat
+    .presence_service
+    .get_group_presence(&user_id, &group_id)
+    .await
+}
+#[tauri::command]
+pub async fn sync_chat_outbox(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  chat_user(&state, &token)?;
+  state.chat.chat_service.flush_outbox().await
+}
+#[tauri::command]
+pub async fn get_chat_outbox(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  chat_user(&state, &token)?;
+  state.chat.chat_service.get_outbox().await
+}
+#[tauri::command]
+pub async fn discard_chat_outbox_entry(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  chat_user(&state, &token)?;
+  state.chat.chat_service.discard_outbox_entry(&id).await
+}
+/// Chat room ids the user takes part in, directly or through a group.
+async fn chat_room_ids(state: &AppState, user_id: &str) -> Vec<String> {
+  let rooms = state
+    .chat
+    .room_service
+    .get_all("all", None, None, None, None)
+    .await
+    .map(|r| r.data)
+    .unwrap_or_default();
+  let groups = state
+    .chat
+    .group_service
+    .get_all("all", None, None, None)
+    .await
+    .map(|r| r.data)
+    .unwrap_or_default();
+  let as_list = |v: serde_json::Value| match v {
+    serde_json::Value::Array(items) => items,
+    _ => Vec::new(),
+  };
+  chat_search::room_ids_for_user(&as_list(rooms), &as_list(groups), user_id)
+}
+#[tauri::command]
+pub async fn search_chat_messages(
+  state: State<'_, AppState>,
+  token: String,
+  query: ChatSearchQuery,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  let room_ids = chat_room_ids(&state, &user_id).await;
+  state.chat.chat_service.search(&room_ids, &query).await
+}
+#[tauri::command]
+pub async fn get_chat_message_context(
+  state: State<'_, AppState>,
+  token: String,
+  message_id: String,
+  radius: Option<usize>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  let response = state
+    .chat
+    .chat_service
+    .get_message_context(&message_id, radius.unwrap_or(10))
+    .await?;
+  let room_id = response.data["room_id"].as_str().unwrap_or("");
+  if !chat_room_ids(&state, &user_id)
+    .await
+    .iter()
+    .any(|id| id == room_id)
+  {
+    return Err(ResponseModel::forbidden(
+      "You do not have access to this room",
+    ));
+  }
+  Ok(response)
+}
+#[tauri::command]
+pub async fn mark_room_read(
+  state: State<'_, AppState>,
+  token: String,
+  room_id: String,
+  message_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  require_room_member(&state, &room_id, &user_id).await?;
+  state
+    .chat
+    .chat_read_service
+    .mark_read_up_to(&room_id, &user_id, &message_id)
+    .await
+}
+#[tauri::command]
+pub async fn get_message_read_receipts(
+  state: State<'_, AppState>,
+  token: String,
+  message_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  let message = state.chat.chat_service.find_message(&message_id).await?;
+  let room_id = message
+    .get("room_id")
+    .and_then(|v| v.as_str())
+    .unwrap_or("");
+  let member_ids = state.chat.presence_service.room_member_ids(room_id).await;
+  if !member_ids.iter().any(|id| id == &user_id) {
+    return Err(ResponseModel::forbidden(
+      "You do not have access to this room",
+    ));
+  }
+  state
+    .chat
+    .chat_read_service
+    .read_receipts(&message_id, &member_ids)
+    .await
+}
+/// Unread counts for every room and group chat the user belongs to.
+#[tauri::command]
+pub async fn get_chat_unread_counts(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  let room_ids = chat_room_ids(&state, &user_id).await;
+  let counts = state
+    .chat
+    .chat_read_service
+    .unread_counts(&room_ids, &user_id)
+    .await;
+  Ok(success_response(json!(counts)))
+}
+/// Fails unless the user participates in the room.
+pub(crate) async fn require_room_member(
+  state: &AppState,
+  room_id: &str,
+  user_id: &str,
+) -> Result<(), ResponseModel> {
+  let member_ids = state.chat.presence_service.room_member_ids(room_id).await;
+  if !member_ids.iter().any(|id| id == user_id) {
+    return Err(ResponseModel::forbidden(
+      "You do not have access to this room",
+    ));
+  }
+  Ok(())
+}
+#[tauri::command]
+pub async fn get_message_revisions(
+  state: State<'_, AppState>,
+  token: String,
+  message_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  let response = state.chat.chat_service.get_revisions(&message_id).await?;
+  let room_id = response.data["room_id"].as_str().unwrap_or("");
+  require_room_member(&state, room_id, &user_id).await?;
+  Ok(response)
+}
+#[tauri::command]
+pub async fn get_chat_retention(
+  state: State<'_, AppState>,
+  token: String,
+  room_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  require_room_member(&state, &room_id, &user_id).await?;
+  state.chat.chat_retention_service.get_policy(&room_id).await
+}
+#[tauri::command]
+pub async fn set_chat_retention(
+  state: State<'_, AppState>,
+  token: String,
+  room_id: String,
+  max_age_days: u32,
+  hard_delete: Option<bool>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  state
+    .chat
+    .chat_retention_service
+    .set_policy(
+      &room_id,
+      &user_id,
+      max_age_days,
+      hard_delete.unwrap_or(false),
+    )
+    .await
+}
+#[tauri::command]
+pub async fn remove_chat_retention(
+  state: State<'_, AppState>,
+  token: String,
+  room_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  state
+    .chat
+    .chat_retention_service
+    .remove_policy(&room_id, &user_id)
+    .await
+}
+#[tauri::command]
+pub async fn get_chat_retention_log(
+  state: State<'_, AppState>,
+  token: String,
+  room_id: String,
+  skip: Option<u64>,
+  limit: Option<u64>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  require_room_member(&state, &room_id, &user_id).await?;
+  state
+    .chat
+    .chat_retention_service
+    .get_purge_log(&room_id, skip, limit)
+    .await
+}
+/// Task previews for each message, checked against what the caller may see.
+#[tauri::command]
+pub async fn get_chat_task_previews(
+  state: State<'_, AppState>,
+  token: String,
+  message_ids: Vec<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  let room_ids = chat_room_ids(&state, &user_id).await;
+  let mut previews = serde_json::Map::new();
+  for message_id in message_ids {
+    let Ok(message) = state.chat.chat_service.find_message(&message_id).await else {
+      continue;
+    };
+    let room_id = message
+      .get("room_id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("");
+    if !room_ids.iter().any(|id| id == room_id) {
+      continue;
+    }
+    let refs: Vec<ChatTaskRef> = message
+      .get("task_refs")
+      .cloned()
+      .and_then(|v| serde_json::from_value(v).ok())
+      .unwrap_or_default();
+    let resolved = state.chat.task_link_service.previews(&refs, &user_id).await;
+    previews.insert(message_id, json!(resolved));
+  }
+  Ok(success_response(serde_json::Value::Object(previews)))
+}
+#[tauri::command]
+pub async fn get_task_chat_backlinks(
+  state: State<'_, AppState>,
+  token: String,
+  task_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  let room_ids = chat_room_ids(&state, &user_id).await;
+  state
+    .chat
+    .task_link_service
+    .backlinks(&task_id, &user_id, &room_ids)
+    .await
+}
+/// Comments and chat messages that mention the caller, newest first.
+#[tauri::command]
+pub async fn get_my_mentions(
+  state: State<'_, AppState>,
+  token: String,
+  skip: Option<usize>,
+  limit: Option<usize>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, &token)?;
+  let room_ids = chat_room_ids(&state, &user_id).await;
+  state
+    .chat
+    .mention_service
+    .mentions_of(&user_id, &room_ids, skip, limit)
+    .await
+}
@@ -0,0 +1,48 @@
+use crate::entities::digest_entity::DigestSettingsUpdateModel;
+use crate::models::response::ResponseModel;
+use crate::utils::access_token::TokenScope;
+use crate::utils::auth::extract_user_with_scope;
+use crate::AppState;
+use tauri::State;
+#[tauri::command]
+pub async fn get_digest_settings(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_user_with_scope(
+    &token,
+    &state.config.config_helper.jwt_secret,
+    TokenScope::ReadTodos,
+  )?;
+  state.system.digest_service.get_settings(&user_id).await
+}
+/// `frequency` is `off`, `daily` or `weekly`; `hour` and `weekday` are in `timezone`.
+#[tauri::command]
+pub async fn set_digest_settings(
+  state: State<'_, AppState>,
+  token: String,
+  settings: DigestSettingsUpdateModel,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_user_with_scope(
+    &token,
+    &state.config.config_helper.jwt_secret,
+    TokenScope::WriteTasks,
+  )?;
+  state
+    .system
+    .digest_service
+    .set_settings(&user_id, settings)
+    .await
+}
+#[tauri::command]
+pub async fn preview_digest(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_user_with_scope(
+    &token,
+    &state.config.config_helper.jwt_secret,
+    TokenScope::ReadTodos,
+  )?;
+  state.system.digest_service.preview(&user_id).await
+}
@@ -1,11 +1,16 @@
+use crate::commands::chat_command::require_room_member;
 use crate::crud_route;
 crud_route!(get_group, "groups", "get");
 crud_route!(get_groups, "groups", "getAll");
 crud_route!(create_group, "groups", "create");
 crud_route!(update_group, "groups", "update");
 crud_route!(delete_group, "groups", "delete");
+use crate::entities::group_entity::GroupRole;
+use crate::entities::group_join_request_entity::JoinRequestStatus;
+use crate::entities::notification_entity::{NotificationKind, NotificationTarget};
 use crate::models::response::ResponseModel;
-use crate::utils::auth::extract_user_from_token;
+use crate::utils::access_token::TokenScope;
+use crate::utils::auth::extract_user_with_scope;
 use crate::utils::response_helper::success_response;
 use crate::AppState;
 use tauri::State;
@@ -15,9 +20,10 @@ pub async fn get_group_by_room(
   room_id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state.chat.group_service.get_by_room_id(&room_id).await
@@ -29,12 +35,17 @@ pub async fn add_group_members(
   member_ids: Vec<String>,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let actor_id = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
-  state.chat.group_service.add_members(&id, member_ids).await
+  state
+    .chat
+    .group_service
+    .add_members(&id, &actor_id, member_ids)
+    .await
 }
 #[tauri::command]
 pub async fn remove_group_members(
@@ -43,15 +54,16 @@ pub async fn remove_group_members(
   member_ids: Vec<String>,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let actor_id = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state
     .chat
     .group_service
-    .remove_members(&id, member_ids)
+    .remove_members(&id, &actor_id, member_ids)
     .await
 }
 #[tauri::command]
@@ -60,9 +72,10 @@ pub async fn delete_group_cascade(
   id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state.chat.group_service.hard_delete_cascade(&id).await
@@ -75,9 +88,10 @@ pub async fn get_messages_by_room(
   limit: Option<u64>,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state
@@ -95,13 +109,29 @@ pub async fn send_message(
   dm_name: String,
   content: String,
   reply_id: Option<String>,
+  parent_id: Option<String>,
+  client_id: Option<String>,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let user_id = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
+  if sender_id != user_id {
+    return Err(ResponseModel::forbidden(
+      "You can only send messages as yourself",
+    ));
+  }
+  if state
+    .chat
+    .group_service
+    .is_muted_in_room(&room_id, &user_id)
+    .await
+  {
+    return Err(ResponseModel::forbidden("You are muted in this group"));
+  }
   let _ = state
     .chat
     .room_service
@@ -121,16 +151,112 @@ pub async fn send_message(
   if let Some(ref rid) = reply_id {
     data["reply_id"] = serde_json::json!(rid);
   }
-  state.chat.chat_service.create(data).await
+  if let Some(ref pid) = parent_id {
+    data["parent_id"] = serde_json::json!(pid);
+  }
+  if let Some(ref cid) = client_id {
+    data["id"] = serde_json::json!(cid);
+  }
+  let result = state.chat.chat_service.create(data).await?;
+  if let Some(message_id) = result.data.get("id").and_then(|v| v.as_str()) {
+    // Your own message is never unread, and nothing before it can be either
+    let _ = state
+      .chat
+      .chat_read_service
+      .mark_read_up_to(&room_id, &user_id, message_id)
+      .await;
+  }
+  notify_thread_author(&state, &result.data).await;
+  state
+    .system
+    .notification_service
+    .notify_mentions("chats", &result.data, None, Some(&user_id))
+    .await;
+  Ok(result)
+}
+/// Tells the author of a thread root that someone replied, unless they replied themselves
+/// or were mentioned in the reply.
+async fn notify_thread_author(state: &AppState, reply: &serde_json::Value) {
+  let Some(parent_id) = reply.get("parent_id").and_then(|v| v.as_str()) else {
+    return;
+  };
+  let Ok(thread) = state
+    .chat
+    .chat_service
+    .get_thread(parent_id, Some(0), Some(0))
+    .await
+  else {
+    return;
+  };
+  let author_id = thread.data["parent"]["sender_id"].as_str().unwrap_or("");
+  let sender_id = reply
+    .get("sender_id")
+    .and_then(|v| v.as_str())
+    .unwrap_or("");
+  let mentioned = reply
+    .get("mention_ids")
+    .and_then(|v| v.as_array())
+    .map(|ids| ids.iter().any(|id| id.as_str() == Some(author_id)))
+    .unwrap_or(false);
+  if author_id.is_empty() || author_id == sender_id || mentioned {
+    return;
+  }
+  let excerpt: String = reply
+    .get("content")
+    .and_then(|v| v.as_str())
+    .unwrap_or("")
+    .chars()
+    .take(120)
+    .collect();
+  let message_id = reply.get("id").and_then(|v| v.as_str()).unwrap_or("");
+  state
+    .system
+    .notification_service
+    .notify(
+      author_id,
+      NotificationKind::Replied,
+      Some(sender_id),
+      NotificationTarget::new("chats", message_id),
+      serde_json::json!({
+        "parent_id": parent_id,
+        "room_id": reply.get("room_id"),
+        "excerpt": excerpt,
+      }),
+    )
+    .await;
+}
+#[tauri::command]
+pub async fn get_message_thread(
+  state: State<'_, AppState>,
+  parent_id: String,
+  skip: Option<u64>,
+  limit: Option<u64>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_user_with_scope(
+    token.as_deref().unwrap_or(""),
+    &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
+  )
+  .map_err(|e| e)?;
+  let parent = state.chat.chat_service.find_message(&parent_id).await?;
+  let room_id = parent["room_id"].as_str().unwrap_or("");
+  require_room_member(&state, room_id, &user_id).await?;
+  state
+    .chat
+    .chat_service
+    .get_thread(&parent_id, skip, limit)
+    .await
 }
 #[tauri::command]
 pub async fn ensure_rooms_for_groups(
   state: State<'_, AppState>,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   let filter = serde_json::json!({});
@@ -191,9 +317,10 @@ pub async fn mark_message_read(
   user_id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state.chat.chat_service.mark_read(&id, &user_id).await
@@ -204,9 +331,10 @@ pub async fn delete_message(
   id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state.chat.chat_service.delete(&id).await
@@ -217,12 +345,15 @@ pub async fn hard_delete_message(
   id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
-  state.chat.chat_service.hard_delete(&id).await
+  let result = state.chat.chat_service.hard_delete(&id).await?;
+  let _ = state.data.attachment_service.collect_garbage().await;
+  Ok(result)
 }
 #[tauri::command]
 pub async fn edit_message(
@@ -231,12 +362,24 @@ pub async fn edit_message(
   content: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let editor_id = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
-  state.chat.chat_service.edit_message(&id, &content).await
+  let before = state.chat.chat_service.find_message(&id).await.ok();
+  let result = state
+    .chat
+    .chat_service
+    .edit_message(&id, &editor_id, &content)
+    .await?;
+  state
+    .system
+    .notification_service
+    .notify_mentions("chats", &result.data, before.as_ref(), Some(&editor_id))
+    .await;
+  Ok(result)
 }
 #[tauri::command]
 pub async fn add_message_reaction(
@@ -245,9 +388,10 @@ pub async fn add_message_reaction(
   emoji: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = extract_user_from_token(
+  let user_id = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state
@@ -263,9 +407,10 @@ pub async fn remove_message_reaction(
   emoji: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = extract_user_from_token(
+  let user_id = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state
@@ -280,9 +425,10 @@ pub async fn delete_room_messages(
   room_id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
   state.chat.chat_service.delete_by_room(&room_id).await
@@ -293,10 +439,201 @@ pub async fn hard_delete_room_messages(
   room_id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let _ = extract_user_from_token(
+  let _ = extract_user_with_scope(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
   )
   .map_err(|e| e)?;
-  state.chat.chat_service.hard_delete_by_room(&room_id).await
+  let result = state
+    .chat
+    .chat_service
+    .hard_delete_by_room(&room_id)
+    .await?;
+  let _ = state.data.attachment_service.collect_garbage().await;
+  Ok(result)
+}
+fn chat_user(state: &AppState, token: Option<&str>) -> Result<String, ResponseModel> {
+  extract_user_with_scope(
+    token.unwrap_or(""),
+    &state.config.config_helper.jwt_secret,
+    TokenScope::Chat,
+  )
+}
+#[tauri::command]
+pub async fn set_group_member_role(
+  state: State<'_, AppState>,
+  id: String,
+  user_id: String,
+  role: GroupRole,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_service
+    .set_role(&id, &actor_id, &user_id, role)
+    .await
+}
+#[tauri::command]
+pub async fn transfer_group_ownership(
+  state: State<'_, AppState>,
+  id: String,
+  new_owner_id: String,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_service
+    .transfer_ownership(&id, &actor_id, &new_owner_id)
+    .await
+}
+#[tauri::command]
+pub async fn mute_group_member(
+  state: State<'_, AppState>,
+  id: String,
+  user_id: String,
+  until: Option<chrono::DateTime<chrono::Utc>>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_service
+    .mute_member(&id, &actor_id, &user_id, until)
+    .await
+}
+#[tauri::command]
+pub async fn unmute_group_member(
+  state: State<'_, AppState>,
+  id: String,
+  user_id: String,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_service
+    .unmute_member(&id, &actor_id, &user_id)
+    .await
+}
+#[tauri::command]
+pub async fn get_group_moderation_log(
+  state: State<'_, AppState>,
+  id: String,
+  skip: Option<u64>,
+  limit: Option<u64>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_service
+    .get_moderation_log(&id, &actor_id, skip, limit)
+    .await
+}
+#[tauri::command]
+pub async fn create_group_invite(
+  state: State<'_, AppState>,
+  id: String,
+  expires_in_hours: Option<i64>,
+  max_uses: Option<u32>,
+  requires_approval: Option<bool>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_invite_service
+    .create_invite(
+      &id,
+      &actor_id,
+      expires_in_hours,
+      max_uses,
+      requires_approval.unwrap_or(false),
+    )
+    .await
+}
+#[tauri::command]
+pub async fn get_group_invites(
+  state: State<'_, AppState>,
+  id: String,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_invite_service
+    .get_invites(&id, &actor_id)
+    .await
+}
+#[tauri::command]
+pub async fn revoke_group_invite(
+  state: State<'_, AppState>,
+  invite_id: String,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_invite_service
+    .revoke_invite(&invite_id, &actor_id)
+    .await
+}
+#[tauri::command]
+pub async fn redeem_group_invite(
+  state: State<'_, AppState>,
+  code: String,
+  message: Option<String>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_invite_service
+    .redeem_invite(code.trim(), &user_id, message)
+    .await
+}
+#[tauri::command]
+pub async fn request_group_join(
+  state: State<'_, AppState>,
+  id: String,
+  message: Option<String>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_invite_service
+    .request_join(&id, &user_id, message)
+    .await
+}
+#[tauri::command]
+pub async fn get_group_join_requests(
+  state: State<'_, AppState>,
+  id: String,
+  status: Option<JoinRequestStatus>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_invite_service
+    .get_join_requests(&id, &actor_id, status)
+    .await
+}
+#[tauri::command]
+pub async fn decide_group_join_request(
+  state: State<'_, AppState>,
+  request_id: String,
+  approve: bool,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let actor_id = chat_user(&state, token.as_deref())?;
+  state
+    .chat
+    .group_invite_service
+    .decide_join_request(&request_id, &actor_id, approve)
+    .await
 }
@@ -15,18 +15,26 @@ macro_rules! crud_route {
       limit: Option<u64>,
       token: Option<String>,
     ) -> Result<crate::models::response::ResponseModel, crate::models::response::ResponseModel> {
-      use crate::utils::auth::{extract_profile_from_token, extract_user_from_token};
+      use crate::utils::access_token::{is_access_token, is_credential_write, required_scope};
+      use crate::utils::auth::{extract_profile_from_token, extract_user_with_scope};
       use crate::utils::response_helper::err_response;
-      let user_id = extract_user_from_token(
-        token.as_deref().unwrap_or(""),
+      let raw_token = token.as_deref().unwrap_or("");
+      let user_id = match extract_user_with_scope(
+        raw_token,
         &state.config.config_helper.jwt_secret,
-      )
-      .ok();
-      let profile_id = extract_profile_from_token(
-        token.as_deref().unwrap_or(""),
-        &state.config.config_helper.jwt_secret,
-      )
-      .ok();
+        required_scope($table, $operation),
+      ) {
+        Ok(user_id) => Some(user_id),
+        Err(e) if is_access_token(raw_token) => return Err(e),
+        Err(_) => None,
+      };
+      if is_access_token(raw_token) && is_credential_write($table, $operation) {
+        return Err(crate::models::response::ResponseModel::forbidden(
+          "Access tokens cannot change credentials",
+        ));
+      }
+      let profile_id =
+        extract_profile_from_token(raw_token, &state.config.config_helper.jwt_secret).ok();
       let data = data.map(|mut d| {
         if let Some(ref uid) = user_id {
           if let serde_json::Value::Object(ref mut obj) = d {
@@ -2,6 +2,8 @@
 pub mod admin_command;
 #[path = "archive.command.rs"]
 pub mod archive_command;
+#[path = "attachment.command.rs"]
+pub mod attachment_command;
 #[path = "auth.command.rs"]
 pub mod auth_command;
 #[path = "category.command.rs"]
@@ -14,14 +16,22 @@ pub mod comment_command;
 pub mod crud_command;
 #[path = "daily_activity.command.rs"]
 pub mod daily_activity_command;
+#[path = "digest.command.rs"]
+pub mod digest_command;
 #[path = "group.command.rs"]
 pub mod group_command;
 #[path = "macros.rs"]
 pub mod macros;
+#[path = "notification.command.rs"]
+pub mod notification_command;
 #[path = "profile.command.rs"]
 pub mod profile_command;
+#[path = "reminder.command.rs"]
+pub mod reminder_command;
 #[path = "room.command.rs"]
 pub mod room_command;
+#[path = "security.command.rs"]
+pub mod security_command;
 #[path = "stats.command.rs"]
 pub mod stats_command;
 #[path = "subtask.command.rs"]
@@ -34,3 +44,7 @@ pub mod todo_command;
 pub mod update_command;
 #[path = "user.command.rs"]
 pub mod user_command;
+#[path = "watcher.command.rs"]
+pub mod watcher_command;
+#[path = "webhook.command.rs"]
+pub mod webhook_command;
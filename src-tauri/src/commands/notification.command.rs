@@ -0,0 +1,59 @@
+use crate::entities::notification_preferences_entity::NotificationPreferencesUpdateModel;
+use crate::models::response::ResponseModel;
+use crate::utils::auth::extract_user_for_command;
+use crate::AppState;
+use tauri::State;
+#[tauri::command]
+pub async fn get_notification_preferences(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_user_for_command(
+    &token,
+    &state.config.config_helper.jwt_secret,
+    "get_notification_preferences",
+  )?;
+  state
+    .system
+    .notification_service
+    .get_preferences(&user_id)
+    .await
+}
+/// Replaces the per-kind channel settings and quiet hours. Mutes are left untouched.
+#[tauri::command]
+pub async fn set_notification_preferences(
+  state: State<'_, AppState>,
+  token: String,
+  preferences: NotificationPreferencesUpdateModel,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_user_for_command(
+    &token,
+    &state.config.config_helper.jwt_secret,
+    "set_notification_preferences",
+  )?;
+  state
+    .system
+    .notification_service
+    .set_preferences(&user_id, preferences)
+    .await
+}
+/// `source` is `"todo"` or `"room"`.
+#[tauri::command]
+pub async fn set_notification_mute(
+  state: State<'_, AppState>,
+  token: String,
+  source: String,
+  id: String,
+  muted: bool,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = extract_user_for_command(
+    &token,
+    &state.config.config_helper.jwt_secret,
+    "set_notification_mute",
+  )?;
+  state
+    .system
+    .notification_service
+    .set_mute(&user_id, &source, &id, muted)
+    .await
+}
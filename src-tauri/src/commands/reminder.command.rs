@@ -0,0 +1,97 @@
+use crate::models::response::ResponseModel;
+use crate::utils::access_token::TokenScope;
+use crate::utils::auth::extract_user_with_scope;
+use crate::AppState;
+use tauri::State;
+fn reminder_user(
+  state: &AppState,
+  token: &str,
+  scope: TokenScope,
+) -> Result<String, ResponseModel> {
+  extract_user_with_scope(token, &state.config.config_helper.jwt_secret, scope)
+}
+#[tauri::command]
+pub async fn get_reminder_settings(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = reminder_user(&state, &token, TokenScope::ReadTodos)?;
+  state.system.reminder_service.get_settings(&user_id).await
+}
+#[tauri::command]
+pub async fn set_reminder_settings(
+  state: State<'_, AppState>,
+  token: String,
+  default_offsets: Vec<i64>,
+  overdue: Option<bool>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = reminder_user(&state, &token, TokenScope::WriteTasks)?;
+  state
+    .system
+    .reminder_service
+    .set_settings(&user_id, default_offsets, overdue.unwrap_or(true))
+    .await
+}
+#[tauri::command]
+pub async fn get_task_reminders(
+  state: State<'_, AppState>,
+  token: String,
+  task_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = reminder_user(&state, &token, TokenScope::ReadTodos)?;
+  state
+    .system
+    .reminder_service
+    .get_task_reminders(&task_id, &user_id)
+    .await
+}
+/// Offsets are minutes before the task's `end_date`; an empty list restores user defaults.
+#[tauri::command]
+pub async fn set_task_reminders(
+  state: State<'_, AppState>,
+  token: String,
+  task_id: String,
+  offsets: Vec<i64>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = reminder_user(&state, &token, TokenScope::WriteTasks)?;
+  state
+    .system
+    .reminder_service
+    .set_task_reminders(&task_id, &user_id, offsets)
+    .await
+}
+#[tauri::command]
+pub async fn get_reminders(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = reminder_user(&state, &token, TokenScope::ReadTodos)?;
+  state.system.reminder_service.get_reminders(&user_id).await
+}
+#[tauri::command]
+pub async fn snooze_reminder(
+  state: State<'_, AppState>,
+  token: String,
+  reminder_id: String,
+  minutes: i64,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = reminder_user(&state, &token, TokenScope::WriteTasks)?;
+  state
+    .system
+    .reminder_service
+    .snooze(&reminder_id, &user_id, minutes)
+    .await
+}
+#[tauri::command]
+pub async fn dismiss_reminder(
+  state: State<'_, AppState>,
+  token: String,
+  reminder_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = reminder_user(&state, &token, TokenScope::WriteTasks)?;
+  state
+    .system
+    .reminder_service
+    .dismiss(&reminder_id, &user_id)
+    .await
+}
@@ -1,6 +1,77 @@
 use crate::crud_route;
+use crate::models::response::ResponseModel;
+use crate::utils::access_token::{is_access_token, required_scope};
+use crate::utils::auth::{extract_profile_from_token, extract_user_with_scope};
+use crate::utils::response_helper::err_response;
+use crate::AppState;
+use serde_json::{json, Value};
+use tauri::State;
 crud_route!(get_room, "rooms", "get");
-crud_route!(get_rooms, "rooms", "getAll");
 crud_route!(create_room, "rooms", "create");
 crud_route!(update_room, "rooms", "update");
 crud_route!(delete_room, "rooms", "delete");
+/// Same as the generic `getAll` route, plus an `unread_count` per room for the caller.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn get_rooms(
+  state: State<'_, AppState>,
+  id: Option<String>,
+  data: Option<Value>,
+  filter: Option<Value>,
+  load: Option<String>,
+  visibility: Option<String>,
+  page: Option<u64>,
+  limit: Option<u64>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let raw_token = token.as_deref().unwrap_or("");
+  let user_id = match extract_user_with_scope(
+    raw_token,
+    &state.config.config_helper.jwt_secret,
+    required_scope("rooms", "getAll"),
+  ) {
+    Ok(user_id) => Some(user_id),
+    Err(e) if is_access_token(raw_token) => return Err(e),
+    Err(_) => None,
+  };
+  let profile_id =
+    extract_profile_from_token(raw_token, &state.config.config_helper.jwt_secret).ok();
+  let mut response = state
+    .data
+    .repository_service
+    .execute(
+      "getAll".to_string(),
+      "rooms".to_string(),
+      id,
+      data,
+      filter,
+      load,
+      visibility,
+      user_id.clone(),
+      profile_id,
+      page,
+      limit,
+    )
+    .await
+    .map_err(|e| err_response(&e.message))?;
+  let (Some(user_id), Value::Array(rooms)) = (user_id, &mut response.data) else {
+    return Ok(response);
+  };
+  for room in rooms.iter_mut() {
+    let room_id = room
+      .get("room")
+      .and_then(|v| v.as_str())
+      .unwrap_or("")
+      .to_string();
+    if room_id.is_empty() {
+      continue;
+    }
+    let unread_count = state
+      .chat
+      .chat_read_service
+      .unread_count(&room_id, &user_id)
+      .await;
+    room["unread_count"] = json!(unread_count);
+  }
+  Ok(response)
+}
@@ -0,0 +1,53 @@
+use crate::models::response::ResponseModel;
+use crate::services::security_audit_service::SecurityEventQuery;
+use crate::utils::auth::{extract_user_for_command, validate_admin_role};
+use crate::AppState;
+use tauri::State;
+async fn resolve_requester(
+  state: &AppState,
+  token: &str,
+  command: &str,
+) -> Result<(String, bool), ResponseModel> {
+  let user_id = extract_user_for_command(token, &state.config.config_helper.jwt_secret, command)?;
+  let is_admin = validate_admin_role(
+    token,
+    &state.config.config_helper.jwt_secret,
+    &state.config.json_provider,
+    state.config.mongodb_provider.as_ref(),
+  )
+  .await
+  .is_ok();
+  Ok((user_id, is_admin))
+}
+#[tauri::command]
+pub async fn get_security_events(
+  state: State<'_, AppState>,
+  token: String,
+  query: Option<SecurityEventQuery>,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = resolve_requester(&state, &token, "get_security_events").await?;
+  state
+    .auth
+    .security_audit_service
+    .query(&user_id, is_admin, query.unwrap_or_default())
+    .await
+}
+#[tauri::command]
+pub async fn export_security_events(
+  state: State<'_, AppState>,
+  token: String,
+  query: Option<SecurityEventQuery>,
+  format: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = resolve_requester(&state, &token, "export_security_events").await?;
+  state
+    .auth
+    .security_audit_service
+    .export(
+      &user_id,
+      is_admin,
+      query.unwrap_or_default(),
+      format.as_deref().unwrap_or("json"),
+    )
+    .await
+}
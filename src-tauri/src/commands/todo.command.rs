@@ -4,6 +4,8 @@ crud_route!(get_todos, "todos", "getAll");
 crud_route!(create_todo, "todos", "create");
 crud_route!(update_todo, "todos", "update");
 crud_route!(delete_todo, "todos", "delete");
+use crate::entities::security_event_entity::{SecurityEventEntity, SecurityEventType};
+use crate::entities::todo_entity::OverduePolicy;
 use crate::models::response::ResponseModel;
 use crate::utils::response_helper::{err_response, success_response};
 use crate::utils::visibility::get_visibility;
@@ -17,9 +19,10 @@ pub async fn change_todo_visibility(
   new_visibility: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = crate::utils::auth::extract_user_from_token(
+  let user_id = crate::utils::auth::extract_user_for_command(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    "change_todo_visibility",
   )
   .map_err(|e| e)?;
   let existing = state
@@ -102,12 +105,13 @@ pub async fn update_todo_permissions(
   assignee_roles: HashMap<String, String>,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = crate::utils::auth::extract_user_from_token(
+  let user_id = crate::utils::auth::extract_user_for_command(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    "update_todo_permissions",
   )
   .map_err(|e| e)?;
-  let response_model = state
+  let result = state
     .data
     .repository_service
     .execute(
@@ -118,16 +122,29 @@ pub async fn update_todo_permissions(
       None,
       None,
       None,
-      Some(user_id),
+      Some(user_id.clone()),
       None,
       None,
       None,
     )
     .await
-    .map_err(|e| err_response(&e.message))?;
-  Ok(success_response(
-    serde_json::to_value(response_model).unwrap_or_default(),
-  ))
+    .map(|response_model| {
+      success_response(serde_json::to_value(response_model).unwrap_or_default())
+    })
+    .map_err(|e| err_response(&e.message));
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::TodoPermissionsChanged)
+        .user(Some(&user_id))
+        .actor(Some(&user_id))
+        .target("todos", &todo_id)
+        .details(serde_json::json!({ "assignee_roles": assignee_roles })),
+      &result,
+    )
+    .await;
+  result
 }
 #[tauri::command(rename_all = "snake_case")]
 pub async fn transfer_todo_ownership(
@@ -136,12 +153,13 @@ pub async fn transfer_todo_ownership(
   new_user_id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = crate::utils::auth::extract_user_from_token(
+  let user_id = crate::utils::auth::extract_user_for_command(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    "transfer_todo_ownership",
   )
   .map_err(|e| e)?;
-  let response_model = state
+  let result = state
     .data
     .repository_service
     .execute(
@@ -152,16 +170,29 @@ pub async fn transfer_todo_ownership(
       None,
       None,
       None,
-      Some(user_id),
+      Some(user_id.clone()),
       None,
       None,
       None,
     )
     .await
-    .map_err(|e| err_response(&e.message))?;
-  Ok(success_response(
-    serde_json::to_value(response_model).unwrap_or_default(),
-  ))
+    .map(|response_model| {
+      success_response(serde_json::to_value(response_model).unwrap_or_default())
+    })
+    .map_err(|e| err_response(&e.message));
+  state
+    .auth
+    .security_audit_service
+    .record_outcome(
+      SecurityEventEntity::new(SecurityEventType::TodoOwnershipTransferred)
+        .user(Some(&user_id))
+        .actor(Some(&user_id))
+        .target("todos", &todo_id)
+        .details(serde_json::json!({ "new_user_id": new_user_id })),
+      &result,
+    )
+    .await;
+  result
 }
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_todo_permissions(
@@ -169,9 +200,10 @@ pub async fn get_todo_permissions(
   todo_id: String,
   token: Option<String>,
 ) -> Result<ResponseModel, ResponseModel> {
-  let user_id = crate::utils::auth::extract_user_from_token(
+  let user_id = crate::utils::auth::extract_user_for_command(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    "get_todo_permissions",
   )
   .map_err(|e| e)?;
   let response = state
@@ -214,3 +246,22 @@ pub async fn get_todo_permissions(
     serde_json::json!({ "assignee_roles": assignee_roles }),
   ))
 }
+/// Opts the todo into automatic handling of late tasks, or out of it with `policy: null`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_todo_overdue_policy(
+  state: State<'_, AppState>,
+  todo_id: String,
+  policy: Option<OverduePolicy>,
+  token: Option<String>,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = crate::utils::auth::extract_user_for_command(
+    token.as_deref().unwrap_or(""),
+    &state.config.config_helper.jwt_secret,
+    "set_todo_overdue_policy",
+  )?;
+  state
+    .data
+    .overdue_service
+    .set_policy(&todo_id, &user_id, policy)
+    .await
+}
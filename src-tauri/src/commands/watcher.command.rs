@@ -0,0 +1,63 @@
+use crate::models::response::ResponseModel;
+use crate::utils::access_token::TokenScope;
+use crate::utils::auth::extract_user_with_scope;
+use crate::AppState;
+use tauri::State;
+fn watcher_user(state: &AppState, token: &str) -> Result<String, ResponseModel> {
+  extract_user_with_scope(
+    token,
+    &state.config.config_helper.jwt_secret,
+    TokenScope::ReadTodos,
+  )
+}
+/// `entity` is `todos` or `tasks`.
+#[tauri::command]
+pub async fn watch_item(
+  state: State<'_, AppState>,
+  token: String,
+  entity: String,
+  target_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = watcher_user(&state, &token)?;
+  state
+    .data
+    .watcher_service
+    .watch(&entity, &target_id, &user_id)
+    .await
+}
+#[tauri::command]
+pub async fn unwatch_item(
+  state: State<'_, AppState>,
+  token: String,
+  entity: String,
+  target_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = watcher_user(&state, &token)?;
+  state
+    .data
+    .watcher_service
+    .unwatch(&entity, &target_id, &user_id)
+    .await
+}
+#[tauri::command]
+pub async fn get_watchers(
+  state: State<'_, AppState>,
+  token: String,
+  entity: String,
+  target_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = watcher_user(&state, &token)?;
+  state
+    .data
+    .watcher_service
+    .get_watchers(&entity, &target_id, &user_id)
+    .await
+}
+#[tauri::command]
+pub async fn get_watched_items(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let user_id = watcher_user(&state, &token)?;
+  state.data.watcher_service.get_watched(&user_id).await
+}
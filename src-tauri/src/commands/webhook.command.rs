@@ -0,0 +1,116 @@
+use crate::entities::webhook_entity::WebhookInput;
+use crate::models::response::ResponseModel;
+use crate::utils::auth::{extract_user_for_command, validate_admin_role};
+use crate::AppState;
+use tauri::State;
+/// The caller and whether they are an admin.
+async fn webhook_user(
+  state: &AppState,
+  token: &str,
+  command: &str,
+) -> Result<(String, bool), ResponseModel> {
+  let user_id = extract_user_for_command(token, &state.config.config_helper.jwt_secret, command)?;
+  let is_admin = validate_admin_role(
+    token,
+    &state.config.config_helper.jwt_secret,
+    &state.config.json_provider,
+    state
+      .system
+      .manage_db_service
+      .get_mongodb_provider()
+      .as_ref(),
+  )
+  .await
+  .is_ok();
+  Ok((user_id, is_admin))
+}
+#[tauri::command]
+pub async fn get_webhooks(
+  state: State<'_, AppState>,
+  token: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = webhook_user(&state, &token, "get_webhooks").await?;
+  state.system.webhook_service.list(&user_id, is_admin).await
+}
+#[tauri::command]
+pub async fn create_webhook(
+  state: State<'_, AppState>,
+  token: String,
+  webhook: WebhookInput,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = webhook_user(&state, &token, "create_webhook").await?;
+  state
+    .system
+    .webhook_service
+    .create(&user_id, is_admin, webhook)
+    .await
+}
+#[tauri::command]
+pub async fn update_webhook(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+  webhook: WebhookInput,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = webhook_user(&state, &token, "update_webhook").await?;
+  state
+    .system
+    .webhook_service
+    .update(&id, &user_id, is_admin, webhook)
+    .await
+}
+#[tauri::command]
+pub async fn delete_webhook(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = webhook_user(&state, &token, "delete_webhook").await?;
+  state
+    .system
+    .webhook_service
+    .delete(&id, &user_id, is_admin)
+    .await
+}
+#[tauri::command]
+pub async fn get_webhook_deliveries(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+  skip: Option<usize>,
+  limit: Option<usize>,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = webhook_user(&state, &token, "get_webhook_deliveries").await?;
+  state
+    .system
+    .webhook_service
+    .deliveries(&id, &user_id, is_admin, skip, limit)
+    .await
+}
+#[tauri::command]
+pub async fn redeliver_webhook(
+  state: State<'_, AppState>,
+  token: String,
+  delivery_id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = webhook_user(&state, &token, "redeliver_webhook").await?;
+  state
+    .system
+    .webhook_service
+    .redeliver(&delivery_id, &user_id, is_admin)
+    .await
+}
+/// Sends a `webhook.ping` right away, e.g. to check a receiver.
+#[tauri::command]
+pub async fn test_webhook(
+  state: State<'_, AppState>,
+  token: String,
+  id: String,
+) -> Result<ResponseModel, ResponseModel> {
+  let (user_id, is_admin) = webhook_user(&state, &token, "test_webhook").await?;
+  state
+    .system
+    .webhook_service
+    .test(&id, &user_id, is_admin)
+    .await
+}
@@ -0,0 +1,33 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+/* helpers */
+use crate::utils::access_token::TokenScope;
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("personal_access_tokens")]
+#[timestamp]
+#[index("user_id", 1)]
+#[index("token_hash", 1)]
+#[frontend_exclude("token_hash")]
+pub struct AccessTokenEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  #[serde(default)]
+  pub profile_id: Option<String>,
+  pub name: String,
+  pub scopes: Vec<TokenScope>,
+  /// hex SHA-256 of the plaintext token, which is only shown once
+  pub token_hash: String,
+  /// leading characters of the token so users can tell tokens apart
+  pub token_hint: String,
+  #[serde(default)]
+  pub expires_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub revoked_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,35 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+/// Metadata for a file attached to a chat message or comment. The bytes live in the
+/// local blob store under `hash`; identical files share one blob.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("attachments")]
+#[timestamp]
+#[index("owner_id", 1)]
+#[index("uploader_id", 1)]
+#[index("hash", 1)]
+pub struct AttachmentEntity {
+  pub id: Option<String>,
+  /// Table of the owning record: "chats" or "comments"
+  pub owner_type: String,
+  pub owner_id: String,
+  pub uploader_id: String,
+  pub file_name: String,
+  pub mime_type: String,
+  pub size: u64,
+  /// SHA-256 of the content, hex encoded
+  pub hash: String,
+  #[serde(default)]
+  pub thumbnail_hash: Option<String>,
+  #[serde(default)]
+  pub width: Option<u32>,
+  #[serde(default)]
+  pub height: Option<u32>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
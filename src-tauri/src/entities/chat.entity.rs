@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 /* nosql_orm */
 use nosql_orm::{Model, Validate};
 /* crate */
+use crate::entities::mention_entity::Mention;
 use crate::entities::user_entity::UserEntity;
 #[derive(Debug, Clone, Serialize, Deserialize, Model)]
 #[table_name("chats")]
@@ -13,6 +14,9 @@ use crate::entities::user_entity::UserEntity;
 #[many_to_many("read_by_users", "users", "read_by")]
 #[index("room_id", 1)]
 #[index("sender_id", 1)]
+#[index("parent_id", 1)]
+#[index("task_ref_ids", 1)]
+#[index("mention_ids", 1)]
 pub struct ChatEntity {
   pub id: Option<String>,
   pub room_id: String,
@@ -20,6 +24,36 @@ pub struct ChatEntity {
   pub content: String,
   #[serde(default)]
   pub read_by: Vec<String>,
+  /// Thread root this message replies to
+  #[serde(default)]
+  pub parent_id: Option<String>,
+  /// Message being quoted, with a snapshot kept in `quoted`
+  #[serde(default)]
+  pub reply_id: Option<String>,
+  #[serde(default)]
+  pub quoted: Option<ChatQuote>,
+  #[serde(default)]
+  pub reply_count: u32,
+  #[serde(default)]
+  pub last_reply_at: Option<DateTime<Utc>>,
+  /// Tasks referenced in `content` that the sender could see when it was saved
+  #[serde(default)]
+  pub task_refs: Vec<ChatTaskRef>,
+  /// Ids from `task_refs`, kept flat so task backlinks can be queried
+  #[serde(default)]
+  pub task_ref_ids: Vec<String>,
+  /// Room members mentioned in `content` when it was saved
+  #[serde(default)]
+  pub mentions: Vec<Mention>,
+  /// User ids from `mentions`, kept flat so mentions of a user can be queried
+  #[serde(default)]
+  pub mention_ids: Vec<String>,
+  /// Earlier versions of `content`, oldest first
+  #[serde(default)]
+  pub revisions: Vec<ChatRevision>,
+  /// Stored locally and still waiting in the chat outbox
+  #[serde(default)]
+  pub pending: bool,
   #[serde(default)]
   pub created_at: Option<DateTime<Utc>>,
   #[serde(default)]
@@ -38,6 +72,35 @@ pub struct ChatCreateModel {
   #[validate(required)]
   #[validate(length(min = 1, max = 5000))]
   pub content: String,
+  #[serde(default)]
+  pub parent_id: Option<String>,
+  #[serde(default)]
+  pub reply_id: Option<String>,
+}
+/// Copy of a quoted message taken when the quote is sent, so it survives later edits or deletes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatQuote {
+  pub id: String,
+  pub sender_id: String,
+  pub content: String,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+}
+/// A `#task:<id>` or `todo-slug#n` reference resolved to a task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatTaskRef {
+  /// The reference as written in the message
+  pub raw: String,
+  pub task_id: String,
+  pub todo_id: String,
+}
+/// The content a message had before an edit replaced it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRevision {
+  pub content: String,
+  pub editor_id: String,
+  /// When this content was replaced
+  pub replaced_at: DateTime<Utc>,
 }
 impl From<ChatCreateModel> for ChatEntity {
   fn from(create: ChatCreateModel) -> Self {
@@ -47,6 +110,17 @@ impl From<ChatCreateModel> for ChatEntity {
       room_id: create.room_id,
       sender_id: sender.clone(),
       content: create.content,
+      parent_id: create.parent_id,
+      reply_id: create.reply_id,
+      quoted: None,
+      reply_count: 0,
+      last_reply_at: None,
+      task_refs: Vec::new(),
+      task_ref_ids: Vec::new(),
+      mentions: Vec::new(),
+      mention_ids: Vec::new(),
+      revisions: Vec::new(),
+      pending: false,
       created_at: None,
       updated_at: None,
       deleted_at: None,
@@ -0,0 +1,46 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+/* nosql_orm */
+use nosql_orm::Model;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatOutboxOp {
+  Create,
+  Edit,
+  Reaction,
+  Delete,
+}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatOutboxStatus {
+  Pending,
+  /// Gave up after repeated rejections; kept so the user can see what was not sent
+  Failed,
+}
+/// A chat change made while MongoDB was unreachable, replayed in `seq` order once it is back.
+/// Local only: this table is never synced.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("chat_outbox")]
+#[timestamp]
+#[index("seq", 1)]
+pub struct ChatOutboxEntity {
+  pub id: Option<String>,
+  pub op: ChatOutboxOp,
+  /// Client-generated message id; also what makes replaying a create idempotent
+  pub message_id: String,
+  pub room_id: String,
+  #[serde(default)]
+  pub payload: Value,
+  pub seq: i64,
+  #[serde(default)]
+  pub attempts: u32,
+  #[serde(default)]
+  pub last_error: Option<String>,
+  pub status: ChatOutboxStatus,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
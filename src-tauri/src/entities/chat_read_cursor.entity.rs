@@ -0,0 +1,25 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+/// How far a user has read in a chat room. Everything up to and including the message
+/// created at `last_read_at` counts as read; the id is `<room_id>:<user_id>` so local
+/// and cloud copies of the same cursor never diverge into two documents.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("chat_read_cursors")]
+#[timestamp]
+#[index("room_id", 1)]
+#[index("user_id", 1)]
+pub struct ChatReadCursorEntity {
+  pub id: Option<String>,
+  pub room_id: String,
+  pub user_id: String,
+  pub last_read_id: String,
+  /// `created_at` of the last read message, copied verbatim so it compares with chat timestamps
+  pub last_read_at: String,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,51 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+/// How long messages in a room (or a group's room) are kept before the retention job purges them.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("chat_retention_policies")]
+#[timestamp]
+#[index("room_id", 1)]
+pub struct ChatRetentionPolicyEntity {
+  pub id: Option<String>,
+  pub room_id: String,
+  pub max_age_days: u32,
+  /// Remove messages outright instead of soft-deleting them
+  #[serde(default)]
+  pub hard_delete: bool,
+  pub set_by: String,
+  /// Participants of a direct room who accepted these terms; the policy only runs once all
+  /// of them have. Group rooms leave it empty since their moderators decide.
+  #[serde(default)]
+  pub agreed_by: Vec<String>,
+  #[serde(default)]
+  pub last_run_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+/// One retention run over one room: what the cutoff was and which messages went.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("chat_retention_log")]
+#[timestamp]
+#[index("room_id", 1)]
+pub struct ChatRetentionRunEntity {
+  pub id: Option<String>,
+  pub room_id: String,
+  pub policy_id: String,
+  pub cutoff: DateTime<Utc>,
+  pub hard_delete: bool,
+  pub purged_count: usize,
+  #[serde(default)]
+  pub message_ids: Vec<String>,
+  /// Why the run failed; nothing was purged then.
+  #[serde(default)]
+  pub error: Option<String>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 /* nosql_orm */
 use nosql_orm::Model;
 use nosql_orm::Validate;
+/* crate */
+use crate::entities::mention_entity::Mention;
 #[derive(Debug, Clone, Serialize, Deserialize, Model)]
 #[table_name("comments")]
 #[many_to_one("user", "users", "user_id")]
@@ -13,6 +15,7 @@ use nosql_orm::Validate;
 #[timestamp]
 #[soft_delete]
 #[index("user_id", 1)]
+#[index("mention_ids", 1)]
 pub struct CommentEntity {
   pub id: Option<String>,
   pub user_id: String,
@@ -23,6 +26,12 @@ pub struct CommentEntity {
   pub subtask_id: Option<String>,
   #[serde(default)]
   pub read_by: Vec<String>,
+  /// People mentioned in `content` who could see the todo when it was saved
+  #[serde(default)]
+  pub mentions: Vec<Mention>,
+  /// User ids from `mentions`, kept flat so mentions of a user can be queried
+  #[serde(default)]
+  pub mention_ids: Vec<String>,
   #[serde(default)]
   pub created_at: Option<DateTime<Utc>>,
   #[serde(default)]
@@ -52,6 +61,8 @@ impl From<CommentCreateModel> for CommentEntity {
       task_id: value.task_id,
       subtask_id: value.subtask_id,
       read_by: vec![],
+      mentions: vec![],
+      mention_ids: vec![],
       deleted_at: None,
     }
   }
@@ -0,0 +1,105 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+/* nosql_orm */
+use nosql_orm::Model;
+/* entities */
+use crate::entities::daily_activity_entity::DailyActivityModel;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+  #[default]
+  Off,
+  Daily,
+  Weekly,
+}
+/// When and how a user receives their digest. The document id is the user id.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("digest_settings")]
+#[timestamp]
+pub struct DigestSettingsEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  #[serde(default)]
+  pub frequency: DigestFrequency,
+  /// Local hour (0-23) from which the digest is sent.
+  #[serde(default = "default_hour")]
+  pub hour: u32,
+  /// Day weekly digests go out on, 0 for Monday through 6 for Sunday.
+  #[serde(default)]
+  pub weekday: u32,
+  /// An IANA zone name such as `Europe/Berlin`, like quiet hours use.
+  #[serde(default = "utc")]
+  pub timezone: String,
+  /// Also send the digest to the account's email address.
+  #[serde(default)]
+  pub email: bool,
+  /// Local date of the last digest, so each day or week gets exactly one.
+  #[serde(default)]
+  pub last_sent_on: Option<String>,
+  #[serde(default)]
+  pub last_sent_at: Option<String>,
+  /// Why the last digest could not be emailed; cleared once one is.
+  #[serde(default)]
+  pub last_email_error: Option<String>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+fn default_hour() -> u32 {
+  8
+}
+fn utc() -> String {
+  "UTC".to_string()
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestSettingsUpdateModel {
+  pub frequency: DigestFrequency,
+  #[serde(default = "default_hour")]
+  pub hour: u32,
+  #[serde(default)]
+  pub weekday: u32,
+  #[serde(default = "utc")]
+  pub timezone: String,
+  #[serde(default)]
+  pub email: bool,
+}
+/// A task as listed in a digest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestTask {
+  pub id: String,
+  pub title: String,
+  pub todo_id: String,
+  pub todo_title: String,
+  pub end_date: String,
+}
+/// What a digest contains. Lists are capped; the counts are not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestSummary {
+  pub user_id: String,
+  pub frequency: DigestFrequency,
+  /// Local date the digest is for.
+  pub date: String,
+  pub due_today: Vec<DigestTask>,
+  pub due_this_week: Vec<DigestTask>,
+  pub overdue: Vec<DigestTask>,
+  /// `assigned` notifications received since the previous digest.
+  pub newly_assigned: Vec<Value>,
+  /// Unread `commented` and `replied` notifications.
+  pub unread_comments: Vec<Value>,
+  /// Other unread notifications the user routed to the email digest.
+  pub other_updates: Vec<Value>,
+  pub yesterday: Option<DailyActivityModel>,
+  pub counts: DigestCounts,
+}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub struct DigestCounts {
+  pub due_today: usize,
+  pub due_this_week: usize,
+  pub overdue: usize,
+  pub newly_assigned: usize,
+  pub unread_comments: usize,
+  pub other_updates: usize,
+}
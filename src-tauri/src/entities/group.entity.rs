@@ -3,6 +3,21 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 /* nosql_orm */
 use nosql_orm::{Model, Validate};
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupRole {
+  Member,
+  Admin,
+  Owner,
+}
+/// A member who may read but not post; `until: None` mutes until an admin lifts it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMute {
+  pub user_id: String,
+  pub muted_by: String,
+  #[serde(default)]
+  pub until: Option<DateTime<Utc>>,
+}
 #[derive(Debug, Clone, Serialize, Deserialize, Model)]
 #[table_name("groups")]
 #[soft_delete]
@@ -17,6 +32,11 @@ pub struct GroupEntity {
   pub owner_id: String,
   #[serde(default)]
   pub member_ids: Vec<String>,
+  /// Members with the admin role; the owner is never listed here
+  #[serde(default)]
+  pub admin_ids: Vec<String>,
+  #[serde(default)]
+  pub muted: Vec<GroupMute>,
   #[serde(default)]
   pub created_at: Option<DateTime<Utc>>,
   #[serde(default)]
@@ -45,6 +65,8 @@ impl From<GroupCreateModel> for GroupEntity {
       room_id: create.room_id,
       owner_id: create.owner_id,
       member_ids: create.member_ids,
+      admin_ids: Vec::new(),
+      muted: Vec::new(),
       created_at: None,
       updated_at: None,
       deleted_at: None,
@@ -0,0 +1,33 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+/// Shareable code that lets someone join a group, optionally only after an admin approves.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("group_invites")]
+#[timestamp]
+#[index("group_id", 1)]
+#[index("code", 1)]
+pub struct GroupInviteEntity {
+  pub id: Option<String>,
+  pub group_id: String,
+  pub code: String,
+  pub created_by: String,
+  #[serde(default)]
+  pub expires_at: Option<DateTime<Utc>>,
+  /// `None` allows unlimited joins until the invite expires or is revoked
+  #[serde(default)]
+  pub max_uses: Option<u32>,
+  #[serde(default)]
+  pub uses: u32,
+  /// Redeeming creates a join request instead of adding the user straight away
+  #[serde(default)]
+  pub requires_approval: bool,
+  #[serde(default)]
+  pub revoked: bool,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
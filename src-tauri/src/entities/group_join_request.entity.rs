@@ -0,0 +1,36 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinRequestStatus {
+  Pending,
+  Approved,
+  Rejected,
+}
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("group_join_requests")]
+#[timestamp]
+#[index("group_id", 1)]
+#[index("user_id", 1)]
+pub struct GroupJoinRequestEntity {
+  pub id: Option<String>,
+  pub group_id: String,
+  pub user_id: String,
+  /// Invite the request came through, if any
+  #[serde(default)]
+  pub invite_id: Option<String>,
+  #[serde(default)]
+  pub message: Option<String>,
+  pub status: JoinRequestStatus,
+  #[serde(default)]
+  pub decided_by: Option<String>,
+  #[serde(default)]
+  pub decided_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,42 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+/* nosql_orm */
+use nosql_orm::Model;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupModerationAction {
+  MemberAdded,
+  MemberRemoved,
+  MemberLeft,
+  MemberMuted,
+  MemberUnmuted,
+  RoleChanged,
+  OwnershipTransferred,
+  InviteCreated,
+  InviteRevoked,
+  JoinedViaInvite,
+  JoinRequested,
+  JoinApproved,
+  JoinRejected,
+}
+/// Append-only record of membership and moderation changes in a group.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("group_moderation_log")]
+#[timestamp]
+#[index("group_id", 1)]
+pub struct GroupModerationEntity {
+  pub id: Option<String>,
+  pub group_id: String,
+  pub actor_id: String,
+  pub action: GroupModerationAction,
+  #[serde(default)]
+  pub target_id: Option<String>,
+  #[serde(default)]
+  pub details: Value,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,11 @@
+/* sys lib */
+use serde::{Deserialize, Serialize};
+/// An `@username` in a comment or chat message, resolved to a user who could see it when
+/// it was saved.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Mention {
+  /// The mention as written, e.g. `@alex`
+  pub raw: String,
+  pub user_id: String,
+  pub username: String,
+}
@@ -1,17 +1,47 @@
+#[path = "access_token.entity.rs"]
+pub mod access_token_entity;
+#[path = "attachment.entity.rs"]
+pub mod attachment_entity;
 #[path = "category.entity.rs"]
 pub mod category_entity;
 #[path = "chat.entity.rs"]
 pub mod chat_entity;
+#[path = "chat_outbox.entity.rs"]
+pub mod chat_outbox_entity;
+#[path = "chat_read_cursor.entity.rs"]
+pub mod chat_read_cursor_entity;
+#[path = "chat_retention.entity.rs"]
+pub mod chat_retention_entity;
 #[path = "comment.entity.rs"]
 pub mod comment_entity;
 #[path = "daily_activity.entity.rs"]
 pub mod daily_activity_entity;
+#[path = "digest.entity.rs"]
+pub mod digest_entity;
 #[path = "email_config.entity.rs"]
 pub mod email_config;
 #[path = "group.entity.rs"]
 pub mod group_entity;
+#[path = "group_invite.entity.rs"]
+pub mod group_invite_entity;
+#[path = "group_join_request.entity.rs"]
+pub mod group_join_request_entity;
+#[path = "group_moderation.entity.rs"]
+pub mod group_moderation_entity;
 #[path = "login_form.entity.rs"]
 pub mod login_form_entity;
+#[path = "mention.entity.rs"]
+pub mod mention_entity;
+#[path = "notification.entity.rs"]
+pub mod notification_entity;
+#[path = "notification_outbox.entity.rs"]
+pub mod notification_outbox_entity;
+#[path = "notification_preferences.entity.rs"]
+pub mod notification_preferences_entity;
+#[path = "oauth_identity.entity.rs"]
+pub mod oauth_identity_entity;
+#[path = "passkey.entity.rs"]
+pub mod passkey_entity;
 #[path = "password_reset.entity.rs"]
 pub mod password_reset;
 #[path = "permission.entity.rs"]
@@ -20,8 +50,12 @@ pub mod permission_entity;
 pub mod profile_entity;
 #[path = "provider_type.entity.rs"]
 pub mod provider_type_entity;
+#[path = "reminder.entity.rs"]
+pub mod reminder_entity;
 #[path = "room.entity.rs"]
 pub mod room_entity;
+#[path = "security_event.entity.rs"]
+pub mod security_event_entity;
 #[path = "signup_form.entity.rs"]
 pub mod signup_form_entity;
 #[path = "statistics.entity.rs"]
@@ -36,3 +70,7 @@ pub mod task_entity;
 pub mod todo_entity;
 #[path = "user.entity.rs"]
 pub mod user_entity;
+#[path = "watcher.entity.rs"]
+pub mod watcher_entity;
+#[path = "webhook.entity.rs"]
+pub mod webhook_entity;
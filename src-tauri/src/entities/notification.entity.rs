@@ -0,0 +1,147 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+/* nosql_orm */
+use nosql_orm::Model;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+  Assigned,
+  Commented,
+  TaskCompleted,
+  RoleChanged,
+  GroupAdded,
+  Replied,
+  Mentioned,
+  StatusChanged,
+  DueDateChanged,
+  DueSoon,
+  Overdue,
+  Digest,
+  #[default]
+  Custom,
+}
+impl NotificationKind {
+  /// Kinds that collapse into one grouped notification per target while unread. Reminders
+  /// and digests are de-duplicated by their own services instead.
+  pub fn groups(&self) -> bool {
+    matches!(
+      self,
+      NotificationKind::Assigned
+        | NotificationKind::Commented
+        | NotificationKind::TaskCompleted
+        | NotificationKind::Replied
+        | NotificationKind::StatusChanged
+        | NotificationKind::DueDateChanged
+    )
+  }
+}
+/// The record a notification is about, e.g. `{ "entity": "tasks", "id": "..." }`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct NotificationTarget {
+  pub entity: String,
+  pub id: String,
+}
+impl NotificationTarget {
+  pub fn new(entity: &str, id: &str) -> Self {
+    Self {
+      entity: entity.to_string(),
+      id: id.to_string(),
+    }
+  }
+}
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("notifications")]
+#[timestamp]
+#[index("user_id", 1)]
+#[index("group_key", 1)]
+pub struct NotificationEntity {
+  pub id: Option<String>,
+  /// Recipient.
+  pub user_id: String,
+  pub kind: NotificationKind,
+  /// Who caused the notification; `None` for system notifications.
+  #[serde(default)]
+  pub actor_id: Option<String>,
+  #[serde(default)]
+  pub target: NotificationTarget,
+  #[serde(default)]
+  pub read: bool,
+  /// When it was last marked read. A read older than the latest grouped event does not
+  /// count when the local and cloud copies are reconciled.
+  #[serde(default)]
+  pub read_at: Option<String>,
+  /// Whether the source is shared (shared or public todo, chat, group), in which case the
+  /// notification is kept in MongoDB as well as the local mirror.
+  #[serde(default)]
+  pub shared: bool,
+  /// Kind-specific details for rendering, such as titles or the old and new role.
+  #[serde(default)]
+  pub payload: Value,
+  /// Channels left on by the recipient's preferences when the notification was created.
+  /// Notifications with `in_app: false` are only kept for the email digest.
+  #[serde(default = "enabled")]
+  pub in_app: bool,
+  #[serde(default = "enabled")]
+  pub desktop: bool,
+  #[serde(default = "enabled")]
+  pub email_digest: bool,
+  /// Recipient, kind and target of a groupable notification; events with the same key
+  /// inside the grouping window are folded into this one.
+  #[serde(default)]
+  pub group_key: Option<String>,
+  /// How many events the notification stands for.
+  #[serde(default = "one")]
+  pub count: u32,
+  /// Everyone who caused one of those events, oldest first. `actor_id` is the latest.
+  #[serde(default)]
+  pub actor_ids: Vec<String>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+fn enabled() -> bool {
+  true
+}
+fn one() -> u32 {
+  1
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationCreateModel {
+  pub user_id: String,
+  #[serde(default)]
+  pub kind: NotificationKind,
+  #[serde(default)]
+  pub actor_id: Option<String>,
+  #[serde(default)]
+  pub target: NotificationTarget,
+  #[serde(default)]
+  pub payload: Value,
+}
+impl From<NotificationCreateModel> for NotificationEntity {
+  fn from(value: NotificationCreateModel) -> Self {
+    let now = Utc::now();
+    let actor_ids = value.actor_id.iter().cloned().collect();
+    NotificationEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      user_id: value.user_id,
+      kind: value.kind,
+      actor_id: value.actor_id,
+      target: value.target,
+      read: false,
+      read_at: None,
+      shared: false,
+      payload: value.payload,
+      in_app: true,
+      desktop: true,
+      email_digest: true,
+      group_key: None,
+      count: 1,
+      actor_ids,
+      created_at: Some(now),
+      updated_at: Some(now),
+    }
+  }
+}
@@ -0,0 +1,38 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+/* nosql_orm */
+use nosql_orm::Model;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationOutboxOp {
+  /// Insert the notification in `payload`, or fold it into its group
+  Create,
+  /// Patch the notification with `payload`, e.g. a grouped event
+  Update,
+  Read,
+  Delete,
+}
+/// A change to a shared notification made while MongoDB was unreachable, replayed in `seq`
+/// order once it is back. Local only: this table is never synced.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("notification_outbox")]
+#[timestamp]
+#[index("seq", 1)]
+pub struct NotificationOutboxEntity {
+  pub id: Option<String>,
+  pub op: NotificationOutboxOp,
+  pub notification_id: String,
+  #[serde(default)]
+  pub payload: Value,
+  pub seq: i64,
+  #[serde(default)]
+  pub attempts: u32,
+  #[serde(default)]
+  pub last_error: Option<String>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,72 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+/* entities */
+use crate::entities::notification_entity::NotificationKind;
+/* nosql_orm */
+use nosql_orm::Model;
+/// Where a kind of notification may be delivered.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NotificationChannels {
+  #[serde(default = "enabled")]
+  pub in_app: bool,
+  #[serde(default = "enabled")]
+  pub desktop: bool,
+  #[serde(default = "enabled")]
+  pub email_digest: bool,
+}
+impl Default for NotificationChannels {
+  fn default() -> Self {
+    Self {
+      in_app: true,
+      desktop: true,
+      email_digest: true,
+    }
+  }
+}
+fn enabled() -> bool {
+  true
+}
+/// A daily window, in the user's timezone, during which desktop toasts are held back.
+/// `start` and `end` are `HH:MM`; a window may wrap past midnight. `timezone` is an IANA
+/// zone name such as `Europe/Berlin`, or `UTC`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuietHours {
+  pub start: String,
+  pub end: String,
+  #[serde(default = "utc")]
+  pub timezone: String,
+}
+fn utc() -> String {
+  "UTC".to_string()
+}
+/// Per-user notification preferences. The document id is the user id.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("notification_preferences")]
+#[timestamp]
+pub struct NotificationPreferencesEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  /// Overrides per kind; kinds not listed use every channel.
+  #[serde(default)]
+  pub kinds: HashMap<NotificationKind, NotificationChannels>,
+  #[serde(default)]
+  pub quiet_hours: Option<QuietHours>,
+  #[serde(default)]
+  pub muted_todos: Vec<String>,
+  #[serde(default)]
+  pub muted_rooms: Vec<String>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+/// Fields a user may change; the mute lists are managed through their own command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPreferencesUpdateModel {
+  #[serde(default)]
+  pub kinds: HashMap<NotificationKind, NotificationChannels>,
+  #[serde(default)]
+  pub quiet_hours: Option<QuietHours>,
+}
@@ -0,0 +1,27 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+/// Link between a TaskFlow user and an account at an external OAuth/OIDC provider.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("oauth_identities")]
+#[timestamp]
+#[index("user_id", 1)]
+#[index("subject", 1)]
+pub struct OAuthIdentityEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  pub provider: String,
+  pub subject: String,
+  #[serde(default)]
+  pub email: Option<String>,
+  #[serde(default)]
+  pub username: Option<String>,
+  #[serde(default)]
+  pub last_login_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,33 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("passkeys")]
+#[timestamp]
+#[index("user_id", 1)]
+#[index("passkey_credential_id", 1)]
+#[frontend_exclude("passkey_public_key")]
+pub struct PasskeyEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  pub name: String,
+  /// base64url credential id as returned by the authenticator
+  pub passkey_credential_id: String,
+  /// base64url SEC1 uncompressed P-256 public key
+  pub passkey_public_key: String,
+  /// hex AAGUID of the authenticator model
+  #[serde(default)]
+  pub passkey_device: String,
+  #[serde(default)]
+  pub transports: Vec<String>,
+  #[serde(default)]
+  pub sign_count: u32,
+  #[serde(default)]
+  pub last_used_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,88 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+/// A user's default reminder offsets, used for tasks without offsets of their own.
+/// The document id is the user id.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("reminder_settings")]
+#[timestamp]
+pub struct ReminderSettingsEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  /// Minutes before the due date, e.g. `[1440, 60]` for a day and an hour before.
+  #[serde(default)]
+  pub default_offsets: Vec<i64>,
+  /// Whether to be told once a task is past its due date.
+  #[serde(default = "default_overdue")]
+  pub overdue: bool,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+fn default_overdue() -> bool {
+  true
+}
+/// Reminder offsets chosen for one task. They replace every recipient's defaults.
+/// The document id is the task id.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("task_reminders")]
+#[timestamp]
+pub struct TaskReminderEntity {
+  pub id: Option<String>,
+  pub task_id: String,
+  #[serde(default)]
+  pub offsets: Vec<i64>,
+  pub set_by: String,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderStatus {
+  Fired,
+  Snoozed,
+  Dismissed,
+}
+/// One reminder that has fired for a user. The id is derived from the task, user, trigger
+/// and due date, so a restarted scheduler finds it and does not fire it again.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("reminder_states")]
+#[timestamp]
+#[index("user_id", 1)]
+pub struct ReminderStateEntity {
+  pub id: Option<String>,
+  pub task_id: String,
+  pub user_id: String,
+  /// Minutes before `due_at`; `None` for the overdue reminder.
+  #[serde(default)]
+  pub offset: Option<i64>,
+  pub due_at: String,
+  pub status: ReminderStatus,
+  pub fired_at: String,
+  #[serde(default)]
+  pub snoozed_until: Option<String>,
+  #[serde(default)]
+  pub notification_id: Option<String>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+/// Marks one firing of a reminder as taken. Each device inserts the claim before notifying
+/// and only the device whose insert succeeds fires, so two devices never fire it twice.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("reminder_claims")]
+#[timestamp]
+pub struct ReminderClaimEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
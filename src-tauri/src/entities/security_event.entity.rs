@@ -0,0 +1,125 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+/* nosql_orm */
+use nosql_orm::Model;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventType {
+  Login,
+  LoginFailed,
+  Register,
+  PasswordChanged,
+  PasswordReset,
+  TotpEnabled,
+  TotpDisabled,
+  RecoveryCodeUsed,
+  RecoveryCodesRegenerated,
+  PasskeyAdded,
+  PasskeyRemoved,
+  AccessTokenCreated,
+  AccessTokenRevoked,
+  OauthLinked,
+  OauthUnlinked,
+  QrApproved,
+  TodoPermissionsChanged,
+  TodoOwnershipTransferred,
+  HardDelete,
+  AdminHardDelete,
+}
+impl SecurityEventType {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      SecurityEventType::Login => "login",
+      SecurityEventType::LoginFailed => "login_failed",
+      SecurityEventType::Register => "register",
+      SecurityEventType::PasswordChanged => "password_changed",
+      SecurityEventType::PasswordReset => "password_reset",
+      SecurityEventType::TotpEnabled => "totp_enabled",
+      SecurityEventType::TotpDisabled => "totp_disabled",
+      SecurityEventType::RecoveryCodeUsed => "recovery_code_used",
+      SecurityEventType::RecoveryCodesRegenerated => "recovery_codes_regenerated",
+      SecurityEventType::PasskeyAdded => "passkey_added",
+      SecurityEventType::PasskeyRemoved => "passkey_removed",
+      SecurityEventType::AccessTokenCreated => "access_token_created",
+      SecurityEventType::AccessTokenRevoked => "access_token_revoked",
+      SecurityEventType::OauthLinked => "oauth_linked",
+      SecurityEventType::OauthUnlinked => "oauth_unlinked",
+      SecurityEventType::QrApproved => "qr_approved",
+      SecurityEventType::TodoPermissionsChanged => "todo_permissions_changed",
+      SecurityEventType::TodoOwnershipTransferred => "todo_ownership_transferred",
+      SecurityEventType::HardDelete => "hard_delete",
+      SecurityEventType::AdminHardDelete => "admin_hard_delete",
+    }
+  }
+}
+/// Append-only record of an authentication or permission change.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("security_events")]
+#[timestamp]
+#[index("user_id", 1)]
+#[index("event_type", 1)]
+#[index("timestamp", 1)]
+pub struct SecurityEventEntity {
+  pub id: Option<String>,
+  pub event_type: SecurityEventType,
+  /// Account the event is about
+  #[serde(default)]
+  pub user_id: Option<String>,
+  /// Account that performed the action, when different from `user_id`
+  #[serde(default)]
+  pub actor_id: Option<String>,
+  #[serde(default)]
+  pub target_type: Option<String>,
+  #[serde(default)]
+  pub target_id: Option<String>,
+  pub success: bool,
+  #[serde(default)]
+  pub device: String,
+  #[serde(default)]
+  pub details: Value,
+  /// Unix milliseconds, used for date range queries
+  pub timestamp: i64,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+impl SecurityEventEntity {
+  /// Events start out successful; `SecurityAuditService::record_outcome` marks failures.
+  pub fn new(event_type: SecurityEventType) -> Self {
+    let now = Utc::now();
+    SecurityEventEntity {
+      id: None,
+      event_type,
+      user_id: None,
+      actor_id: None,
+      target_type: None,
+      target_id: None,
+      success: true,
+      device: String::new(),
+      details: Value::Null,
+      timestamp: now.timestamp_millis(),
+      created_at: Some(now),
+      updated_at: Some(now),
+    }
+  }
+  pub fn user(mut self, user_id: Option<&str>) -> Self {
+    self.user_id = user_id.filter(|s| !s.is_empty()).map(|s| s.to_string());
+    self
+  }
+  pub fn actor(mut self, actor_id: Option<&str>) -> Self {
+    self.actor_id = actor_id.filter(|s| !s.is_empty()).map(|s| s.to_string());
+    self
+  }
+  pub fn target(mut self, target_type: &str, target_id: &str) -> Self {
+    self.target_type = Some(target_type.to_string());
+    self.target_id = Some(target_id.to_string());
+    self
+  }
+  pub fn details(mut self, details: Value) -> Self {
+    self.details = details;
+    self
+  }
+}
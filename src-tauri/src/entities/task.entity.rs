@@ -58,6 +58,9 @@ pub struct TaskEntity {
   pub subtasks_count: i32,
   pub completed_subtasks_count: i32,
   pub comments_count: i32,
+  /// Set by the overdue sweep when the todo's policy only flags late tasks.
+  #[serde(default)]
+  pub overdue_since: Option<String>,
   #[serde(default)]
   pub created_at: Option<DateTime<Utc>>,
   #[serde(default)]
@@ -104,6 +107,7 @@ impl From<TaskCreateModel> for TaskEntity {
       subtasks_count: 0,
       completed_subtasks_count: 0,
       comments_count: 0,
+      overdue_since: None,
       deleted_at: None,
       created_at: None,
       updated_at: None,
@@ -7,6 +7,28 @@ use nosql_orm::Model;
 use nosql_orm::Validate;
 /* helpers */
 use crate::utils::common::format_date;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverdueAction {
+  /// Set the task's status to `failed`.
+  Fail,
+  /// Move the task's dates forward by `roll_over_days`.
+  RollOver,
+  /// Leave the task pending and mark it with `overdue_since`.
+  Flag,
+}
+/// Opt-in handling of tasks still pending after their `end_date` plus a grace period.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OverduePolicy {
+  pub action: OverdueAction,
+  #[serde(default)]
+  pub grace_hours: i64,
+  #[serde(default = "default_roll_over_days")]
+  pub roll_over_days: i64,
+}
+fn default_roll_over_days() -> i64 {
+  1
+}
 #[derive(Debug, Clone, Serialize, Deserialize, Model, Validate)]
 #[table_name("todos")]
 #[soft_delete]
@@ -40,6 +62,8 @@ pub struct TodoEntity {
   pub tasks_count: i32,
   pub completed_tasks_count: i32,
   #[serde(default)]
+  pub overdue_policy: Option<OverduePolicy>,
+  #[serde(default)]
   pub created_at: Option<DateTime<Utc>>,
   #[serde(default)]
   pub updated_at: Option<DateTime<Utc>>,
@@ -68,6 +92,8 @@ pub struct TodoCreateModel {
   pub order: i32,
   pub github_repo_id: Option<String>,
   pub github_repo_name: Option<String>,
+  #[serde(default)]
+  pub overdue_policy: Option<OverduePolicy>,
 }
 impl From<TodoCreateModel> for TodoEntity {
   fn from(value: TodoCreateModel) -> Self {
@@ -90,6 +116,7 @@ impl From<TodoCreateModel> for TodoEntity {
       github_repo_name: value.github_repo_name,
       tasks_count: 0,
       completed_tasks_count: 0,
+      overdue_policy: value.overdue_policy,
       deleted_at: None,
       created_at: None,
       updated_at: None,
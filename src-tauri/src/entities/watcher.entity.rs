@@ -0,0 +1,45 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+/* nosql_orm */
+use nosql_orm::Model;
+/// How a user came to watch something.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchSource {
+  #[default]
+  Manual,
+  Commented,
+  Assigned,
+}
+/// A user following a todo or task. The id is `<entity>:<target_id>:<user_id>`, so watching
+/// twice keeps one record. Unwatching keeps the record with `active: false`, which stops a
+/// later comment or assignment from subscribing the user again.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("watchers")]
+#[timestamp]
+#[index("user_id", 1)]
+#[index("target_id", 1)]
+pub struct WatcherEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  /// `todos` or `tasks`
+  pub entity: String,
+  pub target_id: String,
+  #[serde(default)]
+  pub source: WatchSource,
+  #[serde(default = "default_active")]
+  pub active: bool,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+fn default_active() -> bool {
+  true
+}
+impl WatcherEntity {
+  pub fn id_for(entity: &str, target_id: &str, user_id: &str) -> String {
+    format!("{}:{}:{}", entity, target_id, user_id)
+  }
+}
@@ -0,0 +1,131 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+/* nosql_orm */
+use nosql_orm::Model;
+/// Events a webhook can subscribe to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WebhookEvent {
+  #[serde(rename = "task.created")]
+  TaskCreated,
+  #[serde(rename = "task.completed")]
+  TaskCompleted,
+  #[serde(rename = "comment.created")]
+  CommentCreated,
+  /// A todo became shared or public
+  #[serde(rename = "todo.shared")]
+  TodoShared,
+  /// Sent only when testing a webhook, whatever it subscribes to
+  #[serde(rename = "webhook.ping")]
+  Ping,
+}
+impl WebhookEvent {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      WebhookEvent::TaskCreated => "task.created",
+      WebhookEvent::TaskCompleted => "task.completed",
+      WebhookEvent::CommentCreated => "comment.created",
+      WebhookEvent::TodoShared => "todo.shared",
+      WebhookEvent::Ping => "webhook.ping",
+    }
+  }
+}
+/// An outbound webhook subscription. A user's webhook only receives events about todos its
+/// owner can view; a `global` one, which only admins may create, receives every event. The
+/// signing secret is kept apart in [`WebhookSecretEntity`].
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("webhooks")]
+#[timestamp]
+#[index("user_id", 1)]
+pub struct WebhookEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  pub url: String,
+  #[serde(default)]
+  pub events: Vec<WebhookEvent>,
+  #[serde(default = "default_active")]
+  pub active: bool,
+  #[serde(default)]
+  pub global: bool,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+fn default_active() -> bool {
+  true
+}
+/// Key for the `X-TaskFlow-Signature` HMAC of the webhook with the same id. Only the
+/// owner's devices, which send the deliveries, ever load it.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("webhook_secrets")]
+#[timestamp]
+#[index("user_id", 1)]
+pub struct WebhookSecretEntity {
+  pub id: Option<String>,
+  pub user_id: String,
+  pub secret: String,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+/// Fields accepted when creating or updating a webhook. A missing secret is generated on
+/// create and left unchanged on update.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookInput {
+  pub url: String,
+  pub events: Vec<WebhookEvent>,
+  #[serde(default)]
+  pub secret: Option<String>,
+  #[serde(default)]
+  pub active: Option<bool>,
+  #[serde(default)]
+  pub global: bool,
+}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+  /// Not sent yet, or waiting for its next retry
+  #[default]
+  Pending,
+  Delivered,
+  /// Gave up after the last retry
+  Failed,
+}
+/// One event sent, or still to be sent, to one webhook. Kept as the delivery log.
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+#[table_name("webhook_deliveries")]
+#[timestamp]
+#[index("webhook_id", 1)]
+#[index("user_id", 1)]
+#[index("status", 1)]
+pub struct WebhookDeliveryEntity {
+  pub id: Option<String>,
+  pub webhook_id: String,
+  /// Owner of the webhook; only their devices send the delivery
+  #[serde(default)]
+  pub user_id: String,
+  pub event: WebhookEvent,
+  /// The record the event is about
+  #[serde(default)]
+  pub payload: Value,
+  #[serde(default)]
+  pub status: WebhookDeliveryStatus,
+  #[serde(default)]
+  pub attempts: u32,
+  #[serde(default)]
+  pub next_attempt_at: Option<DateTime<Utc>>,
+  /// HTTP status of the last attempt, when the receiver answered
+  #[serde(default)]
+  pub response_status: Option<u16>,
+  #[serde(default)]
+  pub last_error: Option<String>,
+  #[serde(default)]
+  pub delivered_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub updated_at: Option<DateTime<Utc>>,
+}
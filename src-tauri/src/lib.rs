@@ -8,8 +8,10 @@ mod services;
 mod utils;
 /* sys lib */
 use crate::repositories::data_provider::DataProvider;
+use crate::repositories::email_provider::EmailProvider;
 use std::sync::Arc;
 use tauri::{Manager, State};
+use tokio::net::TcpListener;
 /* utils */
 use crate::utils::{activity_log::ActivityLogHelper, config::ConfigHelper};
 /* commands */
@@ -17,6 +19,10 @@ use commands::{
   archive_command::{
     get_all_archive_data, get_all_archive_paginated, permanent_delete, soft_delete,
   },
+  attachment_command::{
+    delete_attachment, download_attachment, get_attachment_usage, get_attachments,
+    upload_attachment,
+  },
   update_command::{downloadUpdate, getBinaryNameFile, getCurrentVersion, installUpdate, openFile},
 };
 /* commands */
@@ -30,57 +36,105 @@ use commands::{
     upsert_to_json, upsert_to_mongo,
   },
   auth_command::{
-    change_password, check_token, disable_totp, enable_totp, get_user_security_status,
-    github_check_device_flow, github_create_comment, github_create_issue, github_disconnect,
-    github_get_connection_status, github_get_repos, github_oauth_callback, github_oauth_url,
-    github_start_device_flow, github_update_issue, initialize_user_data, login, qr_approve,
-    qr_generate, qr_generate_for_desktop, qr_login_complete, qr_status, qr_toggle, register,
-    request_password_reset, reset_password, setup_totp, use_recovery_code, verify_code,
+    access_token_create, access_token_list, access_token_revoke, change_password, check_token,
+    disable_totp, enable_totp, get_user_security_status, github_check_device_flow,
+    github_create_comment, github_create_issue, github_disconnect, github_get_connection_status,
+    github_get_repos, github_oauth_callback, github_oauth_url, github_start_device_flow,
+    github_update_issue, initialize_user_data, login, logout, oauth_finish, oauth_identities,
+    oauth_link_start, oauth_login_start, oauth_providers, oauth_unlink, passkey_list,
+    passkey_login_finish, passkey_login_start, passkey_registration_finish,
+    passkey_registration_start, passkey_remove, passkey_rename, qr_approve, qr_generate,
+    qr_generate_for_desktop, qr_login_complete, qr_status, qr_toggle, regenerate_recovery_codes,
+    register, request_password_reset, reset_password, setup_totp, use_recovery_code, verify_code,
     verify_login_totp,
   },
   category_command::{
     create_category, delete_category, get_categories, get_category, update_category,
   },
+  chat_command::{
+    chat_realtime_endpoint, chat_typing, discard_chat_outbox_entry, get_chat_message_context,
+    get_chat_outbox, get_chat_retention, get_chat_retention_log, get_chat_task_previews,
+    get_chat_unread_counts, get_group_presence, get_message_read_receipts, get_message_revisions,
+    get_my_mentions, get_room_presence, get_task_chat_backlinks, mark_room_read,
+    presence_heartbeat, remove_chat_retention, search_chat_messages, set_chat_retention,
+    sync_chat_outbox,
+  },
   crud_command::crud_execute,
+  digest_command::{get_digest_settings, preview_digest, set_digest_settings},
   group_command::{
-    add_group_members, add_message_reaction, create_group, delete_group, delete_group_cascade,
-    delete_message, delete_room_messages, edit_message, ensure_rooms_for_groups, get_group_by_room,
-    get_groups, get_messages_by_room, hard_delete_message, hard_delete_room_messages,
-    mark_message_read, remove_group_members, remove_message_reaction, send_message, update_group,
+    add_group_members, add_message_reaction, create_group, create_group_invite,
+    decide_group_join_request, delete_group, delete_group_cascade, delete_message,
+    delete_room_messages, edit_message, ensure_rooms_for_groups, get_group_by_room,
+    get_group_invites, get_group_join_requests, get_group_moderation_log, get_groups,
+    get_message_thread, get_messages_by_room, hard_delete_message, hard_delete_room_messages,
+    mark_message_read, mute_group_member, redeem_group_invite, remove_group_members,
+    remove_message_reaction, request_group_join, revoke_group_invite, send_message,
+    set_group_member_role, transfer_group_ownership, unmute_group_member, update_group,
+  },
+  notification_command::{
+    get_notification_preferences, set_notification_mute, set_notification_preferences,
   },
   profile_command::{create_profile, delete_profile, get_profile, get_profiles, update_profile},
+  reminder_command::{
+    dismiss_reminder, get_reminder_settings, get_reminders, get_task_reminders,
+    set_reminder_settings, set_task_reminders, snooze_reminder,
+  },
   room_command::{create_room, delete_room, get_room, get_rooms, update_room},
+  security_command::{export_security_events, get_security_events},
   stats_command::statistics_get,
   subtask_command::{create_subtask, delete_subtask, get_subtask, get_subtasks, update_subtask},
   task_command::{create_task, delete_task, get_task, get_tasks, update_task},
   todo_command::{
     change_todo_visibility, create_todo, delete_todo, get_todo, get_todo_permissions, get_todos,
-    transfer_todo_ownership, update_todo, update_todo_permissions,
+    set_todo_overdue_policy, transfer_todo_ownership, update_todo, update_todo_permissions,
+  },
+  watcher_command::{get_watched_items, get_watchers, unwatch_item, watch_item},
+  webhook_command::{
+    create_webhook, delete_webhook, get_webhook_deliveries, get_webhooks, redeliver_webhook,
+    test_webhook, update_webhook,
   },
 };
 /* services */
 use services::{
   about_service::AboutService,
   activity_monitor_service::ActivityMonitorService,
-  auth::{auth_data_sync::AuthDataSyncService, auth_qr::QrAuthService, auth_totp::AuthTotpService},
+  attachment_service::AttachmentService,
+  auth::{
+    auth_access_token::AuthAccessTokenService, auth_data_sync::AuthDataSyncService,
+    auth_oauth::AuthOAuthService, auth_passkey::AuthPasskeyService, auth_qr::QrAuthService,
+    auth_totp::AuthTotpService,
+  },
   auth_service::AuthService,
   cascade::{CascadeService, CountService},
   category_service::CategoryService,
+  chat_presence_service::PresenceService,
+  chat_read_service::ChatReadService,
+  chat_retention_service::ChatRetentionService,
   chat_service::ChatService,
+  chat_socket_service::{ChatSocketServer, ServiceChatBackend},
   crud_service::CrudService,
+  digest_service::DigestService,
   entity_resolution_service::EntityResolutionService,
+  group_invite_service::GroupInviteService,
   group_service::GroupService,
   manage_db_service::ManageDbService,
-  notification_service::NotificationService,
+  mention_service::MentionService,
+  notification_service::{NotificationPageQuery, NotificationService},
+  overdue_service::OverdueService,
   profile::profile_sync_unified::ProfileSyncUnifiedService,
   profile_service::ProfileService,
+  reminder_service::ReminderService,
   repository::service::RepositoryService,
   room_service::RoomService,
+  security_audit_service::SecurityAuditService,
   statistics_service::StatisticsService,
   subtask_service::SubtaskService,
+  task_link_service::TaskLinkService,
   task_service::TaskService,
   todo_service::TodoService,
   user::user_sync::UserSyncService,
+  watcher_service::WatcherService,
+  webhook_service::WebhookService,
 };
 /* nosql_orm */
 use crate::models::response::ResponseModel;
@@ -120,10 +174,11 @@ async fn search_data(
   load: Option<String>,
   _offline: Option<bool>,
 ) -> Result<ResponseModel, String> {
-  use crate::utils::auth::extract_user_from_token;
-  let user_id = extract_user_from_token(
+  use crate::utils::auth::extract_user_for_command;
+  let user_id = extract_user_for_command(
     token.as_deref().unwrap_or(""),
     &state.config.config_helper.jwt_secret,
+    "search_data",
   )
   .ok();
   let filter = if !query.is_empty() {
@@ -150,17 +205,29 @@ async fn search_data(
     .await
     .map_err(|e| e.message)
 }
+/// Returns `{ items, next_cursor, unread_count, unread_events }`. Pass `next_cursor` back as
+/// `cursor` for the next page; `page` is an offset used only without a cursor.
 #[tauri::command]
 async fn get_notifications(
   state: State<'_, AppState>,
   user_id: String,
   page: Option<u64>,
   limit: Option<u64>,
+  cursor: Option<String>,
+  unread_only: Option<bool>,
 ) -> Result<ResponseModel, String> {
   state
     .system
     .notification_service
-    .get_by_user(&user_id, "private", page, limit)
+    .get_by_user(
+      &user_id,
+      NotificationPageQuery {
+        cursor,
+        skip: page,
+        limit,
+        unread_only: unread_only.unwrap_or(false),
+      },
+    )
     .await
     .map_err(|e| e.message)
 }
@@ -168,13 +235,11 @@ async fn get_notifications(
 async fn create_notification(
   state: State<'_, AppState>,
   data: serde_json::Value,
-  visibility: Option<String>,
 ) -> Result<ResponseModel, String> {
-  let vis = visibility.unwrap_or_else(|| "private".to_string());
   state
     .system
     .notification_service
-    .create(data, &vis)
+    .create(data)
     .await
     .map_err(|e| e.message)
 }
@@ -182,13 +247,11 @@ async fn create_notification(
 async fn mark_notification_read(
   state: State<'_, AppState>,
   id: String,
-  visibility: Option<String>,
 ) -> Result<ResponseModel, String> {
-  let vis = visibility.unwrap_or_else(|| "private".to_string());
   state
     .system
     .notification_service
-    .mark_as_read(&id, &vis)
+    .mark_as_read(&id)
     .await
     .map_err(|e| e.message)
 }
@@ -196,13 +259,11 @@ async fn mark_notification_read(
 async fn mark_all_notifications_read(
   state: State<'_, AppState>,
   user_id: String,
-  visibility: Option<String>,
 ) -> Result<ResponseModel, String> {
-  let vis = visibility.unwrap_or_else(|| "private".to_string());
   state
     .system
     .notification_service
-    .mark_all_as_read(&user_id, &vis)
+    .mark_all_as_read(&user_id)
     .await
     .map_err(|e| e.message)
 }
@@ -210,13 +271,11 @@ async fn mark_all_notifications_read(
 async fn delete_notification(
   state: State<'_, AppState>,
   id: String,
-  visibility: Option<String>,
 ) -> Result<ResponseModel, String> {
-  let vis = visibility.unwrap_or_else(|| "private".to_string());
   state
     .system
     .notification_service
-    .delete(&id, &vis)
+    .delete(&id)
     .await
     .map_err(|e| e.message)
 }
@@ -224,16 +283,44 @@ async fn delete_notification(
 async fn clear_all_notifications(
   state: State<'_, AppState>,
   user_id: String,
-  visibility: Option<String>,
 ) -> Result<ResponseModel, String> {
-  let vis = visibility.unwrap_or_else(|| "private".to_string());
   state
     .system
     .notification_service
-    .clear_all(&user_id, &vis)
+    .clear_all(&user_id)
+    .await
+    .map_err(|e| e.message)
+}
+/// Replays notification changes queued while offline, e.g. right after reconnecting.
+#[tauri::command]
+async fn sync_notifications(state: State<'_, AppState>) -> Result<ResponseModel, String> {
+  state
+    .system
+    .notification_service
+    .flush_outbox()
     .await
     .map_err(|e| e.message)
 }
+/// How often personal access tokens are reloaded, so revocations elsewhere take effect.
+const ACCESS_TOKEN_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// How often chat changes made on other devices are relayed to realtime subscribers.
+const CHAT_RELAY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+/// How often presence and typing are shared with other devices and stale typing cleared.
+const PRESENCE_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+/// How often queued offline chat changes are retried.
+const CHAT_OUTBOX_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// How often chat retention policies are enforced.
+const CHAT_RETENTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// How often todo overdue policies are applied.
+const OVERDUE_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+/// How often due-date reminders are checked.
+const REMINDER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// How often queued offline notification changes are retried.
+const NOTIFICATION_OUTBOX_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// How often due digests are looked for.
+const DIGEST_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// How often pending webhook deliveries and retries are sent.
+const WEBHOOK_DELIVERY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
 pub struct AppState {
   pub logger: Arc<()>,
   pub config: ConfigState,
@@ -251,7 +338,11 @@ pub struct AuthState {
   pub auth_service: Arc<AuthService>,
   pub totp_service: Arc<AuthTotpService>,
   pub qr_auth_service: Arc<QrAuthService>,
+  pub passkey_service: Arc<AuthPasskeyService>,
+  pub access_token_service: Arc<AuthAccessTokenService>,
+  pub oauth_service: Arc<AuthOAuthService>,
   pub auth_data_sync_service: Arc<AuthDataSyncService>,
+  pub security_audit_service: Arc<SecurityAuditService>,
 }
 pub struct DataState {
   pub repository_service: Arc<RepositoryService>,
@@ -261,16 +352,30 @@ pub struct DataState {
   pub category_service: Arc<CategoryService>,
   pub cascade_service: CascadeService,
   pub crud_service: Arc<CrudService>,
+  pub attachment_service: Arc<AttachmentService>,
+  pub overdue_service: Arc<OverdueService>,
+  pub watcher_service: Arc<WatcherService>,
 }
 pub struct ChatState {
   pub chat_service: Arc<ChatService>,
   pub group_service: Arc<GroupService>,
+  pub group_invite_service: Arc<GroupInviteService>,
   pub room_service: Arc<RoomService>,
+  pub presence_service: Arc<PresenceService>,
+  pub chat_read_service: Arc<ChatReadService>,
+  pub chat_retention_service: Arc<ChatRetentionService>,
+  pub task_link_service: Arc<TaskLinkService>,
+  pub mention_service: Arc<MentionService>,
+  /// The chat WebSocket address, or why the channel is unavailable.
+  pub realtime_url: Result<String, String>,
 }
 pub struct SystemState {
   pub about_service: Arc<AboutService>,
   pub manage_db_service: Arc<ManageDbService>,
   pub notification_service: Arc<NotificationService>,
+  pub reminder_service: Arc<ReminderService>,
+  pub digest_service: Arc<DigestService>,
+  pub webhook_service: Arc<WebhookService>,
   pub profile_service: Arc<ProfileService>,
   pub statistics_service: Arc<StatisticsService>,
 }
@@ -356,16 +461,71 @@ pub fn run() {
         json_provider.clone(),
         mongodb_provider.clone(),
       ));
-      let repository_service = Arc::new(RepositoryService::new(
+      let watcher_service = Arc::new(WatcherService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+      ));
+      let notification_service = Arc::new(
+        NotificationService::new(json_provider.clone(), mongodb_provider.clone())
+          .with_app_handle(app.handle().clone())
+          .with_watchers(watcher_service.clone()),
+      );
+      {
+        let notification_service = notification_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            tokio::time::sleep(NOTIFICATION_OUTBOX_INTERVAL).await;
+            let _ = notification_service.flush_outbox().await;
+          }
+        });
+      }
+      let mention_service = Arc::new(MentionService::new(
         json_provider.clone(),
         mongodb_provider.clone(),
-        cascade_service.clone(),
-        count_service.clone(),
-        entity_resolution.clone(),
-        activity_monitor,
-        profile_service.as_ref().clone(),
-        app.handle().clone(),
       ));
+      let webhook_service = Arc::new(WebhookService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+      ));
+      {
+        let webhook_service = webhook_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            tokio::time::sleep(WEBHOOK_DELIVERY_INTERVAL).await;
+            webhook_service.deliver_due().await;
+          }
+        });
+      }
+      let repository_service = Arc::new(
+        RepositoryService::new(
+          json_provider.clone(),
+          mongodb_provider.clone(),
+          cascade_service.clone(),
+          count_service.clone(),
+          entity_resolution.clone(),
+          activity_monitor,
+          profile_service.as_ref().clone(),
+          app.handle().clone(),
+        )
+        .with_notifications(notification_service.clone())
+        .with_mentions(mention_service.clone())
+        .with_watchers(watcher_service.clone())
+        .with_webhooks(webhook_service.clone()),
+      );
+      let overdue_service = Arc::new(OverdueService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+        repository_service.clone(),
+      ));
+      {
+        let overdue_service = overdue_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            overdue_service.sweep().await;
+            tokio::time::sleep(OVERDUE_SWEEP_INTERVAL).await;
+          }
+        });
+      }
       let crud_service = Arc::new(CrudService::new(json_provider.clone()));
       let data_provider = DataProvider::Json(Arc::new(json_provider.clone()));
       let mongo_data_provider: Option<DataProvider> = mongodb_provider
@@ -387,18 +547,131 @@ pub fn run() {
         data_provider.clone(),
         mongo_data_provider.clone(),
       ));
-      let chat_service = Arc::new(ChatService::new(
+      let task_link_service = Arc::new(TaskLinkService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+      ));
+      let chat_service = Arc::new(
+        ChatService::new(data_provider.clone(), mongo_data_provider.clone())
+          .with_task_links(task_link_service.clone())
+          .with_mentions(mention_service.clone()),
+      );
+      let group_service = Arc::new(
+        GroupService::new(data_provider.clone(), mongo_data_provider.clone())
+          .with_notifications(notification_service.clone()),
+      );
+      let room_service = Arc::new(RoomService::new(
         data_provider.clone(),
         mongo_data_provider.clone(),
       ));
-      let group_service = Arc::new(GroupService::new(
+      let group_invite_service = Arc::new(GroupInviteService::new(
         data_provider.clone(),
         mongo_data_provider.clone(),
+        group_service.clone(),
       ));
-      let room_service = Arc::new(RoomService::new(
+      let chat_read_service = Arc::new(ChatReadService::new(
         data_provider.clone(),
         mongo_data_provider.clone(),
+        chat_service.clone(),
       ));
+      {
+        let chat_service = chat_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            tokio::time::sleep(CHAT_OUTBOX_INTERVAL).await;
+            let _ = chat_service.flush_outbox().await;
+          }
+        });
+      }
+      {
+        let chat_service = chat_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            tokio::time::sleep(CHAT_RELAY_INTERVAL).await;
+            chat_service.relay_remote_changes().await;
+          }
+        });
+      }
+      let attachment_service = Arc::new(AttachmentService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+        app_data_dir.join("attachments"),
+        config_helper.attachment_quota_mb * 1024 * 1024,
+      ));
+      let chat_retention_service = Arc::new(ChatRetentionService::new(
+        data_provider.clone(),
+        mongo_data_provider.clone(),
+        chat_service.clone(),
+        group_service.clone(),
+        room_service.clone(),
+      ));
+      {
+        let chat_retention_service = chat_retention_service.clone();
+        let attachment_service = attachment_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            if let Ok((_, true)) = chat_retention_service.run().await {
+              let _ = attachment_service.collect_garbage().await;
+            }
+            tokio::time::sleep(CHAT_RETENTION_INTERVAL).await;
+          }
+        });
+      }
+      let reminder_service = Arc::new(ReminderService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+        notification_service.clone(),
+      ));
+      {
+        let reminder_service = reminder_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            reminder_service.run().await;
+            tokio::time::sleep(REMINDER_INTERVAL).await;
+          }
+        });
+      }
+      let presence_service = Arc::new(PresenceService::new(
+        chat_service.events().clone(),
+        room_service.clone(),
+        group_service.clone(),
+        mongodb_provider.clone(),
+      ));
+      {
+        let presence_service = presence_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            tokio::time::sleep(PRESENCE_SYNC_INTERVAL).await;
+            presence_service.sync().await;
+          }
+        });
+      }
+      let realtime_url = if config_helper.chat_ws_addr.is_empty() {
+        Err("Realtime chat is not available".to_string())
+      } else {
+        match tauri::async_runtime::block_on(TcpListener::bind(&config_helper.chat_ws_addr)) {
+          Ok(listener) => {
+            let url = listener
+              .local_addr()
+              .map(|addr| format!("ws://{}", addr))
+              .map_err(|e| format!("Realtime chat is not available: {}", e));
+            let backend = Arc::new(ServiceChatBackend::new(
+              chat_service.clone(),
+              room_service.clone(),
+              group_service.clone(),
+              presence_service.clone(),
+              config_helper.jwt_secret.clone(),
+            ));
+            let server = ChatSocketServer::new(backend, chat_service.events().clone());
+            tauri::async_runtime::spawn(server.serve(listener));
+            url
+          }
+          Err(e) => Err(format!(
+            "Realtime chat could not listen on {}: {}",
+            config_helper.chat_ws_addr, e
+          )),
+        }
+      };
       let auth_service = Arc::new(AuthService::new(
         json_provider.clone(),
         mongodb_provider.clone(),
@@ -417,7 +690,54 @@ pub fn run() {
         mongodb_provider.clone(),
         auth_service.token_service.clone(),
       ));
+      let passkey_service = Arc::new(AuthPasskeyService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+        auth_service.token_service.clone(),
+        config_helper.rp_domain.clone(),
+        config_helper.name_app.clone(),
+      ));
+      let oauth_service = Arc::new(AuthOAuthService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+        auth_service.token_service.clone(),
+        &config_helper.oauth_providers,
+      ));
+      let access_token_service = Arc::new(AuthAccessTokenService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+      ));
+      tauri::async_runtime::block_on(access_token_service.load_grants());
+      {
+        let access_token_service = access_token_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            tokio::time::sleep(ACCESS_TOKEN_REFRESH_INTERVAL).await;
+            access_token_service.load_grants().await;
+          }
+        });
+      }
+      let security_audit_service = Arc::new(SecurityAuditService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+      ));
       let statistics_service = Arc::new(StatisticsService::new(json_provider.clone()));
+      let digest_service = Arc::new(DigestService::new(
+        json_provider.clone(),
+        mongodb_provider.clone(),
+        statistics_service.clone(),
+        notification_service.clone(),
+        EmailProvider::from_config(&config_helper).ok(),
+      ));
+      {
+        let digest_service = digest_service.clone();
+        tauri::async_runtime::spawn(async move {
+          loop {
+            digest_service.run().await;
+            tokio::time::sleep(DIGEST_INTERVAL).await;
+          }
+        });
+      }
       let manage_db_service = Arc::new(ManageDbService::new(
         json_provider.clone(),
         mongodb_provider.clone(),
@@ -425,10 +745,6 @@ pub fn run() {
         config_helper.mongo_db_uri.clone(),
         config_helper.mongo_db_name.clone(),
       ));
-      let notification_service = Arc::new(NotificationService::new(
-        json_provider.clone(),
-        mongodb_provider.clone(),
-      ));
       app.manage(AppState {
         logger: Arc::new(()),
         config: ConfigState {
@@ -440,7 +756,11 @@ pub fn run() {
           auth_service,
           totp_service,
           qr_auth_service,
+          passkey_service,
+          access_token_service,
+          oauth_service,
           auth_data_sync_service,
+          security_audit_service,
         },
         data: DataState {
           repository_service,
@@ -450,16 +770,29 @@ pub fn run() {
           category_service,
           cascade_service,
           crud_service,
+          attachment_service,
+          overdue_service,
+          watcher_service,
         },
         chat: ChatState {
           chat_service,
           group_service,
+          group_invite_service,
           room_service,
+          presence_service,
+          chat_read_service,
+          chat_retention_service,
+          task_link_service,
+          mention_service,
+          realtime_url,
         },
         system: SystemState {
           about_service,
           manage_db_service,
           notification_service,
+          reminder_service,
+          digest_service,
+          webhook_service,
           profile_service,
           statistics_service,
         },
@@ -470,8 +803,35 @@ pub fn run() {
     .plugin(tauri_plugin_shell::init())
     .plugin(tauri_plugin_mcp_bridge::Builder::new().build())
     .invoke_handler(tauri::generate_handler![
+      chat_realtime_endpoint,
+      upload_attachment,
+      download_attachment,
+      get_attachments,
+      delete_attachment,
+      get_attachment_usage,
+      presence_heartbeat,
+      chat_typing,
+      get_room_presence,
+      get_group_presence,
+      sync_chat_outbox,
+      get_chat_outbox,
+      discard_chat_outbox_entry,
+      search_chat_messages,
+      mark_room_read,
+      get_message_read_receipts,
+      get_chat_unread_counts,
+      get_message_revisions,
+      get_chat_retention,
+      set_chat_retention,
+      remove_chat_retention,
+      get_chat_retention_log,
+      get_chat_task_previews,
+      get_task_chat_backlinks,
+      get_chat_message_context,
+      get_my_mentions,
       check_token,
       login,
+      logout,
       register,
       request_password_reset,
       reset_password,
@@ -482,6 +842,7 @@ pub fn run() {
       verify_login_totp,
       disable_totp,
       use_recovery_code,
+      regenerate_recovery_codes,
       get_user_security_status,
       qr_generate,
       qr_generate_for_desktop,
@@ -489,6 +850,24 @@ pub fn run() {
       qr_status,
       qr_toggle,
       qr_login_complete,
+      passkey_registration_start,
+      passkey_registration_finish,
+      passkey_login_start,
+      passkey_login_finish,
+      passkey_list,
+      passkey_rename,
+      passkey_remove,
+      access_token_create,
+      access_token_list,
+      access_token_revoke,
+      oauth_providers,
+      oauth_login_start,
+      oauth_link_start,
+      oauth_finish,
+      oauth_identities,
+      oauth_unlink,
+      get_security_events,
+      export_security_events,
       export_to_cloud,
       get_tasks_by_month,
       check_mongodb_connection,
@@ -544,6 +923,13 @@ pub fn run() {
       get_rooms,
       create_room,
       update_room,
+      dismiss_reminder,
+      get_reminder_settings,
+      get_reminders,
+      get_task_reminders,
+      set_reminder_settings,
+      set_task_reminders,
+      snooze_reminder,
       delete_room,
       get_group_by_room,
       get_groups,
@@ -551,10 +937,23 @@ pub fn run() {
       update_group,
       add_group_members,
       remove_group_members,
+      set_group_member_role,
+      transfer_group_ownership,
+      mute_group_member,
+      unmute_group_member,
+      get_group_moderation_log,
+      create_group_invite,
+      get_group_invites,
+      revoke_group_invite,
+      redeem_group_invite,
+      request_group_join,
+      get_group_join_requests,
+      decide_group_join_request,
       delete_group,
       delete_group_cascade,
       ensure_rooms_for_groups,
       get_messages_by_room,
+      get_message_thread,
       send_message,
       mark_message_read,
       delete_message,
@@ -571,6 +970,18 @@ pub fn run() {
       getCurrentVersion,
       sync_data,
       change_todo_visibility,
+      set_todo_overdue_policy,
+      watch_item,
+      unwatch_item,
+      get_watchers,
+      get_watched_items,
+      get_webhooks,
+      create_webhook,
+      update_webhook,
+      delete_webhook,
+      get_webhook_deliveries,
+      redeliver_webhook,
+      test_webhook,
       get_todo_permissions,
       update_todo_permissions,
       transfer_todo_ownership,
@@ -581,6 +992,13 @@ pub fn run() {
       mark_all_notifications_read,
       delete_notification,
       clear_all_notifications,
+      sync_notifications,
+      get_notification_preferences,
+      set_notification_mute,
+      set_notification_preferences,
+      get_digest_settings,
+      preview_digest,
+      set_digest_settings,
       get_profiles,
       get_profile,
       create_profile,
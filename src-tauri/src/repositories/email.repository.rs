@@ -20,4 +20,7 @@ impl EmailProvider {
   pub async fn send_password_reset_code(&self, _email: &str, _code: &str) -> Result<(), String> {
     Ok(())
   }
+  pub async fn send_digest(&self, _email: &str, _subject: &str, _body: &str) -> Result<(), String> {
+    Ok(())
+  }
 }
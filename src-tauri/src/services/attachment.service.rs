@@ -0,0 +1,496 @@
+/* sys lib */
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use data_encoding::HEXLOWER;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::attachment_entity::AttachmentEntity;
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::permission_service::PermissionService;
+/* helpers */
+use crate::utils::response_helper::{err_response, err_response_formatted, success_response};
+const ATTACHMENTS_TABLE: &str = "attachments";
+const MAX_FILE_BYTES: usize = 25 * 1024 * 1024;
+const THUMBNAIL_SIZE: u32 = 256;
+const OWNER_TYPES: [&str; 2] = ["chats", "comments"];
+/// Unreferenced blobs younger than this are kept, since an upload writes its blob before
+/// the attachment that points at it.
+const BLOB_MIN_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+pub struct AttachmentService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  blob_dir: PathBuf,
+  quota_bytes: u64,
+}
+impl AttachmentService {
+  pub fn new(
+    json_provider: JsonProvider,
+    mongodb_provider: Option<Arc<MongoProvider>>,
+    blob_dir: PathBuf,
+    quota_bytes: u64,
+  ) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+      blob_dir,
+      quota_bytes,
+    }
+  }
+  /// Stores the file for a chat message or comment. PNG and JPEG files must decode, since
+  /// they are saved with a thumbnail.
+  pub async fn upload(
+    &self,
+    user_id: &str,
+    owner_type: &str,
+    owner_id: &str,
+    file_name: &str,
+    mime_type: Option<&str>,
+    data_base64: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let owner = self.find_owner(owner_type, owner_id).await?;
+    let author_field = if owner_type == "chats" {
+      "sender_id"
+    } else {
+      "user_id"
+    };
+    if owner.get(author_field).and_then(|v| v.as_str()) != Some(user_id) {
+      return Err(ResponseModel::forbidden(
+        "Only the author can attach files to this item",
+      ));
+    }
+    let file_name = Path::new(file_name.trim())
+      .file_name()
+      .and_then(|n| n.to_str())
+      .filter(|n| !n.is_empty())
+      .ok_or_else(|| err_response("File name cannot be empty"))?
+      .to_string();
+    let bytes = BASE64
+      .decode(data_base64.as_bytes())
+      .map_err(|e| err_response_formatted("Invalid file data", &e.to_string()))?;
+    if bytes.is_empty() {
+      return Err(err_response("File is empty"));
+    }
+    if bytes.len() > MAX_FILE_BYTES {
+      return Err(err_response(&format!(
+        "File exceeds the {} MB limit",
+        MAX_FILE_BYTES / 1024 / 1024
+      )));
+    }
+    let used = self.used_bytes(user_id).await?;
+    if used + bytes.len() as u64 > self.quota_bytes {
+      return Err(err_response(&format!(
+        "Attachment quota exceeded ({} of {} bytes used)",
+        used, self.quota_bytes
+      )));
+    }
+    let image_format = image::guess_format(&bytes)
+      .ok()
+      .filter(|f| matches!(f, image::ImageFormat::Png | image::ImageFormat::Jpeg));
+    let mime_type = match image_format {
+      Some(format) => format.to_mime_type().to_string(),
+      None => mime_type
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or("application/octet-stream")
+        .to_string(),
+    };
+    let (thumbnail_hash, width, height) = match image_format {
+      Some(format) => {
+        let (thumb, w, h) = self.write_thumbnail(&bytes, format)?;
+        (Some(thumb), Some(w), Some(h))
+      }
+      None => (None, None, None),
+    };
+    let hash = self.write_blob(&bytes)?;
+    let now = Utc::now();
+    let attachment = AttachmentEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      owner_type: owner_type.to_string(),
+      owner_id: owner_id.to_string(),
+      uploader_id: user_id.to_string(),
+      file_name,
+      mime_type,
+      size: bytes.len() as u64,
+      hash,
+      thumbnail_hash,
+      width,
+      height,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let value = serde_json::to_value(&attachment)
+      .map_err(|e| err_response(&format!("Failed to serialize attachment: {}", e)))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = mongo.insert(ATTACHMENTS_TABLE, value.clone()).await;
+    }
+    self
+      .json_provider
+      .insert(ATTACHMENTS_TABLE, value.clone())
+      .await
+      .map_err(|e| err_response_formatted("Failed to store attachment", &e.to_string()))?;
+    Ok(success_response(value))
+  }
+  pub async fn download(
+    &self,
+    user_id: &str,
+    id: &str,
+    thumbnail: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let attachment = self.find_attachment(id).await?;
+    self.ensure_can_view(user_id, &attachment).await?;
+    let (hash, mime_type) = match (&attachment.thumbnail_hash, thumbnail) {
+      (Some(thumb), true) => (thumb.as_str(), "image/png"),
+      _ => (attachment.hash.as_str(), attachment.mime_type.as_str()),
+    };
+    let bytes = std::fs::read(self.blob_path(hash)?)
+      .map_err(|_| err_response("Attachment content is not available on this device"))?;
+    Ok(success_response(json!({
+      "id": attachment.id,
+      "file_name": attachment.file_name,
+      "mime_type": mime_type,
+      "size": bytes.len(),
+      "data": BASE64.encode(&bytes),
+    })))
+  }
+  pub async fn list(
+    &self,
+    user_id: &str,
+    owner_type: &str,
+    owner_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let attachments = self.find_by_owner(owner_type, owner_id).await?;
+    if let Some(first) = attachments.first() {
+      self.ensure_can_view(user_id, first).await?;
+    }
+    Ok(success_response(attachments))
+  }
+  pub async fn delete(&self, user_id: &str, id: &str) -> Result<ResponseModel, ResponseModel> {
+    let attachment = self.find_attachment(id).await?;
+    if attachment.uploader_id != user_id {
+      return Err(ResponseModel::forbidden(
+        "Only the uploader can delete this attachment",
+      ));
+    }
+    self.remove_metadata(id).await;
+    self.remove_unreferenced_blobs().await?;
+    Ok(success_response(json!({ "id": id })))
+  }
+  pub async fn usage(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    Ok(success_response(json!({
+      "used": self.used_bytes(user_id).await?,
+      "quota": self.quota_bytes,
+    })))
+  }
+  /// Drops attachments whose message or comment was hard-deleted, then any blob no
+  /// attachment points at any more. Soft-deleted owners keep their files.
+  pub async fn collect_garbage(&self) -> Result<ResponseModel, ResponseModel> {
+    let mut removed = 0;
+    for attachment in self.find_all().await? {
+      if self
+        .owner_is_gone(&attachment.owner_type, &attachment.owner_id)
+        .await
+      {
+        if let Some(id) = &attachment.id {
+          self.remove_metadata(id).await;
+          removed += 1;
+        }
+      }
+    }
+    let blobs = self.remove_unreferenced_blobs().await?;
+    Ok(success_response(
+      json!({ "attachments_removed": removed, "blobs_removed": blobs }),
+    ))
+  }
+  async fn find_owner(&self, owner_type: &str, owner_id: &str) -> Result<Value, ResponseModel> {
+    if !OWNER_TYPES.contains(&owner_type) {
+      return Err(err_response(
+        "Attachments can only belong to chats or comments",
+      ));
+    }
+    self
+      .find_doc(owner_type, owner_id)
+      .await
+      .ok_or_else(|| err_response("Attachment owner not found"))
+  }
+  /// True only when every store positively reports the owner missing; a failed lookup
+  /// or a store that cannot be asked (e.g. MongoDB unreachable) never counts as a deletion.
+  async fn owner_is_gone(&self, owner_type: &str, owner_id: &str) -> bool {
+    if !matches!(
+      self.json_provider.find_by_id(owner_type, owner_id).await,
+      Ok(None)
+    ) {
+      return false;
+    }
+    match &self.mongodb_provider {
+      Some(mongo) => matches!(mongo.find_by_id(owner_type, owner_id).await, Ok(None)),
+      None => false,
+    }
+  }
+  async fn find_doc(&self, table: &str, id: &str) -> Option<Value> {
+    if let Ok(Some(doc)) = self.json_provider.find_by_id(table, id).await {
+      return Some(doc);
+    }
+    match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    }
+  }
+  async fn find_one_by(&self, table: &str, field: &str, value: &str) -> Option<Value> {
+    let filter = Filter::Eq(field.to_string(), json!(value));
+    if let Ok(docs) = self
+      .json_provider
+      .find_many(table, Some(&filter), None, Some(1), None, true)
+      .await
+    {
+      if let Some(doc) = docs.into_iter().next() {
+        return Some(doc);
+      }
+    }
+    let mongo = self.mongodb_provider.as_ref()?;
+    mongo
+      .find_many(table, Some(&filter), None, Some(1), None, true)
+      .await
+      .ok()
+      .and_then(|docs| docs.into_iter().next())
+  }
+  async fn ensure_can_view(
+    &self,
+    user_id: &str,
+    attachment: &AttachmentEntity,
+  ) -> Result<(), ResponseModel> {
+    if attachment.uploader_id == user_id {
+      return Ok(());
+    }
+    let owner = self
+      .find_owner(&attachment.owner_type, &attachment.owner_id)
+      .await?;
+    let allowed = if attachment.owner_type == "chats" {
+      let room_id = owner.get("room_id").and_then(|v| v.as_str()).unwrap_or("");
+      self.is_room_member(user_id, room_id).await
+    } else {
+      self.can_view_comment(user_id, &owner).await
+    };
+    if allowed {
+      Ok(())
+    } else {
+      Err(ResponseModel::forbidden(
+        "You do not have access to this attachment",
+      ))
+    }
+  }
+  async fn is_room_member(&self, user_id: &str, room_id: &str) -> bool {
+    let contains = |doc: &Value, field: &str| {
+      doc
+        .get(field)
+        .and_then(|v| v.as_array())
+        .map(|ids| ids.iter().any(|id| id.as_str() == Some(user_id)))
+        .unwrap_or(false)
+    };
+    if let Some(room) = self.find_one_by("rooms", "room", room_id).await {
+      if contains(&room, "participant_ids") {
+        return true;
+      }
+    }
+    match self.find_one_by("groups", "room_id", room_id).await {
+      Some(group) => contains(&group, "member_ids"),
+      None => false,
+    }
+  }
+  async fn can_view_comment(&self, user_id: &str, comment: &Value) -> bool {
+    let task_id = match comment.get("task_id").and_then(|v| v.as_str()) {
+      Some(task_id) => Some(task_id.to_string()),
+      None => match comment.get("subtask_id").and_then(|v| v.as_str()) {
+        Some(subtask_id) => self.find_doc("subtasks", subtask_id).await.and_then(|s| {
+          s.get("task_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+        }),
+        None => None,
+      },
+    };
+    let Some(task) = (match task_id {
+      Some(task_id) => self.find_doc("tasks", &task_id).await,
+      None => None,
+    }) else {
+      return false;
+    };
+    let Some(todo_id) = task.get("todo_id").and_then(|v| v.as_str()) else {
+      return false;
+    };
+    match self.find_doc("todos", todo_id).await {
+      Some(todo) => PermissionService::can_view_todo(&todo, user_id),
+      None => false,
+    }
+  }
+  async fn find_attachment(&self, id: &str) -> Result<AttachmentEntity, ResponseModel> {
+    let doc = self
+      .find_doc(ATTACHMENTS_TABLE, id)
+      .await
+      .ok_or_else(|| err_response("Attachment not found"))?;
+    serde_json::from_value(doc).map_err(|e| err_response(&format!("Invalid attachment: {}", e)))
+  }
+  async fn find_by_owner(
+    &self,
+    owner_type: &str,
+    owner_id: &str,
+  ) -> Result<Vec<AttachmentEntity>, ResponseModel> {
+    Ok(
+      self
+        .find_all()
+        .await?
+        .into_iter()
+        .filter(|a| a.owner_type == owner_type && a.owner_id == owner_id)
+        .collect(),
+    )
+  }
+  /// Attachment metadata from both stores, deduplicated by id.
+  async fn find_all(&self) -> Result<Vec<AttachmentEntity>, ResponseModel> {
+    let mut docs = self
+      .json_provider
+      .find_many(ATTACHMENTS_TABLE, None, None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      if let Ok(remote) = mongo
+        .find_many(ATTACHMENTS_TABLE, None, None, None, None, true)
+        .await
+      {
+        docs.extend(remote);
+      }
+    }
+    let mut seen = HashSet::new();
+    Ok(
+      docs
+        .into_iter()
+        .filter_map(|doc| serde_json::from_value::<AttachmentEntity>(doc).ok())
+        .filter(|a| seen.insert(a.id.clone()))
+        .collect(),
+    )
+  }
+  async fn used_bytes(&self, user_id: &str) -> Result<u64, ResponseModel> {
+    Ok(
+      self
+        .find_all()
+        .await?
+        .iter()
+        .filter(|a| a.uploader_id == user_id)
+        .map(|a| a.size)
+        .sum(),
+    )
+  }
+  async fn remove_metadata(&self, id: &str) {
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = mongo.delete(ATTACHMENTS_TABLE, id).await;
+    }
+    let _ = self.json_provider.delete(ATTACHMENTS_TABLE, id).await;
+  }
+  /// Deletes blobs and leftover `.tmp` files no attachment points at, once they are older
+  /// than `BLOB_MIN_AGE`.
+  async fn remove_unreferenced_blobs(&self) -> Result<usize, ResponseModel> {
+    let referenced: HashSet<String> = self
+      .find_all()
+      .await?
+      .into_iter()
+      .flat_map(|a| std::iter::once(a.hash).chain(a.thumbnail_hash))
+      .collect();
+    let mut removed = 0;
+    let Ok(shards) = std::fs::read_dir(&self.blob_dir) else {
+      return Ok(0);
+    };
+    for shard in shards.flatten() {
+      let Ok(files) = std::fs::read_dir(shard.path()) else {
+        continue;
+      };
+      for file in files.flatten() {
+        let name = file.file_name().to_string_lossy().to_string();
+        if referenced.contains(&name) {
+          continue;
+        }
+        let age = file
+          .metadata()
+          .and_then(|meta| meta.modified())
+          .ok()
+          .and_then(|modified| modified.elapsed().ok());
+        if !age.is_some_and(|age| age >= BLOB_MIN_AGE) {
+          continue;
+        }
+        if std::fs::remove_file(file.path()).is_ok() {
+          removed += 1;
+        }
+      }
+    }
+    Ok(removed)
+  }
+  fn blob_path(&self, hash: &str) -> Result<PathBuf, ResponseModel> {
+    if !is_blob_hash(hash) {
+      return Err(err_response("Invalid attachment hash"));
+    }
+    Ok(self.blob_dir.join(&hash[..2]).join(hash))
+  }
+  /// Stores `bytes` under their SHA-256 and returns the hash; existing blobs are reused.
+  fn write_blob(&self, bytes: &[u8]) -> Result<String, ResponseModel> {
+    let hash = HEXLOWER.encode(&Sha256::digest(bytes));
+    let path = self.blob_path(&hash)?;
+    if path.exists() {
+      // Restart its age so a collection running alongside this upload keeps it
+      let _ = std::fs::File::options()
+        .append(true)
+        .open(&path)
+        .and_then(|file| file.set_modified(std::time::SystemTime::now()));
+      return Ok(hash);
+    }
+    let io_err = |e: std::io::Error| err_response_formatted("Failed to store file", &e.to_string());
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent).map_err(io_err)?;
+    }
+    // Write then rename so a crash never leaves a truncated blob under a valid hash
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes).map_err(io_err)?;
+    std::fs::rename(&tmp, &path).map_err(io_err)?;
+    Ok(hash)
+  }
+  fn write_thumbnail(
+    &self,
+    bytes: &[u8],
+    format: image::ImageFormat,
+  ) -> Result<(String, u32, u32), ResponseModel> {
+    let img = image::load_from_memory_with_format(bytes, format)
+      .map_err(|e| err_response_formatted("Invalid image", &e.to_string()))?;
+    let (width, height) = (img.width(), img.height());
+    let mut out = Cursor::new(Vec::new());
+    img
+      .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+      .write_to(&mut out, image::ImageFormat::Png)
+      .map_err(|e| err_response_formatted("Failed to create thumbnail", &e.to_string()))?;
+    let hash = self.write_blob(out.get_ref())?;
+    Ok((hash, width, height))
+  }
+}
+/// Blob names are lowercase hex SHA-256 digests; anything else could point outside the
+/// blob directory.
+fn is_blob_hash(hash: &str) -> bool {
+  hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn only_sha256_hex_is_a_blob_hash() {
+    let hash = HEXLOWER.encode(&Sha256::digest(b"content"));
+    assert!(is_blob_hash(&hash));
+    assert!(!is_blob_hash(&hash.to_uppercase()));
+    assert!(!is_blob_hash(&hash[..63]));
+    assert!(!is_blob_hash("../../../../etc/passwd"));
+    assert!(!is_blob_hash(""));
+  }
+}
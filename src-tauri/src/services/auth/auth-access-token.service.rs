@@ -0,0 +1,212 @@
+/* sys lib */
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::JsonProvider;
+use nosql_orm::providers::MongoProvider;
+use nosql_orm::query::Filter;
+/* models */
+use crate::entities::access_token_entity::AccessTokenEntity;
+use crate::models::response::{ResponseModel, ResponseStatus};
+/* helpers */
+use crate::utils::{
+  access_token::{self, AccessTokenGrant, TokenScope},
+  response_helper::{err_response, err_response_formatted, success_response},
+};
+const ACCESS_TOKENS_TABLE: &str = "personal_access_tokens";
+const MAX_TOKENS_PER_USER: usize = 50;
+const MAX_EXPIRY_DAYS: i64 = 365;
+const TOKEN_HINT_LEN: usize = 10;
+pub struct AuthAccessTokenService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+}
+impl AuthAccessTokenService {
+  pub fn new(json_provider: JsonProvider, mongodb_provider: Option<Arc<MongoProvider>>) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+    }
+  }
+  /// Replaces the in-memory grant table used by `extract_user_from_token` with the active
+  /// tokens. Run at startup and then periodically, so a token revoked on another device
+  /// stops working here too. Keeps the current table when the tokens cannot be loaded.
+  pub async fn load_grants(&self) {
+    let Ok(tokens) = self.find_tokens(None).await else {
+      return;
+    };
+    let grants: HashMap<String, AccessTokenGrant> = tokens
+      .into_iter()
+      .filter(Self::is_active)
+      .map(|token| (token.token_hash.clone(), Self::grant_for(&token)))
+      .collect();
+    access_token::replace_grants(grants);
+  }
+  pub async fn create_token(
+    &self,
+    user_id: &str,
+    profile_id: Option<&str>,
+    name: &str,
+    scopes: Vec<TokenScope>,
+    expires_in_days: Option<i64>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let name = name.trim();
+    if name.is_empty() {
+      return Err(err_response("Token name cannot be empty"));
+    }
+    if scopes.is_empty() {
+      return Err(err_response("At least one scope is required"));
+    }
+    let expires_in_days = expires_in_days.unwrap_or(90);
+    if !(1..=MAX_EXPIRY_DAYS).contains(&expires_in_days) {
+      return Err(err_response(&format!(
+        "Expiry must be between 1 and {} days",
+        MAX_EXPIRY_DAYS
+      )));
+    }
+    let existing = self.find_tokens(Some(user_id)).await?;
+    if existing.iter().filter(|t| Self::is_active(t)).count() >= MAX_TOKENS_PER_USER {
+      return Err(err_response(
+        "Access token limit reached; revoke an unused token first",
+      ));
+    }
+    let mut scopes = scopes;
+    scopes.sort_by_key(|s| s.as_str());
+    scopes.dedup();
+    let plaintext = access_token::generate_access_token();
+    let now = Utc::now();
+    let token = AccessTokenEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      user_id: user_id.to_string(),
+      profile_id: profile_id.map(|s| s.to_string()),
+      name: name.to_string(),
+      scopes,
+      token_hash: access_token::hash_access_token(&plaintext),
+      token_hint: plaintext.chars().take(TOKEN_HINT_LEN).collect(),
+      expires_at: Some(now + Duration::days(expires_in_days)),
+      revoked_at: None,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let token_val = serde_json::to_value(&token)
+      .map_err(|e| err_response(&format!("Failed to serialize access token: {}", e)))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      mongo
+        .insert(ACCESS_TOKENS_TABLE, token_val.clone())
+        .await
+        .map_err(|e| err_response_formatted("Failed to store access token", &e.to_string()))?;
+    }
+    self
+      .json_provider
+      .insert(ACCESS_TOKENS_TABLE, token_val)
+      .await
+      .map_err(|e| err_response_formatted("Failed to store access token", &e.to_string()))?;
+    access_token::register_grant(token.token_hash.clone(), Self::grant_for(&token));
+    let mut view = Self::public_view(&token);
+    view["token"] = json!(plaintext);
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: "Access token created. Copy it now, it will not be shown again".to_string(),
+      data: view,
+    })
+  }
+  pub async fn list_tokens(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let mut tokens = self.find_tokens(Some(user_id)).await?;
+    tokens.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    let views: Vec<serde_json::Value> = tokens.iter().map(Self::public_view).collect();
+    Ok(success_response(views))
+  }
+  pub async fn revoke_token(
+    &self,
+    user_id: &str,
+    token_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let mut token = self
+      .find_tokens(Some(user_id))
+      .await?
+      .into_iter()
+      .find(|t| t.id.as_deref() == Some(token_id))
+      .ok_or_else(|| err_response("Access token not found"))?;
+    access_token::revoke_grant(token_id);
+    if token.revoked_at.is_none() {
+      let now = Utc::now();
+      token.revoked_at = Some(now);
+      token.updated_at = Some(now);
+      self.save_token(&token).await?;
+    }
+    Ok(success_response(Self::public_view(&token)))
+  }
+  fn is_active(token: &AccessTokenEntity) -> bool {
+    token.revoked_at.is_none() && token.expires_at.map(|exp| exp > Utc::now()).unwrap_or(true)
+  }
+  fn grant_for(token: &AccessTokenEntity) -> AccessTokenGrant {
+    AccessTokenGrant {
+      id: token.id.clone().unwrap_or_default(),
+      user_id: token.user_id.clone(),
+      profile_id: token.profile_id.clone(),
+      scopes: token.scopes.clone(),
+      expires_at: token.expires_at.map(|exp| exp.timestamp_millis()),
+    }
+  }
+  fn public_view(token: &AccessTokenEntity) -> serde_json::Value {
+    json!({
+      "id": token.id,
+      "name": token.name,
+      "scopes": token.scopes,
+      "token_hint": token.token_hint,
+      "expires_at": token.expires_at,
+      "revoked_at": token.revoked_at,
+      "active": Self::is_active(token),
+      "created_at": token.created_at
+    })
+  }
+  async fn find_tokens(
+    &self,
+    user_id: Option<&str>,
+  ) -> Result<Vec<AccessTokenEntity>, ResponseModel> {
+    let filter = user_id.map(|uid| Filter::Eq("user_id".to_string(), json!(uid)));
+    let docs = match &self.mongodb_provider {
+      Some(mongo) => mongo
+        .find_many(ACCESS_TOKENS_TABLE, filter.as_ref(), None, None, None, true)
+        .await
+        .map_err(|e| err_response_formatted("Failed to load access tokens", &e.to_string()))?,
+      None => self
+        .json_provider
+        .find_many(ACCESS_TOKENS_TABLE, filter.as_ref(), None, None, None, true)
+        .await
+        .unwrap_or_default(),
+    };
+    Ok(
+      docs
+        .into_iter()
+        .filter_map(|doc| serde_json::from_value::<AccessTokenEntity>(doc).ok())
+        .collect(),
+    )
+  }
+  async fn save_token(&self, token: &AccessTokenEntity) -> Result<(), ResponseModel> {
+    let id = token.id.clone().unwrap_or_default();
+    let token_val = serde_json::to_value(token)
+      .map_err(|e| err_response(&format!("Failed to serialize access token: {}", e)))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      mongo
+        .update(ACCESS_TOKENS_TABLE, &id, token_val.clone())
+        .await
+        .map_err(|e| err_response_formatted("Failed to update access token", &e.to_string()))?;
+    }
+    if self
+      .json_provider
+      .update(ACCESS_TOKENS_TABLE, &id, token_val.clone())
+      .await
+      .is_err()
+    {
+      let _ = self
+        .json_provider
+        .insert(ACCESS_TOKENS_TABLE, token_val)
+        .await;
+    }
+    Ok(())
+  }
+}
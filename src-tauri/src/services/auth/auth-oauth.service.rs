@@ -0,0 +1,336 @@
+/* sys lib */
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::JsonProvider;
+use nosql_orm::providers::MongoProvider;
+use nosql_orm::query::Filter;
+/* models */
+use crate::entities::oauth_identity_entity::OAuthIdentityEntity;
+use crate::models::response::{ResponseModel, ResponseStatus};
+/* helpers */
+use crate::utils::{
+  auth::find_user_by_id,
+  config::{OAuthProviderConfig, OAuthProviderKind},
+  profile::check_profile_exists,
+  response_helper::{err_response, err_response_formatted, success_response},
+};
+/* services */
+use super::auth_token::AuthTokenService;
+use crate::services::github_service::GithubService;
+use crate::services::oauth::{
+  oidc_provider::OidcProvider,
+  provider::{pkce_challenge, random_url_token, AuthorizationRequest, OAuthProvider},
+};
+const IDENTITIES_TABLE: &str = "oauth_identities";
+const STATES_TABLE: &str = "oauth_states";
+const STATE_TTL_SECS: i64 = 600;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthPendingState {
+  /// The `state` parameter sent to the provider
+  pub id: String,
+  pub provider: String,
+  pub code_verifier: String,
+  pub nonce: String,
+  /// Set when a signed-in user is linking a new identity instead of logging in
+  pub link_user_id: Option<String>,
+  pub created_at: i64,
+  pub expires_at: i64,
+}
+pub struct AuthOAuthService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  token_service: Arc<AuthTokenService>,
+  providers: HashMap<String, Arc<dyn OAuthProvider>>,
+}
+impl AuthOAuthService {
+  pub fn new(
+    json_provider: JsonProvider,
+    mongodb_provider: Option<Arc<MongoProvider>>,
+    token_service: Arc<AuthTokenService>,
+    configs: &[OAuthProviderConfig],
+  ) -> Self {
+    let providers = configs
+      .iter()
+      .map(|config| {
+        let provider: Arc<dyn OAuthProvider> = match config.kind {
+          OAuthProviderKind::Github => Arc::new(GithubService::new().with_oauth(config.clone())),
+          OAuthProviderKind::Oidc => Arc::new(OidcProvider::new(config.clone())),
+        };
+        (config.id.clone(), provider)
+      })
+      .collect();
+    Self {
+      json_provider,
+      mongodb_provider,
+      token_service,
+      providers,
+    }
+  }
+  pub fn list_providers(&self) -> ResponseModel {
+    let mut providers: Vec<serde_json::Value> = self
+      .providers
+      .values()
+      .map(|p| json!({ "id": p.id(), "name": p.display_name() }))
+      .collect();
+    providers.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+    success_response(providers)
+  }
+  pub async fn start(
+    &self,
+    provider_id: &str,
+    link_user_id: Option<&str>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let provider = self.provider(provider_id)?;
+    let now = chrono::Utc::now().timestamp();
+    let pending = OAuthPendingState {
+      id: random_url_token(),
+      provider: provider_id.to_string(),
+      code_verifier: random_url_token(),
+      nonce: random_url_token(),
+      link_user_id: link_user_id.map(|s| s.to_string()),
+      created_at: now,
+      expires_at: now + STATE_TTL_SECS,
+    };
+    let authorization_url = provider
+      .authorization_url(&AuthorizationRequest {
+        state: pending.id.clone(),
+        nonce: pending.nonce.clone(),
+        code_challenge: pkce_challenge(&pending.code_verifier),
+      })
+      .await
+      .map_err(|e| err_response_formatted("Failed to start OAuth login", &e))?;
+    let pending_val = serde_json::to_value(&pending)
+      .map_err(|e| err_response(&format!("Failed to serialize OAuth state: {}", e)))?;
+    self
+      .json_provider
+      .insert(STATES_TABLE, pending_val)
+      .await
+      .map_err(|e| err_response_formatted("Failed to store OAuth state", &e.to_string()))?;
+    Ok(success_response(json!({
+      "provider": provider_id,
+      "state": pending.id,
+      "authorization_url": authorization_url
+    })))
+  }
+  /// Completes the redirect: logs in through a linked identity, or links it when the flow
+  /// was started by a signed-in user. Identities are never linked implicitly by email.
+  pub async fn finish(
+    &self,
+    state: &str,
+    code: &str,
+    remember: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let pending = self.consume_state(state).await?;
+    let provider = self.provider(&pending.provider)?;
+    let tokens = provider
+      .exchange_code(code, &pending.code_verifier)
+      .await
+      .map_err(|e| err_response_formatted("OAuth code exchange failed", &e))?;
+    let identity = provider
+      .fetch_identity(&tokens, &pending.nonce)
+      .await
+      .map_err(|e| err_response_formatted("OAuth identity verification failed", &e))?;
+    let existing = self
+      .find_identities(&Filter::And(vec![
+        Filter::Eq("provider".to_string(), json!(identity.provider)),
+        Filter::Eq("subject".to_string(), json!(identity.subject)),
+      ]))
+      .await?
+      .into_iter()
+      .next();
+    let now = chrono::Utc::now();
+    if let Some(user_id) = pending.link_user_id {
+      if let Some(link) = existing {
+        if link.user_id != user_id {
+          return Err(err_response(
+            "This account is already linked to another TaskFlow user",
+          ));
+        }
+        return Ok(success_response(Self::public_view(&link)));
+      }
+      let link = OAuthIdentityEntity {
+        id: Some(nosql_orm::utils::generate_id()),
+        user_id,
+        provider: identity.provider,
+        subject: identity.subject,
+        email: identity.email,
+        username: identity.username.or(identity.display_name),
+        last_login_at: None,
+        created_at: Some(now),
+        updated_at: Some(now),
+      };
+      self.insert_identity(&link).await?;
+      let mut view = Self::public_view(&link);
+      view["user_id"] = json!(link.user_id);
+      return Ok(ResponseModel {
+        status: ResponseStatus::Success,
+        message: format!("{} account linked", provider.display_name()),
+        data: view,
+      });
+    }
+    let mut link = existing.ok_or_else(|| {
+      err_response(&format!(
+        "No TaskFlow account is linked to this {} account. Sign in and link it from security settings",
+        provider.display_name()
+      ))
+    })?;
+    link.last_login_at = Some(now);
+    link.updated_at = Some(now);
+    self.save_identity(&link).await?;
+    let user = find_user_by_id(
+      &self.json_provider,
+      self.mongodb_provider.as_ref(),
+      &link.user_id,
+    )
+    .await?;
+    let profile = check_profile_exists(
+      &self.json_provider,
+      self.mongodb_provider.clone(),
+      user.id(),
+    )
+    .await
+    .ok()
+    .flatten();
+    let profile_id = profile.as_ref().and_then(|p| p.id.as_deref());
+    let token =
+      self
+        .token_service
+        .generate_token(user.id(), profile_id, "", &user.role, remember)?;
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: format!("{} login successful", provider.display_name()),
+      data: json!({
+        "token": token,
+        "needsProfile": profile.is_none(),
+        "profile": profile
+      }),
+    })
+  }
+  pub async fn list_identities(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let links = self
+      .find_identities(&Filter::Eq("user_id".to_string(), json!(user_id)))
+      .await?;
+    let views: Vec<serde_json::Value> = links.iter().map(Self::public_view).collect();
+    Ok(success_response(views))
+  }
+  pub async fn unlink_identity(
+    &self,
+    user_id: &str,
+    link_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let link = self
+      .find_identities(&Filter::Eq("user_id".to_string(), json!(user_id)))
+      .await?
+      .into_iter()
+      .find(|l| l.id.as_deref() == Some(link_id))
+      .ok_or_else(|| err_response("Linked account not found"))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      mongo
+        .delete(IDENTITIES_TABLE, link_id)
+        .await
+        .map_err(|e| err_response_formatted("Failed to unlink account", &e.to_string()))?;
+    }
+    let _ = self.json_provider.delete(IDENTITIES_TABLE, link_id).await;
+    Ok(success_response(json!({
+      "id": link_id,
+      "provider": link.provider,
+      "deleted": true
+    })))
+  }
+  fn provider(&self, provider_id: &str) -> Result<Arc<dyn OAuthProvider>, ResponseModel> {
+    self.providers.get(provider_id).cloned().ok_or_else(|| {
+      err_response(&format!(
+        "OAuth provider '{}' is not configured",
+        provider_id
+      ))
+    })
+  }
+  fn public_view(link: &OAuthIdentityEntity) -> serde_json::Value {
+    json!({
+      "id": link.id,
+      "provider": link.provider,
+      "email": link.email,
+      "username": link.username,
+      "last_login_at": link.last_login_at,
+      "created_at": link.created_at
+    })
+  }
+  /// States are single use: they are removed as soon as a redirect tries to finish.
+  async fn consume_state(&self, state: &str) -> Result<OAuthPendingState, ResponseModel> {
+    let pending_val = self
+      .json_provider
+      .find_by_id(STATES_TABLE, state)
+      .await
+      .map_err(|e| err_response_formatted("Failed to load OAuth state", &e.to_string()))?
+      .ok_or_else(|| err_response("OAuth login expired or was already used"))?;
+    let _ = self.json_provider.delete(STATES_TABLE, state).await;
+    let pending: OAuthPendingState = serde_json::from_value(pending_val)
+      .map_err(|e| err_response(&format!("Invalid OAuth state: {}", e)))?;
+    if pending.expires_at < chrono::Utc::now().timestamp() {
+      return Err(err_response("OAuth login expired or was already used"));
+    }
+    Ok(pending)
+  }
+  async fn find_identities(
+    &self,
+    filter: &Filter,
+  ) -> Result<Vec<OAuthIdentityEntity>, ResponseModel> {
+    let docs = match &self.mongodb_provider {
+      Some(mongo) => mongo
+        .find_many(IDENTITIES_TABLE, Some(filter), None, None, None, true)
+        .await
+        .map_err(|e| err_response_formatted("Failed to load linked accounts", &e.to_string()))?,
+      None => self
+        .json_provider
+        .find_many(IDENTITIES_TABLE, Some(filter), None, None, None, true)
+        .await
+        .unwrap_or_default(),
+    };
+    Ok(
+      docs
+        .into_iter()
+        .filter_map(|doc| serde_json::from_value::<OAuthIdentityEntity>(doc).ok())
+        .collect(),
+    )
+  }
+  async fn insert_identity(&self, link: &OAuthIdentityEntity) -> Result<(), ResponseModel> {
+    let link_val = serde_json::to_value(link)
+      .map_err(|e| err_response(&format!("Failed to serialize linked account: {}", e)))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      mongo
+        .insert(IDENTITIES_TABLE, link_val.clone())
+        .await
+        .map_err(|e| err_response_formatted("Failed to link account", &e.to_string()))?;
+    }
+    self
+      .json_provider
+      .insert(IDENTITIES_TABLE, link_val)
+      .await
+      .map_err(|e| err_response_formatted("Failed to link account", &e.to_string()))?;
+    Ok(())
+  }
+  async fn save_identity(&self, link: &OAuthIdentityEntity) -> Result<(), ResponseModel> {
+    let id = link.id.clone().unwrap_or_default();
+    let link_val = serde_json::to_value(link)
+      .map_err(|e| err_response(&format!("Failed to serialize linked account: {}", e)))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      mongo
+        .update(IDENTITIES_TABLE, &id, link_val.clone())
+        .await
+        .map_err(|e| err_response_formatted("Failed to update linked account", &e.to_string()))?;
+    }
+    if self
+      .json_provider
+      .update(IDENTITIES_TABLE, &id, link_val.clone())
+      .await
+      .is_err()
+    {
+      let _ = self.json_provider.insert(IDENTITIES_TABLE, link_val).await;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,517 @@
+/* sys lib */
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::JsonProvider;
+use nosql_orm::providers::MongoProvider;
+use nosql_orm::query::Filter;
+/* models */
+use crate::entities::passkey_entity::PasskeyEntity;
+use crate::models::response::{ResponseModel, ResponseStatus};
+/* helpers */
+use crate::utils::{
+  auth::{find_user_by_id, find_user_by_username},
+  profile::check_profile_exists,
+  response_helper::{err_response, err_response_formatted, success_response},
+  webauthn,
+};
+/* services */
+use super::auth_token::AuthTokenService;
+const PASSKEYS_TABLE: &str = "passkeys";
+const CHALLENGES_TABLE: &str = "passkey_challenges";
+const CHALLENGE_TTL_SECS: i64 = 300;
+const CEREMONY_REGISTRATION: &str = "registration";
+const CEREMONY_AUTHENTICATION: &str = "authentication";
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasskeyChallenge {
+  pub id: String,
+  pub challenge: String,
+  pub ceremony: String,
+  pub user_id: Option<String>,
+  pub created_at: i64,
+  pub expires_at: i64,
+}
+/// Credential returned by `navigator.credentials.create()`, with binary fields base64url encoded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationCredential {
+  pub id: String,
+  pub response: AttestationResponse,
+  #[serde(default)]
+  pub transports: Vec<String>,
+}
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+  pub client_data_json: String,
+  pub attestation_object: String,
+}
+/// Credential returned by `navigator.credentials.get()`, with binary fields base64url encoded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticationCredential {
+  pub id: String,
+  pub response: AssertionResponse,
+}
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+  pub client_data_json: String,
+  pub authenticator_data: String,
+  pub signature: String,
+  #[serde(default)]
+  pub user_handle: Option<String>,
+}
+#[derive(Clone)]
+pub struct AuthPasskeyService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  token_service: Arc<AuthTokenService>,
+  rp_domain: String,
+  rp_name: String,
+}
+impl AuthPasskeyService {
+  pub fn new(
+    json_provider: JsonProvider,
+    mongodb_provider: Option<Arc<MongoProvider>>,
+    token_service: Arc<AuthTokenService>,
+    rp_domain: String,
+    rp_name: String,
+  ) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+      token_service,
+      rp_domain,
+      rp_name,
+    }
+  }
+  pub async fn start_registration(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let user =
+      find_user_by_id(&self.json_provider, self.mongodb_provider.as_ref(), user_id).await?;
+    let existing = self.find_passkeys_by_user(user_id).await?;
+    let challenge = self
+      .create_challenge(CEREMONY_REGISTRATION, Some(user_id))
+      .await?;
+    let exclude_credentials: Vec<serde_json::Value> = existing
+      .iter()
+      .map(|p| json!({ "type": "public-key", "id": p.passkey_credential_id }))
+      .collect();
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: "Passkey registration started".to_string(),
+      data: json!({
+        "challengeId": challenge.id,
+        "publicKey": {
+          "challenge": challenge.challenge,
+          "rp": { "id": self.rp_domain, "name": self.rp_name },
+          "user": {
+            "id": webauthn::encode_base64url(user_id.as_bytes()),
+            "name": user.username,
+            "displayName": user.username
+          },
+          "pubKeyCredParams": [{ "type": "public-key", "alg": -7 }],
+          "timeout": CHALLENGE_TTL_SECS * 1000,
+          "attestation": "none",
+          "excludeCredentials": exclude_credentials,
+          "authenticatorSelection": {
+            "residentKey": "required",
+            "userVerification": "required"
+          }
+        }
+      }),
+    })
+  }
+  pub async fn finish_registration(
+    &self,
+    user_id: &str,
+    challenge_id: &str,
+    name: &str,
+    credential: RegistrationCredential,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let challenge = self
+      .consume_challenge(challenge_id, CEREMONY_REGISTRATION)
+      .await?;
+    if challenge.user_id.as_deref() != Some(user_id) {
+      return Err(err_response("Passkey challenge belongs to another user"));
+    }
+    let client_data = webauthn::decode_base64url(&credential.response.client_data_json)?;
+    webauthn::verify_client_data(
+      &client_data,
+      "webauthn.create",
+      &challenge.challenge,
+      &self.rp_domain,
+    )?;
+    let attestation = webauthn::decode_base64url(&credential.response.attestation_object)?;
+    let (attestation, _) = webauthn::decode_cbor(&attestation)?;
+    let auth_data_bytes = attestation
+      .get_text("authData")
+      .and_then(|v| v.as_bytes())
+      .ok_or_else(|| err_response("Attestation object has no authenticator data"))?;
+    let auth_data = webauthn::parse_authenticator_data(auth_data_bytes)?;
+    self.verify_authenticator_flags(&auth_data)?;
+    let attested = auth_data
+      .attested_credential
+      .ok_or_else(|| err_response("Attestation has no credential data"))?;
+    let credential_id = webauthn::encode_base64url(&attested.credential_id);
+    if credential_id != credential.id.trim_end_matches('=') {
+      return Err(err_response("Credential id mismatch"));
+    }
+    if self
+      .find_passkey_by_credential(&credential_id)
+      .await?
+      .is_some()
+    {
+      return Err(err_response("Passkey is already registered"));
+    }
+    let now = chrono::Utc::now();
+    let name = name.trim();
+    let passkey = PasskeyEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      user_id: user_id.to_string(),
+      name: if name.is_empty() {
+        "Passkey".to_string()
+      } else {
+        name.to_string()
+      },
+      passkey_credential_id: credential_id,
+      passkey_public_key: webauthn::encode_base64url(&attested.public_key),
+      passkey_device: attested
+        .aaguid
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect(),
+      transports: credential.transports,
+      sign_count: auth_data.sign_count,
+      last_used_at: None,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let passkey_val = serde_json::to_value(&passkey)
+      .map_err(|e| err_response(&format!("Failed to serialize passkey: {}", e)))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      mongo
+        .insert(PASSKEYS_TABLE, passkey_val.clone())
+        .await
+        .map_err(|e| err_response_formatted("Failed to store passkey", &e.to_string()))?;
+    }
+    self
+      .json_provider
+      .insert(PASSKEYS_TABLE, passkey_val)
+      .await
+      .map_err(|e| err_response_formatted("Failed to store passkey", &e.to_string()))?;
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: "Passkey registered".to_string(),
+      data: Self::public_view(&passkey),
+    })
+  }
+  /// Starts a login ceremony. Without a username the browser offers any discoverable passkey.
+  pub async fn start_authentication(
+    &self,
+    username: Option<&str>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let allow_credentials: Vec<serde_json::Value> = match username {
+      Some(username) if !username.is_empty() => {
+        let user = find_user_by_username(
+          &self.json_provider,
+          self.mongodb_provider.as_ref(),
+          username,
+        )
+        .await?;
+        let passkeys = self.find_passkeys_by_user(user.id()).await?;
+        if passkeys.is_empty() {
+          return Err(err_response("No passkeys registered for this user"));
+        }
+        passkeys
+          .iter()
+          .map(|p| {
+            json!({
+              "type": "public-key",
+              "id": p.passkey_credential_id,
+              "transports": p.transports
+            })
+          })
+          .collect()
+      }
+      _ => Vec::new(),
+    };
+    let challenge = self.create_challenge(CEREMONY_AUTHENTICATION, None).await?;
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: "Passkey login started".to_string(),
+      data: json!({
+        "challengeId": challenge.id,
+        "publicKey": {
+          "challenge": challenge.challenge,
+          "rpId": self.rp_domain,
+          "timeout": CHALLENGE_TTL_SECS * 1000,
+          "userVerification": "required",
+          "allowCredentials": allow_credentials
+        }
+      }),
+    })
+  }
+  pub async fn finish_authentication(
+    &self,
+    challenge_id: &str,
+    credential: AuthenticationCredential,
+    remember: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let challenge = self
+      .consume_challenge(challenge_id, CEREMONY_AUTHENTICATION)
+      .await?;
+    let credential_id = credential.id.trim_end_matches('=').to_string();
+    let mut passkey = self
+      .find_passkey_by_credential(&credential_id)
+      .await?
+      .ok_or_else(|| err_response("Unknown passkey"))?;
+    if let Some(handle) = credential.response.user_handle.as_deref() {
+      let handle = webauthn::decode_base64url(handle)?;
+      if !handle.is_empty() && handle != passkey.user_id.as_bytes() {
+        return Err(err_response("Passkey user handle mismatch"));
+      }
+    }
+    let client_data = webauthn::decode_base64url(&credential.response.client_data_json)?;
+    webauthn::verify_client_data(
+      &client_data,
+      "webauthn.get",
+      &challenge.challenge,
+      &self.rp_domain,
+    )?;
+    let auth_data_bytes = webauthn::decode_base64url(&credential.response.authenticator_data)?;
+    let auth_data = webauthn::parse_authenticator_data(&auth_data_bytes)?;
+    self.verify_authenticator_flags(&auth_data)?;
+    let public_key = webauthn::decode_base64url(&passkey.passkey_public_key)?;
+    let signature = webauthn::decode_base64url(&credential.response.signature)?;
+    let mut signed_data = auth_data_bytes.clone();
+    signed_data.extend_from_slice(&webauthn::sha256(&client_data));
+    webauthn::verify_es256_signature(&public_key, &signed_data, &signature)?;
+    // Authenticators that do not implement counters always report zero
+    if (auth_data.sign_count != 0 || passkey.sign_count != 0)
+      && auth_data.sign_count <= passkey.sign_count
+    {
+      return Err(err_response(
+        "Passkey signature counter did not increase. The credential may be cloned",
+      ));
+    }
+    let now = chrono::Utc::now();
+    passkey.sign_count = auth_data.sign_count;
+    passkey.last_used_at = Some(now);
+    passkey.updated_at = Some(now);
+    self.save_passkey(&passkey).await?;
+    let user = find_user_by_id(
+      &self.json_provider,
+      self.mongodb_provider.as_ref(),
+      &passkey.user_id,
+    )
+    .await?;
+    let profile = check_profile_exists(
+      &self.json_provider,
+      self.mongodb_provider.clone(),
+      user.id(),
+    )
+    .await
+    .ok()
+    .flatten();
+    let profile_id = profile.as_ref().and_then(|p| p.id.as_deref());
+    let token =
+      self
+        .token_service
+        .generate_token(user.id(), profile_id, "", &user.role, remember)?;
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: "Passkey login successful".to_string(),
+      data: json!({
+        "token": token,
+        "needsProfile": profile.is_none(),
+        "profile": profile
+      }),
+    })
+  }
+  pub async fn list_passkeys(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let passkeys = self.find_passkeys_by_user(user_id).await?;
+    let views: Vec<serde_json::Value> = passkeys.iter().map(Self::public_view).collect();
+    Ok(success_response(views))
+  }
+  pub async fn rename_passkey(
+    &self,
+    user_id: &str,
+    passkey_id: &str,
+    name: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let name = name.trim();
+    if name.is_empty() {
+      return Err(err_response("Passkey name cannot be empty"));
+    }
+    let mut passkey = self.find_owned_passkey(user_id, passkey_id).await?;
+    passkey.name = name.to_string();
+    passkey.updated_at = Some(chrono::Utc::now());
+    self.save_passkey(&passkey).await?;
+    Ok(success_response(Self::public_view(&passkey)))
+  }
+  pub async fn remove_passkey(
+    &self,
+    user_id: &str,
+    passkey_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let passkey = self.find_owned_passkey(user_id, passkey_id).await?;
+    let id = passkey.id.clone().unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      mongo
+        .delete(PASSKEYS_TABLE, &id)
+        .await
+        .map_err(|e| err_response_formatted("Failed to remove passkey", &e.to_string()))?;
+    }
+    let _ = self.json_provider.delete(PASSKEYS_TABLE, &id).await;
+    Ok(success_response(json!({ "id": id, "deleted": true })))
+  }
+  fn verify_authenticator_flags(
+    &self,
+    auth_data: &webauthn::AuthenticatorData,
+  ) -> Result<(), ResponseModel> {
+    if auth_data.rp_id_hash != webauthn::sha256(self.rp_domain.as_bytes()) {
+      return Err(err_response("Passkey was created for a different domain"));
+    }
+    if !auth_data.user_present() {
+      return Err(err_response("User presence was not confirmed"));
+    }
+    // Passkey login replaces password + TOTP, so user verification is mandatory
+    if !auth_data.user_verified() {
+      return Err(err_response("User verification is required for passkeys"));
+    }
+    Ok(())
+  }
+  fn public_view(passkey: &PasskeyEntity) -> serde_json::Value {
+    json!({
+      "id": passkey.id,
+      "name": passkey.name,
+      "credential_id": passkey.passkey_credential_id,
+      "device": passkey.passkey_device,
+      "transports": passkey.transports,
+      "last_used_at": passkey.last_used_at,
+      "created_at": passkey.created_at
+    })
+  }
+  async fn create_challenge(
+    &self,
+    ceremony: &str,
+    user_id: Option<&str>,
+  ) -> Result<PasskeyChallenge, ResponseModel> {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    let now = chrono::Utc::now().timestamp();
+    let challenge = PasskeyChallenge {
+      id: nosql_orm::utils::generate_id(),
+      challenge: webauthn::encode_base64url(&bytes),
+      ceremony: ceremony.to_string(),
+      user_id: user_id.map(|s| s.to_string()),
+      created_at: now,
+      expires_at: now + CHALLENGE_TTL_SECS,
+    };
+    let challenge_val = serde_json::to_value(&challenge)
+      .map_err(|e| err_response(&format!("Failed to serialize challenge: {}", e)))?;
+    self
+      .json_provider
+      .insert(CHALLENGES_TABLE, challenge_val)
+      .await
+      .map_err(|e| err_response_formatted("Failed to store challenge", &e.to_string()))?;
+    Ok(challenge)
+  }
+  /// Challenges are single use: they are removed as soon as a ceremony tries to finish.
+  async fn consume_challenge(
+    &self,
+    challenge_id: &str,
+    ceremony: &str,
+  ) -> Result<PasskeyChallenge, ResponseModel> {
+    let challenge_val = self
+      .json_provider
+      .find_by_id(CHALLENGES_TABLE, challenge_id)
+      .await
+      .ok()
+      .flatten()
+      .ok_or_else(|| err_response("Passkey challenge not found"))?;
+    let _ = self
+      .json_provider
+      .delete(CHALLENGES_TABLE, challenge_id)
+      .await;
+    let challenge: PasskeyChallenge = serde_json::from_value(challenge_val)
+      .map_err(|e| err_response(&format!("Failed to parse challenge: {}", e)))?;
+    if challenge.ceremony != ceremony {
+      return Err(err_response(
+        "Passkey challenge was issued for another ceremony",
+      ));
+    }
+    if challenge.expires_at < chrono::Utc::now().timestamp() {
+      return Err(err_response("Passkey challenge has expired"));
+    }
+    Ok(challenge)
+  }
+  async fn find_passkeys(&self, filter: &Filter) -> Result<Vec<PasskeyEntity>, ResponseModel> {
+    let docs = match &self.mongodb_provider {
+      Some(mongo) => mongo
+        .find_many(PASSKEYS_TABLE, Some(filter), None, None, None, true)
+        .await
+        .map_err(|e| err_response_formatted("Failed to load passkeys", &e.to_string()))?,
+      None => self
+        .json_provider
+        .find_many(PASSKEYS_TABLE, Some(filter), None, None, None, true)
+        .await
+        .unwrap_or_default(),
+    };
+    Ok(
+      docs
+        .into_iter()
+        .filter_map(|doc| serde_json::from_value::<PasskeyEntity>(doc).ok())
+        .collect(),
+    )
+  }
+  async fn find_passkeys_by_user(
+    &self,
+    user_id: &str,
+  ) -> Result<Vec<PasskeyEntity>, ResponseModel> {
+    let filter = Filter::Eq("user_id".to_string(), json!(user_id));
+    self.find_passkeys(&filter).await
+  }
+  async fn find_passkey_by_credential(
+    &self,
+    credential_id: &str,
+  ) -> Result<Option<PasskeyEntity>, ResponseModel> {
+    let filter = Filter::Eq("passkey_credential_id".to_string(), json!(credential_id));
+    Ok(self.find_passkeys(&filter).await?.into_iter().next())
+  }
+  async fn find_owned_passkey(
+    &self,
+    user_id: &str,
+    passkey_id: &str,
+  ) -> Result<PasskeyEntity, ResponseModel> {
+    self
+      .find_passkeys_by_user(user_id)
+      .await?
+      .into_iter()
+      .find(|p| p.id.as_deref() == Some(passkey_id))
+      .ok_or_else(|| err_response("Passkey not found"))
+  }
+  async fn save_passkey(&self, passkey: &PasskeyEntity) -> Result<(), ResponseModel> {
+    let id = passkey.id.clone().unwrap_or_default();
+    let passkey_val = serde_json::to_value(passkey)
+      .map_err(|e| err_response(&format!("Failed to serialize passkey: {}", e)))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      mongo
+        .update(PASSKEYS_TABLE, &id, passkey_val.clone())
+        .await
+        .map_err(|e| err_response_formatted("Failed to update passkey", &e.to_string()))?;
+    }
+    if self
+      .json_provider
+      .update(PASSKEYS_TABLE, &id, passkey_val.clone())
+      .await
+      .is_err()
+    {
+      let _ = self.json_provider.insert(PASSKEYS_TABLE, passkey_val).await;
+    }
+    Ok(())
+  }
+}
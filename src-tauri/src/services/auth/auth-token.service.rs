@@ -14,6 +14,7 @@ use crate::services::profile::profile_sync_unified::ProfileSyncUnifiedService;
 /* helpers */
 use crate::utils::auth::Claims;
 use crate::utils::response_helper::err_response;
+use crate::utils::signed_in;
 #[derive(Clone)]
 pub struct AuthTokenService {
   pub json_provider: JsonProvider,
@@ -136,6 +137,7 @@ impl AuthTokenService {
           {
             // User exists locally but not in cloud - clean up local data
             let _ = self.cleanup_user_data_from_json(&user_id).await;
+            signed_in::clear_signed_in_user();
             return Err(err_response("User session invalid - please login again"));
           }
           Err(err_response("User not found"))
@@ -1,5 +1,6 @@
 /* sys lib */
 use base32::Alphabet;
+use bcrypt::{hash, verify};
 use rand::Rng;
 use std::sync::Arc;
 /* providers */
@@ -18,6 +19,11 @@ use crate::utils::{
 };
 /* services */
 use super::auth_token::AuthTokenService;
+const RECOVERY_CODE_COUNT: usize = 8;
+const RECOVERY_CODE_BYTES: usize = 10;
+/// Recovery codes carry 80 bits of entropy, so a lower bcrypt cost keeps
+/// verification of the whole set fast without weakening them.
+const RECOVERY_CODE_HASH_COST: u32 = 10;
 #[derive(Clone)]
 pub struct AuthTotpService {
   pub json_provider: JsonProvider,
@@ -56,13 +62,58 @@ impl AuthTotpService {
       None
     }
   }
+  /// Generates plaintext recovery codes formatted as `XXXX-XXXX-XXXX-XXXX`.
+  /// Only their hashes are persisted, see [`Self::hash_recovery_codes`].
   pub fn generate_recovery_codes(&self) -> Vec<String> {
-    let mut codes = Vec::new();
-    for _ in 0..8 {
-      let code: u32 = rand::thread_rng().gen();
-      codes.push(format!("{:06}", code % 1000000));
-    }
+    (0..RECOVERY_CODE_COUNT)
+      .map(|_| {
+        let bytes: [u8; RECOVERY_CODE_BYTES] = rand::thread_rng().gen();
+        let encoded = base32::encode(Alphabet::Rfc4648 { padding: false }, &bytes);
+        encoded
+          .as_bytes()
+          .chunks(4)
+          .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+          .collect::<Vec<String>>()
+          .join("-")
+      })
+      .collect()
+  }
+  fn normalize_recovery_code(code: &str) -> String {
+    code
+      .chars()
+      .filter(|c| c.is_ascii_alphanumeric())
+      .map(|c| c.to_ascii_uppercase())
+      .collect()
+  }
+  pub fn hash_recovery_codes(&self, codes: &[String]) -> Result<Vec<String>, ResponseModel> {
+    codes
+      .iter()
+      .map(|code| {
+        hash(Self::normalize_recovery_code(code), RECOVERY_CODE_HASH_COST)
+          .map_err(|e| err_response(&format!("Error hashing recovery code: {}", e)))
+      })
+      .collect()
+  }
+  /// Counts the stored codes that can still be redeemed. Codes saved before
+  /// hashing was introduced are plaintext, never verify, and must be
+  /// regenerated instead.
+  pub fn usable_recovery_codes(codes: &[String]) -> usize {
     codes
+      .iter()
+      .filter(|code| Self::is_hashed_recovery_code(code))
+      .count()
+  }
+  fn is_hashed_recovery_code(code: &str) -> bool {
+    code.starts_with("$2")
+  }
+  fn find_recovery_code(hashes: &[String], code: &str) -> Option<usize> {
+    let normalized = Self::normalize_recovery_code(code);
+    if normalized.is_empty() {
+      return None;
+    }
+    hashes
+      .iter()
+      .position(|hashed| verify(&normalized, hashed).unwrap_or(false))
   }
   pub async fn verify_totp_code(&self, secret: &str, code: &str) -> bool {
     let code = code.trim();
@@ -99,9 +150,10 @@ impl AuthTotpService {
     let secret = self.generate_secret();
     let secret_lower = secret.to_ascii_lowercase();
     let recovery_codes = self.generate_recovery_codes();
+    let hashed_codes = self.hash_recovery_codes(&recovery_codes)?;
     let qr_code = self.generate_qr_code(&secret_lower, &user.email);
     self
-      .update_totp_settings(username, false, &secret_lower, recovery_codes.clone())
+      .update_totp_settings(username, false, &secret_lower, hashed_codes)
       .await?;
     Ok(ResponseModel {
       status: ResponseStatus::Success,
@@ -205,18 +257,58 @@ impl AuthTotpService {
       return Err(err_response("TOTP is not enabled"));
     }
     let mut new_recovery_codes = user.recovery_codes.clone();
-    if let Some(pos) = new_recovery_codes.iter().position(|c| c == code) {
+    if let Some(pos) = Self::find_recovery_code(&new_recovery_codes, code) {
       new_recovery_codes.remove(pos);
+      let remaining = Self::usable_recovery_codes(&new_recovery_codes);
+      let need_regeneration = remaining < new_recovery_codes.len();
       let updated_user = UserEntity {
         recovery_codes: new_recovery_codes,
         ..user
       };
       self.save_user(&updated_user).await?;
-      Ok(success_response("Recovery code accepted"))
+      Ok(ResponseModel {
+        status: ResponseStatus::Success,
+        message: "Recovery code accepted".to_string(),
+        data: serde_json::json!({
+          "recovery_codes_remaining": remaining,
+          "recovery_codes_need_regeneration": need_regeneration,
+        }),
+      })
     } else {
       Err(err_response("Invalid recovery code"))
     }
   }
+  pub async fn regenerate_recovery_codes(
+    &self,
+    username: &str,
+    password: &str,
+    code: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let user = self.find_user(username).await?;
+    if !user.totp_enabled || user.totp_secret.is_empty() {
+      return Err(err_response("TOTP is not enabled or not properly setup"));
+    }
+    let valid_password = verify(password, &user.password)
+      .map_err(|e| err_response(&format!("Error verifying password: {}", e)))?;
+    if !valid_password {
+      return Err(err_response("Invalid password"));
+    }
+    if !self.verify_totp_code(&user.totp_secret, code).await {
+      return Err(err_response("Invalid TOTP code"));
+    }
+    let recovery_codes = self.generate_recovery_codes();
+    let hashed_codes = self.hash_recovery_codes(&recovery_codes)?;
+    let updated_user = UserEntity {
+      recovery_codes: hashed_codes,
+      ..user
+    };
+    self.save_user(&updated_user).await?;
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: "Recovery codes regenerated".to_string(),
+      data: serde_json::json!({ "recovery_codes": recovery_codes }),
+    })
+  }
   pub async fn init_totp_qr_login(&self, username: &str) -> Result<ResponseModel, ResponseModel> {
     let user = self.find_user(username).await?;
     if !user.totp_enabled {
@@ -312,3 +404,13 @@ impl AuthTotpService {
     Ok(None)
   }
 }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn legacy_plaintext_codes_are_not_counted_as_usable() {
+    let hashed = hash("ABCD", 4).unwrap();
+    let codes = vec![hashed, "ABCD-EFGH-IJKL-MNOP".to_string()];
+    assert_eq!(AuthTotpService::usable_recovery_codes(&codes), 1);
+  }
+}
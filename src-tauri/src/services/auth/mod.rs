@@ -1,7 +1,13 @@
+#[path = "auth-access-token.service.rs"]
+pub mod auth_access_token;
 #[path = "auth_data_sync.service.rs"]
 pub mod auth_data_sync;
 #[path = "auth-login.service.rs"]
 pub mod auth_login;
+#[path = "auth-oauth.service.rs"]
+pub mod auth_oauth;
+#[path = "auth-passkey.service.rs"]
+pub mod auth_passkey;
 #[path = "auth-password.service.rs"]
 pub mod auth_password;
 #[path = "auth-qr.service.rs"]
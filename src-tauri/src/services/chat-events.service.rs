@@ -0,0 +1,70 @@
+/* sys lib */
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+/// Events buffered per subscriber before it is reported as lagging.
+const EVENT_BUFFER: usize = 1024;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatEventKind {
+  MessageCreated,
+  MessageEdited,
+  MessageDeleted,
+  ReactionAdded,
+  ReactionRemoved,
+  RoomCleared,
+  TypingStarted,
+  TypingStopped,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatEvent {
+  pub kind: ChatEventKind,
+  pub room_id: String,
+  #[serde(default)]
+  pub message_id: Option<String>,
+  /// Message document after the change (empty for deletions)
+  #[serde(default)]
+  pub message: Value,
+  pub emitted_at: String,
+}
+impl ChatEvent {
+  pub fn new(kind: ChatEventKind, room_id: &str, message_id: Option<&str>, message: Value) -> Self {
+    Self {
+      kind,
+      room_id: room_id.to_string(),
+      message_id: message_id.map(|s| s.to_string()),
+      message,
+      emitted_at: chrono::Utc::now().to_rfc3339(),
+    }
+  }
+  /// Builds an event from a chat document, taking the room and id from the document itself.
+  pub fn from_message(kind: ChatEventKind, message: &Value) -> Option<Self> {
+    let room_id = message.get("room_id").and_then(|v| v.as_str())?;
+    let message_id = message.get("id").and_then(|v| v.as_str());
+    Some(Self::new(kind, room_id, message_id, message.clone()))
+  }
+}
+/// Fan-out of chat changes to realtime subscribers. Changes made through other devices are
+/// fed in by `ChatService::relay_remote_changes`.
+#[derive(Clone)]
+pub struct ChatEventHub {
+  sender: broadcast::Sender<ChatEvent>,
+}
+impl Default for ChatEventHub {
+  fn default() -> Self {
+    let (sender, _) = broadcast::channel(EVENT_BUFFER);
+    Self { sender }
+  }
+}
+impl ChatEventHub {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub fn publish(&self, event: ChatEvent) {
+    // No subscribers is the normal state when no client is connected
+    let _ = self.sender.send(event);
+  }
+  pub fn subscribe(&self) -> broadcast::Receiver<ChatEvent> {
+    self.sender.subscribe()
+  }
+}
@@ -0,0 +1,139 @@
+/* sys lib */
+use chrono::Utc;
+use serde_json::{json, Value};
+use tokio::sync::{Mutex, MutexGuard};
+/* providers */
+use crate::repositories::data_provider::DataProvider;
+/* entities */
+use crate::entities::chat_outbox_entity::{ChatOutboxEntity, ChatOutboxOp, ChatOutboxStatus};
+/* models */
+use crate::models::response::ResponseModel;
+/* helpers */
+use crate::utils::response_helper::err_response;
+const OUTBOX_TABLE: &str = "chat_outbox";
+/// Rejections in a row before an entry is parked as failed instead of retried.
+pub const MAX_OUTBOX_ATTEMPTS: u32 = 5;
+/// Local queue of chat changes waiting for MongoDB.
+pub struct ChatOutbox {
+  json_provider: DataProvider,
+  /// Serialises enqueue and replay so sequence numbers and ordering stay consistent
+  lock: Mutex<()>,
+}
+impl ChatOutbox {
+  pub fn new(json_provider: DataProvider) -> Self {
+    Self {
+      json_provider,
+      lock: Mutex::new(()),
+    }
+  }
+  pub async fn lock(&self) -> MutexGuard<'_, ()> {
+    self.lock.lock().await
+  }
+  pub async fn enqueue(
+    &self,
+    op: ChatOutboxOp,
+    message_id: &str,
+    room_id: &str,
+    payload: Value,
+  ) -> Result<ChatOutboxEntity, ResponseModel> {
+    let _guard = self.lock.lock().await;
+    let seq = self
+      .entries()
+      .await?
+      .iter()
+      .map(|entry| entry.seq)
+      .max()
+      .unwrap_or(0)
+      + 1;
+    let now = Utc::now();
+    let entry = ChatOutboxEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      op,
+      message_id: message_id.to_string(),
+      room_id: room_id.to_string(),
+      payload,
+      seq,
+      attempts: 0,
+      last_error: None,
+      status: ChatOutboxStatus::Pending,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let value = serde_json::to_value(&entry)
+      .map_err(|e| err_response(&format!("Failed to queue chat change: {}", e)))?;
+    self.json_provider.insert(OUTBOX_TABLE, value).await?;
+    Ok(entry)
+  }
+  /// All entries, oldest first.
+  pub async fn entries(&self) -> Result<Vec<ChatOutboxEntity>, ResponseModel> {
+    let docs = self
+      .json_provider
+      .find_many(OUTBOX_TABLE, None, None, None, None, true)
+      .await?;
+    let mut entries: Vec<ChatOutboxEntity> = docs
+      .into_iter()
+      .filter_map(|doc| serde_json::from_value(doc).ok())
+      .collect();
+    entries.sort_by_key(|entry| entry.seq);
+    Ok(entries)
+  }
+  pub async fn pending(&self) -> Result<Vec<ChatOutboxEntity>, ResponseModel> {
+    Ok(
+      self
+        .entries()
+        .await?
+        .into_iter()
+        .filter(|entry| entry.status == ChatOutboxStatus::Pending)
+        .collect(),
+    )
+  }
+  pub async fn has_pending(&self) -> bool {
+    self
+      .pending()
+      .await
+      .map(|entries| !entries.is_empty())
+      .unwrap_or(false)
+  }
+  pub async fn complete(&self, entry: &ChatOutboxEntity) {
+    if let Some(id) = &entry.id {
+      let _ = self.json_provider.delete(OUTBOX_TABLE, id).await;
+    }
+  }
+  /// Records a rejected replay; returns true when the entry was parked as failed.
+  pub async fn record_failure(&self, entry: &ChatOutboxEntity, error: &str) -> bool {
+    let attempts = entry.attempts + 1;
+    let failed = attempts >= MAX_OUTBOX_ATTEMPTS;
+    self.mark(entry, attempts, error, failed).await;
+    failed
+  }
+  /// Parks every later entry for a message whose create could not be delivered.
+  pub async fn fail_dependents(&self, message_id: &str, error: &str) {
+    let Ok(entries) = self.pending().await else {
+      return;
+    };
+    for entry in entries.iter().filter(|e| e.message_id == message_id) {
+      self.mark(entry, entry.attempts, error, true).await;
+    }
+  }
+  pub async fn discard(&self, id: &str) -> Result<(), ResponseModel> {
+    self.json_provider.delete(OUTBOX_TABLE, id).await?;
+    Ok(())
+  }
+  async fn mark(&self, entry: &ChatOutboxEntity, attempts: u32, error: &str, failed: bool) {
+    let Some(id) = &entry.id else {
+      return;
+    };
+    let status = if failed {
+      ChatOutboxStatus::Failed
+    } else {
+      ChatOutboxStatus::Pending
+    };
+    let update = json!({
+      "attempts": attempts,
+      "last_error": error,
+      "status": status,
+      "updated_at": Utc::now().to_rfc3339(),
+    });
+    let _ = self.json_provider.patch(OUTBOX_TABLE, id, update).await;
+  }
+}
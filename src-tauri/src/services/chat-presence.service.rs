@@ -0,0 +1,617 @@
+/* sys lib */
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::MongoProvider;
+use nosql_orm::query::Filter;
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::chat_events_service::{ChatEvent, ChatEventHub, ChatEventKind};
+use crate::services::group_service::GroupService;
+use crate::services::room_service::RoomService;
+/* helpers */
+use crate::utils::response_helper::{err_response, success_response};
+/// A user without a heartbeat or open socket for this long is reported offline.
+const HEARTBEAT_TIMEOUT_MS: i64 = 60_000;
+/// Typing indicators clear themselves if the client never sends a stop.
+const TYPING_TTL_MS: i64 = 8_000;
+/// Each device keeps one document per signed-in user here, read by every other device.
+const PRESENCE_TABLE: &str = "chat_presence";
+/// Shared documents are re-read this far back, so a device whose clock runs slightly behind
+/// is not missed.
+const PULL_OVERLAP_MS: i64 = 5_000;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+  Online,
+  Away,
+  Offline,
+}
+#[derive(Debug, Clone)]
+struct PresenceEntry {
+  status: PresenceStatus,
+  last_seen: i64,
+  connections: usize,
+}
+#[derive(Default)]
+struct PresenceState {
+  users: HashMap<String, PresenceEntry>,
+  /// room_id -> user_id -> expiry (unix millis)
+  typing: HashMap<String, HashMap<String, i64>>,
+}
+/// Clock-agnostic presence bookkeeping; every call takes `now` in unix millis.
+#[derive(Default)]
+pub struct PresenceTracker {
+  state: RwLock<PresenceState>,
+}
+impl PresenceTracker {
+  pub fn heartbeat(&self, user_id: &str, status: PresenceStatus, now: i64) {
+    if let Ok(mut state) = self.state.write() {
+      let entry = state
+        .users
+        .entry(user_id.to_string())
+        .or_insert(PresenceEntry {
+          status,
+          last_seen: now,
+          connections: 0,
+        });
+      entry.status = status;
+      entry.last_seen = now;
+    }
+  }
+  pub fn connect(&self, user_id: &str, now: i64) {
+    if let Ok(mut state) = self.state.write() {
+      let entry = state
+        .users
+        .entry(user_id.to_string())
+        .or_insert(PresenceEntry {
+          status: PresenceStatus::Online,
+          last_seen: now,
+          connections: 0,
+        });
+      entry.connections += 1;
+      entry.last_seen = now;
+      if entry.status == PresenceStatus::Offline {
+        entry.status = PresenceStatus::Online;
+      }
+    }
+  }
+  /// Returns true when the last open connection of the user went away.
+  pub fn disconnect(&self, user_id: &str, now: i64) -> bool {
+    let Ok(mut state) = self.state.write() else {
+      return false;
+    };
+    let Some(entry) = state.users.get_mut(user_id) else {
+      return false;
+    };
+    entry.connections = entry.connections.saturating_sub(1);
+    entry.last_seen = now;
+    if entry.connections == 0 {
+      entry.status = PresenceStatus::Offline;
+    }
+    entry.connections == 0
+  }
+  pub fn status(&self, user_id: &str, now: i64) -> (PresenceStatus, Option<i64>) {
+    let Some(entry) = self
+      .state
+      .read()
+      .ok()
+      .and_then(|state| state.users.get(user_id).cloned())
+    else {
+      return (PresenceStatus::Offline, None);
+    };
+    let alive = entry.connections > 0 || now - entry.last_seen < HEARTBEAT_TIMEOUT_MS;
+    let status = if alive {
+      entry.status
+    } else {
+      PresenceStatus::Offline
+    };
+    (status, Some(entry.last_seen))
+  }
+  /// Applies a heartbeat shared by another device. Older news than what is known, and users
+  /// with a connection open here, are left alone.
+  pub fn observe(&self, user_id: &str, status: PresenceStatus, last_seen: i64) {
+    if let Ok(mut state) = self.state.write() {
+      let entry = state
+        .users
+        .entry(user_id.to_string())
+        .or_insert(PresenceEntry {
+          status,
+          last_seen,
+          connections: 0,
+        });
+      if entry.connections == 0 && last_seen >= entry.last_seen {
+        entry.status = status;
+        entry.last_seen = last_seen;
+      }
+    }
+  }
+  /// Users with a realtime connection open on this device.
+  pub fn connected_users(&self) -> Vec<String> {
+    self
+      .state
+      .read()
+      .map(|state| {
+        state
+          .users
+          .iter()
+          .filter(|(_, entry)| entry.connections > 0)
+          .map(|(user_id, _)| user_id.clone())
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+  /// Returns true when the indicator actually changed (start after stop or vice versa).
+  pub fn set_typing(&self, room_id: &str, user_id: &str, typing: bool, now: i64) -> bool {
+    if typing {
+      return self.set_typing_until(room_id, user_id, now + TYPING_TTL_MS, now);
+    }
+    let Ok(mut state) = self.state.write() else {
+      return false;
+    };
+    let Some(room) = state.typing.get_mut(room_id) else {
+      return false;
+    };
+    let changed = room
+      .remove(user_id)
+      .map(|expires| expires > now)
+      .unwrap_or(false);
+    if room.is_empty() {
+      state.typing.remove(room_id);
+    }
+    changed
+  }
+  /// Keeps the indicator up until `expires`; returns true when the user was not typing before.
+  pub fn set_typing_until(&self, room_id: &str, user_id: &str, expires: i64, now: i64) -> bool {
+    if expires <= now {
+      return false;
+    }
+    let Ok(mut state) = self.state.write() else {
+      return false;
+    };
+    state
+      .typing
+      .entry(room_id.to_string())
+      .or_default()
+      .insert(user_id.to_string(), expires)
+      .map(|previous| previous <= now)
+      .unwrap_or(true)
+  }
+  /// Drops the indicators that ran out and returns them as `(room_id, user_id)`.
+  pub fn expire_typing(&self, now: i64) -> Vec<(String, String)> {
+    let Ok(mut state) = self.state.write() else {
+      return Vec::new();
+    };
+    let mut expired = Vec::new();
+    state.typing.retain(|room_id, users| {
+      users.retain(|user_id, expires| {
+        let live = *expires > now;
+        if !live {
+          expired.push((room_id.clone(), user_id.clone()));
+        }
+        live
+      });
+      !users.is_empty()
+    });
+    expired
+  }
+  /// Rooms the user is typing in, with the expiry of each indicator.
+  pub fn typing_of(&self, user_id: &str, now: i64) -> HashMap<String, i64> {
+    self
+      .state
+      .read()
+      .map(|state| {
+        state
+          .typing
+          .iter()
+          .filter_map(|(room_id, users)| {
+            users
+              .get(user_id)
+              .filter(|expires| **expires > now)
+              .map(|expires| (room_id.clone(), *expires))
+          })
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+  pub fn typing(&self, room_id: &str, now: i64) -> Vec<String> {
+    let mut users: Vec<String> = self
+      .state
+      .read()
+      .ok()
+      .and_then(|state| {
+        state.typing.get(room_id).map(|room| {
+          room
+            .iter()
+            .filter(|(_, expires)| **expires > now)
+            .map(|(user_id, _)| user_id.clone())
+            .collect()
+        })
+      })
+      .unwrap_or_default();
+    users.sort();
+    users
+  }
+  /// Drops every typing indicator of the user and returns the affected rooms.
+  pub fn clear_typing(&self, user_id: &str) -> Vec<String> {
+    let Ok(mut state) = self.state.write() else {
+      return Vec::new();
+    };
+    let mut rooms = Vec::new();
+    state.typing.retain(|room_id, users| {
+      if users.remove(user_id).is_some() {
+        rooms.push(room_id.clone());
+      }
+      !users.is_empty()
+    });
+    rooms
+  }
+}
+#[derive(Default)]
+struct PresenceSync {
+  /// Users whose presence or typing changed here since the last push
+  dirty: HashSet<String>,
+  /// user_id -> when their document was last pushed (unix millis)
+  pushed: HashMap<String, i64>,
+  /// Documents of other devices were read up to this `last_seen`
+  since: i64,
+}
+/// Presence and typing state for chat. Each device tracks its own users in memory and shares
+/// them through MongoDB, where every other device picks them up on `sync`.
+pub struct PresenceService {
+  tracker: PresenceTracker,
+  hub: ChatEventHub,
+  room_service: Arc<RoomService>,
+  group_service: Arc<GroupService>,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  /// Marks this device's documents, so its own writes are not read back
+  device: String,
+  sync: Mutex<PresenceSync>,
+}
+impl PresenceService {
+  pub fn new(
+    hub: ChatEventHub,
+    room_service: Arc<RoomService>,
+    group_service: Arc<GroupService>,
+    mongodb_provider: Option<Arc<MongoProvider>>,
+  ) -> Self {
+    Self {
+      tracker: PresenceTracker::default(),
+      hub,
+      room_service,
+      group_service,
+      mongodb_provider,
+      device: nosql_orm::utils::generate_id(),
+      sync: Mutex::new(PresenceSync {
+        since: now_millis() - HEARTBEAT_TIMEOUT_MS,
+        ..Default::default()
+      }),
+    }
+  }
+  pub fn heartbeat(&self, user_id: &str, status: Option<PresenceStatus>) {
+    let status = status.unwrap_or(PresenceStatus::Online);
+    self.tracker.heartbeat(user_id, status, now_millis());
+    self.mark_dirty(user_id);
+    if status == PresenceStatus::Offline {
+      self.clear_typing(user_id);
+    }
+  }
+  /// Called when a realtime socket authenticates; the user stays online while it is open.
+  pub fn connect(&self, user_id: &str) {
+    self.tracker.connect(user_id, now_millis());
+    self.mark_dirty(user_id);
+  }
+  pub fn disconnect(&self, user_id: &str) {
+    if self.tracker.disconnect(user_id, now_millis()) {
+      self.mark_dirty(user_id);
+      self.clear_typing(user_id);
+    }
+  }
+  pub fn set_typing(&self, room_id: &str, user_id: &str, typing: bool) {
+    // Refreshing an active indicator is silent; only transitions reach subscribers
+    if self
+      .tracker
+      .set_typing(room_id, user_id, typing, now_millis())
+    {
+      self.publish_typing(room_id, user_id, typing);
+    }
+    self.mark_dirty(user_id);
+  }
+  /// Clears typing indicators that ran out without a stop, shares this device's changes and
+  /// applies the ones other devices shared, publishing typing transitions to subscribers here.
+  pub async fn sync(&self) {
+    let now = now_millis();
+    for (room_id, user_id) in self.tracker.expire_typing(now) {
+      self.publish_typing(&room_id, &user_id, false);
+    }
+    let Some(mongo) = &self.mongodb_provider else {
+      return;
+    };
+    self.push(mongo, now).await;
+    self.pull(mongo, now).await;
+  }
+  async fn push(&self, mongo: &MongoProvider, now: i64) {
+    let users: Vec<String> = {
+      let Ok(mut sync) = self.sync.lock() else {
+        return;
+      };
+      let mut users = std::mem::take(&mut sync.dirty);
+      // An open socket keeps its user online without heartbeats, so refresh their document
+      // before other devices time it out
+      for user_id in self.tracker.connected_users() {
+        let pushed = sync.pushed.get(&user_id).copied().unwrap_or(0);
+        if now - pushed >= HEARTBEAT_TIMEOUT_MS / 2 {
+          users.insert(user_id);
+        }
+      }
+      for user_id in &users {
+        sync.pushed.insert(user_id.clone(), now);
+      }
+      users.into_iter().collect()
+    };
+    for user_id in users {
+      let id = format!("{}:{}", self.device, user_id);
+      let doc = json!({
+        "id": id,
+        "user_id": user_id,
+        "device": self.device,
+        "status": self.tracker.status(&user_id, now).0,
+        "last_seen": now,
+        "typing": self.tracker.typing_of(&user_id, now),
+      });
+      let _ = match mongo.find_by_id(PRESENCE_TABLE, &id).await {
+        Ok(Some(_)) => mongo.update(PRESENCE_TABLE, &id, doc).await.map(|_| ()),
+        Ok(None) => mongo.insert(PRESENCE_TABLE, doc).await.map(|_| ()),
+        Err(_) => continue,
+      };
+    }
+  }
+  async fn pull(&self, mongo: &MongoProvider, now: i64) {
+    let since = match self.sync.lock() {
+      Ok(sync) => sync.since,
+      Err(_) => return,
+    };
+    let filter = Filter::And(vec![
+      Filter::Gt("last_seen".to_string(), json!(since)),
+      Filter::Ne("device".to_string(), json!(self.device)),
+    ]);
+    let Ok(docs) = mongo
+      .find_many(
+        PRESENCE_TABLE,
+        Some(&filter),
+        None,
+        None,
+        Some("last_seen"),
+        true,
+      )
+      .await
+    else {
+      return;
+    };
+    let mut newest = since;
+    for doc in &docs {
+      let user_id = doc.get("user_id").and_then(|v| v.as_str()).unwrap_or("");
+      if user_id.is_empty() {
+        continue;
+      }
+      let last_seen = doc.get("last_seen").and_then(|v| v.as_i64()).unwrap_or(0);
+      newest = newest.max(last_seen);
+      let status = doc
+        .get("status")
+        .cloned()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or(PresenceStatus::Offline);
+      self.tracker.observe(user_id, status, last_seen);
+      let typing: HashMap<String, i64> = doc
+        .get("typing")
+        .cloned()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+      for (room_id, expires) in &typing {
+        if self
+          .tracker
+          .set_typing_until(room_id, user_id, *expires, now)
+        {
+          self.publish_typing(room_id, user_id, true);
+        }
+      }
+      for room_id in self.tracker.typing_of(user_id, now).into_keys() {
+        if !typing.contains_key(&room_id) && self.tracker.set_typing(&room_id, user_id, false, now)
+        {
+          self.publish_typing(&room_id, user_id, false);
+        }
+      }
+    }
+    if let Ok(mut sync) = self.sync.lock() {
+      sync.since = sync.since.max(newest - PULL_OVERLAP_MS);
+    }
+  }
+  fn mark_dirty(&self, user_id: &str) {
+    if let Ok(mut sync) = self.sync.lock() {
+      sync.dirty.insert(user_id.to_string());
+    }
+  }
+  /// Participants of the room, falling back to the members of the group owning it.
+  pub async fn room_member_ids(&self, room_id: &str) -> Vec<String> {
+    if let Ok(room) = self.room_service.get_by_room(room_id).await {
+      let ids = string_list(&room.data, "participant_ids");
+      if !ids.is_empty() {
+        return ids;
+      }
+    }
+    match self.group_service.get_by_room_id(room_id).await {
+      Ok(group) => string_list(&group.data, "member_ids"),
+      Err(_) => Vec::new(),
+    }
+  }
+  pub async fn get_room_presence(
+    &self,
+    requester_id: &str,
+    room_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let member_ids = self.room_member_ids(room_id).await;
+    if !member_ids.iter().any(|id| id == requester_id) {
+      return Err(ResponseModel::forbidden(
+        "You do not have access to this room",
+      ));
+    }
+    Ok(success_response(self.describe(room_id, &member_ids)))
+  }
+  pub async fn get_group_presence(
+    &self,
+    requester_id: &str,
+    group_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let group = self.group_service.get_by_id(group_id).await?.data;
+    let member_ids = string_list(&group, "member_ids");
+    if !member_ids.iter().any(|id| id == requester_id) {
+      return Err(ResponseModel::forbidden(
+        "You are not a member of this group",
+      ));
+    }
+    let room_id = group.get("room_id").and_then(|v| v.as_str()).unwrap_or("");
+    let mut view = self.describe(room_id, &member_ids);
+    view["group_id"] = json!(group_id);
+    Ok(success_response(view))
+  }
+  pub async fn update_typing(
+    &self,
+    user_id: &str,
+    room_id: &str,
+    typing: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let member_ids = self.room_member_ids(room_id).await;
+    if !member_ids.iter().any(|id| id == user_id) {
+      return Err(err_response("You do not have access to this room"));
+    }
+    self.set_typing(room_id, user_id, typing);
+    Ok(success_response(
+      json!({ "room_id": room_id, "typing": typing }),
+    ))
+  }
+  fn describe(&self, room_id: &str, member_ids: &[String]) -> Value {
+    let now = now_millis();
+    let members: Vec<Value> = member_ids
+      .iter()
+      .map(|user_id| {
+        let (status, last_seen) = self.tracker.status(user_id, now);
+        json!({ "user_id": user_id, "status": status, "last_seen": last_seen })
+      })
+      .collect();
+    json!({
+      "room_id": room_id,
+      "members": members,
+      "typing": self.tracker.typing(room_id, now),
+    })
+  }
+  fn clear_typing(&self, user_id: &str) {
+    self.mark_dirty(user_id);
+    for room_id in self.tracker.clear_typing(user_id) {
+      self.publish_typing(&room_id, user_id, false);
+    }
+  }
+  fn publish_typing(&self, room_id: &str, user_id: &str, typing: bool) {
+    let kind = if typing {
+      ChatEventKind::TypingStarted
+    } else {
+      ChatEventKind::TypingStopped
+    };
+    self.hub.publish(ChatEvent::new(
+      kind,
+      room_id,
+      None,
+      json!({ "user_id": user_id }),
+    ));
+  }
+}
+fn now_millis() -> i64 {
+  Utc::now().timestamp_millis()
+}
+fn string_list(doc: &Value, field: &str) -> Vec<String> {
+  doc
+    .get(field)
+    .and_then(|v| v.as_array())
+    .map(|ids| {
+      ids
+        .iter()
+        .filter_map(|id| id.as_str().map(|s| s.to_string()))
+        .collect()
+    })
+    .unwrap_or_default()
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn heartbeat_expires_to_offline() {
+    let tracker = PresenceTracker::default();
+    assert_eq!(tracker.status("u1", 0).0, PresenceStatus::Offline);
+    tracker.heartbeat("u1", PresenceStatus::Away, 1_000);
+    assert_eq!(tracker.status("u1", 2_000).0, PresenceStatus::Away);
+    let later = 1_000 + HEARTBEAT_TIMEOUT_MS;
+    assert_eq!(
+      tracker.status("u1", later),
+      (PresenceStatus::Offline, Some(1_000))
+    );
+  }
+  #[test]
+  fn open_connections_keep_user_online() {
+    let tracker = PresenceTracker::default();
+    tracker.connect("u1", 0);
+    tracker.connect("u1", 0);
+    assert_eq!(
+      tracker.status("u1", 10 * HEARTBEAT_TIMEOUT_MS).0,
+      PresenceStatus::Online
+    );
+    assert!(!tracker.disconnect("u1", 5));
+    assert!(tracker.disconnect("u1", 6));
+    assert_eq!(tracker.status("u1", 7).0, PresenceStatus::Offline);
+  }
+  #[test]
+  fn typing_reports_transitions_and_expires() {
+    let tracker = PresenceTracker::default();
+    assert!(tracker.set_typing("room", "u1", true, 0));
+    assert!(!tracker.set_typing("room", "u1", true, 1_000));
+    assert!(tracker.set_typing("room", "u2", true, 1_000));
+    assert_eq!(tracker.typing("room", 2_000), vec!["u1", "u2"]);
+    assert_eq!(
+      tracker.typing("room", 1_000 + TYPING_TTL_MS),
+      Vec::<String>::new()
+    );
+    assert!(tracker.set_typing("room", "u1", true, 20_000));
+    assert!(tracker.set_typing("room", "u1", false, 20_001));
+    assert!(!tracker.set_typing("room", "u1", false, 20_002));
+    tracker.set_typing("other", "u3", true, 0);
+    assert_eq!(tracker.clear_typing("u3"), vec!["other".to_string()]);
+  }
+  #[test]
+  fn expired_typing_is_reported_once() {
+    let tracker = PresenceTracker::default();
+    tracker.set_typing("room", "u1", true, 0);
+    tracker.set_typing("room", "u2", true, 5_000);
+    assert!(tracker.expire_typing(TYPING_TTL_MS - 1).is_empty());
+    assert_eq!(
+      tracker.expire_typing(TYPING_TTL_MS),
+      vec![("room".to_string(), "u1".to_string())]
+    );
+    assert!(tracker.expire_typing(TYPING_TTL_MS).is_empty());
+    assert_eq!(tracker.typing("room", TYPING_TTL_MS), vec!["u2"]);
+  }
+  #[test]
+  fn remote_presence_does_not_override_local_connections() {
+    let tracker = PresenceTracker::default();
+    tracker.observe("u1", PresenceStatus::Away, 1_000);
+    assert_eq!(tracker.status("u1", 2_000).0, PresenceStatus::Away);
+    tracker.observe("u1", PresenceStatus::Online, 500);
+    assert_eq!(tracker.status("u1", 2_000).0, PresenceStatus::Away);
+    tracker.connect("u2", 0);
+    tracker.observe("u2", PresenceStatus::Offline, 1_000);
+    assert_eq!(tracker.status("u2", 2_000).0, PresenceStatus::Online);
+    assert_eq!(tracker.connected_users(), vec!["u2".to_string()]);
+  }
+}
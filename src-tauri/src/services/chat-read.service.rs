@@ -0,0 +1,281 @@
+/* sys lib */
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+/* providers */
+use crate::repositories::data_provider::DataProvider;
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::chat_service::ChatService;
+/* helpers */
+use crate::utils::response_helper::{err_response, success_response};
+/* nosql_orm */
+use nosql_orm::query::Filter;
+const CURSOR_TABLE: &str = "chat_read_cursors";
+/// Unread badges stop counting here; the client renders anything at the cap as "99+".
+pub const MAX_UNREAD_COUNT: u64 = 100;
+/// Per-user, per-room read cursors. Unread counts and read receipts are derived from the
+/// cursors instead of the per-message `read_by` arrays.
+pub struct ChatReadService {
+  json_provider: DataProvider,
+  mongo_provider: Option<DataProvider>,
+  chat_service: Arc<ChatService>,
+}
+impl ChatReadService {
+  pub fn new(
+    json_provider: DataProvider,
+    mongo_provider: Option<DataProvider>,
+    chat_service: Arc<ChatService>,
+  ) -> Self {
+    Self {
+      json_provider,
+      mongo_provider,
+      chat_service,
+    }
+  }
+  fn get_mongo_provider(&self) -> Option<&DataProvider> {
+    self.mongo_provider.as_ref()
+  }
+  /// The user's cursor in the room, taking the furthest of the local and cloud copies.
+  pub async fn cursor(&self, room_id: &str, user_id: &str) -> Option<Value> {
+    let id = cursor_id(room_id, user_id);
+    let local = self
+      .json_provider
+      .find_by_id(CURSOR_TABLE, &id)
+      .await
+      .ok()
+      .flatten();
+    let cloud = match self.get_mongo_provider() {
+      Some(mongo) => mongo.find_by_id(CURSOR_TABLE, &id).await.ok().flatten(),
+      None => None,
+    };
+    furthest(local, cloud)
+  }
+  /// One cursor per user that has read anything in the room.
+  pub async fn room_cursors(&self, room_id: &str) -> Vec<Value> {
+    let filter = Filter::Eq("room_id".to_string(), json!(room_id));
+    let mut docs = self
+      .json_provider
+      .find_many(CURSOR_TABLE, Some(&filter), None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = self.get_mongo_provider() {
+      docs.extend(
+        mongo
+          .find_many(CURSOR_TABLE, Some(&filter), None, None, None, true)
+          .await
+          .unwrap_or_default(),
+      );
+    }
+    let mut by_user: HashMap<String, Value> = HashMap::new();
+    for doc in docs {
+      let user_id = doc
+        .get("user_id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+      let current = by_user.remove(&user_id);
+      if let Some(cursor) = furthest(current, Some(doc)) {
+        by_user.insert(user_id, cursor);
+      }
+    }
+    by_user.into_values().collect()
+  }
+  /// Moves the user's cursor to `message_id`. Cursors only move forward, so marking an
+  /// older message read is a no-op.
+  pub async fn mark_read_up_to(
+    &self,
+    room_id: &str,
+    user_id: &str,
+    message_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let message = self.chat_service.find_message(message_id).await?;
+    if message.get("room_id").and_then(|v| v.as_str()) != Some(room_id) {
+      return Err(err_response("Message belongs to another room"));
+    }
+    let created_at = message
+      .get("created_at")
+      .and_then(|v| v.as_str())
+      .ok_or_else(|| err_response("Message has no timestamp"))?;
+    let current = self.cursor(room_id, user_id).await;
+    let cursor = match current {
+      Some(cursor) if read_at(&cursor) >= created_at => cursor,
+      current => {
+        let now = Utc::now().to_rfc3339();
+        let created = current
+          .as_ref()
+          .and_then(|c| c.get("created_at").cloned())
+          .unwrap_or_else(|| json!(now));
+        let doc = json!({
+          "id": cursor_id(room_id, user_id),
+          "room_id": room_id,
+          "user_id": user_id,
+          "last_read_id": message_id,
+          "last_read_at": created_at,
+          "created_at": created,
+          "updated_at": now,
+        });
+        self.store(doc).await?
+      }
+    };
+    let unread_count = self.unread_count(room_id, user_id).await;
+    Ok(success_response(
+      json!({ "cursor": cursor, "unread_count": unread_count }),
+    ))
+  }
+  /// Messages from other users after the cursor, capped at `MAX_UNREAD_COUNT`.
+  pub async fn unread_count(&self, room_id: &str, user_id: &str) -> u64 {
+    let mut filters = vec![
+      Filter::Eq("room_id".to_string(), json!(room_id)),
+      Filter::Ne("sender_id".to_string(), json!(user_id)),
+    ];
+    if let Some(cursor) = self.cursor(room_id, user_id).await {
+      filters.push(Filter::Gt(
+        "created_at".to_string(),
+        json!(read_at(&cursor)),
+      ));
+    }
+    let filter = Filter::And(filters);
+    let provider = self.get_mongo_provider().unwrap_or(&self.json_provider);
+    let docs = match provider
+      .find_many(
+        "chats",
+        Some(&filter),
+        None,
+        Some(MAX_UNREAD_COUNT),
+        None,
+        true,
+      )
+      .await
+    {
+      Ok(docs) => docs,
+      Err(_) => self
+        .json_provider
+        .find_many(
+          "chats",
+          Some(&filter),
+          None,
+          Some(MAX_UNREAD_COUNT),
+          None,
+          true,
+        )
+        .await
+        .unwrap_or_default(),
+    };
+    docs
+      .iter()
+      .filter(|doc| doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true))
+      .count() as u64
+  }
+  pub async fn unread_counts(&self, room_ids: &[String], user_id: &str) -> HashMap<String, u64> {
+    let mut counts = HashMap::new();
+    for room_id in room_ids {
+      counts.insert(room_id.clone(), self.unread_count(room_id, user_id).await);
+    }
+    counts
+  }
+  /// Who has read a message, based on how far each room member's cursor has moved.
+  pub async fn read_receipts(
+    &self,
+    message_id: &str,
+    member_ids: &[String],
+  ) -> Result<ResponseModel, ResponseModel> {
+    let message = self.chat_service.find_message(message_id).await?;
+    let room_id = message
+      .get("room_id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("");
+    let sender_id = message
+      .get("sender_id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("");
+    let created_at = message
+      .get("created_at")
+      .and_then(|v| v.as_str())
+      .unwrap_or("");
+    let cursors = self.room_cursors(room_id).await;
+    let mut read_by: Vec<Value> = cursors
+      .iter()
+      .filter(|cursor| {
+        let user_id = cursor.get("user_id").and_then(|v| v.as_str()).unwrap_or("");
+        user_id != sender_id && read_at(cursor) >= created_at
+      })
+      .map(|cursor| {
+        json!({
+          "user_id": cursor.get("user_id"),
+          "read_at": cursor.get("updated_at"),
+        })
+      })
+      .collect();
+    read_by.sort_by(|a, b| a["user_id"].as_str().cmp(&b["user_id"].as_str()));
+    let unread_by: Vec<&String> = member_ids
+      .iter()
+      .filter(|id| id.as_str() != sender_id)
+      .filter(|id| {
+        !read_by
+          .iter()
+          .any(|r| r["user_id"].as_str() == Some(id.as_str()))
+      })
+      .collect();
+    Ok(success_response(json!({
+      "message_id": message_id,
+      "room_id": room_id,
+      "read_count": read_by.len(),
+      "read_by": read_by,
+      "unread_by": unread_by,
+    })))
+  }
+  /// Writes the cursor locally and, when reachable, to MongoDB so other members see it.
+  async fn store(&self, doc: Value) -> Result<Value, ResponseModel> {
+    let id = doc.get("id").and_then(|v| v.as_str()).unwrap_or("");
+    let saved = match self.json_provider.find_by_id(CURSOR_TABLE, id).await? {
+      Some(_) => {
+        self
+          .json_provider
+          .update(CURSOR_TABLE, id, doc.clone())
+          .await?
+      }
+      None => self.json_provider.insert(CURSOR_TABLE, doc.clone()).await?,
+    };
+    if let Some(mongo) = self.get_mongo_provider() {
+      let _ = match mongo.find_by_id(CURSOR_TABLE, id).await {
+        Ok(Some(_)) => mongo.update(CURSOR_TABLE, id, doc).await,
+        Ok(None) => mongo.insert(CURSOR_TABLE, doc).await,
+        Err(e) => Err(e),
+      };
+    }
+    Ok(saved)
+  }
+}
+fn cursor_id(room_id: &str, user_id: &str) -> String {
+  format!("{}:{}", room_id, user_id)
+}
+fn read_at(cursor: &Value) -> &str {
+  cursor
+    .get("last_read_at")
+    .and_then(|v| v.as_str())
+    .unwrap_or("")
+}
+fn furthest(a: Option<Value>, b: Option<Value>) -> Option<Value> {
+  match (a, b) {
+    (Some(a), Some(b)) => Some(if read_at(&b) > read_at(&a) { b } else { a }),
+    (a, b) => a.or(b),
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn furthest_cursor_wins() {
+    let older = json!({ "last_read_at": "2024-05-01T10:00:00+00:00" });
+    let newer = json!({ "last_read_at": "2024-05-01T11:00:00+00:00" });
+    assert_eq!(
+      furthest(Some(newer.clone()), Some(older.clone())),
+      Some(newer.clone())
+    );
+    assert_eq!(furthest(Some(older), Some(newer.clone())), Some(newer));
+    assert_eq!(furthest(None, None), None);
+  }
+}
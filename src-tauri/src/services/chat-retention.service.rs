@@ -0,0 +1,344 @@
+/* sys lib */
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use std::sync::Arc;
+/* providers */
+use crate::repositories::data_provider::DataProvider;
+/* entities */
+use crate::entities::chat_retention_entity::{ChatRetentionPolicyEntity, ChatRetentionRunEntity};
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::chat_events_service::{ChatEvent, ChatEventKind};
+use crate::services::chat_service::ChatService;
+use crate::services::group_service::GroupService;
+use crate::services::room_service::RoomService;
+/* helpers */
+use crate::utils::group_roles;
+use crate::utils::response_helper::{err_response, success_response};
+/* nosql_orm */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::query::Filter;
+const POLICIES_TABLE: &str = "chat_retention_policies";
+const RUNS_TABLE: &str = "chat_retention_log";
+/// Shortest retention a policy may ask for.
+const MIN_RETENTION_DAYS: u32 = 1;
+/// Per-room message retention and the background purge that enforces it.
+pub struct ChatRetentionService {
+  json_provider: DataProvider,
+  mongo_provider: Option<DataProvider>,
+  chat_service: Arc<ChatService>,
+  group_service: Arc<GroupService>,
+  room_service: Arc<RoomService>,
+}
+impl ChatRetentionService {
+  pub fn new(
+    json_provider: DataProvider,
+    mongo_provider: Option<DataProvider>,
+    chat_service: Arc<ChatService>,
+    group_service: Arc<GroupService>,
+    room_service: Arc<RoomService>,
+  ) -> Self {
+    Self {
+      json_provider,
+      mongo_provider,
+      chat_service,
+      group_service,
+      room_service,
+    }
+  }
+  fn get_mongo_provider(&self) -> Result<&DataProvider, ResponseModel> {
+    self
+      .mongo_provider
+      .as_ref()
+      .ok_or_else(|| err_response("MongoDB not available"))
+  }
+  /// Group rooms are managed by group admins. In other rooms any participant may propose or
+  /// withdraw a policy, but it only runs once every participant agreed to it.
+  async fn can_manage(&self, room_id: &str, actor_id: &str) -> bool {
+    if let Ok(group) = self.group_service.get_by_room_id(room_id).await {
+      return group_roles::can_moderate(group_roles::role_of(&group.data, actor_id));
+    }
+    match self.room_service.get_by_room(room_id).await {
+      Ok(room) => group_roles::string_list(&room.data, "participant_ids")
+        .iter()
+        .any(|id| id == actor_id),
+      Err(_) => false,
+    }
+  }
+  /// Participants who all have to agree to a policy, or `None` for group rooms.
+  async fn required_agreement(&self, room_id: &str) -> Option<Vec<String>> {
+    if self.group_service.get_by_room_id(room_id).await.is_ok() {
+      return None;
+    }
+    Some(match self.room_service.get_by_room(room_id).await {
+      Ok(room) => group_roles::string_list(&room.data, "participant_ids"),
+      Err(_) => Vec::new(),
+    })
+  }
+  async fn find_policy(&self, room_id: &str) -> Result<Option<Value>, ResponseModel> {
+    let filter = Filter::Eq("room_id".to_string(), json!(room_id));
+    Ok(
+      self
+        .get_mongo_provider()?
+        .find_many(POLICIES_TABLE, Some(&filter), None, Some(1), None, true)
+        .await?
+        .into_iter()
+        .next(),
+    )
+  }
+  pub async fn get_policy(&self, room_id: &str) -> Result<ResponseModel, ResponseModel> {
+    Ok(success_response(
+      self.find_policy(room_id).await?.unwrap_or(Value::Null),
+    ))
+  }
+  pub async fn set_policy(
+    &self,
+    room_id: &str,
+    actor_id: &str,
+    max_age_days: u32,
+    hard_delete: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    if !self.can_manage(room_id, actor_id).await {
+      return Err(ResponseModel::forbidden(
+        "You do not have permission to change retention for this room",
+      ));
+    }
+    if max_age_days < MIN_RETENTION_DAYS {
+      return Err(err_response("Retention must be at least one day"));
+    }
+    let mongo = self.get_mongo_provider()?;
+    let now = Utc::now();
+    let existing = self.find_policy(room_id).await?;
+    let agreed_by = match self.required_agreement(room_id).await {
+      Some(_) => agreed_after(existing.as_ref(), max_age_days, hard_delete, actor_id),
+      None => Vec::new(),
+    };
+    let doc = match existing {
+      Some(existing) => {
+        let id = existing.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        let update_data = json!({
+          "max_age_days": max_age_days,
+          "hard_delete": hard_delete,
+          "set_by": actor_id,
+          "agreed_by": agreed_by,
+          "updated_at": now.to_rfc3339(),
+        });
+        let doc = mongo.patch(POLICIES_TABLE, id, update_data.clone()).await?;
+        if let DataProvider::Json(p) = &self.json_provider {
+          let _ = p.patch(POLICIES_TABLE, id, update_data).await;
+        }
+        doc
+      }
+      None => {
+        let policy = ChatRetentionPolicyEntity {
+          id: Some(nosql_orm::utils::generate_id()),
+          room_id: room_id.to_string(),
+          max_age_days,
+          hard_delete,
+          set_by: actor_id.to_string(),
+          agreed_by,
+          last_run_at: None,
+          created_at: Some(now),
+          updated_at: Some(now),
+        };
+        let value = serde_json::to_value(&policy)
+          .map_err(|e| err_response(&format!("Failed to save retention policy: {}", e)))?;
+        let doc = mongo.insert(POLICIES_TABLE, value).await?;
+        if let DataProvider::Json(p) = &self.json_provider {
+          let _ = p.insert(POLICIES_TABLE, doc.clone()).await;
+        }
+        doc
+      }
+    };
+    Ok(success_response(doc))
+  }
+  pub async fn remove_policy(
+    &self,
+    room_id: &str,
+    actor_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    if !self.can_manage(room_id, actor_id).await {
+      return Err(ResponseModel::forbidden(
+        "You do not have permission to change retention for this room",
+      ));
+    }
+    if let Some(existing) = self.find_policy(room_id).await? {
+      let id = existing.get("id").and_then(|v| v.as_str()).unwrap_or("");
+      self
+        .get_mongo_provider()?
+        .delete(POLICIES_TABLE, id)
+        .await?;
+      if let DataProvider::Json(p) = &self.json_provider {
+        let _ = p.delete(POLICIES_TABLE, id).await;
+      }
+    }
+    Ok(success_response(
+      json!({ "room_id": room_id, "removed": true }),
+    ))
+  }
+  /// Past retention runs for the room, newest first.
+  pub async fn get_purge_log(
+    &self,
+    room_id: &str,
+    skip: Option<u64>,
+    limit: Option<u64>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let filter = Filter::Eq("room_id".to_string(), json!(room_id));
+    let docs = self
+      .get_mongo_provider()?
+      .find_many(
+        RUNS_TABLE,
+        Some(&filter),
+        skip,
+        limit,
+        Some("created_at"),
+        false,
+      )
+      .await?;
+    Ok(success_response(docs))
+  }
+  /// Applies every policy that is in force once. Returns how many messages were purged in total and
+  /// whether any of them were hard-deleted (their attachments may now be orphaned).
+  pub async fn run(&self) -> Result<(usize, bool), ResponseModel> {
+    let policies = self
+      .get_mongo_provider()?
+      .find_many(POLICIES_TABLE, None, None, None, None, true)
+      .await?;
+    let mut total = 0;
+    let mut any_hard = false;
+    for doc in policies {
+      let Ok(policy) = serde_json::from_value::<ChatRetentionPolicyEntity>(doc) else {
+        continue;
+      };
+      if let Some(participants) = self.required_agreement(&policy.room_id).await {
+        if !fully_agreed(&participants, &policy.agreed_by) {
+          continue;
+        }
+      }
+      match self.apply(&policy).await {
+        Ok(count) => {
+          total += count;
+          any_hard |= policy.hard_delete && count > 0;
+        }
+        Err(e) => {
+          let cutoff = Utc::now() - Duration::days(policy.max_age_days as i64);
+          self
+            .log_run(&policy, cutoff, Vec::new(), Some(e.message))
+            .await;
+        }
+      }
+    }
+    Ok((total, any_hard))
+  }
+  async fn apply(&self, policy: &ChatRetentionPolicyEntity) -> Result<usize, ResponseModel> {
+    let now = Utc::now();
+    let cutoff = now - Duration::days(policy.max_age_days as i64);
+    let purged = self
+      .chat_service
+      .purge_room(
+        &policy.room_id,
+        Some(&cutoff.to_rfc3339()),
+        policy.hard_delete,
+      )
+      .await?;
+    let mongo = self.get_mongo_provider()?;
+    let _ = mongo
+      .patch(
+        POLICIES_TABLE,
+        policy.id.as_deref().unwrap_or_default(),
+        json!({ "last_run_at": now.to_rfc3339() }),
+      )
+      .await;
+    if purged.is_empty() {
+      return Ok(0);
+    }
+    for message_id in &purged {
+      self.chat_service.events().publish(ChatEvent::new(
+        ChatEventKind::MessageDeleted,
+        &policy.room_id,
+        Some(message_id),
+        Value::Null,
+      ));
+    }
+    let count = purged.len();
+    self.log_run(policy, cutoff, purged, None).await;
+    Ok(count)
+  }
+  /// Records a run in the room's retention log, with the error when it failed.
+  async fn log_run(
+    &self,
+    policy: &ChatRetentionPolicyEntity,
+    cutoff: DateTime<Utc>,
+    purged: Vec<String>,
+    error: Option<String>,
+  ) {
+    let now = Utc::now();
+    let run = ChatRetentionRunEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      room_id: policy.room_id.clone(),
+      policy_id: policy.id.clone().unwrap_or_default(),
+      cutoff,
+      hard_delete: policy.hard_delete,
+      purged_count: purged.len(),
+      message_ids: purged,
+      error,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let Ok(value) = serde_json::to_value(&run) else {
+      return;
+    };
+    if let Ok(mongo) = self.get_mongo_provider() {
+      let _ = mongo.insert(RUNS_TABLE, value.clone()).await;
+    }
+    if let DataProvider::Json(p) = &self.json_provider {
+      let _ = p.insert(RUNS_TABLE, value).await;
+    }
+  }
+}
+/// Who has agreed once `actor_id` sets these terms: changed terms start over with just the
+/// actor, the same terms add them to the earlier agreements.
+fn agreed_after(
+  existing: Option<&Value>,
+  max_age_days: u32,
+  hard_delete: bool,
+  actor_id: &str,
+) -> Vec<String> {
+  let same_terms = existing.is_some_and(|policy| {
+    policy.get("max_age_days").and_then(|v| v.as_u64()) == Some(max_age_days as u64)
+      && policy
+        .get("hard_delete")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        == hard_delete
+  });
+  let mut agreed = match existing {
+    Some(policy) if same_terms => group_roles::string_list(policy, "agreed_by"),
+    _ => Vec::new(),
+  };
+  if !agreed.iter().any(|id| id == actor_id) {
+    agreed.push(actor_id.to_string());
+  }
+  agreed
+}
+fn fully_agreed(participants: &[String], agreed_by: &[String]) -> bool {
+  !participants.is_empty() && participants.iter().all(|id| agreed_by.contains(id))
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn direct_room_policies_need_every_participant() {
+    let participants = vec!["a".to_string(), "b".to_string()];
+    let proposed = agreed_after(None, 30, false, "a");
+    assert!(!fully_agreed(&participants, &proposed));
+    let policy = json!({ "max_age_days": 30, "hard_delete": false, "agreed_by": proposed });
+    let accepted = agreed_after(Some(&policy), 30, false, "b");
+    assert!(fully_agreed(&participants, &accepted));
+    // Changing the terms needs everyone's agreement again
+    let changed = agreed_after(Some(&policy), 7, true, "b");
+    assert_eq!(changed, vec!["b".to_string()]);
+    assert!(!fully_agreed(&[], &accepted));
+  }
+}
@@ -0,0 +1,483 @@
+/* sys lib */
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::Message;
+/* services */
+use crate::services::chat_events_service::{ChatEvent, ChatEventHub, ChatEventKind};
+use crate::services::chat_presence_service::{PresenceService, PresenceStatus};
+use crate::services::chat_service::ChatService;
+use crate::services::group_service::GroupService;
+use crate::services::room_service::RoomService;
+/* helpers */
+use crate::utils::access_token::TokenScope;
+use crate::utils::auth::extract_user_with_scope;
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+/// What the socket server needs from the rest of the app; swapped for a stub in tests.
+#[async_trait]
+pub trait ChatRealtimeBackend: Send + Sync {
+  async fn authenticate(&self, token: &str) -> Result<String, String>;
+  async fn can_access_room(&self, user_id: &str, room_id: &str) -> bool;
+  /// Messages after `last_message_id`, or `None` when that message is unknown.
+  async fn messages_since(
+    &self,
+    room_id: &str,
+    last_message_id: &str,
+  ) -> Result<Option<Vec<Value>>, String>;
+  /// Presence hooks; no-ops unless the backend tracks presence.
+  fn connected(&self, _user_id: &str) {}
+  fn disconnected(&self, _user_id: &str) {}
+  fn heartbeat(&self, _user_id: &str, _status: Option<PresenceStatus>) {}
+  fn typing(&self, _room_id: &str, _user_id: &str, _typing: bool) {}
+}
+pub struct ServiceChatBackend {
+  chat_service: Arc<ChatService>,
+  room_service: Arc<RoomService>,
+  group_service: Arc<GroupService>,
+  presence_service: Arc<PresenceService>,
+  jwt_secret: String,
+}
+impl ServiceChatBackend {
+  pub fn new(
+    chat_service: Arc<ChatService>,
+    room_service: Arc<RoomService>,
+    group_service: Arc<GroupService>,
+    presence_service: Arc<PresenceService>,
+    jwt_secret: String,
+  ) -> Self {
+    Self {
+      chat_service,
+      room_service,
+      group_service,
+      presence_service,
+      jwt_secret,
+    }
+  }
+}
+fn contains_id(doc: &Value, field: &str, user_id: &str) -> bool {
+  doc
+    .get(field)
+    .and_then(|v| v.as_array())
+    .map(|ids| ids.iter().any(|id| id.as_str() == Some(user_id)))
+    .unwrap_or(false)
+}
+#[async_trait]
+impl ChatRealtimeBackend for ServiceChatBackend {
+  async fn authenticate(&self, token: &str) -> Result<String, String> {
+    extract_user_with_scope(token, &self.jwt_secret, TokenScope::Chat).map_err(|e| e.message)
+  }
+  async fn can_access_room(&self, user_id: &str, room_id: &str) -> bool {
+    if let Ok(room) = self.room_service.get_by_room(room_id).await {
+      if contains_id(&room.data, "participant_ids", user_id) {
+        return true;
+      }
+    }
+    match self.group_service.get_by_room_id(room_id).await {
+      Ok(group) => contains_id(&group.data, "member_ids", user_id),
+      Err(_) => false,
+    }
+  }
+  async fn messages_since(
+    &self,
+    room_id: &str,
+    last_message_id: &str,
+  ) -> Result<Option<Vec<Value>>, String> {
+    self
+      .chat_service
+      .get_messages_after(room_id, last_message_id)
+      .await
+      .map_err(|e| e.message)
+  }
+  fn connected(&self, user_id: &str) {
+    self.presence_service.connect(user_id);
+  }
+  fn disconnected(&self, user_id: &str) {
+    self.presence_service.disconnect(user_id);
+  }
+  fn heartbeat(&self, user_id: &str, status: Option<PresenceStatus>) {
+    self.presence_service.heartbeat(user_id, status);
+  }
+  fn typing(&self, room_id: &str, user_id: &str, typing: bool) {
+    self.presence_service.set_typing(room_id, user_id, typing);
+  }
+}
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientFrame {
+  Auth {
+    token: String,
+  },
+  Subscribe {
+    room_id: String,
+    #[serde(default)]
+    last_message_id: Option<String>,
+  },
+  Unsubscribe {
+    room_id: String,
+  },
+  Typing {
+    room_id: String,
+    typing: bool,
+  },
+  Presence {
+    status: PresenceStatus,
+  },
+  Ping,
+}
+/// WebSocket endpoint that pushes chat events to clients subscribed to their rooms.
+///
+/// Protocol (JSON text frames): the client sends `auth` first, then `subscribe` /
+/// `unsubscribe` per room, plus `typing` / `presence` / `ping` while connected.
+/// Passing `last_message_id` on subscribe replays everything the client missed
+/// while disconnected before live events resume.
+pub struct ChatSocketServer {
+  backend: Arc<dyn ChatRealtimeBackend>,
+  hub: ChatEventHub,
+}
+impl ChatSocketServer {
+  pub fn new(backend: Arc<dyn ChatRealtimeBackend>, hub: ChatEventHub) -> Self {
+    Self { backend, hub }
+  }
+  pub async fn serve(self, listener: TcpListener) {
+    let server = Arc::new(self);
+    loop {
+      match listener.accept().await {
+        Ok((stream, _)) => {
+          let server = server.clone();
+          tokio::spawn(async move {
+            let _ = server.handle_connection(stream).await;
+          });
+        }
+        Err(_) => {}
+      }
+    }
+  }
+  async fn handle_connection(&self, stream: TcpStream) -> Result<(), String> {
+    let ws = tokio_tungstenite::accept_async(stream)
+      .await
+      .map_err(|e| e.to_string())?;
+    let (mut sink, mut source) = ws.split();
+    // Subscribe before authenticating so nothing published during the handshake is lost
+    let events = self.hub.subscribe();
+    let user_id = match tokio::time::timeout(AUTH_TIMEOUT, source.next()).await {
+      Ok(Some(Ok(Message::Text(text)))) => match serde_json::from_str::<ClientFrame>(&text) {
+        Ok(ClientFrame::Auth { token }) => self.backend.authenticate(&token).await,
+        _ => Err("Expected an auth frame".to_string()),
+      },
+      _ => Err("Authentication required".to_string()),
+    };
+    let user_id = match user_id {
+      Ok(user_id) => user_id,
+      Err(message) => {
+        let _ = send_json(&mut sink, json!({ "type": "error", "message": message })).await;
+        let _ = sink.close().await;
+        return Ok(());
+      }
+    };
+    send_json(&mut sink, json!({ "type": "ready", "user_id": user_id })).await?;
+    self.backend.connected(&user_id);
+    let result = self.run_session(&user_id, sink, source, events).await;
+    self.backend.disconnected(&user_id);
+    result
+  }
+  async fn run_session<S, R>(
+    &self,
+    user_id: &str,
+    mut sink: S,
+    mut source: R,
+    mut events: tokio::sync::broadcast::Receiver<ChatEvent>,
+  ) -> Result<(), String>
+  where
+    S: SinkExt<Message> + Unpin,
+    S::Error: std::fmt::Display,
+    R: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+  {
+    let mut rooms: HashSet<String> = HashSet::new();
+    // Ids already delivered by a resume replay, so the live copy is not sent twice
+    let mut replayed: HashSet<String> = HashSet::new();
+    loop {
+      tokio::select! {
+        incoming = source.next() => {
+          let text = match incoming {
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(Message::Ping(payload))) => {
+              sink.send(Message::Pong(payload)).await.map_err(|e| e.to_string())?;
+              continue;
+            }
+            Some(Ok(Message::Close(_))) | None => break,
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.to_string()),
+          };
+          let reply = match serde_json::from_str::<ClientFrame>(&text) {
+            Ok(frame) => {
+              self
+                .handle_frame(frame, user_id, &mut rooms, &mut replayed, &mut sink)
+                .await?
+            }
+            Err(e) => Some(json!({ "type": "error", "message": format!("Invalid frame: {}", e) })),
+          };
+          if let Some(reply) = reply {
+            send_json(&mut sink, reply).await?;
+          }
+        }
+        event = events.recv() => match event {
+          Ok(event) => {
+            if !rooms.contains(&event.room_id) {
+              continue;
+            }
+            if event.kind == ChatEventKind::MessageCreated {
+              if let Some(id) = &event.message_id {
+                if replayed.remove(id) {
+                  continue;
+                }
+              }
+            }
+            send_json(&mut sink, json!({ "type": "event", "event": event })).await?;
+          }
+          Err(RecvError::Lagged(skipped)) => {
+            // The client resumes from its last message id via a fresh subscribe
+            send_json(&mut sink, json!({ "type": "lagged", "skipped": skipped })).await?;
+          }
+          Err(RecvError::Closed) => break,
+        }
+      }
+    }
+    Ok(())
+  }
+  async fn handle_frame<S>(
+    &self,
+    frame: ClientFrame,
+    user_id: &str,
+    rooms: &mut HashSet<String>,
+    replayed: &mut HashSet<String>,
+    sink: &mut S,
+  ) -> Result<Option<Value>, String>
+  where
+    S: SinkExt<Message> + Unpin,
+    S::Error: std::fmt::Display,
+  {
+    match frame {
+      ClientFrame::Auth { .. } => Ok(Some(
+        json!({ "type": "error", "message": "Already authenticated" }),
+      )),
+      ClientFrame::Ping => {
+        self.backend.heartbeat(user_id, None);
+        Ok(Some(json!({ "type": "pong" })))
+      }
+      ClientFrame::Presence { status } => {
+        self.backend.heartbeat(user_id, Some(status));
+        Ok(None)
+      }
+      ClientFrame::Typing { room_id, typing } => {
+        if !rooms.contains(&room_id) {
+          return Ok(Some(
+            json!({ "type": "error", "room_id": room_id, "message": "Subscribe to the room first" }),
+          ));
+        }
+        self.backend.typing(&room_id, user_id, typing);
+        Ok(None)
+      }
+      ClientFrame::Unsubscribe { room_id } => {
+        rooms.remove(&room_id);
+        Ok(Some(json!({ "type": "unsubscribed", "room_id": room_id })))
+      }
+      ClientFrame::Subscribe {
+        room_id,
+        last_message_id,
+      } => {
+        if !self.backend.can_access_room(user_id, &room_id).await {
+          return Ok(Some(json!({
+            "type": "error",
+            "room_id": room_id,
+            "message": "You do not have access to this room"
+          })));
+        }
+        rooms.insert(room_id.clone());
+        let mut missed = 0;
+        if let Some(last_id) = last_message_id {
+          match self.backend.messages_since(&room_id, &last_id).await {
+            Err(message) => {
+              return Ok(Some(
+                json!({ "type": "error", "room_id": room_id, "message": message }),
+              ));
+            }
+            Ok(Some(messages)) => {
+              for message in messages {
+                let Some(event) = ChatEvent::from_message(ChatEventKind::MessageCreated, &message)
+                else {
+                  continue;
+                };
+                if let Some(id) = &event.message_id {
+                  replayed.insert(id.clone());
+                }
+                let frame = json!({ "type": "event", "replay": true, "event": event });
+                send_json(sink, frame).await?;
+                missed += 1;
+              }
+            }
+            Ok(None) => {
+              send_json(
+                sink,
+                json!({ "type": "resync_required", "room_id": room_id }),
+              )
+              .await?;
+            }
+          }
+        }
+        Ok(Some(
+          json!({ "type": "subscribed", "room_id": room_id, "missed": missed }),
+        ))
+      }
+    }
+  }
+}
+async fn send_json<S>(sink: &mut S, value: Value) -> Result<(), String>
+where
+  S: SinkExt<Message> + Unpin,
+  S::Error: std::fmt::Display,
+{
+  sink
+    .send(Message::text(value.to_string()))
+    .await
+    .map_err(|e| e.to_string())
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tokio_tungstenite::connect_async;
+  struct StubBackend {
+    history: Vec<Value>,
+  }
+  #[async_trait]
+  impl ChatRealtimeBackend for StubBackend {
+    async fn authenticate(&self, token: &str) -> Result<String, String> {
+      match token {
+        "good" => Ok("user-1".to_string()),
+        _ => Err("Invalid token".to_string()),
+      }
+    }
+    async fn can_access_room(&self, user_id: &str, room_id: &str) -> bool {
+      user_id == "user-1" && room_id == "room-a"
+    }
+    async fn messages_since(
+      &self,
+      _room_id: &str,
+      last_message_id: &str,
+    ) -> Result<Option<Vec<Value>>, String> {
+      let Some(pos) = self
+        .history
+        .iter()
+        .position(|m| m["id"] == json!(last_message_id))
+      else {
+        return Ok(None);
+      };
+      Ok(Some(self.history[pos + 1..].to_vec()))
+    }
+  }
+  fn message(id: &str) -> Value {
+    json!({ "id": id, "room_id": "room-a", "content": id })
+  }
+  async fn start(history: Vec<Value>) -> (String, ChatEventHub) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let hub = ChatEventHub::new();
+    let server = ChatSocketServer::new(Arc::new(StubBackend { history }), hub.clone());
+    tokio::spawn(server.serve(listener));
+    (url, hub)
+  }
+  async fn recv<S>(source: &mut S) -> Value
+  where
+    S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+  {
+    loop {
+      let next = tokio::time::timeout(Duration::from_secs(5), source.next())
+        .await
+        .expect("timed out waiting for frame")
+        .expect("socket closed")
+        .unwrap();
+      if let Message::Text(text) = next {
+        return serde_json::from_str(&text).unwrap();
+      }
+    }
+  }
+  #[tokio::test]
+  async fn rejects_invalid_token() {
+    let (url, _hub) = start(vec![]).await;
+    let (mut ws, _) = connect_async(url.as_str()).await.unwrap();
+    ws.send(Message::text(
+      json!({ "type": "auth", "token": "bad" }).to_string(),
+    ))
+    .await
+    .unwrap();
+    let reply = recv(&mut ws).await;
+    assert_eq!(reply["type"], "error");
+  }
+  #[tokio::test]
+  async fn pushes_events_for_subscribed_rooms_only() {
+    let (url, hub) = start(vec![]).await;
+    let (mut ws, _) = connect_async(url.as_str()).await.unwrap();
+    ws.send(Message::text(
+      json!({ "type": "auth", "token": "good" }).to_string(),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(recv(&mut ws).await["type"], "ready");
+    ws.send(Message::text(
+      json!({ "type": "subscribe", "room_id": "room-b" }).to_string(),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(recv(&mut ws).await["type"], "error");
+    ws.send(Message::text(
+      json!({ "type": "subscribe", "room_id": "room-a" }).to_string(),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(recv(&mut ws).await["type"], "subscribed");
+    hub.publish(ChatEvent::new(
+      ChatEventKind::MessageCreated,
+      "room-b",
+      Some("x"),
+      json!({}),
+    ));
+    hub.publish(ChatEvent::from_message(ChatEventKind::MessageEdited, &message("m1")).unwrap());
+    let frame = recv(&mut ws).await;
+    assert_eq!(frame["type"], "event");
+    assert_eq!(frame["event"]["kind"], "message_edited");
+    assert_eq!(frame["event"]["message_id"], "m1");
+  }
+  #[tokio::test]
+  async fn resumes_from_last_seen_message() {
+    let history = vec![message("m1"), message("m2"), message("m3")];
+    let (url, hub) = start(history).await;
+    let (mut ws, _) = connect_async(url.as_str()).await.unwrap();
+    ws.send(Message::text(
+      json!({ "type": "auth", "token": "good" }).to_string(),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(recv(&mut ws).await["type"], "ready");
+    let subscribe = json!({ "type": "subscribe", "room_id": "room-a", "last_message_id": "m1" });
+    ws.send(Message::text(subscribe.to_string())).await.unwrap();
+    let first = recv(&mut ws).await;
+    assert_eq!(first["replay"], true);
+    assert_eq!(first["event"]["message_id"], "m2");
+    assert_eq!(recv(&mut ws).await["event"]["message_id"], "m3");
+    let ack = recv(&mut ws).await;
+    assert_eq!(ack["type"], "subscribed");
+    assert_eq!(ack["missed"], 2);
+    // A late live copy of a replayed message is dropped, new ones still arrive
+    hub.publish(ChatEvent::from_message(ChatEventKind::MessageCreated, &message("m3")).unwrap());
+    hub.publish(ChatEvent::from_message(ChatEventKind::MessageCreated, &message("m4")).unwrap());
+    assert_eq!(recv(&mut ws).await["event"]["message_id"], "m4");
+    let unknown = json!({ "type": "subscribe", "room_id": "room-a", "last_message_id": "gone" });
+    ws.send(Message::text(unknown.to_string())).await.unwrap();
+    assert_eq!(recv(&mut ws).await["type"], "resync_required");
+  }
+}
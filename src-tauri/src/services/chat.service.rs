@@ -1,19 +1,176 @@
+use crate::entities::chat_entity::ChatRevision;
+use crate::entities::chat_outbox_entity::{ChatOutboxEntity, ChatOutboxOp};
 use crate::models::response::ResponseModel;
 use crate::repositories::data_provider::DataProvider;
+use crate::services::chat_events_service::{ChatEvent, ChatEventHub, ChatEventKind};
+use crate::services::chat_outbox_service::ChatOutbox;
+use crate::services::mention_service::{self, MentionService};
+use crate::services::task_link_service::TaskLinkService;
+use crate::utils::chat_search;
 use crate::utils::response_helper::{err_response, success_response};
 use crate::utils::visibility::get_visibility;
 use nosql_orm::cascade::CascadeManager;
 use nosql_orm::provider::DatabaseProvider;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+const RESUME_LIMIT: usize = 500;
+const QUOTE_EXCERPT_CHARS: usize = 280;
+const MAX_SEARCH_CONTEXT: usize = 10;
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChatSearchQuery {
+  pub query: String,
+  #[serde(default)]
+  pub room_id: Option<String>,
+  #[serde(default)]
+  pub sender_id: Option<String>,
+  /// RFC3339 or YYYY-MM-DD, inclusive
+  #[serde(default)]
+  pub from: Option<String>,
+  #[serde(default)]
+  pub to: Option<String>,
+  /// Messages of surrounding context to return on each side of a hit
+  #[serde(default)]
+  pub context: Option<usize>,
+  #[serde(default)]
+  pub skip: Option<usize>,
+  #[serde(default)]
+  pub limit: Option<usize>,
+}
+/// Progress of relaying changes made through other devices to local subscribers.
+struct RelayState {
+  /// Changes with an `updated_at` after this have not been looked at yet
+  since: String,
+  /// Message id -> `updated_at` of the version this process already published
+  published: HashMap<String, String>,
+}
 pub struct ChatService {
   json_provider: DataProvider,
   mongo_provider: Option<DataProvider>,
+  events: ChatEventHub,
+  relay: Mutex<RelayState>,
+  outbox: ChatOutbox,
+  task_links: Option<Arc<TaskLinkService>>,
+  mentions: Option<Arc<MentionService>>,
 }
 impl ChatService {
   pub fn new(json_provider: DataProvider, mongo_provider: Option<DataProvider>) -> Self {
     Self {
+      outbox: ChatOutbox::new(json_provider.clone()),
       json_provider,
       mongo_provider,
+      events: ChatEventHub::new(),
+      relay: Mutex::new(RelayState {
+        since: chrono::Utc::now().to_rfc3339(),
+        published: HashMap::new(),
+      }),
+      task_links: None,
+      mentions: None,
+    }
+  }
+  /// Enables resolving `#task:<id>` / `todo-slug#n` references when messages are saved.
+  pub fn with_task_links(mut self, task_links: Arc<TaskLinkService>) -> Self {
+    self.task_links = Some(task_links);
+    self
+  }
+  /// Enables resolving `@username` mentions against room members when messages are saved.
+  pub fn with_mentions(mut self, mentions: Arc<MentionService>) -> Self {
+    self.mentions = Some(mentions);
+    self
+  }
+  /// Resolved mentions for `content`, as the fields stored on the message.
+  async fn mention_fields(&self, content: &str, room_id: &str) -> Option<(Value, Value)> {
+    let mentions = self.mentions.as_ref()?;
+    let resolved = mentions.resolve_for_room(content, room_id).await;
+    Some(mention_service::mention_fields(&resolved))
+  }
+  /// Resolved task references for `content`, as the fields stored on the message.
+  async fn task_ref_fields(&self, content: &str, sender_id: &str) -> Option<(Value, Value)> {
+    let links = self.task_links.as_ref()?;
+    let refs = links.resolve(content, sender_id).await;
+    let ids: Vec<&str> = refs.iter().map(|r| r.task_id.as_str()).collect();
+    Some((json!(refs), json!(ids)))
+  }
+  pub fn events(&self) -> &ChatEventHub {
+    &self.events
+  }
+  fn publish(&self, kind: ChatEventKind, message: &Value) {
+    if let Some(event) = ChatEvent::from_message(kind, message) {
+      if let (Some(id), Some(updated_at)) = (
+        event.message_id.as_deref(),
+        message.get("updated_at").and_then(|v| v.as_str()),
+      ) {
+        self.note_published(id, updated_at);
+      }
+      self.events.publish(event);
+    }
+  }
+  /// Remembers a version published from here so the relay does not announce it again.
+  fn note_published(&self, id: &str, updated_at: &str) {
+    if let Ok(mut relay) = self.relay.lock() {
+      relay
+        .published
+        .insert(id.to_string(), updated_at.to_string());
+    }
+  }
+  /// Publishes messages created, edited or deleted through other devices since the last
+  /// call, so realtime subscribers are not limited to changes made in this process.
+  pub async fn relay_remote_changes(&self) {
+    let Some(mongo) = self.get_mongo_provider() else {
+      return;
+    };
+    let since = match self.relay.lock() {
+      Ok(relay) => relay.since.clone(),
+      Err(_) => return,
+    };
+    let filter = nosql_orm::query::Filter::Gt("updated_at".to_string(), json!(since));
+    // A failed poll keeps `since`, so the next call picks the same changes up.
+    let Ok(docs) = mongo
+      .find_many("chats", Some(&filter), None, None, Some("updated_at"), true)
+      .await
+    else {
+      return;
+    };
+    let mut events = Vec::new();
+    if let Ok(mut relay) = self.relay.lock() {
+      for doc in &docs {
+        let (Some(id), Some(updated_at)) = (
+          doc.get("id").and_then(|v| v.as_str()),
+          doc.get("updated_at").and_then(|v| v.as_str()),
+        ) else {
+          continue;
+        };
+        if updated_at > relay.since.as_str() {
+          relay.since = updated_at.to_string();
+        }
+        if relay.published.get(id).map(String::as_str) == Some(updated_at) {
+          continue;
+        }
+        relay
+          .published
+          .insert(id.to_string(), updated_at.to_string());
+        let event = match remote_change_kind(doc) {
+          ChatEventKind::MessageDeleted => {
+            doc.get("room_id").and_then(|v| v.as_str()).map(|room_id| {
+              ChatEvent::new(
+                ChatEventKind::MessageDeleted,
+                room_id,
+                Some(id),
+                Value::Null,
+              )
+            })
+          }
+          kind => ChatEvent::from_message(kind, doc),
+        };
+        events.extend(event);
+      }
+      // Versions older than the watermark can no longer come back from the poll
+      let since = relay.since.clone();
+      relay.published.retain(|_, at| *at >= since);
+    }
+    for event in events {
+      self.events.publish(event);
     }
   }
   fn get_json_provider(&self) -> &DataProvider {
@@ -22,6 +179,28 @@ impl ChatService {
   fn get_mongo_provider(&self) -> Option<&DataProvider> {
     self.mongo_provider.as_ref()
   }
+  /// MongoDB when it can take writes directly. While older changes are still queued,
+  /// new ones queue behind them so the server sees them in the order they were made.
+  async fn get_online_provider(&self) -> Option<&DataProvider> {
+    let mongo = self.get_mongo_provider()?;
+    if self.outbox.has_pending().await {
+      return None;
+    }
+    Some(mongo)
+  }
+  async fn queue_change(
+    &self,
+    op: ChatOutboxOp,
+    message_id: &str,
+    room_id: &str,
+    payload: Value,
+  ) -> Result<(), ResponseModel> {
+    self
+      .outbox
+      .enqueue(op, message_id, room_id, payload)
+      .await
+      .map(|_| ())
+  }
   pub async fn get_by_id(&self, id: &str) -> Result<ResponseModel, ResponseModel> {
     let doc = self
       .json_provider
@@ -108,6 +287,375 @@ impl ChatService {
     }
     Ok(success_response(enriched_docs))
   }
+  /// Messages in `room_id` created after `message_id`, oldest first. `None` means the anchor
+  /// is unknown (e.g. purged) and the caller has to reload the room instead of resuming.
+  pub async fn get_messages_after(
+    &self,
+    room_id: &str,
+    message_id: &str,
+  ) -> Result<Option<Vec<Value>>, ResponseModel> {
+    let filter = nosql_orm::query::Filter::Eq("room_id".to_string(), json!(room_id));
+    // Messages sent from other devices only reach the local mirror when the room is loaded
+    let provider = self.get_mongo_provider().unwrap_or(&self.json_provider);
+    let docs = provider
+      .find_many("chats", Some(&filter), None, None, Some("created_at"), true)
+      .await?;
+    let Some(anchor) = docs
+      .iter()
+      .position(|doc| doc.get("id").and_then(|v| v.as_str()) == Some(message_id))
+    else {
+      return Ok(None);
+    };
+    Ok(Some(
+      docs
+        .into_iter()
+        .skip(anchor + 1)
+        .filter(|doc| doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true))
+        .take(RESUME_LIMIT)
+        .collect(),
+    ))
+  }
+  /// The thread root and its replies, oldest reply first.
+  pub async fn get_thread(
+    &self,
+    parent_id: &str,
+    skip: Option<u64>,
+    limit: Option<u64>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let parent = self.find_message(parent_id).await?;
+    let replies = self
+      .find_replies(parent_id)
+      .await?
+      .into_iter()
+      .skip(skip.unwrap_or(0) as usize)
+      .take(limit.map(|l| l as usize).unwrap_or(usize::MAX))
+      .collect::<Vec<Value>>();
+    Ok(success_response(
+      json!({ "parent": parent, "replies": replies }),
+    ))
+  }
+  async fn find_replies(&self, parent_id: &str) -> Result<Vec<Value>, ResponseModel> {
+    let filter = nosql_orm::query::Filter::Eq("parent_id".to_string(), json!(parent_id));
+    // Shared rooms are authoritative in MongoDB; the JSON mirror may miss other devices' replies
+    let provider = self.get_mongo_provider().unwrap_or(&self.json_provider);
+    let docs = provider
+      .find_many("chats", Some(&filter), None, None, Some("created_at"), true)
+      .await?;
+    Ok(
+      docs
+        .into_iter()
+        .filter(|doc| doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true))
+        .collect(),
+    )
+  }
+  /// A single message from the local mirror, falling back to MongoDB.
+  pub async fn find_message(&self, id: &str) -> Result<Value, ResponseModel> {
+    if let Some(doc) = self.json_provider.find_by_id("chats", id).await? {
+      return Ok(doc);
+    }
+    match self.get_mongo_provider() {
+      Some(mongo) => mongo
+        .find_by_id("chats", id)
+        .await?
+        .ok_or_else(|| err_response("Message not found")),
+      None => Err(err_response("Message not found")),
+    }
+  }
+  async fn find_in_room(&self, id: &str, room_id: &str) -> Result<Value, ResponseModel> {
+    let doc = self.find_message(id).await?;
+    if doc.get("room_id").and_then(|v| v.as_str()) != Some(room_id) {
+      return Err(err_response("Referenced message belongs to another room"));
+    }
+    Ok(doc)
+  }
+  /// Threads are one level deep: replying to a reply attaches to the same root.
+  async fn resolve_thread_root(
+    &self,
+    parent_id: &str,
+    room_id: &str,
+  ) -> Result<String, ResponseModel> {
+    let parent = self.find_in_room(parent_id, room_id).await?;
+    if parent
+      .get("deleted_at")
+      .map(|v| !v.is_null())
+      .unwrap_or(false)
+    {
+      return Err(err_response("Cannot reply to a deleted message"));
+    }
+    Ok(
+      parent
+        .get("parent_id")
+        .and_then(|v| v.as_str())
+        .unwrap_or(parent_id)
+        .to_string(),
+    )
+  }
+  fn quote_of(message: &Value) -> Value {
+    let content: String = message
+      .get("content")
+      .and_then(|v| v.as_str())
+      .unwrap_or("")
+      .chars()
+      .take(QUOTE_EXCERPT_CHARS)
+      .collect();
+    json!({
+      "id": message.get("id"),
+      "sender_id": message.get("sender_id"),
+      "content": content,
+      "created_at": message.get("created_at"),
+    })
+  }
+  /// Recomputes `reply_count` / `last_reply_at` on a thread root from its live replies.
+  async fn refresh_thread_stats(&self, parent_id: &str) {
+    let Ok(replies) = self.find_replies(parent_id).await else {
+      return;
+    };
+    let last_reply_at = replies
+      .iter()
+      .filter_map(|doc| doc.get("created_at").and_then(|v| v.as_str()))
+      .max()
+      .map(|s| s.to_string());
+    let update_data = json!({ "reply_count": replies.len(), "last_reply_at": last_reply_at });
+    let Some(mongo) = self.get_mongo_provider() else {
+      return;
+    };
+    let Ok(doc) = mongo.patch("chats", parent_id, update_data.clone()).await else {
+      return;
+    };
+    if let DataProvider::Json(p) = self.get_json_provider() {
+      let _ = p.patch("chats", parent_id, update_data).await;
+    }
+    self.publish(ChatEventKind::MessageEdited, &doc);
+  }
+  /// Searches message content in `room_ids` (the rooms the caller belongs to), newest first.
+  pub async fn search(
+    &self,
+    room_ids: &[String],
+    query: &ChatSearchQuery,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let terms = chat_search::parse_terms(&query.query);
+    if terms.is_empty() {
+      return Err(err_response("Search query cannot be empty"));
+    }
+    let room_ids: Vec<Value> = room_ids
+      .iter()
+      .filter(|id| query.room_id.as_ref().map(|r| r == *id).unwrap_or(true))
+      .map(|id| json!(id))
+      .collect();
+    if room_ids.is_empty() {
+      return Ok(success_response(
+        json!({ "has_more": false, "results": [] }),
+      ));
+    }
+    let from = query
+      .from
+      .as_deref()
+      .map(|d| parse_date_bound(d, false))
+      .transpose()?;
+    let to = query
+      .to
+      .as_deref()
+      .map(|d| parse_date_bound(d, true))
+      .transpose()?;
+    // Narrow down in the store; the exact checks below only tidy up case-folding and
+    // timestamp-format differences at the edges
+    let mut conditions = vec![
+      json!({ "room_id": { "$in": room_ids } }),
+      json!({ "deleted_at": { "$isNull": true } }),
+    ];
+    if let Some(sender_id) = &query.sender_id {
+      conditions.push(json!({ "sender_id": sender_id }));
+    }
+    if let Some(from) = from {
+      conditions
+        .push(json!({ "created_at": { "$gte": from.with_timezone(&chrono::Utc).to_rfc3339() } }));
+    }
+    if let Some(to) = to {
+      conditions
+        .push(json!({ "created_at": { "$lte": to.with_timezone(&chrono::Utc).to_rfc3339() } }));
+    }
+    for term in &terms {
+      conditions.push(json!({ "content": { "$regex": regex::escape(term), "$options": "i" } }));
+    }
+    let filter = nosql_orm::query::Filter::from_json(&json!({ "$and": conditions }))
+      .map_err(|e| err_response(&format!("Filter error: {}", e)))?;
+    let skip = query.skip.unwrap_or(0) as u64;
+    let limit = query.limit.unwrap_or(50);
+    // One extra tells whether another page follows
+    let fetch = Some(limit as u64 + 1);
+    let provider = self.get_mongo_provider().unwrap_or(&self.json_provider);
+    let (provider, docs) = match provider
+      .find_many(
+        "chats",
+        Some(&filter),
+        Some(skip),
+        fetch,
+        Some("created_at"),
+        false,
+      )
+      .await
+    {
+      Ok(docs) => (provider, docs),
+      Err(_) => (
+        &self.json_provider,
+        self
+          .json_provider
+          .find_many(
+            "chats",
+            Some(&filter),
+            Some(skip),
+            fetch,
+            Some("created_at"),
+            false,
+          )
+          .await?,
+      ),
+    };
+    let has_more = docs.len() > limit;
+    let hits: Vec<Value> = docs
+      .into_iter()
+      .take(limit)
+      .filter(|doc| {
+        let created = doc
+          .get("created_at")
+          .and_then(|v| v.as_str())
+          .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok());
+        let in_range = match (created, from, to) {
+          (None, None, None) => true,
+          (None, _, _) => false,
+          (Some(c), from, to) => {
+            from.map(|f| c >= f).unwrap_or(true) && to.map(|t| c <= t).unwrap_or(true)
+          }
+        };
+        in_range
+          && chat_search::matches_all(
+            doc.get("content").and_then(|v| v.as_str()).unwrap_or(""),
+            &terms,
+          )
+      })
+      .collect();
+    let context = query.context.unwrap_or(0).min(MAX_SEARCH_CONTEXT);
+    let mut results = Vec::with_capacity(hits.len());
+    for doc in hits {
+      let content = doc.get("content").and_then(|v| v.as_str()).unwrap_or("");
+      let (snippet, highlights) = chat_search::snippet(content, &terms);
+      let mut result = json!({
+        "message": doc,
+        "snippet": snippet,
+        "highlights": highlights,
+      });
+      if context > 0 {
+        let (before, after) = self.neighbours(provider, &doc, context).await;
+        result["before"] = json!(before);
+        result["after"] = json!(after);
+      }
+      results.push(result);
+    }
+    Ok(success_response(
+      json!({ "has_more": has_more, "results": results }),
+    ))
+  }
+  /// Up to `count` live messages on each side of `doc` in its room, oldest first.
+  async fn neighbours(
+    &self,
+    provider: &DataProvider,
+    doc: &Value,
+    count: usize,
+  ) -> (Vec<Value>, Vec<Value>) {
+    if count == 0 {
+      return (Vec::new(), Vec::new());
+    }
+    let room_id = doc.get("room_id").cloned().unwrap_or(Value::Null);
+    let created_at = doc.get("created_at").cloned().unwrap_or(Value::Null);
+    let side = |op: &str| {
+      nosql_orm::query::Filter::from_json(&json!({ "$and": [
+        { "room_id": room_id },
+        { "deleted_at": { "$isNull": true } },
+        { "created_at": { (op): created_at } },
+      ] }))
+      .ok()
+    };
+    let mut before = match side("$lt") {
+      Some(filter) => provider
+        .find_many(
+          "chats",
+          Some(&filter),
+          None,
+          Some(count as u64),
+          Some("created_at"),
+          false,
+        )
+        .await
+        .unwrap_or_default(),
+      None => Vec::new(),
+    };
+    before.reverse();
+    let after = match side("$gt") {
+      Some(filter) => provider
+        .find_many(
+          "chats",
+          Some(&filter),
+          None,
+          Some(count as u64),
+          Some("created_at"),
+          true,
+        )
+        .await
+        .unwrap_or_default(),
+      None => Vec::new(),
+    };
+    (before, after)
+  }
+  /// Up to `radius` messages on each side of `message_id`, for jumping to a search hit. Reads
+  /// the same store search does, so hits only present in MongoDB still get their context.
+  pub async fn get_message_context(
+    &self,
+    message_id: &str,
+    radius: usize,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let in_mongo = match self.get_mongo_provider() {
+      Some(mongo) => match mongo.find_by_id("chats", message_id).await {
+        Ok(Some(doc)) => Some((mongo, doc)),
+        _ => None,
+      },
+      None => None,
+    };
+    let (provider, message) = match in_mongo {
+      Some(found) => found,
+      None => (
+        &self.json_provider,
+        self
+          .json_provider
+          .find_by_id("chats", message_id)
+          .await?
+          .ok_or_else(|| err_response("Message not found"))?,
+      ),
+    };
+    if !message
+      .get("deleted_at")
+      .map(|v| v.is_null())
+      .unwrap_or(true)
+    {
+      return Err(err_response("Message is no longer in the room's timeline"));
+    }
+    let room_id = message
+      .get("room_id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("")
+      .to_string();
+    let radius = radius.min(MAX_SEARCH_CONTEXT * 5);
+    let (before, after) = self.neighbours(provider, &message, radius).await;
+    let anchor_index = before.len();
+    let mut messages = before;
+    messages.push(message);
+    messages.extend(after);
+    Ok(success_response(json!({
+      "room_id": room_id,
+      "anchor_id": message_id,
+      "anchor_index": anchor_index,
+      "messages": messages,
+    })))
+  }
   pub async fn get_all(
     &self,
     visibility: &str,
@@ -137,19 +685,69 @@ impl ChatService {
       .await?;
     Ok(success_response(docs))
   }
+  /// Creates a message. A client-supplied `id` makes retries idempotent; when MongoDB is
+  /// unreachable the message is stored locally as `pending` and queued for delivery.
   pub async fn create(&self, data: Value) -> Result<ResponseModel, ResponseModel> {
-    let mongo = self
-      .get_mongo_provider()
-      .ok_or_else(|| err_response("MongoDB not available"))?;
     let now = chrono::Utc::now().to_rfc3339();
     let mut create_data = data;
+    let id = create_data
+      .get("id")
+      .and_then(|v| v.as_str())
+      .map(|s| s.to_string())
+      .unwrap_or_else(nosql_orm::utils::generate_id);
+    if let Ok(existing) = self.find_message(&id).await {
+      // Same client id sent twice: hand back what was stored the first time, but only to
+      // the sender and room it was stored for
+      if !is_retry_of(&existing, &create_data) {
+        return Err(ResponseModel::validation_error(
+          "Message id is already in use",
+        ));
+      }
+      return Ok(success_response(existing));
+    }
+    create_data["id"] = json!(id);
     create_data["created_at"] = serde_json::json!(now);
     create_data["updated_at"] = serde_json::json!(now);
-    let doc: Value = mongo.insert("chats", create_data).await?;
-    let json_provider = self.get_json_provider();
-    if let DataProvider::Json(p) = json_provider {
-      let _ = p.insert("chats", doc.clone()).await;
+    let room_id = create_data
+      .get("room_id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("")
+      .to_string();
+    let parent_id = match create_data.get("parent_id").and_then(|v| v.as_str()) {
+      Some(parent_id) => Some(self.resolve_thread_root(parent_id, &room_id).await?),
+      None => None,
+    };
+    create_data["parent_id"] = json!(parent_id);
+    if let Some(reply_id) = create_data.get("reply_id").and_then(|v| v.as_str()) {
+      let quoted = self.find_in_room(reply_id, &room_id).await?;
+      create_data["quoted"] = Self::quote_of(&quoted);
     }
+    let content = create_data["content"].as_str().unwrap_or("").to_string();
+    let sender_id = create_data["sender_id"].as_str().unwrap_or("").to_string();
+    if let Some((refs, ids)) = self.task_ref_fields(&content, &sender_id).await {
+      create_data["task_refs"] = refs;
+      create_data["task_ref_ids"] = ids;
+    }
+    if let Some((mentions, ids)) = self.mention_fields(&content, &room_id).await {
+      create_data["mentions"] = mentions;
+      create_data["mention_ids"] = ids;
+    }
+    if let Some(mongo) = self.get_online_provider().await {
+      if let Ok(doc) = mongo.insert("chats", create_data.clone()).await {
+        let _ = self.json_provider.insert("chats", doc.clone()).await;
+        self.publish(ChatEventKind::MessageCreated, &doc);
+        if let Some(parent_id) = &parent_id {
+          self.refresh_thread_stats(parent_id).await;
+        }
+        return Ok(success_response(doc));
+      }
+    }
+    create_data["pending"] = json!(true);
+    let doc = self.json_provider.insert("chats", create_data).await?;
+    self
+      .queue_change(ChatOutboxOp::Create, &id, &room_id, doc.clone())
+      .await?;
+    self.publish(ChatEventKind::MessageCreated, &doc);
     Ok(success_response(doc))
   }
   pub async fn update(&self, id: &str, data: Value) -> Result<ResponseModel, ResponseModel> {
@@ -197,22 +795,41 @@ impl ChatService {
     Ok(success_response(doc))
   }
   pub async fn delete(&self, id: &str) -> Result<ResponseModel, ResponseModel> {
-    let mongo = self
-      .get_mongo_provider()
-      .ok_or_else(|| err_response("MongoDB not available"))?;
-    let existing: Value = mongo
-      .find_by_id("chats", id)
-      .await?
-      .ok_or_else(|| err_response("Chat not found"))?;
+    let existing = self
+      .find_message(id)
+      .await
+      .map_err(|_| err_response("Chat not found"))?;
     let _visibility = get_visibility(&existing);
+    let room_id = existing
+      .get("room_id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("")
+      .to_string();
     let now = chrono::Utc::now().to_rfc3339();
     let update_data = json!({ "deleted_at": now, "updated_at": now });
-    let _doc: Value = mongo.patch("chats", id, update_data.clone()).await?;
-    let json_provider = self.get_json_provider();
-    if let DataProvider::Json(p) = json_provider {
+    let mut delivered = false;
+    if let Some(mongo) = self.get_online_provider().await {
+      delivered = mongo.patch("chats", id, update_data.clone()).await.is_ok();
+    }
+    if let DataProvider::Json(p) = self.get_json_provider() {
       let cascade = CascadeManager::new(p.as_ref().clone());
       let _ = cascade.soft_delete("chats", id).await;
-      let _ = p.patch("chats", id, update_data).await;
+      let _ = p.patch("chats", id, update_data.clone()).await;
+    }
+    if !delivered {
+      self
+        .queue_change(ChatOutboxOp::Delete, id, &room_id, update_data)
+        .await?;
+    }
+    self.note_published(id, &now);
+    self.events.publish(ChatEvent::new(
+      ChatEventKind::MessageDeleted,
+      &room_id,
+      Some(id),
+      Value::Null,
+    ));
+    if let Some(parent_id) = existing.get("parent_id").and_then(|v| v.as_str()) {
+      self.refresh_thread_stats(parent_id).await;
     }
     Ok(success_response(json!({})))
   }
@@ -220,80 +837,123 @@ impl ChatService {
     let mongo = self
       .get_mongo_provider()
       .ok_or_else(|| err_response("MongoDB not available"))?;
+    let existing = mongo.find_by_id("chats", id).await.ok().flatten();
     let _ = mongo.delete("chats", id).await;
     let json_provider = self.get_json_provider();
     if let DataProvider::Json(p) = json_provider {
       let _ = p.delete("chats", id).await;
     }
+    if let Some(room_id) = existing
+      .as_ref()
+      .and_then(|doc| doc.get("room_id"))
+      .and_then(|v| v.as_str())
+    {
+      self.events.publish(ChatEvent::new(
+        ChatEventKind::MessageDeleted,
+        room_id,
+        Some(id),
+        Value::Null,
+      ));
+    }
+    if let Some(parent_id) = existing
+      .as_ref()
+      .and_then(|doc| doc.get("parent_id"))
+      .and_then(|v| v.as_str())
+    {
+      self.refresh_thread_stats(parent_id).await;
+    }
     Ok(success_response(json!({ "id": id, "deleted": true })))
   }
+  /// Replaces the content of a message, keeping the previous content as a revision.
   pub async fn edit_message(
     &self,
     id: &str,
+    editor_id: &str,
     content: &str,
   ) -> Result<ResponseModel, ResponseModel> {
-    let mongo = self
-      .get_mongo_provider()
-      .ok_or_else(|| err_response("MongoDB not available"))?;
-    let now = chrono::Utc::now().to_rfc3339();
-    let update_data = json!({ "content": content, "updated_at": now, "is_edited": true });
-    let doc: Value = mongo.patch("chats", id, update_data.clone()).await?;
-    let json_provider = self.get_json_provider();
-    if let DataProvider::Json(p) = json_provider {
-      let _ = p.patch("chats", id, update_data).await;
+    let existing = self.find_message(id).await?;
+    if existing.get("sender_id").and_then(|v| v.as_str()) != Some(editor_id) {
+      return Err(ResponseModel::forbidden(
+        "Only the author can edit this message",
+      ));
     }
+    let previous = existing
+      .get("content")
+      .and_then(|v| v.as_str())
+      .unwrap_or("");
+    if previous == content {
+      return Ok(success_response(existing));
+    }
+    let now = chrono::Utc::now();
+    let mut revisions = existing
+      .get("revisions")
+      .and_then(|v| v.as_array())
+      .cloned()
+      .unwrap_or_default();
+    revisions.push(json!(ChatRevision {
+      content: previous.to_string(),
+      editor_id: editor_id.to_string(),
+      replaced_at: now,
+    }));
+    let mut update_data = json!({
+      "content": content,
+      "revisions": revisions,
+      "updated_at": now.to_rfc3339(),
+      "is_edited": true,
+    });
+    if let Some((refs, ids)) = self.task_ref_fields(content, editor_id).await {
+      update_data["task_refs"] = refs;
+      update_data["task_ref_ids"] = ids;
+    }
+    let room_id = existing
+      .get("room_id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("");
+    if let Some((mentions, ids)) = self.mention_fields(content, room_id).await {
+      update_data["mentions"] = mentions;
+      update_data["mention_ids"] = ids;
+    }
+    if let Some(mongo) = self.get_online_provider().await {
+      if let Ok(doc) = mongo.patch("chats", id, update_data.clone()).await {
+        let _ = self.json_provider.patch("chats", id, update_data).await;
+        self.publish(ChatEventKind::MessageEdited, &doc);
+        return Ok(success_response(doc));
+      }
+    }
+    let doc = self
+      .json_provider
+      .patch("chats", id, update_data.clone())
+      .await?;
+    self
+      .queue_change(ChatOutboxOp::Edit, id, room_id, update_data)
+      .await?;
+    self.publish(ChatEventKind::MessageEdited, &doc);
     Ok(success_response(doc))
   }
+  /// The current content and every earlier version, newest first.
+  pub async fn get_revisions(&self, id: &str) -> Result<ResponseModel, ResponseModel> {
+    let message = self.find_message(id).await?;
+    let mut revisions = message
+      .get("revisions")
+      .and_then(|v| v.as_array())
+      .cloned()
+      .unwrap_or_default();
+    revisions.reverse();
+    Ok(success_response(json!({
+      "message_id": id,
+      "room_id": message.get("room_id"),
+      "content": message.get("content"),
+      "updated_at": message.get("updated_at"),
+      "revisions": revisions,
+    })))
+  }
   pub async fn add_reaction(
     &self,
     message_id: &str,
     emoji: &str,
     user_id: &str,
   ) -> Result<ResponseModel, ResponseModel> {
-    let mongo = self
-      .get_mongo_provider()
-      .ok_or_else(|| err_response("MongoDB not available"))?;
-    let chat = mongo
-      .find_by_id("chats", message_id)
-      .await?
-      .ok_or_else(|| err_response("Message not found"))?;
-    let mut reactions = chat
-      .get("reactions")
-      .and_then(|v| v.as_array())
-      .cloned()
-      .unwrap_or_default();
-    let emoji_str = emoji.to_string();
-    if let Some(existing) = reactions
-      .iter_mut()
-      .find(|r| r.get("emoji").and_then(|v| v.as_str()) == Some(&emoji_str))
-    {
-      let count = existing.get("count").and_then(|v| v.as_i64()).unwrap_or(0);
-      existing["count"] = serde_json::json!(count + 1);
-      let mut user_ids = existing
-        .get("user_ids")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-      if !user_ids.iter().any(|id| id.as_str() == Some(user_id)) {
-        user_ids.push(serde_json::json!(user_id));
-      }
-      existing["user_ids"] = serde_json::json!(user_ids);
-    } else {
-      reactions.push(serde_json::json!({
-        "emoji": emoji_str,
-        "count": 1,
-        "user_ids": [user_id]
-      }));
-    }
-    let update_data = json!({ "reactions": reactions });
-    let doc = mongo
-      .patch("chats", message_id, update_data.clone())
-      .await?;
-    let json_provider = self.get_json_provider();
-    if let DataProvider::Json(p) = json_provider {
-      let _ = p.patch("chats", message_id, update_data).await;
-    }
-    Ok(success_response(doc))
+    self.react(message_id, emoji, user_id, true).await
   }
   pub async fn remove_reaction(
     &self,
@@ -301,133 +961,359 @@ impl ChatService {
     emoji: &str,
     user_id: &str,
   ) -> Result<ResponseModel, ResponseModel> {
-    let mongo = self
-      .get_mongo_provider()
-      .ok_or_else(|| err_response("MongoDB not available"))?;
-    let chat = mongo
-      .find_by_id("chats", message_id)
-      .await?
-      .ok_or_else(|| err_response("Message not found"))?;
-    let reactions = chat
-      .get("reactions")
-      .and_then(|v| v.as_array())
-      .cloned()
-      .unwrap_or_default();
-    let emoji_str = emoji.to_string();
-    let mut new_reactions: Vec<Value> = Vec::new();
-    for r in reactions.into_iter() {
-      if r.get("emoji").and_then(|v| v.as_str()) == Some(&emoji_str) {
-        let count = r.get("count").and_then(|v| v.as_i64()).unwrap_or(0);
-        if count > 1 {
-          let mut new_r = r.clone();
-          new_r["count"] = serde_json::json!(count - 1);
-          let mut user_ids = r
-            .get("user_ids")
-            .and_then(|v| v.as_array())
-            .cloned()
-            .unwrap_or_default();
-          user_ids.retain(|id| id.as_str() != Some(user_id));
-          new_r["user_ids"] = serde_json::json!(user_ids);
-          new_reactions.push(new_r);
+    self.react(message_id, emoji, user_id, false).await
+  }
+  async fn react(
+    &self,
+    message_id: &str,
+    emoji: &str,
+    user_id: &str,
+    add: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let kind = if add {
+      ChatEventKind::ReactionAdded
+    } else {
+      ChatEventKind::ReactionRemoved
+    };
+    if let Some(mongo) = self.get_online_provider().await {
+      if let Ok(Some(chat)) = mongo.find_by_id("chats", message_id).await {
+        let update_data = json!({
+          "reactions": apply_reaction(&chat, emoji, user_id, add),
+          "updated_at": chrono::Utc::now().to_rfc3339(),
+        });
+        if let Ok(doc) = mongo.patch("chats", message_id, update_data.clone()).await {
+          let _ = self
+            .json_provider
+            .patch("chats", message_id, update_data)
+            .await;
+          self.publish(kind, &doc);
+          return Ok(success_response(doc));
         }
-      } else {
-        new_reactions.push(r);
       }
     }
-    let update_data = json!({ "reactions": new_reactions });
-    let doc = mongo
-      .patch("chats", message_id, update_data.clone())
+    let chat = self
+      .json_provider
+      .find_by_id("chats", message_id)
+      .await?
+      .ok_or_else(|| err_response("Message not found"))?;
+    let update_data = json!({ "reactions": apply_reaction(&chat, emoji, user_id, add) });
+    let doc = self
+      .json_provider
+      .patch("chats", message_id, update_data)
       .await?;
-    let json_provider = self.get_json_provider();
-    if let DataProvider::Json(p) = json_provider {
-      let _ = p.patch("chats", message_id, update_data).await;
-    }
+    let room_id = doc.get("room_id").and_then(|v| v.as_str()).unwrap_or("");
+    let payload = json!({ "emoji": emoji, "user_id": user_id, "add": add });
+    self
+      .queue_change(ChatOutboxOp::Reaction, message_id, room_id, payload)
+      .await?;
+    self.publish(kind, &doc);
     Ok(success_response(doc))
   }
-  pub async fn delete_by_room(&self, room_id: &str) -> Result<ResponseModel, ResponseModel> {
-    let filter = json!({ "room_id": room_id });
-    let filter_opt = Some(
-      nosql_orm::query::Filter::from_json(&filter)
-        .map_err(|e| err_response(&format!("Invalid filter: {}", e)))?,
-    );
+  /// Replays queued chat changes against MongoDB in the order they were made.
+  /// Stops at the first change that cannot be delivered so later ones never overtake it.
+  pub async fn flush_outbox(&self) -> Result<ResponseModel, ResponseModel> {
     let mongo = self
       .get_mongo_provider()
       .ok_or_else(|| err_response("MongoDB not available"))?;
-    let now = chrono::Utc::now().to_rfc3339();
-    let update_data = json!({ "deleted_at": now, "updated_at": now });
-    let docs: Vec<serde_json::Value> = mongo
-      .find_many("chats", filter_opt.as_ref(), None, None, None, true)
-      .await
-      .unwrap_or_default();
-    for doc in docs {
-      if let Some(id) = doc.get("id").and_then(|v| v.as_str()) {
-        let _ = mongo.patch("chats", id, update_data.clone()).await;
+    let _guard = self.outbox.lock().await;
+    let mut sent = 0;
+    let mut failed = 0;
+    for entry in self.outbox.pending().await? {
+      match self.replay(mongo, &entry).await {
+        Ok(doc) => {
+          self.outbox.complete(&entry).await;
+          sent += 1;
+          if entry.op == ChatOutboxOp::Create {
+            let _ = self
+              .json_provider
+              .patch("chats", &entry.message_id, json!({ "pending": false }))
+              .await;
+            if let Some(parent_id) = doc.get("parent_id").and_then(|v| v.as_str()) {
+              self.refresh_thread_stats(parent_id).await;
+            }
+          }
+        }
+        Err(e) => {
+          if !self.outbox.record_failure(&entry, &e.message).await {
+            break;
+          }
+          failed += 1;
+          if entry.op == ChatOutboxOp::Create {
+            self
+              .outbox
+              .fail_dependents(&entry.message_id, "Message could not be sent")
+              .await;
+          }
+        }
       }
     }
-    let json_provider = self.get_json_provider();
-    if let DataProvider::Json(p) = json_provider {
-      let cascade = CascadeManager::new(p.as_ref().clone());
-      let docs: Vec<serde_json::Value> = DatabaseProvider::find_many(
-        p.as_ref(),
-        "chats",
-        filter_opt.as_ref(),
-        None,
-        None,
-        None,
-        true,
-      )
-      .await
-      .unwrap_or_default();
-      for doc in docs {
-        if let Some(id) = doc.get("id").and_then(|v| v.as_str()) {
-          let _ = cascade.soft_delete("chats", id).await;
-          let _ = p.patch("chats", id, update_data.clone()).await;
+    let remaining = self.outbox.pending().await?.len();
+    Ok(success_response(
+      json!({ "sent": sent, "failed": failed, "remaining": remaining }),
+    ))
+  }
+  pub async fn get_outbox(&self) -> Result<ResponseModel, ResponseModel> {
+    Ok(success_response(self.outbox.entries().await?))
+  }
+  pub async fn discard_outbox_entry(&self, id: &str) -> Result<ResponseModel, ResponseModel> {
+    self.outbox.discard(id).await?;
+    Ok(success_response(json!({ "id": id })))
+  }
+  async fn replay(
+    &self,
+    mongo: &DataProvider,
+    entry: &ChatOutboxEntity,
+  ) -> Result<Value, ResponseModel> {
+    let id = entry.message_id.as_str();
+    match entry.op {
+      ChatOutboxOp::Create => {
+        // An earlier attempt may have landed before the connection dropped
+        if let Some(existing) = mongo.find_by_id("chats", id).await? {
+          return Ok(existing);
         }
+        let mut doc = entry.payload.clone();
+        if let Value::Object(map) = &mut doc {
+          map.remove("pending");
+        }
+        mongo.insert("chats", doc).await
+      }
+      ChatOutboxOp::Edit | ChatOutboxOp::Delete => {
+        mongo.patch("chats", id, entry.payload.clone()).await
+      }
+      ChatOutboxOp::Reaction => {
+        let chat = mongo
+          .find_by_id("chats", id)
+          .await?
+          .ok_or_else(|| err_response("Message not found"))?;
+        let emoji = entry.payload["emoji"].as_str().unwrap_or("");
+        let user_id = entry.payload["user_id"].as_str().unwrap_or("");
+        let add = entry.payload["add"].as_bool().unwrap_or(true);
+        let update_data = json!({
+          "reactions": apply_reaction(&chat, emoji, user_id, add),
+          "updated_at": chrono::Utc::now().to_rfc3339(),
+        });
+        let doc = mongo.patch("chats", id, update_data).await?;
+        let kind = if add {
+          ChatEventKind::ReactionAdded
+        } else {
+          ChatEventKind::ReactionRemoved
+        };
+        self.publish(kind, &doc);
+        Ok(doc)
       }
     }
+  }
+  pub async fn delete_by_room(&self, room_id: &str) -> Result<ResponseModel, ResponseModel> {
+    self.purge_room(room_id, None, false).await?;
+    self.events.publish(ChatEvent::new(
+      ChatEventKind::RoomCleared,
+      room_id,
+      None,
+      Value::Null,
+    ));
     Ok(success_response(
       json!({ "room_id": room_id, "deleted": true }),
     ))
   }
   pub async fn hard_delete_by_room(&self, room_id: &str) -> Result<ResponseModel, ResponseModel> {
-    let filter = json!({ "room_id": room_id });
-    let filter_opt = Some(
-      nosql_orm::query::Filter::from_json(&filter)
-        .map_err(|e| err_response(&format!("Invalid filter: {}", e)))?,
-    );
+    self.purge_room(room_id, None, true).await?;
+    self.events.publish(ChatEvent::new(
+      ChatEventKind::RoomCleared,
+      room_id,
+      None,
+      Value::Null,
+    ));
+    Ok(success_response(
+      json!({ "room_id": room_id, "deleted": true }),
+    ))
+  }
+  /// Soft- or hard-deletes the room's messages, only those created before `before` when
+  /// given, in MongoDB and the local mirror. Returns the ids that were purged.
+  pub async fn purge_room(
+    &self,
+    room_id: &str,
+    before: Option<&str>,
+    hard: bool,
+  ) -> Result<Vec<String>, ResponseModel> {
+    let mut filters = vec![nosql_orm::query::Filter::Eq(
+      "room_id".to_string(),
+      json!(room_id),
+    )];
+    if let Some(before) = before {
+      filters.push(nosql_orm::query::Filter::Lt(
+        "created_at".to_string(),
+        json!(before),
+      ));
+    }
+    let filter = nosql_orm::query::Filter::And(filters);
     let mongo = self
       .get_mongo_provider()
       .ok_or_else(|| err_response("MongoDB not available"))?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let update_data = json!({ "deleted_at": now, "updated_at": now });
+    // Soft purges skip messages that are already deleted so they are not reported twice
+    let purgeable =
+      |doc: &Value| hard || doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true);
+    let mut purged: Vec<String> = Vec::new();
     let docs: Vec<serde_json::Value> = mongo
-      .find_many("chats", filter_opt.as_ref(), None, None, None, true)
+      .find_many("chats", Some(&filter), None, None, None, true)
       .await
       .unwrap_or_default();
-    for doc in docs {
+    for doc in docs.iter().filter(|doc| purgeable(doc)) {
       if let Some(id) = doc.get("id").and_then(|v| v.as_str()) {
-        let _ = mongo.delete("chats", id).await;
+        let _ = if hard {
+          mongo.delete("chats", id).await.map(|_| ())
+        } else {
+          mongo
+            .patch("chats", id, update_data.clone())
+            .await
+            .map(|_| ())
+        };
+        self.note_published(id, &now);
+        purged.push(id.to_string());
       }
     }
     let json_provider = self.get_json_provider();
     if let DataProvider::Json(p) = json_provider {
-      let docs: Vec<serde_json::Value> = DatabaseProvider::find_many(
-        p.as_ref(),
-        "chats",
-        filter_opt.as_ref(),
-        None,
-        None,
-        None,
-        true,
-      )
-      .await
-      .unwrap_or_default();
-      for doc in docs {
+      let cascade = CascadeManager::new(p.as_ref().clone());
+      let docs: Vec<serde_json::Value> =
+        DatabaseProvider::find_many(p.as_ref(), "chats", Some(&filter), None, None, None, true)
+          .await
+          .unwrap_or_default();
+      for doc in docs.iter().filter(|doc| purgeable(doc)) {
         if let Some(id) = doc.get("id").and_then(|v| v.as_str()) {
-          let _ = p.delete("chats", id).await;
+          if hard {
+            let _ = p.delete("chats", id).await;
+          } else {
+            let _ = cascade.soft_delete("chats", id).await;
+            let _ = p.patch("chats", id, update_data.clone()).await;
+          }
+          if !purged.iter().any(|p| p == id) {
+            purged.push(id.to_string());
+          }
         }
       }
     }
-    Ok(success_response(
-      json!({ "room_id": room_id, "deleted": true }),
-    ))
+    Ok(purged)
+  }
+}
+/// The event a change found in MongoDB stands for. Reactions and read receipts surface as
+/// edits since the document does not record which field changed.
+fn remote_change_kind(doc: &Value) -> ChatEventKind {
+  if doc.get("deleted_at").map(|v| !v.is_null()).unwrap_or(false) {
+    ChatEventKind::MessageDeleted
+  } else if doc.get("created_at") == doc.get("updated_at") {
+    ChatEventKind::MessageCreated
+  } else {
+    ChatEventKind::MessageEdited
+  }
+}
+fn parse_date_bound(
+  value: &str,
+  end_of_day: bool,
+) -> Result<chrono::DateTime<chrono::FixedOffset>, ResponseModel> {
+  if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+    return Ok(dt);
+  }
+  let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+    .map_err(|_| err_response(&format!("Invalid date: {}", value)))?;
+  let time = if end_of_day {
+    date.and_hms_milli_opt(23, 59, 59, 999)
+  } else {
+    date.and_hms_opt(0, 0, 0)
+  };
+  Ok(time.unwrap_or_default().and_utc().fixed_offset())
+}
+/// Reaction list of `message` with `user_id`'s `emoji` added or removed. Idempotent, so a
+/// replayed reaction never counts twice.
+fn apply_reaction(message: &Value, emoji: &str, user_id: &str, add: bool) -> Vec<Value> {
+  let mut reactions: Vec<Value> = message
+    .get("reactions")
+    .and_then(|v| v.as_array())
+    .cloned()
+    .unwrap_or_default();
+  let position = reactions
+    .iter()
+    .position(|r| r.get("emoji").and_then(|v| v.as_str()) == Some(emoji));
+  let mut user_ids: Vec<Value> = position
+    .and_then(|i| {
+      reactions[i]
+        .get("user_ids")
+        .and_then(|v| v.as_array())
+        .cloned()
+    })
+    .unwrap_or_default();
+  user_ids.retain(|id| id.as_str() != Some(user_id));
+  if add {
+    user_ids.push(json!(user_id));
+  }
+  let entry = json!({ "emoji": emoji, "count": user_ids.len(), "user_ids": user_ids });
+  match position {
+    Some(i) if user_ids.is_empty() => {
+      reactions.remove(i);
+    }
+    Some(i) => reactions[i] = entry,
+    None if add => reactions.push(entry),
+    None => {}
+  }
+  reactions
+}
+/// Whether `data` resends `existing`: same room and same sender.
+fn is_retry_of(existing: &Value, data: &Value) -> bool {
+  let same = |field: &str| {
+    let stored = existing.get(field).and_then(|v| v.as_str()).unwrap_or("");
+    !stored.is_empty() && Some(stored) == data.get(field).and_then(|v| v.as_str())
+  };
+  same("room_id") && same("sender_id")
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn remote_changes_map_to_events() {
+    let at = "2026-01-01T00:00:00+00:00";
+    let later = "2026-01-01T00:05:00+00:00";
+    assert_eq!(
+      remote_change_kind(&json!({ "created_at": at, "updated_at": at })),
+      ChatEventKind::MessageCreated
+    );
+    assert_eq!(
+      remote_change_kind(&json!({ "created_at": at, "updated_at": later })),
+      ChatEventKind::MessageEdited
+    );
+    assert_eq!(
+      remote_change_kind(&json!({ "created_at": at, "updated_at": later, "deleted_at": later })),
+      ChatEventKind::MessageDeleted
+    );
+  }
+  #[test]
+  fn retries_must_match_room_and_sender() {
+    let stored = json!({ "id": "m1", "room_id": "r1", "sender_id": "a", "content": "hi" });
+    assert!(is_retry_of(
+      &stored,
+      &json!({ "id": "m1", "room_id": "r1", "sender_id": "a" })
+    ));
+    assert!(!is_retry_of(
+      &stored,
+      &json!({ "id": "m1", "room_id": "r1", "sender_id": "b" })
+    ));
+    assert!(!is_retry_of(
+      &stored,
+      &json!({ "id": "m1", "room_id": "r2", "sender_id": "a" })
+    ));
+    assert!(!is_retry_of(&json!({ "id": "m1" }), &json!({ "id": "m1" })));
+  }
+  #[test]
+  fn reactions_are_idempotent_per_user() {
+    let message = json!({ "reactions": [{ "emoji": "👍", "count": 1, "user_ids": ["a"] }] });
+    let once = apply_reaction(&message, "👍", "b", true);
+    let twice = apply_reaction(&json!({ "reactions": once.clone() }), "👍", "b", true);
+    assert_eq!(once, twice);
+    assert_eq!(twice[0]["count"], 2);
+    let removed = apply_reaction(&json!({ "reactions": twice }), "👍", "a", false);
+    assert_eq!(removed[0]["user_ids"], json!(["b"]));
+    let empty = apply_reaction(&json!({ "reactions": removed }), "👍", "b", false);
+    assert!(empty.is_empty());
+    assert!(apply_reaction(&json!({}), "🎉", "a", false).is_empty());
   }
 }
@@ -0,0 +1,386 @@
+/* sys lib */
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+/* providers */
+use crate::repositories::email_provider::EmailProvider;
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::digest_entity::{
+  DigestCounts, DigestFrequency, DigestSettingsEntity, DigestSettingsUpdateModel, DigestSummary,
+  DigestTask,
+};
+use crate::entities::notification_entity::{NotificationKind, NotificationTarget};
+use crate::entities::task_entity::TaskStatus;
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::notification_service::NotificationService;
+use crate::services::statistics_service::StatisticsService;
+/* helpers */
+use crate::utils::digest::{self, DueBucket};
+use crate::utils::notification_prefs::is_zone_name;
+use crate::utils::reminders::parse_due;
+use crate::utils::response_helper::{err_response, success_response};
+use crate::utils::signed_in;
+const SETTINGS_TABLE: &str = "digest_settings";
+/// Items listed per section; the counts include everything.
+const MAX_ITEMS: usize = 20;
+/// Daily and weekly summaries of due, overdue and newly assigned tasks, unread comments and
+/// yesterday's activity. Task data and activity come from `StatisticsService`, so a digest
+/// reflects the same local data the statistics page does.
+pub struct DigestService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  statistics_service: Arc<StatisticsService>,
+  notification_service: Arc<NotificationService>,
+  email_provider: Option<EmailProvider>,
+}
+impl DigestService {
+  pub fn new(
+    json_provider: JsonProvider,
+    mongodb_provider: Option<Arc<MongoProvider>>,
+    statistics_service: Arc<StatisticsService>,
+    notification_service: Arc<NotificationService>,
+    email_provider: Option<EmailProvider>,
+  ) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+      statistics_service,
+      notification_service,
+      email_provider,
+    }
+  }
+  pub async fn get_settings(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    Ok(success_response(serde_json::to_value(
+      self.settings_for(user_id).await,
+    )?))
+  }
+  pub async fn set_settings(
+    &self,
+    user_id: &str,
+    update: DigestSettingsUpdateModel,
+  ) -> Result<ResponseModel, ResponseModel> {
+    if update.hour > 23 {
+      return Err(err_response("Digest hour must be between 0 and 23"));
+    }
+    if update.weekday > 6 {
+      return Err(err_response(
+        "Digest weekday must be between 0 (Monday) and 6 (Sunday)",
+      ));
+    }
+    if !is_zone_name(&update.timezone) {
+      return Err(err_response(
+        "Timezone must be an IANA zone name such as Europe/Berlin",
+      ));
+    }
+    let mut settings = self.settings_for(user_id).await;
+    let now = Utc::now();
+    settings.frequency = update.frequency;
+    settings.hour = update.hour;
+    settings.weekday = update.weekday;
+    settings.timezone = update.timezone;
+    settings.email = update.email;
+    settings.created_at = settings.created_at.or(Some(now));
+    settings.updated_at = Some(now);
+    Ok(success_response(
+      self
+        .store(SETTINGS_TABLE, serde_json::to_value(&settings)?)
+        .await?,
+    ))
+  }
+  /// The digest the user would get right now, without sending it.
+  pub async fn preview(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let settings = self.settings_for(user_id).await;
+    let summary = self.build(&settings, Utc::now()).await;
+    Ok(success_response(serde_json::to_value(summary)?))
+  }
+  /// Sends the signed-in user's digest when it is due and returns how many went out. Other
+  /// users' digests are left to their own devices, which hold their data.
+  pub async fn run(&self) -> usize {
+    let Some(user_id) = signed_in::signed_in_user() else {
+      return 0;
+    };
+    let now = Utc::now();
+    let filter = Filter::And(vec![
+      Filter::Eq("user_id".to_string(), json!(user_id)),
+      Filter::Ne("frequency".to_string(), json!(DigestFrequency::Off)),
+    ]);
+    let mut sent = 0;
+    for doc in self.find_all(SETTINGS_TABLE, &filter).await {
+      let Ok(settings) = serde_json::from_value::<DigestSettingsEntity>(doc) else {
+        continue;
+      };
+      let Some(date) = digest::due_on(&settings, now) else {
+        continue;
+      };
+      // Another device may have sent it since the list was read
+      let latest = self.settings_for(&settings.user_id).await;
+      if digest::due_on(&latest, now) != Some(date) {
+        continue;
+      }
+      if self.send(latest, now).await {
+        sent += 1;
+      }
+    }
+    sent
+  }
+  async fn send(&self, mut settings: DigestSettingsEntity, now: DateTime<Utc>) -> bool {
+    let summary = self.build(&settings, now).await;
+    let Ok(payload) = serde_json::to_value(&summary) else {
+      return false;
+    };
+    self
+      .notification_service
+      .notify(
+        &settings.user_id,
+        NotificationKind::Digest,
+        None,
+        NotificationTarget::new("digests", &summary.date),
+        payload,
+      )
+      .await;
+    if settings.email {
+      settings.last_email_error = self.send_email(&summary).await.err();
+    }
+    settings.last_sent_on = Some(summary.date.clone());
+    settings.last_sent_at = Some(now.to_rfc3339_opts(SecondsFormat::Secs, true));
+    settings.updated_at = Some(now);
+    let Ok(value) = serde_json::to_value(&settings) else {
+      return false;
+    };
+    self.store(SETTINGS_TABLE, value).await.is_ok()
+  }
+  async fn send_email(&self, summary: &DigestSummary) -> Result<(), String> {
+    let Some(email_provider) = &self.email_provider else {
+      return Err("Email is not configured".to_string());
+    };
+    let user = self
+      .find_doc("users", &summary.user_id)
+      .await
+      .ok_or_else(|| "User not found".to_string())?;
+    let email = str_field(&user, "email");
+    if email.is_empty() {
+      return Err("The account has no email address".to_string());
+    }
+    email_provider
+      .send_digest(
+        email,
+        &digest::subject(summary),
+        &digest::render_text(summary),
+      )
+      .await
+  }
+  async fn build(&self, settings: &DigestSettingsEntity, now: DateTime<Utc>) -> DigestSummary {
+    let user_id = settings.user_id.as_str();
+    let zone = digest::timezone(settings);
+    let today = now.with_timezone(&zone).date_naive();
+    let profile_id = self.profile_id(user_id).await;
+    let todos: HashMap<String, Value> = self
+      .statistics_service
+      .get_visible_todos(user_id, profile_id.as_deref())
+      .await
+      .into_iter()
+      .filter(is_live)
+      .map(|todo| (str_field(&todo, "id").to_string(), todo))
+      .collect();
+    let todo_ids: Vec<String> = todos.keys().cloned().collect();
+    let mut due_today = Vec::new();
+    let mut due_this_week = Vec::new();
+    let mut overdue = Vec::new();
+    for task in self.statistics_service.get_todo_tasks(&todo_ids).await {
+      if !is_live(&task) || task.get("status") != Some(&json!(TaskStatus::Pending)) {
+        continue;
+      }
+      let Some(due) = parse_due(str_field(&task, "end_date")) else {
+        continue;
+      };
+      let list = match digest::bucket(due, today, zone, now) {
+        Some(DueBucket::Overdue) => &mut overdue,
+        Some(DueBucket::Today) => &mut due_today,
+        Some(DueBucket::ThisWeek) => &mut due_this_week,
+        None => continue,
+      };
+      list.push((
+        due,
+        digest_task(&task, todos.get(str_field(&task, "todo_id"))),
+      ));
+    }
+    let since = digest::window_start(settings, now).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let mut newly_assigned = Vec::new();
+    let mut unread_comments = Vec::new();
+    let mut other_updates = Vec::new();
+    for notification in self.notification_service.find_for_user(user_id, None).await {
+      let kind = notification
+        .get("kind")
+        .cloned()
+        .and_then(|v| serde_json::from_value::<NotificationKind>(v).ok())
+        .unwrap_or_default();
+      let unread = notification.get("read").and_then(|v| v.as_bool()) != Some(true);
+      let recent = str_field(&notification, "created_at") >= since.as_str();
+      match kind {
+        NotificationKind::Assigned if recent => newly_assigned.push(notification),
+        NotificationKind::Commented | NotificationKind::Replied | NotificationKind::Mentioned
+          if unread =>
+        {
+          unread_comments.push(notification)
+        }
+        NotificationKind::Digest => {}
+        _ if unread && notification.get("email_digest").and_then(|v| v.as_bool()) == Some(true) => {
+          other_updates.push(notification)
+        }
+        _ => {}
+      }
+    }
+    let yesterday = self
+      .statistics_service
+      .get_daily_activity(user_id, &(today - Duration::days(1)))
+      .await;
+    let counts = DigestCounts {
+      due_today: due_today.len(),
+      due_this_week: due_this_week.len(),
+      overdue: overdue.len(),
+      newly_assigned: newly_assigned.len(),
+      unread_comments: unread_comments.len(),
+      other_updates: other_updates.len(),
+    };
+    DigestSummary {
+      user_id: user_id.to_string(),
+      frequency: settings.frequency,
+      date: today.format("%Y-%m-%d").to_string(),
+      due_today: by_due_date(due_today),
+      due_this_week: by_due_date(due_this_week),
+      overdue: by_due_date(overdue),
+      newly_assigned: newest(newly_assigned),
+      unread_comments: newest(unread_comments),
+      other_updates: newest(other_updates),
+      yesterday,
+      counts,
+    }
+  }
+  async fn settings_for(&self, user_id: &str) -> DigestSettingsEntity {
+    self
+      .find_doc(SETTINGS_TABLE, user_id)
+      .await
+      .and_then(|doc| serde_json::from_value(doc).ok())
+      .unwrap_or_else(|| DigestSettingsEntity {
+        id: Some(user_id.to_string()),
+        user_id: user_id.to_string(),
+        frequency: DigestFrequency::Off,
+        hour: 8,
+        weekday: 0,
+        timezone: "UTC".to_string(),
+        email: false,
+        last_sent_on: None,
+        last_sent_at: None,
+        last_email_error: None,
+        created_at: None,
+        updated_at: None,
+      })
+  }
+  /// Todos may list the user's profile id as an assignee instead of their user id.
+  async fn profile_id(&self, user_id: &str) -> Option<String> {
+    let filter = Filter::Eq("user_id".to_string(), json!(user_id));
+    self
+      .find_all("profiles", &filter)
+      .await
+      .first()
+      .map(|profile| str_field(profile, "id").to_string())
+  }
+  /// The most recently updated of the local and cloud copies.
+  async fn find_doc(&self, table: &str, id: &str) -> Option<Value> {
+    if id.is_empty() {
+      return None;
+    }
+    let local = self
+      .json_provider
+      .find_by_id(table, id)
+      .await
+      .ok()
+      .flatten();
+    let cloud = match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    };
+    match (local, cloud) {
+      (Some(local), Some(cloud)) => Some(
+        if str_field(&cloud, "updated_at") > str_field(&local, "updated_at") {
+          cloud
+        } else {
+          local
+        },
+      ),
+      (local, cloud) => local.or(cloud),
+    }
+  }
+  /// Matches from the local store and MongoDB, de-duplicated by id.
+  async fn find_all(&self, table: &str, filter: &Filter) -> Vec<Value> {
+    let mut docs = self
+      .json_provider
+      .find_many(table, Some(filter), None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      for doc in mongo
+        .find_many(table, Some(filter), None, None, None, true)
+        .await
+        .unwrap_or_default()
+      {
+        if !docs.iter().any(|d| d.get("id") == doc.get("id")) {
+          docs.push(doc);
+        }
+      }
+    }
+    docs
+  }
+  /// Upserts locally and, when reachable, in MongoDB so other devices see the same state.
+  async fn store(&self, table: &str, doc: Value) -> Result<Value, ResponseModel> {
+    let id = str_field(&doc, "id").to_string();
+    let saved = match self.json_provider.find_by_id(table, &id).await? {
+      Some(_) => self.json_provider.update(table, &id, doc.clone()).await?,
+      None => self.json_provider.insert(table, doc.clone()).await?,
+    };
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = match mongo.find_by_id(table, &id).await {
+        Ok(Some(_)) => mongo.update(table, &id, doc).await,
+        Ok(None) => mongo.insert(table, doc).await,
+        Err(e) => Err(e),
+      };
+    }
+    Ok(saved)
+  }
+}
+fn digest_task(task: &Value, todo: Option<&Value>) -> DigestTask {
+  DigestTask {
+    id: str_field(task, "id").to_string(),
+    title: str_field(task, "title").to_string(),
+    todo_id: str_field(task, "todo_id").to_string(),
+    todo_title: todo
+      .map(|todo| str_field(todo, "title").to_string())
+      .unwrap_or_default(),
+    end_date: str_field(task, "end_date").to_string(),
+  }
+}
+fn by_due_date(mut tasks: Vec<(DateTime<Utc>, DigestTask)>) -> Vec<DigestTask> {
+  tasks.sort_by_key(|(due, _)| *due);
+  tasks
+    .into_iter()
+    .take(MAX_ITEMS)
+    .map(|(_, task)| task)
+    .collect()
+}
+fn newest(mut notifications: Vec<Value>) -> Vec<Value> {
+  notifications.sort_by(|a, b| str_field(b, "created_at").cmp(str_field(a, "created_at")));
+  notifications.truncate(MAX_ITEMS);
+  notifications
+}
+fn is_live(doc: &Value) -> bool {
+  doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true)
+}
+fn str_field<'a>(doc: &'a Value, field: &str) -> &'a str {
+  doc.get(field).and_then(|v| v.as_str()).unwrap_or("")
+}
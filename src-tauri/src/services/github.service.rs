@@ -1,6 +1,15 @@
 /* sys lib */
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tauri_plugin_http::reqwest;
+/* services */
+use crate::services::oauth::provider::{
+  AuthorizationRequest, OAuthIdentity, OAuthProvider, OAuthTokens,
+};
+/* helpers */
+use crate::utils::config::OAuthProviderConfig;
+const AUTHORIZE_URL: &str = "https://github.com/login/oauth/authorize";
+const TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GithubRepo {
   pub id: i64,
@@ -46,6 +55,8 @@ pub struct GithubOAuthTokens {
 }
 pub struct GithubService {
   http_client: reqwest::Client,
+  /// Sign-in provider settings; only needed when used as an `OAuthProvider`
+  oauth: Option<OAuthProviderConfig>,
 }
 impl GithubService {
   pub fn new() -> Self {
@@ -53,14 +64,24 @@ impl GithubService {
       .user_agent("TaskFlow/1.0")
       .build()
       .expect("Failed to create HTTP client");
-    Self { http_client }
+    Self {
+      http_client,
+      oauth: None,
+    }
+  }
+  /// Enables signing in with GitHub through the `OAuthProvider` trait.
+  pub fn with_oauth(mut self, config: OAuthProviderConfig) -> Self {
+    self.oauth = Some(config);
+    self
+  }
+  fn oauth_config(&self) -> Result<&OAuthProviderConfig, String> {
+    self
+      .oauth
+      .as_ref()
+      .ok_or_else(|| "GitHub sign-in is not configured".to_string())
   }
   pub async fn get_authorization_url(&self, client_id: &str, redirect_uri: &str) -> String {
-    let scope = "repo";
-    format!(
-      "https://github.com/login/oauth/authorize?client_id={}&redirect_uri={}&scope={}",
-      client_id, redirect_uri, scope
-    )
+    authorize_url(client_id, redirect_uri, "repo", &[]).unwrap_or_default()
   }
   pub async fn start_device_code_flow(
     &self,
@@ -104,7 +125,7 @@ impl GithubService {
     ];
     let response = self
       .http_client
-      .post("https://github.com/login/oauth/access_token")
+      .post(TOKEN_URL)
       .header("Accept", "application/json")
       .form(&params)
       .send()
@@ -149,21 +170,47 @@ impl GithubService {
     client_secret: &str,
     code: &str,
   ) -> Result<GithubOAuthTokens, String> {
-    let params = [
-      ("client_id", client_id),
-      ("client_secret", client_secret),
-      ("code", code),
-    ];
+    self
+      .request_token(&[
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("code", code),
+      ])
+      .await
+  }
+  /// Posts to GitHub's token endpoint; shared by the repository integration and sign-in.
+  async fn request_token(&self, params: &[(&str, &str)]) -> Result<GithubOAuthTokens, String> {
     let response = self
       .http_client
-      .post("https://github.com/login/oauth/access_token")
+      .post(TOKEN_URL)
       .header("Accept", "application/json")
-      .form(&params)
+      .form(params)
       .send()
       .await
       .map_err(|e| e.to_string())?;
-    let tokens: GithubOAuthTokens = response.json().await.map_err(|e| e.to_string())?;
-    Ok(tokens)
+    #[derive(Deserialize)]
+    struct TokenResponse {
+      access_token: Option<String>,
+      refresh_token: Option<String>,
+      expires_in: Option<i64>,
+      token_type: Option<String>,
+      error_description: Option<String>,
+    }
+    let token_resp: TokenResponse = response.json().await.map_err(|e| e.to_string())?;
+    match token_resp.access_token {
+      // OAuth apps without token expiration return neither a refresh token nor expires_in
+      Some(access_token) => Ok(GithubOAuthTokens {
+        access_token,
+        refresh_token: token_resp.refresh_token.unwrap_or_default(),
+        expires_in: token_resp.expires_in.unwrap_or(0),
+        token_type: token_resp.token_type.unwrap_or_default(),
+      }),
+      None => Err(
+        token_resp
+          .error_description
+          .unwrap_or_else(|| "Missing access token in response".to_string()),
+      ),
+    }
   }
   pub async fn get_user(&self, access_token: &str) -> Result<GithubUser, String> {
     let response = self
@@ -329,3 +376,82 @@ impl Default for GithubService {
     Self::new()
   }
 }
+/// GitHub is plain OAuth2 without id_tokens, so the identity comes from the `/user` API.
+#[async_trait]
+impl OAuthProvider for GithubService {
+  fn id(&self) -> &str {
+    self
+      .oauth
+      .as_ref()
+      .map(|c| c.id.as_str())
+      .unwrap_or("github")
+  }
+  fn display_name(&self) -> &str {
+    self
+      .oauth
+      .as_ref()
+      .map(|c| c.display_name.as_str())
+      .unwrap_or("GitHub")
+  }
+  async fn authorization_url(&self, request: &AuthorizationRequest) -> Result<String, String> {
+    let config = self.oauth_config()?;
+    authorize_url(
+      &config.client_id,
+      &config.redirect_uri,
+      &config.scopes.join(" "),
+      &[
+        ("state", request.state.as_str()),
+        ("code_challenge", request.code_challenge.as_str()),
+        ("code_challenge_method", "S256"),
+      ],
+    )
+  }
+  async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<OAuthTokens, String> {
+    let config = self.oauth_config()?;
+    let tokens = self
+      .request_token(&[
+        ("client_id", config.client_id.as_str()),
+        ("client_secret", config.client_secret.as_str()),
+        ("code", code),
+        ("redirect_uri", config.redirect_uri.as_str()),
+        ("code_verifier", code_verifier),
+      ])
+      .await?;
+    Ok(OAuthTokens {
+      access_token: tokens.access_token,
+      refresh_token: Some(tokens.refresh_token).filter(|t| !t.is_empty()),
+      id_token: None,
+      expires_in: Some(tokens.expires_in).filter(|e| *e > 0),
+    })
+  }
+  async fn fetch_identity(
+    &self,
+    tokens: &OAuthTokens,
+    _nonce: &str,
+  ) -> Result<OAuthIdentity, String> {
+    let user = self.get_user(&tokens.access_token).await?;
+    Ok(OAuthIdentity {
+      provider: self.id().to_string(),
+      subject: user.id.to_string(),
+      email: None,
+      email_verified: false,
+      username: Some(user.login.clone()),
+      display_name: Some(user.login),
+    })
+  }
+}
+fn authorize_url(
+  client_id: &str,
+  redirect_uri: &str,
+  scope: &str,
+  extra: &[(&str, &str)],
+) -> Result<String, String> {
+  let params = [
+    ("client_id", client_id),
+    ("redirect_uri", redirect_uri),
+    ("scope", scope),
+  ];
+  reqwest::Url::parse_with_params(AUTHORIZE_URL, params.iter().chain(extra))
+    .map(|url| url.to_string())
+    .map_err(|e| e.to_string())
+}
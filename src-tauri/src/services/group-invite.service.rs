@@ -0,0 +1,474 @@
+/* sys lib */
+use chrono::{DateTime, Duration, Utc};
+use data_encoding::BASE32_NOPAD;
+use rand::RngCore;
+use serde_json::{json, Value};
+use std::sync::Arc;
+/* providers */
+use crate::repositories::data_provider::DataProvider;
+/* entities */
+use crate::entities::group_invite_entity::GroupInviteEntity;
+use crate::entities::group_join_request_entity::{GroupJoinRequestEntity, JoinRequestStatus};
+use crate::entities::group_moderation_entity::GroupModerationAction;
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::group_service::GroupService;
+/* helpers */
+use crate::utils::group_roles;
+use crate::utils::response_helper::{err_response, success_response};
+/* nosql_orm */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::query::Filter;
+const INVITES_TABLE: &str = "group_invites";
+const JOIN_REQUESTS_TABLE: &str = "group_join_requests";
+/// Invite links and join requests for groups. Membership changes go through
+/// `GroupService::admit` so they land in the moderation log.
+pub struct GroupInviteService {
+  json_provider: DataProvider,
+  mongo_provider: Option<DataProvider>,
+  group_service: Arc<GroupService>,
+}
+impl GroupInviteService {
+  pub fn new(
+    json_provider: DataProvider,
+    mongo_provider: Option<DataProvider>,
+    group_service: Arc<GroupService>,
+  ) -> Self {
+    Self {
+      json_provider,
+      mongo_provider,
+      group_service,
+    }
+  }
+  fn get_mongo_provider(&self) -> Result<&DataProvider, ResponseModel> {
+    self
+      .mongo_provider
+      .as_ref()
+      .ok_or_else(|| err_response("MongoDB not available"))
+  }
+  pub async fn create_invite(
+    &self,
+    group_id: &str,
+    actor_id: &str,
+    expires_in_hours: Option<i64>,
+    max_uses: Option<u32>,
+    requires_approval: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    invite_limits_valid(expires_in_hours, max_uses).map_err(ResponseModel::validation_error)?;
+    let group = self.moderated_group(group_id, actor_id).await?;
+    let group_id = group_doc_id(&group);
+    let now = Utc::now();
+    let invite = GroupInviteEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      group_id: group_id.clone(),
+      code: invite_code(),
+      created_by: actor_id.to_string(),
+      expires_at: expires_in_hours.map(|hours| now + Duration::hours(hours)),
+      max_uses,
+      uses: 0,
+      requires_approval,
+      revoked: false,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let doc = self.save(INVITES_TABLE, to_doc(&invite)?).await?;
+    self
+      .group_service
+      .log_action(
+        &group_id,
+        actor_id,
+        GroupModerationAction::InviteCreated,
+        None,
+        json!({ "invite_id": invite.id, "max_uses": max_uses, "expires_at": invite.expires_at }),
+      )
+      .await;
+    Ok(success_response(doc))
+  }
+  pub async fn get_invites(
+    &self,
+    group_id: &str,
+    actor_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let group = self.moderated_group(group_id, actor_id).await?;
+    let filter = Filter::Eq("group_id".to_string(), json!(group_doc_id(&group)));
+    let docs = self
+      .get_mongo_provider()?
+      .find_many(
+        INVITES_TABLE,
+        Some(&filter),
+        None,
+        None,
+        Some("created_at"),
+        false,
+      )
+      .await?;
+    Ok(success_response(docs))
+  }
+  pub async fn revoke_invite(
+    &self,
+    invite_id: &str,
+    actor_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let invite = self
+      .get_mongo_provider()?
+      .find_by_id(INVITES_TABLE, invite_id)
+      .await?
+      .ok_or_else(|| err_response("Invite not found"))?;
+    let group_id = invite
+      .get("group_id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("");
+    self.moderated_group(group_id, actor_id).await?;
+    let doc = self
+      .patch(INVITES_TABLE, invite_id, json!({ "revoked": true }))
+      .await?;
+    self
+      .group_service
+      .log_action(
+        group_id,
+        actor_id,
+        GroupModerationAction::InviteRevoked,
+        None,
+        json!({ "invite_id": invite_id }),
+      )
+      .await;
+    Ok(success_response(doc))
+  }
+  /// Joins the group behind `code`, or files a join request when the invite asks for approval.
+  /// A use is counted once someone is admitted, so pending requests do not use up the invite.
+  pub async fn redeem_invite(
+    &self,
+    code: &str,
+    user_id: &str,
+    message: Option<String>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let filter = Filter::Eq("code".to_string(), json!(code));
+    let doc = self
+      .get_mongo_provider()?
+      .find_many(INVITES_TABLE, Some(&filter), None, Some(1), None, true)
+      .await?
+      .into_iter()
+      .next()
+      .ok_or_else(|| err_response("Invite not found"))?;
+    let invite: GroupInviteEntity =
+      serde_json::from_value(doc).map_err(|e| err_response(&format!("Invalid invite: {}", e)))?;
+    invite_usable(&invite, Utc::now()).map_err(err_response)?;
+    let group = self.group_service.find_group(&invite.group_id).await?;
+    if group_roles::role_of(&group, user_id).is_some() {
+      return Ok(success_response(
+        json!({ "status": "member", "group": group }),
+      ));
+    }
+    let invite_id = invite.id.clone().unwrap_or_default();
+    let result = if invite.requires_approval {
+      let request = self
+        .file_request(&group, user_id, Some(&invite_id), message)
+        .await?;
+      json!({ "status": "pending", "request": request })
+    } else {
+      let joined = self
+        .group_service
+        .admit(
+          &group,
+          user_id,
+          vec![user_id.to_string()],
+          GroupModerationAction::JoinedViaInvite,
+        )
+        .await?;
+      self.count_use(&invite_id).await?;
+      json!({ "status": "member", "group": joined.data })
+    };
+    Ok(success_response(result))
+  }
+  pub async fn request_join(
+    &self,
+    group_id: &str,
+    user_id: &str,
+    message: Option<String>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let group = self.group_service.find_group(group_id).await?;
+    if group_roles::role_of(&group, user_id).is_some() {
+      return Err(err_response("You are already a member of this group"));
+    }
+    let request = self.file_request(&group, user_id, None, message).await?;
+    Ok(success_response(request))
+  }
+  pub async fn get_join_requests(
+    &self,
+    group_id: &str,
+    actor_id: &str,
+    status: Option<JoinRequestStatus>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let group = self.moderated_group(group_id, actor_id).await?;
+    let status = status.unwrap_or(JoinRequestStatus::Pending);
+    let filter = Filter::And(vec![
+      Filter::Eq("group_id".to_string(), json!(group_doc_id(&group))),
+      Filter::Eq("status".to_string(), json!(status)),
+    ]);
+    let docs = self
+      .get_mongo_provider()?
+      .find_many(
+        JOIN_REQUESTS_TABLE,
+        Some(&filter),
+        None,
+        None,
+        Some("created_at"),
+        true,
+      )
+      .await?;
+    Ok(success_response(docs))
+  }
+  pub async fn decide_join_request(
+    &self,
+    request_id: &str,
+    actor_id: &str,
+    approve: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let doc = self
+      .get_mongo_provider()?
+      .find_by_id(JOIN_REQUESTS_TABLE, request_id)
+      .await?
+      .ok_or_else(|| err_response("Join request not found"))?;
+    let request: GroupJoinRequestEntity = serde_json::from_value(doc)
+      .map_err(|e| err_response(&format!("Invalid join request: {}", e)))?;
+    if request.status != JoinRequestStatus::Pending {
+      return Err(err_response("Join request was already decided"));
+    }
+    let group = self.moderated_group(&request.group_id, actor_id).await?;
+    let (status, action) = if approve {
+      (
+        JoinRequestStatus::Approved,
+        GroupModerationAction::JoinApproved,
+      )
+    } else {
+      (
+        JoinRequestStatus::Rejected,
+        GroupModerationAction::JoinRejected,
+      )
+    };
+    if approve {
+      self
+        .group_service
+        .admit(&group, actor_id, vec![request.user_id.clone()], action)
+        .await?;
+      if let Some(invite_id) = &request.invite_id {
+        self.count_use(invite_id).await?;
+      }
+    } else {
+      self
+        .group_service
+        .log_action(
+          &request.group_id,
+          actor_id,
+          action,
+          Some(&request.user_id),
+          Value::Null,
+        )
+        .await;
+    }
+    let decided = self
+      .patch(
+        JOIN_REQUESTS_TABLE,
+        request_id,
+        json!({
+          "status": status,
+          "decided_by": actor_id,
+          "decided_at": Utc::now().to_rfc3339(),
+        }),
+      )
+      .await?;
+    Ok(success_response(decided))
+  }
+  /// One pending request per user and group; asking again returns the open one.
+  async fn file_request(
+    &self,
+    group: &Value,
+    user_id: &str,
+    invite_id: Option<&str>,
+    message: Option<String>,
+  ) -> Result<Value, ResponseModel> {
+    let group_id = group_doc_id(group);
+    let filter = Filter::And(vec![
+      Filter::Eq("group_id".to_string(), json!(group_id)),
+      Filter::Eq("user_id".to_string(), json!(user_id)),
+      Filter::Eq("status".to_string(), json!(JoinRequestStatus::Pending)),
+    ]);
+    if let Some(open) = self
+      .get_mongo_provider()?
+      .find_many(
+        JOIN_REQUESTS_TABLE,
+        Some(&filter),
+        None,
+        Some(1),
+        None,
+        true,
+      )
+      .await?
+      .into_iter()
+      .next()
+    {
+      return Ok(open);
+    }
+    let now = Utc::now();
+    let request = GroupJoinRequestEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      group_id: group_id.clone(),
+      user_id: user_id.to_string(),
+      invite_id: invite_id.map(|s| s.to_string()),
+      message,
+      status: JoinRequestStatus::Pending,
+      decided_by: None,
+      decided_at: None,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let doc = self.save(JOIN_REQUESTS_TABLE, to_doc(&request)?).await?;
+    self
+      .group_service
+      .log_action(
+        &group_id,
+        user_id,
+        GroupModerationAction::JoinRequested,
+        Some(user_id),
+        Value::Null,
+      )
+      .await;
+    Ok(doc)
+  }
+  /// Records that someone joined through the invite, counting from its stored value.
+  async fn count_use(&self, invite_id: &str) -> Result<(), ResponseModel> {
+    let Some(doc) = self
+      .get_mongo_provider()?
+      .find_by_id(INVITES_TABLE, invite_id)
+      .await?
+    else {
+      return Ok(());
+    };
+    let uses = doc.get("uses").and_then(|v| v.as_u64()).unwrap_or(0);
+    self
+      .patch(INVITES_TABLE, invite_id, json!({ "uses": uses + 1 }))
+      .await?;
+    Ok(())
+  }
+  async fn moderated_group(&self, group_id: &str, actor_id: &str) -> Result<Value, ResponseModel> {
+    let group = self.group_service.find_group(group_id).await?;
+    if !group_roles::can_moderate(group_roles::role_of(&group, actor_id)) {
+      return Err(ResponseModel::forbidden(
+        "Only group admins can manage invites and join requests",
+      ));
+    }
+    Ok(group)
+  }
+  async fn save(&self, table: &str, doc: Value) -> Result<Value, ResponseModel> {
+    let saved = self.get_mongo_provider()?.insert(table, doc).await?;
+    if let DataProvider::Json(p) = &self.json_provider {
+      let _ = p.insert(table, saved.clone()).await;
+    }
+    Ok(saved)
+  }
+  async fn patch(&self, table: &str, id: &str, data: Value) -> Result<Value, ResponseModel> {
+    let mut update_data = data;
+    update_data["updated_at"] = json!(Utc::now().to_rfc3339());
+    let doc = self
+      .get_mongo_provider()?
+      .patch(table, id, update_data.clone())
+      .await?;
+    if let DataProvider::Json(p) = &self.json_provider {
+      let _ = p.patch(table, id, update_data).await;
+    }
+    Ok(doc)
+  }
+}
+fn invite_code() -> String {
+  let mut bytes = [0u8; 10];
+  rand::thread_rng().fill_bytes(&mut bytes);
+  BASE32_NOPAD.encode(&bytes)
+}
+/// Why the requested expiry or usage limit is not allowed, if it isn't.
+fn invite_limits_valid(
+  expires_in_hours: Option<i64>,
+  max_uses: Option<u32>,
+) -> Result<(), &'static str> {
+  if expires_in_hours.is_some_and(|hours| hours <= 0) {
+    return Err("Expiry must be at least one hour");
+  }
+  if max_uses == Some(0) {
+    return Err("Usage limit must be at least one");
+  }
+  Ok(())
+}
+/// Why an invite can no longer be redeemed, if it can't.
+fn invite_usable(invite: &GroupInviteEntity, now: DateTime<Utc>) -> Result<(), &'static str> {
+  if invite.revoked {
+    return Err("This invite was revoked");
+  }
+  if invite
+    .expires_at
+    .is_some_and(|expires_at| expires_at <= now)
+  {
+    return Err("This invite has expired");
+  }
+  if invite.max_uses.is_some_and(|max| invite.uses >= max) {
+    return Err("This invite has reached its usage limit");
+  }
+  Ok(())
+}
+fn group_doc_id(group: &Value) -> String {
+  group
+    .get("id")
+    .and_then(|v| v.as_str())
+    .unwrap_or("")
+    .to_string()
+}
+fn to_doc<T: serde::Serialize>(value: &T) -> Result<Value, ResponseModel> {
+  serde_json::to_value(value).map_err(|e| err_response(&format!("Serialization failed: {}", e)))
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  fn invite() -> GroupInviteEntity {
+    GroupInviteEntity {
+      id: Some("i1".to_string()),
+      group_id: "g1".to_string(),
+      code: "CODE".to_string(),
+      created_by: "owner".to_string(),
+      expires_at: None,
+      max_uses: None,
+      uses: 0,
+      requires_approval: false,
+      revoked: false,
+      created_at: None,
+      updated_at: None,
+    }
+  }
+  #[test]
+  fn invites_respect_expiry_cap_and_revocation() {
+    let now = Utc::now();
+    assert!(invite_usable(&invite(), now).is_ok());
+    let expired = GroupInviteEntity {
+      expires_at: Some(now - Duration::minutes(1)),
+      ..invite()
+    };
+    assert!(invite_usable(&expired, now).is_err());
+    let used_up = GroupInviteEntity {
+      max_uses: Some(3),
+      uses: 3,
+      ..invite()
+    };
+    assert!(invite_usable(&used_up, now).is_err());
+    let revoked = GroupInviteEntity {
+      revoked: true,
+      ..invite()
+    };
+    assert!(invite_usable(&revoked, now).is_err());
+  }
+  #[test]
+  fn invite_limits_must_be_positive() {
+    assert!(invite_limits_valid(None, None).is_ok());
+    assert!(invite_limits_valid(Some(24), Some(5)).is_ok());
+    assert!(invite_limits_valid(Some(0), None).is_err());
+    assert!(invite_limits_valid(Some(-3), None).is_err());
+    assert!(invite_limits_valid(None, Some(0)).is_err());
+  }
+}
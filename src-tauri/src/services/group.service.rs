@@ -1,19 +1,33 @@
+use crate::entities::group_entity::{GroupMute, GroupRole};
+use crate::entities::group_moderation_entity::{GroupModerationAction, GroupModerationEntity};
 use crate::models::response::ResponseModel;
 use crate::repositories::data_provider::DataProvider;
+use crate::services::notification_service::NotificationService;
+use crate::utils::group_roles;
 use crate::utils::response_helper::{err_response, success_response};
+use chrono::{DateTime, Utc};
 use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::query::Filter;
 use serde_json::{json, Value};
+use std::sync::Arc;
+const MODERATION_TABLE: &str = "group_moderation_log";
 pub struct GroupService {
   json_provider: DataProvider,
   mongo_provider: Option<DataProvider>,
+  notification_service: Option<Arc<NotificationService>>,
 }
 impl GroupService {
   pub fn new(json_provider: DataProvider, mongo_provider: Option<DataProvider>) -> Self {
     Self {
       json_provider,
       mongo_provider,
+      notification_service: None,
     }
   }
+  pub fn with_notifications(mut self, notification_service: Arc<NotificationService>) -> Self {
+    self.notification_service = Some(notification_service);
+    self
+  }
   fn get_json_provider(&self) -> &DataProvider {
     &self.json_provider
   }
@@ -134,6 +148,9 @@ impl GroupService {
         }
       }
     }
+    if let Some(notifications) = &self.notification_service {
+      notifications.notify_group_members(&doc, None, None).await;
+    }
     Ok(success_response(doc))
   }
   pub async fn update(&self, id: &str, data: Value) -> Result<ResponseModel, ResponseModel> {
@@ -150,85 +167,380 @@ impl GroupService {
     }
     Ok(success_response(doc))
   }
+  /// Adds members to the group owning `room_id`. Only the owner and admins may add people.
   pub async fn add_members(
     &self,
     room_id: &str,
+    actor_id: &str,
     member_ids: Vec<String>,
   ) -> Result<ResponseModel, ResponseModel> {
-    let mongo = self
-      .get_mongo_provider()
-      .ok_or_else(|| err_response("MongoDB not available"))?;
-    let filter = json!({ "room_id": room_id });
-    let filter_opt = Some(
-      nosql_orm::query::Filter::from_json(&filter)
-        .map_err(|e| err_response(&format!("Invalid filter: {}", e)))?,
-    );
-    let docs = mongo
-      .find_many("groups", filter_opt.as_ref(), None, Some(1), None, true)
-      .await?;
-    let existing = docs
-      .first()
-      .cloned()
-      .ok_or_else(|| err_response("Group not found"))?;
-    let group_id = existing
-      .get("id")
-      .and_then(|v| v.as_str())
-      .unwrap_or(room_id);
-    let mut members: Vec<String> = existing
-      .get("member_ids")
-      .and_then(|v| v.as_array())
-      .map(|arr| {
-        arr
-          .iter()
-          .filter_map(|v| v.as_str().map(String::from))
-          .collect()
-      })
-      .unwrap_or_default();
+    let existing = self.find_group(room_id).await?;
+    if !group_roles::can_moderate(group_roles::role_of(&existing, actor_id)) {
+      return Err(ResponseModel::forbidden(
+        "Only group admins can add members",
+      ));
+    }
+    self
+      .admit(
+        &existing,
+        actor_id,
+        member_ids,
+        GroupModerationAction::MemberAdded,
+      )
+      .await
+  }
+  /// Adds users to an already loaded group and records `action` for each newcomer.
+  /// Authorisation is up to the caller (a moderator, a valid invite or an approved request).
+  pub async fn admit(
+    &self,
+    group: &Value,
+    actor_id: &str,
+    member_ids: Vec<String>,
+    action: GroupModerationAction,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let group_id = doc_id(group, "");
+    let mut members = group_roles::string_list(group, "member_ids");
+    let mut added = Vec::new();
     for member_id in member_ids {
       if !members.contains(&member_id) {
-        members.push(member_id);
+        members.push(member_id.clone());
+        added.push(member_id);
       }
     }
-    let now = chrono::Utc::now().to_rfc3339();
-    let update_data = json!({ "member_ids": members.clone(), "updated_at": now });
-    let doc = mongo.patch("groups", group_id, update_data.clone()).await?;
-    let json_provider = self.get_json_provider();
-    if let DataProvider::Json(p) = json_provider {
-      let _ = p.patch("groups", group_id, update_data).await;
+    let doc = self
+      .write(&group_id, json!({ "member_ids": members.clone() }))
+      .await?;
+    self.sync_room_participants(group, &members).await;
+    for member_id in &added {
+      self
+        .log_action(&group_id, actor_id, action, Some(member_id), Value::Null)
+        .await;
+    }
+    if let Some(notifications) = &self.notification_service {
+      notifications
+        .notify_group_members(&doc, Some(group), Some(actor_id))
+        .await;
     }
     Ok(success_response(doc))
   }
+  /// Removes members. Moderators may remove anyone ranked below them; any member may remove
+  /// themselves, except the owner, who has to transfer ownership first.
   pub async fn remove_members(
     &self,
     id: &str,
+    actor_id: &str,
     member_ids: Vec<String>,
   ) -> Result<ResponseModel, ResponseModel> {
+    let existing = self.find_group(id).await?;
+    let actor_role = group_roles::role_of(&existing, actor_id);
+    for member_id in &member_ids {
+      let target_role = group_roles::role_of(&existing, member_id);
+      if target_role == Some(GroupRole::Owner) {
+        return Err(err_response(
+          "The owner cannot leave or be removed; transfer ownership first",
+        ));
+      }
+      if member_id != actor_id && !group_roles::outranks(actor_role, target_role) {
+        return Err(ResponseModel::forbidden(
+          "You do not have permission to remove this member",
+        ));
+      }
+    }
+    let group_id = doc_id(&existing, id);
+    let mut members = group_roles::string_list(&existing, "member_ids");
+    members.retain(|m| !member_ids.contains(m));
+    let mut admins = group_roles::string_list(&existing, "admin_ids");
+    admins.retain(|m| !member_ids.contains(m));
+    let doc = self
+      .write(
+        &group_id,
+        json!({ "member_ids": members.clone(), "admin_ids": admins }),
+      )
+      .await?;
+    self.sync_room_participants(&existing, &members).await;
+    for member_id in &member_ids {
+      let action = if member_id == actor_id {
+        GroupModerationAction::MemberLeft
+      } else {
+        GroupModerationAction::MemberRemoved
+      };
+      self
+        .log_action(&group_id, actor_id, action, Some(member_id), Value::Null)
+        .await;
+    }
+    Ok(success_response(doc))
+  }
+  /// Promotes a member to admin or demotes an admin. Owner only.
+  pub async fn set_role(
+    &self,
+    id: &str,
+    actor_id: &str,
+    user_id: &str,
+    role: GroupRole,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let existing = self.find_group(id).await?;
+    if group_roles::role_of(&existing, actor_id) != Some(GroupRole::Owner) {
+      return Err(ResponseModel::forbidden(
+        "Only the group owner can change roles",
+      ));
+    }
+    let previous = group_roles::role_of(&existing, user_id);
+    match (previous, role) {
+      (None, _) => return Err(err_response("User is not a member of this group")),
+      (Some(GroupRole::Owner), _) => {
+        return Err(err_response(
+          "The owner's role changes only through an ownership transfer",
+        ))
+      }
+      (_, GroupRole::Owner) => {
+        return Err(err_response(
+          "Use an ownership transfer to change the owner",
+        ))
+      }
+      _ => {}
+    }
+    let group_id = doc_id(&existing, id);
+    let mut admins = group_roles::string_list(&existing, "admin_ids");
+    admins.retain(|a| a != user_id);
+    if role == GroupRole::Admin {
+      admins.push(user_id.to_string());
+    }
+    let doc = self
+      .write(&group_id, json!({ "admin_ids": admins }))
+      .await?;
+    self
+      .log_action(
+        &group_id,
+        actor_id,
+        GroupModerationAction::RoleChanged,
+        Some(user_id),
+        json!({ "from": previous, "to": role }),
+      )
+      .await;
+    Ok(success_response(doc))
+  }
+  /// Hands the group to another member. The previous owner stays on as an admin.
+  pub async fn transfer_ownership(
+    &self,
+    id: &str,
+    actor_id: &str,
+    new_owner_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let existing = self.find_group(id).await?;
+    if group_roles::role_of(&existing, actor_id) != Some(GroupRole::Owner) {
+      return Err(ResponseModel::forbidden(
+        "Only the group owner can transfer ownership",
+      ));
+    }
+    if group_roles::role_of(&existing, new_owner_id).is_none() {
+      return Err(err_response("The new owner must be a member of the group"));
+    }
+    let group_id = doc_id(&existing, id);
+    let mut admins = group_roles::string_list(&existing, "admin_ids");
+    admins.retain(|a| a != new_owner_id && a != actor_id);
+    admins.push(actor_id.to_string());
+    let mut muted = muted_list(&existing);
+    muted.retain(|m| m.get("user_id").and_then(|v| v.as_str()) != Some(new_owner_id));
+    let doc = self
+      .write(
+        &group_id,
+        json!({ "owner_id": new_owner_id, "admin_ids": admins, "muted": muted }),
+      )
+      .await?;
+    self
+      .log_action(
+        &group_id,
+        actor_id,
+        GroupModerationAction::OwnershipTransferred,
+        Some(new_owner_id),
+        Value::Null,
+      )
+      .await;
+    Ok(success_response(doc))
+  }
+  /// Stops a member from posting, until `until` or until unmuted.
+  pub async fn mute_member(
+    &self,
+    id: &str,
+    actor_id: &str,
+    user_id: &str,
+    until: Option<DateTime<Utc>>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let existing = self.find_group(id).await?;
+    let target_role = group_roles::role_of(&existing, user_id);
+    if target_role.is_none() {
+      return Err(err_response("User is not a member of this group"));
+    }
+    if !group_roles::outranks(group_roles::role_of(&existing, actor_id), target_role) {
+      return Err(ResponseModel::forbidden(
+        "You do not have permission to mute this member",
+      ));
+    }
+    let group_id = doc_id(&existing, id);
+    let mut muted = muted_list(&existing);
+    muted.retain(|m| m.get("user_id").and_then(|v| v.as_str()) != Some(user_id));
+    muted.push(json!(GroupMute {
+      user_id: user_id.to_string(),
+      muted_by: actor_id.to_string(),
+      until,
+    }));
+    let doc = self.write(&group_id, json!({ "muted": muted })).await?;
+    self
+      .log_action(
+        &group_id,
+        actor_id,
+        GroupModerationAction::MemberMuted,
+        Some(user_id),
+        json!({ "until": until }),
+      )
+      .await;
+    Ok(success_response(doc))
+  }
+  pub async fn unmute_member(
+    &self,
+    id: &str,
+    actor_id: &str,
+    user_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let existing = self.find_group(id).await?;
+    if !group_roles::can_moderate(group_roles::role_of(&existing, actor_id)) {
+      return Err(ResponseModel::forbidden(
+        "Only group admins can unmute members",
+      ));
+    }
+    let group_id = doc_id(&existing, id);
+    let mut muted = muted_list(&existing);
+    muted.retain(|m| m.get("user_id").and_then(|v| v.as_str()) != Some(user_id));
+    let doc = self.write(&group_id, json!({ "muted": muted })).await?;
+    self
+      .log_action(
+        &group_id,
+        actor_id,
+        GroupModerationAction::MemberUnmuted,
+        Some(user_id),
+        Value::Null,
+      )
+      .await;
+    Ok(success_response(doc))
+  }
+  /// True when `room_id` belongs to a group in which the user is currently muted.
+  pub async fn is_muted_in_room(&self, room_id: &str, user_id: &str) -> bool {
+    match self.get_by_room_id(room_id).await {
+      Ok(group) => group_roles::is_muted(&group.data, user_id, Utc::now()),
+      Err(_) => false,
+    }
+  }
+  pub async fn get_moderation_log(
+    &self,
+    id: &str,
+    actor_id: &str,
+    skip: Option<u64>,
+    limit: Option<u64>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let existing = self.find_group(id).await?;
+    if !group_roles::can_moderate(group_roles::role_of(&existing, actor_id)) {
+      return Err(ResponseModel::forbidden(
+        "Only group admins can view the moderation log",
+      ));
+    }
+    let filter = Filter::Eq("group_id".to_string(), json!(doc_id(&existing, id)));
+    let provider = self.get_mongo_provider().unwrap_or(&self.json_provider);
+    let docs = provider
+      .find_many(
+        MODERATION_TABLE,
+        Some(&filter),
+        skip,
+        limit,
+        Some("created_at"),
+        false,
+      )
+      .await?;
+    Ok(success_response(docs))
+  }
+  /// Appends to the moderation log. Logging never fails the action it records.
+  pub async fn log_action(
+    &self,
+    group_id: &str,
+    actor_id: &str,
+    action: GroupModerationAction,
+    target_id: Option<&str>,
+    details: Value,
+  ) {
+    let now = Utc::now();
+    let entry = GroupModerationEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      group_id: group_id.to_string(),
+      actor_id: actor_id.to_string(),
+      action,
+      target_id: target_id.map(|s| s.to_string()),
+      details,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let Ok(doc) = serde_json::to_value(&entry) else {
+      return;
+    };
+    if let Some(mongo) = self.get_mongo_provider() {
+      let _ = mongo.insert(MODERATION_TABLE, doc.clone()).await;
+    }
+    if let DataProvider::Json(p) = self.get_json_provider() {
+      let _ = p.insert(MODERATION_TABLE, doc).await;
+    }
+  }
+  /// Looks a group up by document id, falling back to its chat room id.
+  pub async fn find_group(&self, id: &str) -> Result<Value, ResponseModel> {
     let mongo = self
       .get_mongo_provider()
       .ok_or_else(|| err_response("MongoDB not available"))?;
-    let existing = mongo
-      .find_by_id("groups", id)
+    if let Some(doc) = mongo.find_by_id("groups", id).await? {
+      return Ok(doc);
+    }
+    let filter = Filter::Eq("room_id".to_string(), json!(id));
+    mongo
+      .find_many("groups", Some(&filter), None, Some(1), None, true)
       .await?
-      .ok_or_else(|| err_response("Group not found"))?;
-    let mut members: Vec<String> = existing
-      .get("member_ids")
-      .and_then(|v| v.as_array())
-      .map(|arr| {
-        arr
-          .iter()
-          .filter_map(|v| v.as_str().map(String::from))
-          .collect()
-      })
+      .into_iter()
+      .next()
+      .ok_or_else(|| err_response("Group not found"))
+  }
+  async fn write(&self, group_id: &str, data: Value) -> Result<Value, ResponseModel> {
+    let mongo = self
+      .get_mongo_provider()
+      .ok_or_else(|| err_response("MongoDB not available"))?;
+    let mut update_data = data;
+    update_data["updated_at"] = json!(Utc::now().to_rfc3339());
+    let doc = mongo.patch("groups", group_id, update_data.clone()).await?;
+    if let DataProvider::Json(p) = self.get_json_provider() {
+      let _ = p.patch("groups", group_id, update_data).await;
+    }
+    Ok(doc)
+  }
+  /// Keeps the group's chat room participants in line with the member list, so removed
+  /// members lose access to the room as well.
+  async fn sync_room_participants(&self, group: &Value, member_ids: &[String]) {
+    let Some(room_id) = group.get("room_id").and_then(|v| v.as_str()) else {
+      return;
+    };
+    let Some(mongo) = self.get_mongo_provider() else {
+      return;
+    };
+    let filter = Filter::Eq("room".to_string(), json!(room_id));
+    let rooms = mongo
+      .find_many("rooms", Some(&filter), None, None, None, true)
+      .await
       .unwrap_or_default();
-    members.retain(|m| !member_ids.contains(m));
-    let now = chrono::Utc::now().to_rfc3339();
-    let update_data = json!({ "member_ids": members.clone(), "updated_at": now });
-    let doc = mongo.patch("groups", id, update_data.clone()).await?;
-    let json_provider = self.get_json_provider();
-    if let DataProvider::Json(p) = json_provider {
-      let _ = p.patch("groups", id, update_data).await;
+    let update_data = json!({
+      "participant_ids": member_ids,
+      "updated_at": Utc::now().to_rfc3339(),
+    });
+    for room in rooms {
+      if let Some(room_doc_id) = room.get("id").and_then(|v| v.as_str()) {
+        let _ = mongo.patch("rooms", room_doc_id, update_data.clone()).await;
+        if let DataProvider::Json(p) = self.get_json_provider() {
+          let _ = p.patch("rooms", room_doc_id, update_data.clone()).await;
+        }
+      }
     }
-    Ok(success_response(doc))
   }
   pub async fn delete(&self, id: &str) -> Result<ResponseModel, ResponseModel> {
     let mongo = self
@@ -498,3 +810,17 @@ impl GroupService {
     })))
   }
 }
+fn doc_id(doc: &Value, fallback: &str) -> String {
+  doc
+    .get("id")
+    .and_then(|v| v.as_str())
+    .unwrap_or(fallback)
+    .to_string()
+}
+fn muted_list(group: &Value) -> Vec<Value> {
+  group
+    .get("muted")
+    .and_then(|v| v.as_array())
+    .cloned()
+    .unwrap_or_default()
+}
@@ -0,0 +1,251 @@
+/* sys lib */
+use serde_json::{json, Value};
+use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::mention_entity::Mention;
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::permission_service::PermissionService;
+/* helpers */
+use crate::utils::mentions;
+use crate::utils::profile;
+use crate::utils::response_helper::success_response;
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+const MENTION_EXCERPT_CHARS: usize = 160;
+/// Resolves `@username` mentions in comments and chat messages and lists where a user was
+/// mentioned. A mention only resolves to someone who can see the todo or room it was written in.
+pub struct MentionService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+}
+impl MentionService {
+  pub fn new(json_provider: JsonProvider, mongodb_provider: Option<Arc<MongoProvider>>) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+    }
+  }
+  /// Mentions in a comment that resolve to people who can see `todo`.
+  pub async fn resolve_for_todo(&self, content: &str, todo: &Value) -> Vec<Mention> {
+    self
+      .resolve(content, |user| {
+        PermissionService::can_view_todo_with_profile(
+          todo,
+          str_field(user, "id"),
+          Some(str_field(user, "profile_id")),
+        )
+      })
+      .await
+  }
+  /// Mentions in a chat message that resolve to members of the room.
+  pub async fn resolve_for_room(&self, content: &str, room_id: &str) -> Vec<Mention> {
+    if mentions::parse_mentions(content).is_empty() {
+      return Vec::new();
+    }
+    let members = self.room_members(room_id).await;
+    self
+      .resolve(content, |user| {
+        members.iter().any(|id| id == str_field(user, "id"))
+      })
+      .await
+  }
+  /// The todo a comment belongs to, through its task or its subtask's task.
+  pub async fn todo_for_comment(&self, comment: &Value) -> Option<Value> {
+    let task_id = match comment.get("task_id").and_then(|v| v.as_str()) {
+      Some(task_id) => task_id.to_string(),
+      None => {
+        let subtask = self
+          .find_doc("subtasks", str_field(comment, "subtask_id"))
+          .await?;
+        str_field(&subtask, "task_id").to_string()
+      }
+    };
+    let task = self.find_doc("tasks", &task_id).await?;
+    self
+      .find_doc("todos", str_field(&task, "todo_id"))
+      .await
+      .filter(is_live)
+  }
+  /// Comments and chat messages that mention the user, newest first. Messages are limited
+  /// to `room_ids` and comments to todos the user can still see.
+  pub async fn mentions_of(
+    &self,
+    user_id: &str,
+    room_ids: &[String],
+    skip: Option<usize>,
+    limit: Option<usize>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let filter = Filter::In("mention_ids".to_string(), vec![json!(user_id)]);
+    let profile_id =
+      profile::find_profile_id(&self.json_provider, self.mongodb_provider.as_ref(), user_id).await;
+    let mut items: Vec<Value> = Vec::new();
+    for comment in self.find_all("comments", &filter).await {
+      if !is_live(&comment) {
+        continue;
+      }
+      let Some(todo) = self.todo_for_comment(&comment).await else {
+        continue;
+      };
+      if !PermissionService::can_view_todo_with_profile(&todo, user_id, profile_id.as_deref()) {
+        continue;
+      }
+      items.push(json!({
+        "source": "comments",
+        "id": comment.get("id"),
+        "author_id": comment.get("user_id"),
+        "task_id": comment.get("task_id"),
+        "subtask_id": comment.get("subtask_id"),
+        "todo_id": todo.get("id"),
+        "todo_title": todo.get("title"),
+        "excerpt": excerpt(str_field(&comment, "content")),
+        "created_at": comment.get("created_at"),
+      }));
+    }
+    for message in self.find_all("chats", &filter).await {
+      if !is_live(&message) {
+        continue;
+      }
+      let room_id = str_field(&message, "room_id");
+      if !room_ids.iter().any(|id| id == room_id) {
+        continue;
+      }
+      items.push(json!({
+        "source": "chats",
+        "id": message.get("id"),
+        "author_id": message.get("sender_id"),
+        "room_id": room_id,
+        "parent_id": message.get("parent_id"),
+        "excerpt": excerpt(str_field(&message, "content")),
+        "created_at": message.get("created_at"),
+      }));
+    }
+    items.sort_by(|a, b| {
+      b["created_at"]
+        .as_str()
+        .unwrap_or("")
+        .cmp(a["created_at"].as_str().unwrap_or(""))
+    });
+    let total = items.len();
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let page: Vec<Value> = items
+      .into_iter()
+      .skip(skip.unwrap_or(0))
+      .take(limit)
+      .collect();
+    Ok(success_response(
+      json!({ "mentions": page, "total": total }),
+    ))
+  }
+  /// Mentioned usernames that match a user accepted by `can_see`. Usernames match as written.
+  async fn resolve<F>(&self, content: &str, can_see: F) -> Vec<Mention>
+  where
+    F: Fn(&Value) -> bool,
+  {
+    let mut resolved: Vec<Mention> = Vec::new();
+    for username in mentions::parse_mentions(content) {
+      let filter = Filter::Eq("username".to_string(), json!(username));
+      let Some(user) = self
+        .find_all("users", &filter)
+        .await
+        .into_iter()
+        .find(|user| is_live(user) && can_see(user))
+      else {
+        continue;
+      };
+      let user_id = str_field(&user, "id").to_string();
+      if user_id.is_empty() || resolved.iter().any(|m| m.user_id == user_id) {
+        continue;
+      }
+      resolved.push(Mention {
+        raw: format!("@{}", username),
+        user_id,
+        username,
+      });
+    }
+    resolved
+  }
+  /// Participants of the direct room and members of the group that use `room_id`.
+  async fn room_members(&self, room_id: &str) -> Vec<String> {
+    let rooms = self
+      .find_all("rooms", &Filter::Eq("room".to_string(), json!(room_id)))
+      .await;
+    let groups = self
+      .find_all("groups", &Filter::Eq("room_id".to_string(), json!(room_id)))
+      .await;
+    rooms
+      .iter()
+      .filter(|room| is_live(room))
+      .flat_map(|room| ids_in(room, "participant_ids"))
+      .chain(
+        groups
+          .iter()
+          .filter(|group| is_live(group))
+          .flat_map(|group| ids_in(group, "member_ids")),
+      )
+      .collect()
+  }
+  async fn find_doc(&self, table: &str, id: &str) -> Option<Value> {
+    if id.is_empty() {
+      return None;
+    }
+    if let Ok(Some(doc)) = self.json_provider.find_by_id(table, id).await {
+      return Some(doc);
+    }
+    match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    }
+  }
+  /// Matches from the local store and MongoDB, de-duplicated by id.
+  async fn find_all(&self, table: &str, filter: &Filter) -> Vec<Value> {
+    let mut docs = self
+      .json_provider
+      .find_many(table, Some(filter), None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      for doc in mongo
+        .find_many(table, Some(filter), None, None, None, true)
+        .await
+        .unwrap_or_default()
+      {
+        if !docs.iter().any(|d| d.get("id") == doc.get("id")) {
+          docs.push(doc);
+        }
+      }
+    }
+    docs
+  }
+}
+/// The fields stored on a comment or message for its resolved mentions.
+pub fn mention_fields(resolved: &[Mention]) -> (Value, Value) {
+  let ids: Vec<&str> = resolved.iter().map(|m| m.user_id.as_str()).collect();
+  (json!(resolved), json!(ids))
+}
+fn ids_in(doc: &Value, field: &str) -> Vec<String> {
+  doc
+    .get(field)
+    .and_then(|v| v.as_array())
+    .map(|ids| {
+      ids
+        .iter()
+        .filter_map(|id| id.as_str().map(String::from))
+        .collect()
+    })
+    .unwrap_or_default()
+}
+fn excerpt(content: &str) -> String {
+  content.chars().take(MENTION_EXCERPT_CHARS).collect()
+}
+fn is_live(doc: &Value) -> bool {
+  doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true)
+}
+fn str_field<'a>(doc: &'a Value, field: &str) -> &'a str {
+  doc.get(field).and_then(|v| v.as_str()).unwrap_or("")
+}
@@ -1,30 +1,64 @@
+#[path = "attachment.service.rs"]
+pub mod attachment_service;
 #[path = "base-crud.service.rs"]
 pub mod base_crud_service;
 #[path = "category.service.rs"]
 pub mod category_service;
+#[path = "chat-events.service.rs"]
+pub mod chat_events_service;
+#[path = "chat-outbox.service.rs"]
+pub mod chat_outbox_service;
+#[path = "chat-presence.service.rs"]
+pub mod chat_presence_service;
+#[path = "chat-read.service.rs"]
+pub mod chat_read_service;
+#[path = "chat-retention.service.rs"]
+pub mod chat_retention_service;
 #[path = "chat.service.rs"]
 pub mod chat_service;
+#[path = "chat-socket.service.rs"]
+pub mod chat_socket_service;
 #[path = "crud_service.rs"]
 pub mod crud_service;
 #[path = "db-backup.service.rs"]
 pub mod db_backup;
+#[path = "digest.service.rs"]
+pub mod digest_service;
+#[path = "group-invite.service.rs"]
+pub mod group_invite_service;
 #[path = "group.service.rs"]
 pub mod group_service;
 #[path = "manage-db.service.rs"]
 pub mod manage_db_service;
+#[path = "mention.service.rs"]
+pub mod mention_service;
+#[path = "notification-outbox.service.rs"]
+pub mod notification_outbox_service;
 #[path = "notification.service.rs"]
 pub mod notification_service;
+#[path = "overdue.service.rs"]
+pub mod overdue_service;
 #[path = "permission.service.rs"]
 pub mod permission_service;
+#[path = "reminder.service.rs"]
+pub mod reminder_service;
 pub mod repository;
 #[path = "room.service.rs"]
 pub mod room_service;
+#[path = "security-audit.service.rs"]
+pub mod security_audit_service;
 #[path = "subtask.service.rs"]
 pub mod subtask_service;
+#[path = "task-link.service.rs"]
+pub mod task_link_service;
 #[path = "task.service.rs"]
 pub mod task_service;
 #[path = "todo.service.rs"]
 pub mod todo_service;
+#[path = "watcher.service.rs"]
+pub mod watcher_service;
+#[path = "webhook.service.rs"]
+pub mod webhook_service;
 // Special services (not CRUD)
 #[path = "about.service.rs"]
 pub mod about_service;
@@ -43,6 +77,7 @@ pub mod cascade;
 pub mod entity_resolution_service;
 #[path = "github.service.rs"]
 pub mod github_service;
+pub mod oauth;
 pub mod profile;
 pub mod statistics;
 #[path = "statistics.service.rs"]
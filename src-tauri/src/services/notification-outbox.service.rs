@@ -0,0 +1,102 @@
+/* sys lib */
+use chrono::Utc;
+use serde_json::{json, Value};
+use tokio::sync::{Mutex, MutexGuard};
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::JsonProvider;
+/* entities */
+use crate::entities::notification_outbox_entity::{NotificationOutboxEntity, NotificationOutboxOp};
+/* models */
+use crate::models::response::ResponseModel;
+const OUTBOX_TABLE: &str = "notification_outbox";
+/// Rejections in a row before an entry is dropped instead of retried.
+pub const MAX_OUTBOX_ATTEMPTS: u32 = 5;
+/// Local queue of shared-notification changes waiting for MongoDB.
+pub struct NotificationOutbox {
+  json_provider: JsonProvider,
+  /// Serialises enqueue and replay so sequence numbers and ordering stay consistent
+  lock: Mutex<()>,
+}
+impl NotificationOutbox {
+  pub fn new(json_provider: JsonProvider) -> Self {
+    Self {
+      json_provider,
+      lock: Mutex::new(()),
+    }
+  }
+  pub async fn lock(&self) -> MutexGuard<'_, ()> {
+    self.lock.lock().await
+  }
+  pub async fn enqueue(
+    &self,
+    op: NotificationOutboxOp,
+    notification_id: &str,
+    payload: Value,
+  ) -> Result<(), ResponseModel> {
+    let _guard = self.lock.lock().await;
+    let seq = self
+      .entries()
+      .await
+      .iter()
+      .map(|entry| entry.seq)
+      .max()
+      .unwrap_or(0)
+      + 1;
+    let now = Utc::now();
+    let entry = NotificationOutboxEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      op,
+      notification_id: notification_id.to_string(),
+      payload,
+      seq,
+      attempts: 0,
+      last_error: None,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    self
+      .json_provider
+      .insert(OUTBOX_TABLE, serde_json::to_value(&entry)?)
+      .await?;
+    Ok(())
+  }
+  /// All entries, oldest first.
+  pub async fn entries(&self) -> Vec<NotificationOutboxEntity> {
+    let mut entries: Vec<NotificationOutboxEntity> = self
+      .json_provider
+      .find_many(OUTBOX_TABLE, None, None, None, None, true)
+      .await
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|doc| serde_json::from_value(doc).ok())
+      .collect();
+    entries.sort_by_key(|entry| entry.seq);
+    entries
+  }
+  pub async fn has_pending(&self) -> bool {
+    !self.entries().await.is_empty()
+  }
+  pub async fn complete(&self, entry: &NotificationOutboxEntity) {
+    if let Some(id) = &entry.id {
+      let _ = self.json_provider.delete(OUTBOX_TABLE, id).await;
+    }
+  }
+  /// Records a rejected replay; returns true when the entry was dropped for good.
+  pub async fn record_failure(&self, entry: &NotificationOutboxEntity, error: &str) -> bool {
+    let attempts = entry.attempts + 1;
+    if attempts >= MAX_OUTBOX_ATTEMPTS {
+      self.complete(entry).await;
+      return true;
+    }
+    if let Some(id) = &entry.id {
+      let update = json!({
+        "attempts": attempts,
+        "last_error": error,
+        "updated_at": Utc::now().to_rfc3339(),
+      });
+      let _ = self.json_provider.patch(OUTBOX_TABLE, id, update).await;
+    }
+    false
+  }
+}
@@ -1,113 +1,1085 @@
 /* sys lib */
+use chrono::{Duration, SecondsFormat, Utc};
 use serde_json::{json, Value};
+use tauri::Emitter;
 /* nosql_orm */
+use nosql_orm::provider::DatabaseProvider;
 use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-/* providers */
-use crate::repositories::data_provider::DataProvider;
 /* entities */
+use crate::entities::notification_entity::{
+  NotificationCreateModel, NotificationEntity, NotificationKind, NotificationTarget,
+};
+use crate::entities::notification_outbox_entity::NotificationOutboxOp;
+use crate::entities::notification_preferences_entity::{
+  NotificationPreferencesEntity, NotificationPreferencesUpdateModel,
+};
 use crate::models::response::ResponseModel;
+/* services */
+use crate::services::notification_outbox_service::NotificationOutbox;
+use crate::services::watcher_service::WatcherService;
 /* helpers */
+use crate::utils::notification_groups;
+use crate::utils::notification_prefs;
+use crate::utils::notification_sync;
+use crate::utils::notification_triggers;
 use crate::utils::response_helper::{err_response, success_response};
+use crate::utils::signed_in;
+const COMMENT_EXCERPT_CHARS: usize = 120;
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+const NOTIFICATIONS_TABLE: &str = "notifications";
+const PREFERENCES_TABLE: &str = "notification_preferences";
+/// Frontend event used to show a desktop toast.
+const DESKTOP_EVENT: &str = "notification-desktop";
+/// Notifications are always written to the local store so they stay readable offline.
+/// Those about a shared source (a shared or public todo, a chat or a group) also live in
+/// MongoDB so they reach the recipient's other devices; changes to them that cannot be
+/// written there right away are queued and replayed by `flush_outbox`.
 pub struct NotificationService {
   json_provider: JsonProvider,
   mongodb_provider: Option<Arc<MongoProvider>>,
+  outbox: NotificationOutbox,
+  app_handle: Option<tauri::AppHandle>,
+  watchers: Option<Arc<WatcherService>>,
 }
 impl NotificationService {
   pub fn new(json_provider: JsonProvider, mongodb_provider: Option<Arc<MongoProvider>>) -> Self {
     Self {
+      outbox: NotificationOutbox::new(json_provider.clone()),
       json_provider,
       mongodb_provider,
+      app_handle: None,
+      watchers: None,
     }
   }
-  fn get_provider(&self, visibility: &str) -> Result<DataProvider, ResponseModel> {
-    if visibility == "private" {
-      Ok(DataProvider::Json(Arc::new(self.json_provider.clone())))
-    } else {
-      match self.mongodb_provider.as_ref() {
-        Some(p) => Ok(DataProvider::Mongo(p.clone())),
-        None => Err(err_response("MongoDB not available")),
+  pub fn with_app_handle(mut self, app_handle: tauri::AppHandle) -> Self {
+    self.app_handle = Some(app_handle);
+    self
+  }
+  /// Enables notifying the watchers of todos and tasks.
+  pub fn with_watchers(mut self, watchers: Arc<WatcherService>) -> Self {
+    self.watchers = Some(watchers);
+    self
+  }
+  /// MongoDB when it can take writes directly. While older changes are still queued, new
+  /// ones queue behind them so they replay in the order they were made.
+  async fn online_mongo(&self) -> Option<&Arc<MongoProvider>> {
+    let mongo = self.mongodb_provider.as_ref()?;
+    if self.outbox.has_pending().await {
+      return None;
+    }
+    Some(mongo)
+  }
+  /// Creates a notification from client data. Fields outside the typed model are kept in
+  /// `payload` when the client did not send one.
+  pub async fn create(&self, data: Value) -> Result<ResponseModel, ResponseModel> {
+    let mut model: NotificationCreateModel = serde_json::from_value(data.clone())
+      .map_err(|e| err_response(&format!("Invalid notification: {}", e)))?;
+    if model.payload.is_null() {
+      if let Value::Object(mut extra) = data {
+        for key in [
+          "id", "user_id", "kind", "actor_id", "target", "read", "payload", "shared",
+        ] {
+          extra.remove(key);
+        }
+        model.payload = Value::Object(extra);
       }
     }
+    let Some(entity) = self
+      .apply_preferences(NotificationEntity::from(model))
+      .await
+    else {
+      return Ok(success_response(json!({ "suppressed": true })));
+    };
+    let (doc, fresh) = self.deliver(entity).await?;
+    if fresh {
+      self.show_desktop(&doc);
+    }
+    Ok(success_response(doc))
   }
-  pub async fn create(
+  /// Sends a notification to `recipient_id`, which may be a user or a profile id.
+  /// Nobody is notified about their own actions.
+  pub async fn notify(
     &self,
-    data: Value,
-    visibility: &str,
-  ) -> Result<ResponseModel, ResponseModel> {
-    let provider = self.get_provider(visibility)?;
-    let doc = provider.insert("notifications", data).await?;
-    Ok(success_response(doc))
+    recipient_id: &str,
+    kind: NotificationKind,
+    actor_id: Option<&str>,
+    target: NotificationTarget,
+    payload: Value,
+  ) -> Option<Value> {
+    let user_id = self.resolve_user_id(recipient_id).await;
+    if user_id.is_empty() || Some(user_id.as_str()) == actor_id {
+      return None;
+    }
+    let entity = self
+      .apply_preferences(NotificationEntity::from(NotificationCreateModel {
+        user_id,
+        kind,
+        actor_id: actor_id.map(String::from),
+        target,
+        payload,
+      }))
+      .await?;
+    let (doc, fresh) = self.deliver(entity).await.ok()?;
+    if fresh {
+      self.show_desktop(&doc);
+    }
+    Some(doc)
   }
-  pub async fn get_by_user(
+  /// Stores the notification, folding groupable kinds into the recipient's unread group for
+  /// the same target when one started within the window. Returns the stored document and
+  /// whether it carries a new event (`false` for exact repeats, which are dropped).
+  async fn deliver(&self, mut entity: NotificationEntity) -> Result<(Value, bool), ResponseModel> {
+    entity.shared = self.is_shared_source(&entity.target, &entity.payload).await;
+    if entity.kind.groups() {
+      let key = notification_groups::group_key(&entity.user_id, entity.kind, &entity.target);
+      let now = Utc::now();
+      let window_start = now - Duration::minutes(notification_groups::GROUP_WINDOW_MINUTES);
+      let filter = Filter::And(vec![
+        Filter::Eq("group_key".to_string(), json!(key)),
+        Filter::Eq("read".to_string(), json!(false)),
+        Filter::Gte(
+          "created_at".to_string(),
+          json!(window_start.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+        ),
+      ]);
+      let open_group = self
+        .find_notifications(&filter, None)
+        .await
+        .into_iter()
+        .filter(is_unread)
+        .max_by_key(notification_groups::sort_key);
+      if let Some(group) = open_group {
+        return match notification_groups::merge(&group, &entity, now) {
+          Some(patch) => Ok((
+            self
+              .store_patch(&group, patch, NotificationOutboxOp::Update)
+              .await?,
+            true,
+          )),
+          None => Ok((group, false)),
+        };
+      }
+      entity.group_key = Some(key);
+    }
+    Ok((self.store_new(serde_json::to_value(&entity)?).await?, true))
+  }
+  /// Where a notification is routed follows from what it is about: chats and groups are
+  /// always shared, todos and their tasks when the todo is not private. Anything else, such
+  /// as digests, stays on this device.
+  async fn is_shared_source(&self, target: &NotificationTarget, payload: &Value) -> bool {
+    let todo_id = match target.entity.as_str() {
+      "chats" | "groups" | "rooms" => return true,
+      "todos" => target.id.clone(),
+      "tasks" => match self.find_doc("tasks", &target.id).await {
+        Some(task) => str_field(&task, "todo_id").to_string(),
+        None => str_field(payload, "todo_id").to_string(),
+      },
+      _ => {
+        if payload
+          .get("room_id")
+          .map(|v| !v.is_null())
+          .unwrap_or(false)
+        {
+          return true;
+        }
+        str_field(payload, "todo_id").to_string()
+      }
+    };
+    match self.find_doc("todos", &todo_id).await {
+      Some(todo) => matches!(str_field(&todo, "visibility"), "shared" | "public"),
+      None => false,
+    }
+  }
+  async fn store_new(&self, doc: Value) -> Result<Value, ResponseModel> {
+    let saved = self
+      .json_provider
+      .insert(NOTIFICATIONS_TABLE, doc.clone())
+      .await?;
+    if is_shared(&doc) {
+      let id = str_field(&doc, "id").to_string();
+      self.push(NotificationOutboxOp::Create, &id, doc).await?;
+    }
+    Ok(saved)
+  }
+  /// Applies `patch` to the local copy, mirroring the notification first when only the
+  /// cloud had it, and forwards the change for shared notifications.
+  async fn store_patch(
+    &self,
+    doc: &Value,
+    patch: Value,
+    op: NotificationOutboxOp,
+  ) -> Result<Value, ResponseModel> {
+    let id = str_field(doc, "id").to_string();
+    let saved = match self
+      .json_provider
+      .find_by_id(NOTIFICATIONS_TABLE, &id)
+      .await?
+    {
+      Some(_) => {
+        self
+          .json_provider
+          .patch(NOTIFICATIONS_TABLE, &id, patch.clone())
+          .await?
+      }
+      None => {
+        let mut mirrored = doc.clone();
+        if let (Value::Object(target), Value::Object(fields)) = (&mut mirrored, &patch) {
+          for (key, value) in fields {
+            target.insert(key.clone(), value.clone());
+          }
+        }
+        self
+          .json_provider
+          .insert(NOTIFICATIONS_TABLE, mirrored)
+          .await?
+      }
+    };
+    if is_shared(doc) {
+      self.push(op, &id, patch).await?;
+    }
+    Ok(saved)
+  }
+  /// Applies a change to MongoDB now when possible, otherwise queues it.
+  async fn push(
+    &self,
+    op: NotificationOutboxOp,
+    id: &str,
+    payload: Value,
+  ) -> Result<(), ResponseModel> {
+    if let Some(mongo) = self.online_mongo().await {
+      if self.replay(mongo, op, id, &payload).await.is_ok() {
+        return Ok(());
+      }
+    }
+    self.outbox.enqueue(op, id, payload).await
+  }
+  async fn replay(
+    &self,
+    mongo: &MongoProvider,
+    op: NotificationOutboxOp,
+    id: &str,
+    payload: &Value,
+  ) -> Result<(), ResponseModel> {
+    match op {
+      NotificationOutboxOp::Create => {
+        // An earlier attempt may have landed before the connection dropped
+        if mongo.find_by_id(NOTIFICATIONS_TABLE, id).await?.is_none() {
+          mongo.insert(NOTIFICATIONS_TABLE, payload.clone()).await?;
+        }
+      }
+      NotificationOutboxOp::Update => {
+        mongo
+          .patch(NOTIFICATIONS_TABLE, id, payload.clone())
+          .await?;
+      }
+      NotificationOutboxOp::Read => {
+        // A read does not cover events grouped in after it, e.g. from another device
+        if let Some(doc) = mongo.find_by_id(NOTIFICATIONS_TABLE, id).await? {
+          if notification_sync::read_covers(&doc, str_field(payload, "read_at")) {
+            mongo
+              .patch(NOTIFICATIONS_TABLE, id, payload.clone())
+              .await?;
+          }
+        }
+      }
+      NotificationOutboxOp::Delete => {
+        mongo.delete(NOTIFICATIONS_TABLE, id).await?;
+      }
+    }
+    Ok(())
+  }
+  /// Replays queued changes to shared notifications in order, stopping at the first one
+  /// MongoDB still rejects.
+  pub async fn flush_outbox(&self) -> Result<ResponseModel, ResponseModel> {
+    let mongo = self
+      .mongodb_provider
+      .as_ref()
+      .ok_or_else(|| err_response("MongoDB not available"))?;
+    let _guard = self.outbox.lock().await;
+    let mut sent = 0;
+    let mut dropped = 0;
+    for entry in self.outbox.entries().await {
+      match self
+        .replay(mongo, entry.op, &entry.notification_id, &entry.payload)
+        .await
+      {
+        Ok(()) => {
+          self.outbox.complete(&entry).await;
+          sent += 1;
+        }
+        Err(e) => {
+          if !self.outbox.record_failure(&entry, &e.message).await {
+            break;
+          }
+          dropped += 1;
+        }
+      }
+    }
+    let remaining = self.outbox.entries().await.len();
+    Ok(success_response(
+      json!({ "sent": sent, "dropped": dropped, "remaining": remaining }),
+    ))
+  }
+  /// Notifications matching `filter` from the local mirror and, when reachable, MongoDB,
+  /// reconciled per id. With `limit`, only the newest that many from each store are read.
+  /// Cloud copies that are new or newer are written to the mirror so they are still there
+  /// offline, unless a delete for them is still queued. Shared notifications the cloud no
+  /// longer has were deleted elsewhere and are dropped from the mirror.
+  ///
+  /// The stores can disagree on filtered fields such as `read`, so a copy missing from one
+  /// side's results is looked up by id before it is treated as new or deleted.
+  async fn find_notifications(&self, filter: &Filter, limit: Option<u64>) -> Vec<Value> {
+    let local = self
+      .json_provider
+      .find_many(
+        NOTIFICATIONS_TABLE,
+        Some(filter),
+        None,
+        limit,
+        Some("created_at"),
+        false,
+      )
+      .await
+      .unwrap_or_default();
+    // Read before the cloud so a change replayed in between is seen in one or the other
+    let queued = self.outbox.entries().await;
+    let queued_ids = |op: NotificationOutboxOp| -> HashSet<String> {
+      queued
+        .iter()
+        .filter(|entry| entry.op == op)
+        .map(|entry| entry.notification_id.clone())
+        .collect()
+    };
+    let deleting = queued_ids(NotificationOutboxOp::Delete);
+    let creating = queued_ids(NotificationOutboxOp::Create);
+    let cloud = match &self.mongodb_provider {
+      Some(mongo) => mongo
+        .find_many(
+          NOTIFICATIONS_TABLE,
+          Some(filter),
+          None,
+          limit,
+          Some("created_at"),
+          false,
+        )
+        .await
+        .ok(),
+      None => None,
+    };
+    let mut local_by_id: HashMap<String, Value> = local
+      .into_iter()
+      .map(|doc| (str_field(&doc, "id").to_string(), doc))
+      .collect();
+    let mut docs = Vec::new();
+    let cloud_loaded = cloud.is_some();
+    for cloud_doc in cloud.unwrap_or_default() {
+      let id = str_field(&cloud_doc, "id").to_string();
+      if deleting.contains(&id) {
+        local_by_id.remove(&id);
+        continue;
+      }
+      let local_doc = match local_by_id.remove(&id) {
+        Some(doc) => Some(doc),
+        None => self
+          .json_provider
+          .find_by_id(NOTIFICATIONS_TABLE, &id)
+          .await
+          .ok()
+          .flatten(),
+      };
+      let Some(merged) = notification_sync::reconcile(local_doc.clone(), Some(cloud_doc)) else {
+        continue;
+      };
+      if local_doc.as_ref() != Some(&merged) {
+        let _ = match local_doc {
+          Some(_) => {
+            self
+              .json_provider
+              .update(NOTIFICATIONS_TABLE, &id, merged.clone())
+              .await
+          }
+          None => {
+            if merged.get("read").and_then(|v| v.as_bool()) != Some(true) {
+              self.show_desktop(&merged);
+            }
+            self
+              .json_provider
+              .insert(NOTIFICATIONS_TABLE, merged.clone())
+              .await
+          }
+        };
+      }
+      docs.push(merged);
+    }
+    for (id, doc) in local_by_id {
+      if cloud_loaded && is_shared(&doc) && !creating.contains(&id) && self.cloud_lacks(&id).await {
+        let _ = self.json_provider.delete(NOTIFICATIONS_TABLE, &id).await;
+        continue;
+      }
+      docs.push(doc);
+    }
+    docs
+  }
+  /// True only when MongoDB positively reports the notification missing.
+  async fn cloud_lacks(&self, id: &str) -> bool {
+    match &self.mongodb_provider {
+      Some(mongo) => matches!(mongo.find_by_id(NOTIFICATIONS_TABLE, id).await, Ok(None)),
+      None => false,
+    }
+  }
+  async fn find_notification(&self, id: &str) -> Option<Value> {
+    let local = self
+      .json_provider
+      .find_by_id(NOTIFICATIONS_TABLE, id)
+      .await
+      .ok()
+      .flatten();
+    let cloud = match &self.mongodb_provider {
+      Some(mongo) => mongo
+        .find_by_id(NOTIFICATIONS_TABLE, id)
+        .await
+        .ok()
+        .flatten(),
+      None => None,
+    };
+    notification_sync::reconcile(local, cloud)
+  }
+  /// Applies the recipient's preferences: drops notifications from muted todos and rooms or
+  /// of kinds they turned off, and records which channels are left.
+  async fn apply_preferences(&self, mut entity: NotificationEntity) -> Option<NotificationEntity> {
+    let prefs = self.preferences_for(&entity.user_id).await;
+    let channels = notification_prefs::delivery(
+      prefs.as_ref(),
+      entity.kind,
+      &entity.target,
+      &entity.payload,
+      Utc::now(),
+    )?;
+    entity.in_app = channels.in_app;
+    entity.desktop = channels.desktop;
+    entity.email_digest = channels.email_digest;
+    Some(entity)
+  }
+  /// Toasts only on the recipient's own device. A notification created here for someone else
+  /// (an assignment, a completion) is toasted by `find_notifications` once it first syncs
+  /// to their device.
+  fn show_desktop(&self, doc: &Value) {
+    if doc.get("desktop").and_then(|v| v.as_bool()) != Some(true) {
+      return;
+    }
+    if !signed_in::is_signed_in_user(str_field(doc, "user_id")) {
+      return;
+    }
+    if let Some(app_handle) = &self.app_handle {
+      let _ = app_handle.emit(DESKTOP_EVENT, doc);
+    }
+  }
+  async fn preferences_for(&self, user_id: &str) -> Option<NotificationPreferencesEntity> {
+    self
+      .find_doc(PREFERENCES_TABLE, user_id)
+      .await
+      .and_then(|doc| serde_json::from_value(doc).ok())
+  }
+  /// The user's preferences, or the defaults (everything on, nothing muted).
+  pub async fn get_preferences(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let prefs = self
+      .preferences_for(user_id)
+      .await
+      .unwrap_or_else(|| default_preferences(user_id));
+    Ok(success_response(serde_json::to_value(prefs)?))
+  }
+  pub async fn set_preferences(
     &self,
     user_id: &str,
-    visibility: &str,
-    skip: Option<u64>,
-    limit: Option<u64>,
+    update: NotificationPreferencesUpdateModel,
   ) -> Result<ResponseModel, ResponseModel> {
-    let provider = self.get_provider(visibility)?;
-    let filter = nosql_orm::query::Filter::Eq("user_id".to_string(), json!(user_id));
-    let docs = provider
-      .find_many("notifications", Some(&filter), skip, limit, None, true)
-      .await?;
-    Ok(success_response(docs))
+    if let Some(quiet) = &update.quiet_hours {
+      if notification_prefs::parse_clock(&quiet.start).is_none()
+        || notification_prefs::parse_clock(&quiet.end).is_none()
+      {
+        return Err(err_response("Quiet hours must use HH:MM"));
+      }
+      if !notification_prefs::is_zone_name(&quiet.timezone) {
+        return Err(err_response(
+          "Timezone must be an IANA zone name such as Europe/Berlin",
+        ));
+      }
+    }
+    let mut prefs = self
+      .preferences_for(user_id)
+      .await
+      .unwrap_or_else(|| default_preferences(user_id));
+    prefs.kinds = update.kinds;
+    prefs.quiet_hours = update.quiet_hours;
+    self.store_preferences(prefs).await
   }
-  pub async fn mark_as_read(
+  /// Mutes or unmutes notifications coming from a todo (`source = "todo"`) or a chat room
+  /// (`source = "room"`).
+  pub async fn set_mute(
     &self,
+    user_id: &str,
+    source: &str,
     id: &str,
-    visibility: &str,
+    muted: bool,
   ) -> Result<ResponseModel, ResponseModel> {
-    let provider = self.get_provider(visibility)?;
-    let update = json!({ "read": true });
-    let doc = provider.patch("notifications", id, update).await?;
-    Ok(success_response(doc))
+    let mut prefs = self
+      .preferences_for(user_id)
+      .await
+      .unwrap_or_else(|| default_preferences(user_id));
+    let list = match source {
+      "todo" => &mut prefs.muted_todos,
+      "room" => &mut prefs.muted_rooms,
+      _ => return Err(err_response("Mute source must be \"todo\" or \"room\"")),
+    };
+    list.retain(|existing| existing != id);
+    if muted {
+      list.push(id.to_string());
+    }
+    self.store_preferences(prefs).await
   }
-  pub async fn mark_all_as_read(
+  async fn store_preferences(
     &self,
-    user_id: &str,
-    visibility: &str,
+    mut prefs: NotificationPreferencesEntity,
   ) -> Result<ResponseModel, ResponseModel> {
-    let provider = self.get_provider(visibility)?;
-    let filter = nosql_orm::query::Filter::Eq("user_id".to_string(), json!(user_id));
-    let docs = provider
-      .find_many("notifications", Some(&filter), None, None, None, true)
-      .await?;
-    for doc in docs {
-      if let Some(id) = doc.get("id").and_then(|v: &serde_json::Value| v.as_str()) {
-        if doc
-          .get("read")
-          .and_then(|v: &serde_json::Value| v.as_bool())
-          != Some(true)
-        {
-          let _ = provider
-            .patch("notifications", id, json!({ "read": true }))
+    let now = Utc::now();
+    prefs.created_at = prefs.created_at.or(Some(now));
+    prefs.updated_at = Some(now);
+    let doc = serde_json::to_value(&prefs)?;
+    let id = prefs.user_id.as_str();
+    let saved = match self.json_provider.find_by_id(PREFERENCES_TABLE, id).await? {
+      Some(_) => {
+        self
+          .json_provider
+          .update(PREFERENCES_TABLE, id, doc.clone())
+          .await?
+      }
+      None => {
+        self
+          .json_provider
+          .insert(PREFERENCES_TABLE, doc.clone())
+          .await?
+      }
+    };
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = match mongo.find_by_id(PREFERENCES_TABLE, id).await {
+        Ok(Some(_)) => mongo.update(PREFERENCES_TABLE, id, doc).await,
+        Ok(None) => mongo.insert(PREFERENCES_TABLE, doc).await,
+        Err(e) => Err(e),
+      };
+    }
+    Ok(success_response(saved))
+  }
+  /// Notifications that follow from a record being created through the repository.
+  pub async fn on_record_created(&self, table: &str, record: &Value, actor_id: Option<&str>) {
+    match table {
+      "todos" => {
+        let owner_id = str_field(record, "user_id");
+        for assignee in notification_triggers::added_ids(None, record, "assignees") {
+          if assignee != owner_id {
+            self.notify_assigned(record, &assignee, actor_id).await;
+          }
+        }
+      }
+      "comments" => {
+        self.notify_comment(record, actor_id).await;
+        self
+          .notify_mentions("comments", record, None, actor_id)
+          .await;
+      }
+      "groups" => self.notify_group_members(record, None, actor_id).await,
+      _ => {}
+    }
+  }
+  /// Notifications that follow from an update, comparing the record before and after.
+  pub async fn on_record_updated(
+    &self,
+    table: &str,
+    old: &Value,
+    new: &Value,
+    actor_id: Option<&str>,
+  ) {
+    match table {
+      "todos" => {
+        for assignee in notification_triggers::added_ids(Some(old), new, "assignees") {
+          self.notify_assigned(new, &assignee, actor_id).await;
+        }
+        for change in notification_triggers::role_changes(old, new) {
+          self
+            .notify(
+              &change.user_id,
+              NotificationKind::RoleChanged,
+              actor_id,
+              NotificationTarget::new("todos", str_field(new, "id")),
+              json!({
+                "todo_title": new.get("title"),
+                "from": change.from,
+                "to": change.to,
+              }),
+            )
             .await;
         }
+        if old.get("end_date") != new.get("end_date") {
+          let todo_id = str_field(new, "id");
+          for watcher in self.watchers_of("todos", todo_id).await {
+            self
+              .notify(
+                &watcher,
+                NotificationKind::DueDateChanged,
+                actor_id,
+                NotificationTarget::new("todos", todo_id),
+                json!({
+                  "todo_title": new.get("title"),
+                  "from": old.get("end_date"),
+                  "to": new.get("end_date"),
+                }),
+              )
+              .await;
+          }
+        }
       }
+      "tasks" => {
+        let owner_id = str_field(new, "user_id");
+        let actor = actor_id.filter(|a| !a.is_empty());
+        let owner_told = notification_triggers::became_completed(old, new)
+          && actor.map(|actor| actor != owner_id).unwrap_or(false);
+        if let Some(actor) = actor.filter(|_| owner_told) {
+          self
+            .notify(
+              owner_id,
+              NotificationKind::TaskCompleted,
+              Some(actor),
+              NotificationTarget::new("tasks", str_field(new, "id")),
+              json!({
+                "task_title": new.get("title"),
+                "todo_id": new.get("todo_id"),
+              }),
+            )
+            .await;
+        }
+        self.notify_task_watchers(old, new, actor, owner_told).await;
+      }
+      "comments" => {
+        self
+          .notify_mentions("comments", new, Some(old), actor_id)
+          .await
+      }
+      "groups" => self.notify_group_members(new, Some(old), actor_id).await,
+      _ => {}
     }
-    Ok(success_response(serde_json::Value::Array(vec![])))
   }
-  pub async fn delete(&self, id: &str, visibility: &str) -> Result<ResponseModel, ResponseModel> {
-    let provider = self.get_provider(visibility)?;
-    let _ = provider.delete("notifications", id).await;
-    Ok(success_response(serde_json::json!(id.to_string())))
+  /// Tells people newly listed in a comment's or chat message's `mention_ids` that they were
+  /// mentioned. Comment mentions point at the task, message mentions at the message.
+  pub async fn notify_mentions(
+    &self,
+    table: &str,
+    record: &Value,
+    before: Option<&Value>,
+    actor_id: Option<&str>,
+  ) {
+    let mentioned = notification_triggers::added_ids(before, record, "mention_ids");
+    if mentioned.is_empty() {
+      return;
+    }
+    let excerpt =
+      notification_triggers::excerpt(str_field(record, "content"), COMMENT_EXCERPT_CHARS);
+    let (author_id, target, payload) = if table == "comments" {
+      let Some(task) = self.comment_task(record).await else {
+        return;
+      };
+      (
+        str_field(record, "user_id"),
+        NotificationTarget::new("tasks", str_field(&task, "id")),
+        json!({
+          "comment_id": record.get("id"),
+          "subtask_id": record.get("subtask_id"),
+          "task_title": task.get("title"),
+          "todo_id": task.get("todo_id"),
+          "excerpt": excerpt,
+        }),
+      )
+    } else {
+      (
+        str_field(record, "sender_id"),
+        NotificationTarget::new("chats", str_field(record, "id")),
+        json!({
+          "parent_id": record.get("parent_id"),
+          "room_id": record.get("room_id"),
+          "excerpt": excerpt,
+        }),
+      )
+    };
+    for user_id in mentioned {
+      self
+        .notify(
+          &user_id,
+          NotificationKind::Mentioned,
+          actor_id.or(Some(author_id)),
+          target.clone(),
+          payload.clone(),
+        )
+        .await;
+    }
   }
-  pub async fn clear_all(
+  /// Tells people newly listed in a group's `member_ids` that they were added.
+  pub async fn notify_group_members(
+    &self,
+    group: &Value,
+    before: Option<&Value>,
+    actor_id: Option<&str>,
+  ) {
+    let owner_id = str_field(group, "owner_id");
+    for member_id in notification_triggers::added_ids(before, group, "member_ids") {
+      if member_id == owner_id && before.is_none() {
+        continue;
+      }
+      self
+        .notify(
+          &member_id,
+          NotificationKind::GroupAdded,
+          actor_id.or(Some(owner_id)),
+          NotificationTarget::new("groups", str_field(group, "id")),
+          json!({
+            "group_name": group.get("name"),
+            "room_id": group.get("room_id"),
+          }),
+        )
+        .await;
+    }
+  }
+  async fn notify_assigned(&self, todo: &Value, assignee: &str, actor_id: Option<&str>) {
+    let role = todo
+      .get("assignee_roles")
+      .and_then(|roles| roles.get(assignee))
+      .cloned()
+      .unwrap_or(Value::Null);
+    self
+      .notify(
+        assignee,
+        NotificationKind::Assigned,
+        actor_id.or(Some(str_field(todo, "user_id"))),
+        NotificationTarget::new("todos", str_field(todo, "id")),
+        json!({ "todo_title": todo.get("title"), "role": role }),
+      )
+      .await;
+  }
+  /// The task a comment was left on, directly or through its subtask.
+  async fn comment_task(&self, comment: &Value) -> Option<Value> {
+    let task_id = match comment.get("task_id").and_then(|v| v.as_str()) {
+      Some(task_id) => task_id.to_string(),
+      None => {
+        let subtask = self
+          .find_doc("subtasks", str_field(comment, "subtask_id"))
+          .await?;
+        str_field(&subtask, "task_id").to_string()
+      }
+    };
+    self.find_doc("tasks", &task_id).await
+  }
+  /// Tells the owner of the commented task and the watchers of the task and its todo,
+  /// except the author and anyone mentioned, who hears about it through the mention instead.
+  async fn notify_comment(&self, comment: &Value, actor_id: Option<&str>) {
+    let Some(task) = self.comment_task(comment).await else {
+      return;
+    };
+    let task_id = str_field(&task, "id");
+    let mentioned = notification_triggers::added_ids(None, comment, "mention_ids");
+    let mut recipients = vec![self.resolve_user_id(str_field(&task, "user_id")).await];
+    for watcher in self.watchers_of_task(&task).await {
+      if !recipients.contains(&watcher) {
+        recipients.push(watcher);
+      }
+    }
+    let author_id = actor_id.unwrap_or(str_field(comment, "user_id"));
+    let payload = json!({
+      "comment_id": comment.get("id"),
+      "subtask_id": comment.get("subtask_id"),
+      "task_title": task.get("title"),
+      "todo_id": task.get("todo_id"),
+      "excerpt": notification_triggers::excerpt(
+        str_field(comment, "content"),
+        COMMENT_EXCERPT_CHARS,
+      ),
+    });
+    for recipient in recipients.iter().filter(|id| !mentioned.contains(id)) {
+      self
+        .notify(
+          recipient,
+          NotificationKind::Commented,
+          Some(author_id),
+          NotificationTarget::new("tasks", task_id),
+          payload.clone(),
+        )
+        .await;
+    }
+  }
+  /// Tells the watchers of a task, or of its todo, that its status or due date changed.
+  /// An owner who was just told the task was completed is not told again.
+  async fn notify_task_watchers(
+    &self,
+    old: &Value,
+    new: &Value,
+    actor_id: Option<&str>,
+    owner_told: bool,
+  ) {
+    let status_changed = old.get("status") != new.get("status");
+    let due_changed = old.get("end_date") != new.get("end_date");
+    if !status_changed && !due_changed {
+      return;
+    }
+    let owner_id = self.resolve_user_id(str_field(new, "user_id")).await;
+    let target = NotificationTarget::new("tasks", str_field(new, "id"));
+    for watcher in self.watchers_of_task(new).await {
+      if status_changed && !(owner_told && watcher == owner_id) {
+        self
+          .notify(
+            &watcher,
+            NotificationKind::StatusChanged,
+            actor_id,
+            target.clone(),
+            json!({
+              "task_title": new.get("title"),
+              "todo_id": new.get("todo_id"),
+              "from": old.get("status"),
+              "to": new.get("status"),
+            }),
+          )
+          .await;
+      }
+      if due_changed {
+        self
+          .notify(
+            &watcher,
+            NotificationKind::DueDateChanged,
+            actor_id,
+            target.clone(),
+            json!({
+              "task_title": new.get("title"),
+              "todo_id": new.get("todo_id"),
+              "from": old.get("end_date"),
+              "to": new.get("end_date"),
+            }),
+          )
+          .await;
+      }
+    }
+  }
+  async fn watchers_of(&self, entity: &str, target_id: &str) -> Vec<String> {
+    match &self.watchers {
+      Some(watchers) => watchers.watchers_of(entity, target_id).await,
+      None => Vec::new(),
+    }
+  }
+  /// Watchers of the task and of its todo, each once.
+  async fn watchers_of_task(&self, task: &Value) -> Vec<String> {
+    let mut watchers = self.watchers_of("tasks", str_field(task, "id")).await;
+    for watcher in self.watchers_of("todos", str_field(task, "todo_id")).await {
+      if !watchers.contains(&watcher) {
+        watchers.push(watcher);
+      }
+    }
+    watchers
+  }
+  /// Assignees may be stored as profile ids; notifications are always addressed to users.
+  pub async fn resolve_user_id(&self, id: &str) -> String {
+    match self.find_doc("profiles", id).await {
+      Some(profile) => str_field(&profile, "user_id").to_string(),
+      None => id.to_string(),
+    }
+  }
+  /// The most recently updated of the local and cloud copies.
+  async fn find_doc(&self, table: &str, id: &str) -> Option<Value> {
+    if id.is_empty() {
+      return None;
+    }
+    let local = self
+      .json_provider
+      .find_by_id(table, id)
+      .await
+      .ok()
+      .flatten();
+    let cloud = match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    };
+    match (local, cloud) {
+      (Some(local), Some(cloud)) => Some(
+        if str_field(&cloud, "updated_at") > str_field(&local, "updated_at") {
+          cloud
+        } else {
+          local
+        },
+      ),
+      (local, cloud) => local.or(cloud),
+    }
+  }
+  /// One page of the user's notifications, newest first. Pass the previous page's
+  /// `next_cursor` to continue; `skip` is only used without a cursor. Grouped notifications
+  /// count once towards `unread_count`, with their events totalled in `unread_events`.
+  pub async fn get_by_user(
     &self,
     user_id: &str,
-    visibility: &str,
+    query: NotificationPageQuery,
   ) -> Result<ResponseModel, ResponseModel> {
-    let provider = self.get_provider(visibility)?;
-    let filter = nosql_orm::query::Filter::Eq("user_id".to_string(), json!(user_id));
-    let docs = provider
-      .find_many("notifications", Some(&filter), None, None, None, true)
-      .await?;
-    for doc in docs {
-      if let Some(id) = doc.get("id").and_then(|v: &serde_json::Value| v.as_str()) {
-        let _ = provider.delete("notifications", id).await;
+    let base = vec![
+      Filter::Eq("user_id".to_string(), json!(user_id)),
+      Filter::Ne("in_app".to_string(), json!(false)),
+    ];
+    let unread_filter = Filter::And(
+      base
+        .iter()
+        .cloned()
+        .chain([Filter::Ne("read".to_string(), json!(true))])
+        .collect(),
+    );
+    let unread: Vec<Value> = self
+      .find_notifications(&unread_filter, None)
+      .await
+      .into_iter()
+      .filter(is_unread)
+      .collect();
+    let unread_count = unread.len();
+    let unread_events: u64 = unread
+      .iter()
+      .map(|doc| doc.get("count").and_then(|v| v.as_u64()).unwrap_or(1))
+      .sum();
+    let mut filters = base;
+    if query.unread_only {
+      filters.push(Filter::Ne("read".to_string(), json!(true)));
+    }
+    let skip = match query.cursor.as_deref() {
+      Some(cursor) => {
+        let (created_at, id) = notification_groups::decode_cursor(cursor)
+          .ok_or_else(|| err_response("Invalid notification cursor"))?;
+        filters.push(Filter::Or(vec![
+          Filter::Lt("created_at".to_string(), json!(created_at)),
+          Filter::And(vec![
+            Filter::Eq("created_at".to_string(), json!(created_at)),
+            Filter::Lt("id".to_string(), json!(id)),
+          ]),
+        ]));
+        0
       }
+      None => query.skip.unwrap_or(0) as usize,
+    };
+    let limit = query
+      .limit
+      .map(|l| l as usize)
+      .unwrap_or(DEFAULT_PAGE_SIZE)
+      .clamp(1, MAX_PAGE_SIZE);
+    // One extra tells whether another page follows
+    let fetch = (skip + limit + 1) as u64;
+    let mut docs = self
+      .find_notifications(&Filter::And(filters), Some(fetch))
+      .await;
+    if query.unread_only {
+      docs.retain(is_unread);
+    }
+    docs.sort_by_key(|doc| std::cmp::Reverse(notification_groups::sort_key(doc)));
+    let page: Vec<Value> = docs.iter().skip(skip).take(limit).cloned().collect();
+    let next_cursor = match page.last() {
+      Some(last) if skip + page.len() < docs.len() => {
+        Some(notification_groups::encode_cursor(last))
+      }
+      _ => None,
+    };
+    Ok(success_response(json!({
+      "items": page,
+      "next_cursor": next_cursor,
+      "unread_count": unread_count,
+      "unread_events": unread_events,
+    })))
+  }
+  /// The user's in-app notifications from both stores, optionally only those created at or
+  /// after `since` (RFC 3339).
+  pub async fn find_for_user(&self, user_id: &str, since: Option<&str>) -> Vec<Value> {
+    let mut filters = vec![
+      Filter::Eq("user_id".to_string(), json!(user_id)),
+      Filter::Ne("in_app".to_string(), json!(false)),
+    ];
+    if let Some(since) = since {
+      filters.push(Filter::Gte("created_at".to_string(), json!(since)));
+    }
+    self.find_notifications(&Filter::And(filters), None).await
+  }
+  pub async fn mark_as_read(&self, id: &str) -> Result<ResponseModel, ResponseModel> {
+    let doc = self
+      .find_notification(id)
+      .await
+      .ok_or_else(|| err_response("Notification not found"))?;
+    Ok(success_response(self.set_read(&doc).await?))
+  }
+  pub async fn mark_all_as_read(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let filter = Filter::Eq("user_id".to_string(), json!(user_id));
+    for doc in self.find_notifications(&filter, None).await {
+      if is_unread(&doc) {
+        let _ = self.set_read(&doc).await;
+      }
+    }
+    Ok(success_response(serde_json::Value::Array(vec![])))
+  }
+  async fn set_read(&self, doc: &Value) -> Result<Value, ResponseModel> {
+    let read_at = Utc::now().to_rfc3339_opts(SecondsFormat::AutoSi, true);
+    self
+      .store_patch(
+        doc,
+        json!({ "read": true, "read_at": read_at }),
+        NotificationOutboxOp::Read,
+      )
+      .await
+  }
+  pub async fn delete(&self, id: &str) -> Result<ResponseModel, ResponseModel> {
+    if let Some(doc) = self.find_notification(id).await {
+      self.remove(&doc).await;
+    }
+    Ok(success_response(serde_json::json!(id.to_string())))
+  }
+  pub async fn clear_all(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let filter = Filter::Eq("user_id".to_string(), json!(user_id));
+    for doc in self.find_notifications(&filter, None).await {
+      self.remove(&doc).await;
     }
     Ok(success_response(serde_json::Value::Array(vec![])))
   }
+  async fn remove(&self, doc: &Value) {
+    let id = str_field(doc, "id").to_string();
+    let _ = self.json_provider.delete(NOTIFICATIONS_TABLE, &id).await;
+    if is_shared(doc) {
+      self
+        .push(NotificationOutboxOp::Delete, &id, Value::Null)
+        .await;
+    }
+  }
+}
+/// Paging options for `get_by_user`.
+#[derive(Debug, Clone, Default)]
+pub struct NotificationPageQuery {
+  pub cursor: Option<String>,
+  pub skip: Option<u64>,
+  pub limit: Option<u64>,
+  pub unread_only: bool,
+}
+fn is_shared(doc: &Value) -> bool {
+  doc.get("shared").and_then(|v| v.as_bool()) == Some(true)
+}
+fn is_unread(doc: &Value) -> bool {
+  doc.get("read").and_then(|v| v.as_bool()) != Some(true)
+}
+fn default_preferences(user_id: &str) -> NotificationPreferencesEntity {
+  NotificationPreferencesEntity {
+    id: Some(user_id.to_string()),
+    user_id: user_id.to_string(),
+    kinds: Default::default(),
+    quiet_hours: None,
+    muted_todos: Vec::new(),
+    muted_rooms: Vec::new(),
+    created_at: None,
+    updated_at: None,
+  }
+}
+fn str_field<'a>(doc: &'a Value, field: &str) -> &'a str {
+  doc.get(field).and_then(|v| v.as_str()).unwrap_or("")
 }
@@ -0,0 +1,4 @@
+#[path = "oidc.provider.rs"]
+pub mod oidc_provider;
+#[path = "oauth-provider.rs"]
+pub mod provider;
@@ -0,0 +1,67 @@
+/* sys lib */
+use async_trait::async_trait;
+use data_encoding::BASE64URL_NOPAD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+/// Parameters carried through the authorization redirect.
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+  pub state: String,
+  pub nonce: String,
+  pub code_challenge: String,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthTokens {
+  pub access_token: String,
+  #[serde(default)]
+  pub refresh_token: Option<String>,
+  #[serde(default)]
+  pub id_token: Option<String>,
+  #[serde(default)]
+  pub expires_in: Option<i64>,
+}
+/// Identity asserted by a provider once the code exchange succeeded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthIdentity {
+  pub provider: String,
+  /// Stable account id at the provider (`sub` for OIDC)
+  pub subject: String,
+  pub email: Option<String>,
+  pub email_verified: bool,
+  pub username: Option<String>,
+  pub display_name: Option<String>,
+}
+#[async_trait]
+pub trait OAuthProvider: Send + Sync {
+  fn id(&self) -> &str;
+  fn display_name(&self) -> &str;
+  async fn authorization_url(&self, request: &AuthorizationRequest) -> Result<String, String>;
+  async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<OAuthTokens, String>;
+  /// Resolves the account behind `tokens`; OIDC providers validate the id_token and `nonce` here.
+  async fn fetch_identity(
+    &self,
+    tokens: &OAuthTokens,
+    nonce: &str,
+  ) -> Result<OAuthIdentity, String>;
+}
+pub fn random_url_token() -> String {
+  let mut bytes = [0u8; 32];
+  rand::thread_rng().fill_bytes(&mut bytes);
+  BASE64URL_NOPAD.encode(&bytes)
+}
+/// RFC 7636 S256 code challenge for `verifier`.
+pub fn pkce_challenge(verifier: &str) -> String {
+  BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes()))
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn pkce_challenge_matches_rfc7636_example() {
+    assert_eq!(
+      pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+      "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+    );
+  }
+}
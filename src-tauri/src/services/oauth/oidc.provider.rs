@@ -0,0 +1,457 @@
+/* sys lib */
+use async_trait::async_trait;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::sync::RwLock;
+use tauri_plugin_http::reqwest;
+/* services */
+use super::provider::{AuthorizationRequest, OAuthIdentity, OAuthProvider, OAuthTokens};
+/* helpers */
+use crate::utils::config::OAuthProviderConfig;
+const ID_TOKEN_LEEWAY_SECS: u64 = 60;
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcDiscovery {
+  pub issuer: String,
+  pub authorization_endpoint: String,
+  pub token_endpoint: String,
+  pub jwks_uri: String,
+  #[serde(default)]
+  pub userinfo_endpoint: Option<String>,
+}
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+  sub: String,
+  #[serde(default)]
+  nonce: Option<String>,
+  #[serde(default)]
+  email: Option<String>,
+  #[serde(default)]
+  email_verified: Option<bool>,
+  #[serde(default)]
+  preferred_username: Option<String>,
+  #[serde(default)]
+  name: Option<String>,
+}
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+  access_token: Option<String>,
+  #[serde(default)]
+  refresh_token: Option<String>,
+  #[serde(default)]
+  id_token: Option<String>,
+  #[serde(default)]
+  expires_in: Option<i64>,
+  #[serde(default)]
+  error: Option<String>,
+  #[serde(default)]
+  error_description: Option<String>,
+}
+/// Generic OpenID Connect provider (GitLab, Google, Keycloak, ...) driven by discovery.
+pub struct OidcProvider {
+  config: OAuthProviderConfig,
+  http_client: reqwest::Client,
+  discovery: RwLock<Option<OidcDiscovery>>,
+  jwks: RwLock<Option<JwkSet>>,
+}
+impl OidcProvider {
+  pub fn new(config: OAuthProviderConfig) -> Self {
+    let http_client = reqwest::Client::builder()
+      .user_agent("TaskFlow/1.0")
+      .build()
+      .expect("Failed to create HTTP client");
+    Self {
+      config,
+      http_client,
+      discovery: RwLock::new(None),
+      jwks: RwLock::new(None),
+    }
+  }
+  pub async fn discover(&self) -> Result<OidcDiscovery, String> {
+    if let Some(cached) = self.discovery.read().ok().and_then(|d| d.clone()) {
+      return Ok(cached);
+    }
+    let issuer = self.config.issuer.trim_end_matches('/');
+    require_secure_url(issuer)?;
+    let url = format!("{}/.well-known/openid-configuration", issuer);
+    let discovery: OidcDiscovery = self
+      .http_client
+      .get(&url)
+      .send()
+      .await
+      .map_err(|e| format!("OIDC discovery failed: {}", e))?
+      .error_for_status()
+      .map_err(|e| format!("OIDC discovery failed: {}", e))?
+      .json()
+      .await
+      .map_err(|e| format!("Invalid OIDC discovery document: {}", e))?;
+    if discovery.issuer.trim_end_matches('/') != issuer {
+      return Err(format!(
+        "OIDC issuer mismatch: expected {}, got {}",
+        issuer, discovery.issuer
+      ));
+    }
+    for endpoint in [
+      &discovery.authorization_endpoint,
+      &discovery.token_endpoint,
+      &discovery.jwks_uri,
+    ] {
+      require_secure_url(endpoint)?;
+    }
+    if let Ok(mut slot) = self.discovery.write() {
+      *slot = Some(discovery.clone());
+    }
+    Ok(discovery)
+  }
+  async fn fetch_jwks(&self, refresh: bool) -> Result<JwkSet, String> {
+    if !refresh {
+      if let Some(cached) = self.jwks.read().ok().and_then(|j| j.clone()) {
+        return Ok(cached);
+      }
+    }
+    let discovery = self.discover().await?;
+    let jwks: JwkSet = self
+      .http_client
+      .get(&discovery.jwks_uri)
+      .send()
+      .await
+      .map_err(|e| format!("Failed to fetch JWKS: {}", e))?
+      .json()
+      .await
+      .map_err(|e| format!("Invalid JWKS: {}", e))?;
+    if let Ok(mut slot) = self.jwks.write() {
+      *slot = Some(jwks.clone());
+    }
+    Ok(jwks)
+  }
+  async fn validate_id_token(&self, id_token: &str, nonce: &str) -> Result<IdTokenClaims, String> {
+    let header = decode_header(id_token).map_err(|e| format!("Malformed id_token: {}", e))?;
+    // Symmetric algorithms would let anyone holding the client secret mint tokens
+    if matches!(
+      header.alg,
+      Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+      return Err("id_token must be signed with an asymmetric key".to_string());
+    }
+    let kid = header.kid.clone().unwrap_or_default();
+    let mut jwks = self.fetch_jwks(false).await?;
+    if jwks.find(&kid).is_none() {
+      // Keys rotate; retry once with a fresh set before giving up
+      jwks = self.fetch_jwks(true).await?;
+    }
+    let jwk = if kid.is_empty() && jwks.keys.len() == 1 {
+      &jwks.keys[0]
+    } else {
+      jwks
+        .find(&kid)
+        .ok_or_else(|| "No matching signing key for id_token".to_string())?
+    };
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| format!("Unsupported signing key: {}", e))?;
+    let discovery = self.discover().await?;
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[self.config.client_id.as_str()]);
+    validation.set_issuer(&[discovery.issuer.as_str()]);
+    validation.leeway = ID_TOKEN_LEEWAY_SECS;
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+      .map_err(|e| format!("Invalid id_token: {}", e))?
+      .claims;
+    if claims.nonce.as_deref() != Some(nonce) {
+      return Err("id_token nonce does not match the login request".to_string());
+    }
+    Ok(claims)
+  }
+}
+#[async_trait]
+impl OAuthProvider for OidcProvider {
+  fn id(&self) -> &str {
+    &self.config.id
+  }
+  fn display_name(&self) -> &str {
+    &self.config.display_name
+  }
+  async fn authorization_url(&self, request: &AuthorizationRequest) -> Result<String, String> {
+    let discovery = self.discover().await?;
+    let scope = self.config.scopes.join(" ");
+    reqwest::Url::parse_with_params(
+      &discovery.authorization_endpoint,
+      &[
+        ("response_type", "code"),
+        ("client_id", self.config.client_id.as_str()),
+        ("redirect_uri", self.config.redirect_uri.as_str()),
+        ("scope", scope.as_str()),
+        ("state", request.state.as_str()),
+        ("nonce", request.nonce.as_str()),
+        ("code_challenge", request.code_challenge.as_str()),
+        ("code_challenge_method", "S256"),
+      ],
+    )
+    .map(|url| url.to_string())
+    .map_err(|e| e.to_string())
+  }
+  async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<OAuthTokens, String> {
+    let discovery = self.discover().await?;
+    let mut params = vec![
+      ("grant_type", "authorization_code"),
+      ("code", code),
+      ("redirect_uri", self.config.redirect_uri.as_str()),
+      ("client_id", self.config.client_id.as_str()),
+      ("code_verifier", code_verifier),
+    ];
+    if !self.config.client_secret.is_empty() {
+      params.push(("client_secret", self.config.client_secret.as_str()));
+    }
+    let token_resp: TokenResponse = self
+      .http_client
+      .post(&discovery.token_endpoint)
+      .header("Accept", "application/json")
+      .form(&params)
+      .send()
+      .await
+      .map_err(|e| format!("Token request failed: {}", e))?
+      .json()
+      .await
+      .map_err(|e| format!("Invalid token response: {}", e))?;
+    if let Some(err) = token_resp.error {
+      return Err(token_resp.error_description.unwrap_or(err));
+    }
+    Ok(OAuthTokens {
+      access_token: token_resp
+        .access_token
+        .ok_or_else(|| "Missing access token in response".to_string())?,
+      refresh_token: token_resp.refresh_token,
+      id_token: token_resp.id_token,
+      expires_in: token_resp.expires_in,
+    })
+  }
+  async fn fetch_identity(
+    &self,
+    tokens: &OAuthTokens,
+    nonce: &str,
+  ) -> Result<OAuthIdentity, String> {
+    let id_token = tokens
+      .id_token
+      .as_deref()
+      .ok_or_else(|| "Provider did not return an id_token; is the openid scope set?".to_string())?;
+    let claims = self.validate_id_token(id_token, nonce).await?;
+    Ok(OAuthIdentity {
+      provider: self.config.id.clone(),
+      subject: claims.sub,
+      email: claims.email,
+      email_verified: claims.email_verified.unwrap_or(false),
+      username: claims.preferred_username,
+      display_name: claims.name,
+    })
+  }
+}
+/// Provider endpoints must use https; plain http is only accepted for loopback hosts.
+fn require_secure_url(url: &str) -> Result<(), String> {
+  let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+  let loopback = matches!(
+    parsed.host_str(),
+    Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+  );
+  if parsed.scheme() == "https" || (parsed.scheme() == "http" && loopback) {
+    Ok(())
+  } else {
+    Err(format!("OAuth endpoint must use https: {}", url))
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::oauth::provider::pkce_challenge;
+  use crate::utils::config::OAuthProviderKind;
+  use data_encoding::BASE64URL_NOPAD;
+  use jsonwebtoken::{encode, EncodingKey, Header};
+  use p256::pkcs8::{EncodePrivateKey, LineEnding};
+  use std::io::{BufRead, BufReader, Read, Write};
+  use std::net::TcpListener;
+  const CLIENT_ID: &str = "taskflow-desktop";
+  const VERIFIER: &str = "test-code-verifier-0123456789-abcdefghijklmnop";
+  /// Minimal IdP serving discovery, JWKS and a token endpoint that checks the PKCE verifier.
+  fn start_mock_idp(id_token_for: impl Fn(&str) -> String + Send + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let issuer = format!("http://{}", listener.local_addr().unwrap());
+    let issuer_clone = issuer.clone();
+    std::thread::spawn(move || {
+      let jwks = jwks_json();
+      for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut content_length = 0usize;
+        loop {
+          let mut line = String::new();
+          reader.read_line(&mut line).unwrap();
+          if line.trim().is_empty() {
+            break;
+          }
+          if let Some(v) = line.to_lowercase().strip_prefix("content-length:") {
+            content_length = v.trim().parse().unwrap_or(0);
+          }
+        }
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body).unwrap();
+        let body = String::from_utf8_lossy(&body).to_string();
+        let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+        let (status, payload) = match path {
+          "/.well-known/openid-configuration" => (
+            "200 OK",
+            serde_json::json!({
+              "issuer": issuer_clone,
+              "authorization_endpoint": format!("{}/authorize", issuer_clone),
+              "token_endpoint": format!("{}/token", issuer_clone),
+              "jwks_uri": format!("{}/jwks", issuer_clone)
+            })
+            .to_string(),
+          ),
+          "/jwks" => ("200 OK", jwks.clone()),
+          "/token" if body.contains(&format!("code_verifier={}", VERIFIER)) => {
+            let code = body
+              .split('&')
+              .find_map(|kv| kv.strip_prefix("code="))
+              .unwrap_or_default();
+            (
+              "200 OK",
+              serde_json::json!({
+                "access_token": "mock-access-token",
+                "token_type": "Bearer",
+                "id_token": id_token_for(code)
+              })
+              .to_string(),
+            )
+          }
+          "/token" => (
+            "400 Bad Request",
+            r#"{"error":"invalid_grant","error_description":"PKCE verification failed"}"#
+              .to_string(),
+          ),
+          _ => ("404 Not Found", "{}".to_string()),
+        };
+        let response = format!(
+          "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+          status,
+          payload.len(),
+          payload
+        );
+        let _ = stream.write_all(response.as_bytes());
+      }
+    });
+    issuer
+  }
+  fn signing_key() -> p256::SecretKey {
+    p256::SecretKey::from_slice(&[7u8; 32]).unwrap()
+  }
+  fn jwks_json() -> String {
+    let point = signing_key().public_key().to_sec1_bytes();
+    serde_json::json!({
+      "keys": [{
+        "kty": "EC",
+        "crv": "P-256",
+        "kid": "mock-key",
+        "use": "sig",
+        "alg": "ES256",
+        "x": BASE64URL_NOPAD.encode(&point[1..33]),
+        "y": BASE64URL_NOPAD.encode(&point[33..65])
+      }]
+    })
+    .to_string()
+  }
+  fn sign_id_token(claims: serde_json::Value) -> String {
+    let pem = signing_key().to_pkcs8_pem(LineEnding::LF).unwrap();
+    let mut header = Header::new(Algorithm::ES256);
+    header.kid = Some("mock-key".to_string());
+    encode(
+      &header,
+      &claims,
+      &EncodingKey::from_ec_pem(pem.as_bytes()).unwrap(),
+    )
+    .unwrap()
+  }
+  fn provider(issuer: &str) -> OidcProvider {
+    OidcProvider::new(OAuthProviderConfig {
+      id: "keycloak".to_string(),
+      display_name: "Keycloak".to_string(),
+      kind: OAuthProviderKind::Oidc,
+      issuer: issuer.to_string(),
+      client_id: CLIENT_ID.to_string(),
+      client_secret: String::new(),
+      redirect_uri: "http://localhost/callback".to_string(),
+      scopes: vec!["openid".to_string(), "email".to_string()],
+    })
+  }
+  fn claims(issuer: &str, audience: &str, nonce: &str) -> serde_json::Value {
+    let now = chrono::Utc::now().timestamp();
+    serde_json::json!({
+      "iss": issuer,
+      "aud": audience,
+      "sub": "user-42",
+      "nonce": nonce,
+      "email": "jane@example.com",
+      "email_verified": true,
+      "preferred_username": "jane",
+      "iat": now,
+      "exp": now + 300
+    })
+  }
+  #[tokio::test]
+  async fn completes_code_flow_against_mock_idp() {
+    let issuer = std::sync::Arc::new(std::sync::OnceLock::<String>::new());
+    let issuer_for_tokens = issuer.clone();
+    let url = start_mock_idp(move |_| {
+      sign_id_token(claims(
+        issuer_for_tokens.get().unwrap(),
+        CLIENT_ID,
+        "nonce-1",
+      ))
+    });
+    issuer.set(url.clone()).unwrap();
+    let provider = provider(&url);
+    let auth_url = provider
+      .authorization_url(&AuthorizationRequest {
+        state: "state-1".to_string(),
+        nonce: "nonce-1".to_string(),
+        code_challenge: pkce_challenge(VERIFIER),
+      })
+      .await
+      .unwrap();
+    assert!(auth_url.starts_with(&format!("{}/authorize?", url)));
+    assert!(auth_url.contains("code_challenge_method=S256"));
+    let tokens = provider.exchange_code("code-1", VERIFIER).await.unwrap();
+    let identity = provider.fetch_identity(&tokens, "nonce-1").await.unwrap();
+    assert_eq!(identity.subject, "user-42");
+    assert_eq!(identity.email.as_deref(), Some("jane@example.com"));
+    assert!(identity.email_verified);
+    assert!(provider
+      .fetch_identity(&tokens, "other-nonce")
+      .await
+      .is_err());
+    assert!(provider
+      .exchange_code("code-1", "wrong-verifier")
+      .await
+      .is_err());
+  }
+  #[tokio::test]
+  async fn rejects_id_token_for_other_audience() {
+    let issuer = std::sync::Arc::new(std::sync::OnceLock::<String>::new());
+    let issuer_for_tokens = issuer.clone();
+    let url = start_mock_idp(move |_| {
+      sign_id_token(claims(
+        issuer_for_tokens.get().unwrap(),
+        "other-client",
+        "nonce-1",
+      ))
+    });
+    issuer.set(url.clone()).unwrap();
+    let provider = provider(&url);
+    let tokens = provider.exchange_code("code-1", VERIFIER).await.unwrap();
+    assert!(provider.fetch_identity(&tokens, "nonce-1").await.is_err());
+  }
+  #[test]
+  fn requires_https_outside_loopback() {
+    assert!(require_secure_url("https://gitlab.com").is_ok());
+    assert!(require_secure_url("http://127.0.0.1:8080").is_ok());
+    assert!(require_secure_url("http://idp.example.com").is_err());
+  }
+}
@@ -0,0 +1,230 @@
+/* sys lib */
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::task_entity::TaskStatus;
+use crate::entities::todo_entity::{OverdueAction, OverduePolicy};
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::permission_service::PermissionService;
+use crate::services::repository::service::RepositoryService;
+/* helpers */
+use crate::utils::overdue;
+use crate::utils::reminders::parse_due;
+use crate::utils::response_helper::err_response;
+use crate::utils::signed_in;
+/// Longest grace period a policy may use, 30 days.
+const MAX_GRACE_HOURS: i64 = 30 * 24;
+const MAX_ROLL_OVER_DAYS: i64 = 365;
+/// Applies each todo's opt-in overdue policy to its late pending tasks.
+///
+/// Only `pending` tasks are touched and none of the actions complete a task, so
+/// `completed_tasks_count` is unaffected; if a failed task is completed later the
+/// repository counts it like any other completion. Changes go through the repository so
+/// they raise the same change events, webhooks and watcher notifications as user edits.
+pub struct OverdueService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  repository: Arc<RepositoryService>,
+}
+impl OverdueService {
+  pub fn new(
+    json_provider: JsonProvider,
+    mongodb_provider: Option<Arc<MongoProvider>>,
+    repository: Arc<RepositoryService>,
+  ) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+      repository,
+    }
+  }
+  /// Sets or, with `None`, removes the todo's policy. Only people who can edit the todo may.
+  pub async fn set_policy(
+    &self,
+    todo_id: &str,
+    actor_id: &str,
+    policy: Option<OverduePolicy>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let todo = self
+      .find_doc("todos", todo_id)
+      .await
+      .ok_or_else(|| err_response("Todo not found"))?;
+    if !PermissionService::can_edit_todo(&todo, actor_id) {
+      return Err(ResponseModel::forbidden(
+        "You do not have permission to change this todo",
+      ));
+    }
+    if let Some(policy) = &policy {
+      if !(0..=MAX_GRACE_HOURS).contains(&policy.grace_hours) {
+        return Err(err_response("Grace period must be between 0 and 720 hours"));
+      }
+      if !(1..=MAX_ROLL_OVER_DAYS).contains(&policy.roll_over_days) {
+        return Err(err_response("Roll-over must be between 1 and 365 days"));
+      }
+    }
+    self
+      .update(
+        "todos",
+        todo_id,
+        json!({ "overdue_policy": policy }),
+        &todo,
+        actor_id,
+      )
+      .await
+  }
+  /// Applies the policies of the signed-in user's todos once and returns how many tasks were
+  /// changed. Each device sweeps only its own user's todos so shared ones are not swept twice.
+  pub async fn sweep(&self) -> usize {
+    let Some(user_id) = signed_in::signed_in_user() else {
+      return 0;
+    };
+    let now = Utc::now();
+    let mut changed = 0;
+    let owned = Filter::Eq("user_id".to_string(), json!(user_id));
+    for todo in self.find_all("todos", Some(&owned)).await {
+      if !is_live(&todo) {
+        continue;
+      }
+      let Some(policy) = todo
+        .get("overdue_policy")
+        .cloned()
+        .and_then(|v| serde_json::from_value::<OverduePolicy>(v).ok())
+      else {
+        continue;
+      };
+      let filter = Filter::And(vec![
+        Filter::Eq("todo_id".to_string(), todo["id"].clone()),
+        Filter::Eq("status".to_string(), json!(TaskStatus::Pending)),
+      ]);
+      for task in self.find_all("tasks", Some(&filter)).await {
+        if !is_live(&task) {
+          continue;
+        }
+        let Some(due) = task
+          .get("end_date")
+          .and_then(|v| v.as_str())
+          .and_then(parse_due)
+        else {
+          continue;
+        };
+        if !overdue::past_grace(due, policy.grace_hours, now) {
+          continue;
+        }
+        let Some(update) = self.update_for(&policy, &task, due, now) else {
+          continue;
+        };
+        let task_id = task.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        // A failed update is retried by the next sweep, as the task is still overdue.
+        if self
+          .update("tasks", task_id, update, &todo, &user_id)
+          .await
+          .is_ok()
+        {
+          changed += 1;
+        }
+      }
+    }
+    changed
+  }
+  fn update_for(
+    &self,
+    policy: &OverduePolicy,
+    task: &Value,
+    due: chrono::DateTime<Utc>,
+    now: chrono::DateTime<Utc>,
+  ) -> Option<Value> {
+    match policy.action {
+      OverdueAction::Fail => Some(json!({ "status": TaskStatus::Failed })),
+      OverdueAction::RollOver => {
+        let days = overdue::roll_over_steps(due, policy.roll_over_days, policy.grace_hours, now)
+          * policy.roll_over_days;
+        let end_date = overdue::shift_date(task.get("end_date")?.as_str()?, days)?;
+        let start_date = task
+          .get("start_date")
+          .and_then(|v| v.as_str())
+          .and_then(|start| overdue::shift_date(start, days));
+        let mut update = json!({ "end_date": end_date, "overdue_since": null });
+        if let Some(start_date) = start_date {
+          update["start_date"] = json!(start_date);
+        }
+        Some(update)
+      }
+      OverdueAction::Flag => {
+        let flagged = task
+          .get("overdue_since")
+          .map(|v| !v.is_null())
+          .unwrap_or(false);
+        (!flagged).then(|| json!({ "overdue_since": now.to_rfc3339() }))
+      }
+    }
+  }
+  /// Updates the record through the repository, stored where `todo`'s visibility puts it.
+  async fn update(
+    &self,
+    table: &str,
+    id: &str,
+    update: Value,
+    todo: &Value,
+    actor_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let visibility = todo
+      .get("visibility")
+      .and_then(|v| v.as_str())
+      .map(|s| s.to_string());
+    self
+      .repository
+      .execute(
+        "update".to_string(),
+        table.to_string(),
+        Some(id.to_string()),
+        Some(update),
+        None,
+        None,
+        visibility,
+        Some(actor_id.to_string()),
+        None,
+        None,
+        None,
+      )
+      .await
+  }
+  async fn find_doc(&self, table: &str, id: &str) -> Option<Value> {
+    if let Ok(Some(doc)) = self.json_provider.find_by_id(table, id).await {
+      return Some(doc);
+    }
+    match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    }
+  }
+  /// Matches from the local store and MongoDB, de-duplicated by id.
+  async fn find_all(&self, table: &str, filter: Option<&Filter>) -> Vec<Value> {
+    let mut docs = self
+      .json_provider
+      .find_many(table, filter, None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      for doc in mongo
+        .find_many(table, filter, None, None, None, true)
+        .await
+        .unwrap_or_default()
+      {
+        if !docs.iter().any(|d| d.get("id") == doc.get("id")) {
+          docs.push(doc);
+        }
+      }
+    }
+    docs
+  }
+}
+fn is_live(doc: &Value) -> bool {
+  doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true)
+}
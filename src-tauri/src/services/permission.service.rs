@@ -72,6 +72,10 @@ impl PermissionService {
     false
   }
   pub fn can_view_todo(todo: &Value, user_id: &str) -> bool {
+    Self::can_view_todo_with_profile(todo, user_id, None)
+  }
+  /// Assignees are stored as profile ids, so shared todos also match the user's profile.
+  pub fn can_view_todo_with_profile(todo: &Value, user_id: &str, profile_id: Option<&str>) -> bool {
     let visibility = todo
       .get("visibility")
       .and_then(|v| v.as_str())
@@ -83,7 +87,12 @@ impl PermissionService {
       "shared" => {
         owner_id == user_id
           || assignees
-            .map(|a| a.iter().any(|id| id.as_str() == Some(user_id)))
+            .map(|a| {
+              a.iter().any(|id| {
+                id.as_str() == Some(user_id)
+                  || (profile_id.is_some_and(|pid| !pid.is_empty()) && id.as_str() == profile_id)
+              })
+            })
             .unwrap_or(false)
       }
       "public" => true,
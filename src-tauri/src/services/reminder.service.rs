@@ -0,0 +1,556 @@
+/* sys lib */
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+/* providers */
+use nosql_orm::error::OrmError;
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::notification_entity::{NotificationKind, NotificationTarget};
+use crate::entities::reminder_entity::{
+  ReminderClaimEntity, ReminderSettingsEntity, ReminderStateEntity, ReminderStatus,
+  TaskReminderEntity,
+};
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::notification_service::NotificationService;
+use crate::services::permission_service::PermissionService;
+/* helpers */
+use crate::utils::group_roles::string_list;
+use crate::utils::profile;
+use crate::utils::reminders::{self, ReminderTrigger};
+use crate::utils::response_helper::{err_response, success_response};
+use crate::utils::signed_in;
+const SETTINGS_TABLE: &str = "reminder_settings";
+const TASK_REMINDERS_TABLE: &str = "task_reminders";
+const STATES_TABLE: &str = "reminder_states";
+const CLAIMS_TABLE: &str = "reminder_claims";
+/// Due-date reminders for tasks. `run` is called periodically by the app for the user signed
+/// in on the device; every reminder it fires is recorded under a stable id and claimed before
+/// it fires, so it never fires twice, even across restarts or from two devices.
+/// Desktop toasts go through `NotificationService`, which honours quiet hours and mutes.
+pub struct ReminderService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  notification_service: Arc<NotificationService>,
+}
+impl ReminderService {
+  pub fn new(
+    json_provider: JsonProvider,
+    mongodb_provider: Option<Arc<MongoProvider>>,
+    notification_service: Arc<NotificationService>,
+  ) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+      notification_service,
+    }
+  }
+  /// The user's default offsets, or the built-in ones when they never chose any.
+  pub async fn get_settings(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let settings = self.settings_for(user_id).await;
+    Ok(success_response(json!({
+      "user_id": user_id,
+      "default_offsets": settings.0,
+      "overdue": settings.1,
+    })))
+  }
+  pub async fn set_settings(
+    &self,
+    user_id: &str,
+    default_offsets: Vec<i64>,
+    overdue: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let now = Utc::now();
+    let settings = ReminderSettingsEntity {
+      id: Some(user_id.to_string()),
+      user_id: user_id.to_string(),
+      default_offsets: reminders::normalize_offsets(&default_offsets),
+      overdue,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let value = serde_json::to_value(&settings)
+      .map_err(|e| err_response(&format!("Failed to save reminder settings: {}", e)))?;
+    Ok(success_response(self.store(SETTINGS_TABLE, value).await?))
+  }
+  pub async fn get_task_reminders(
+    &self,
+    task_id: &str,
+    viewer_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let (_, todo) = self.find_task(task_id).await?;
+    if !PermissionService::can_view_todo(&todo, viewer_id) {
+      return Err(ResponseModel::forbidden(
+        "You do not have access to this task",
+      ));
+    }
+    let offsets = self.task_offsets(task_id).await;
+    Ok(success_response(json!({
+      "task_id": task_id,
+      "offsets": offsets,
+      "uses_defaults": offsets.is_empty(),
+    })))
+  }
+  /// Sets the task's offsets for everyone it reminds. An empty list goes back to each
+  /// user's defaults.
+  pub async fn set_task_reminders(
+    &self,
+    task_id: &str,
+    actor_id: &str,
+    offsets: Vec<i64>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let (task, todo) = self.find_task(task_id).await?;
+    if !PermissionService::can_edit_task(&task, &todo, actor_id) {
+      return Err(ResponseModel::forbidden(
+        "You do not have permission to change reminders for this task",
+      ));
+    }
+    let offsets = reminders::normalize_offsets(&offsets);
+    if offsets.is_empty() {
+      let _ = self
+        .json_provider
+        .delete(TASK_REMINDERS_TABLE, task_id)
+        .await;
+      if let Some(mongo) = &self.mongodb_provider {
+        let _ = mongo.delete(TASK_REMINDERS_TABLE, task_id).await;
+      }
+      return Ok(success_response(
+        json!({ "task_id": task_id, "offsets": [], "uses_defaults": true }),
+      ));
+    }
+    let now = Utc::now();
+    let reminder = TaskReminderEntity {
+      id: Some(task_id.to_string()),
+      task_id: task_id.to_string(),
+      offsets,
+      set_by: actor_id.to_string(),
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let value = serde_json::to_value(&reminder)
+      .map_err(|e| err_response(&format!("Failed to save task reminders: {}", e)))?;
+    Ok(success_response(
+      self.store(TASK_REMINDERS_TABLE, value).await?,
+    ))
+  }
+  /// Reminders that fired for the user and were not dismissed, newest first.
+  pub async fn get_reminders(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let filter = Filter::Eq("user_id".to_string(), json!(user_id));
+    let mut states: Vec<Value> = self
+      .find_all(STATES_TABLE, &filter)
+      .await
+      .into_iter()
+      .filter(|state| state.get("status") != Some(&json!(ReminderStatus::Dismissed)))
+      .collect();
+    states.sort_by(|a, b| {
+      b["fired_at"]
+        .as_str()
+        .unwrap_or("")
+        .cmp(a["fired_at"].as_str().unwrap_or(""))
+    });
+    Ok(success_response(states))
+  }
+  /// Hides the reminder for `minutes`; the scheduler fires it again afterwards.
+  pub async fn snooze(
+    &self,
+    reminder_id: &str,
+    user_id: &str,
+    minutes: i64,
+  ) -> Result<ResponseModel, ResponseModel> {
+    if minutes <= 0 || minutes > reminders::MAX_OFFSET_MINUTES {
+      return Err(err_response(
+        "Snooze must be between one minute and 30 days",
+      ));
+    }
+    let until = (Utc::now() + Duration::minutes(minutes)).to_rfc3339();
+    self
+      .set_status(reminder_id, user_id, ReminderStatus::Snoozed, Some(until))
+      .await
+  }
+  pub async fn dismiss(
+    &self,
+    reminder_id: &str,
+    user_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    self
+      .set_status(reminder_id, user_id, ReminderStatus::Dismissed, None)
+      .await
+  }
+  /// Fires the signed-in user's reminders that are due and have not fired yet, plus snoozed
+  /// ones whose snooze ran out. Other users' reminders fire on their own devices. Returns how
+  /// many fired.
+  pub async fn run(&self) -> usize {
+    let Some(user_id) = signed_in::signed_in_user() else {
+      return 0;
+    };
+    let now = Utc::now();
+    let (default_offsets, overdue) = self.settings_for(&user_id).await;
+    let mut fired = 0;
+    for (task, todo) in self.candidate_tasks(&user_id).await {
+      let end_date = str_field(&task, "end_date");
+      let Some(due) = reminders::parse_due(end_date) else {
+        continue;
+      };
+      if !self.recipients(&task, &todo).await.contains(&user_id) {
+        continue;
+      }
+      let task_id = str_field(&task, "id");
+      let task_offsets = self.task_offsets(task_id).await;
+      let offsets = if task_offsets.is_empty() {
+        &default_offsets
+      } else {
+        &task_offsets
+      };
+      let Some(trigger) = reminders::current_trigger(due, offsets, overdue, now) else {
+        continue;
+      };
+      let id = reminders::reminder_id(task_id, &user_id, trigger, end_date);
+      let (refire, claim) = match self.find_doc(STATES_TABLE, &id).await {
+        None => (false, reminders::claim_id(&id, None)),
+        Some(state) if snooze_ended(&state, &now.to_rfc3339()) => (
+          true,
+          reminders::claim_id(&id, Some(str_field(&state, "snoozed_until"))),
+        ),
+        Some(_) => continue,
+      };
+      if !self.claim(&claim, &user_id).await {
+        continue;
+      }
+      if self
+        .fire(&id, &task, &user_id, trigger, end_date, refire)
+        .await
+      {
+        fired += 1;
+      }
+    }
+    fired
+  }
+  /// Pending tasks that may remind the user, with their todos: the user's own tasks and the
+  /// tasks of todos the user is assigned to.
+  async fn candidate_tasks(&self, user_id: &str) -> Vec<(Value, Value)> {
+    let pending = Filter::Eq("status".to_string(), json!("pending"));
+    let mut assignee_ids = vec![json!(user_id)];
+    if let Some(profile_id) =
+      profile::find_profile_id(&self.json_provider, self.mongodb_provider.as_ref(), user_id).await
+    {
+      assignee_ids.push(json!(profile_id));
+    }
+    let assigned_todo_ids: Vec<Value> = self
+      .find_all("todos", &Filter::In("assignees".to_string(), assignee_ids))
+      .await
+      .iter()
+      .filter(|todo| is_live(todo))
+      .map(|todo| json!(str_field(todo, "id")))
+      .collect();
+    let mut tasks = self
+      .find_all(
+        "tasks",
+        &Filter::And(vec![
+          pending.clone(),
+          Filter::Eq("user_id".to_string(), json!(user_id)),
+        ]),
+      )
+      .await;
+    if !assigned_todo_ids.is_empty() {
+      for task in self
+        .find_all(
+          "tasks",
+          &Filter::And(vec![
+            pending,
+            Filter::In("todo_id".to_string(), assigned_todo_ids),
+          ]),
+        )
+        .await
+      {
+        if !tasks.iter().any(|t| t.get("id") == task.get("id")) {
+          tasks.push(task);
+        }
+      }
+    }
+    let mut todos: HashMap<String, Option<Value>> = HashMap::new();
+    let mut candidates = Vec::new();
+    for task in tasks.into_iter().filter(is_live) {
+      let todo_id = str_field(&task, "todo_id").to_string();
+      let todo = match todos.get(&todo_id) {
+        Some(todo) => todo.clone(),
+        None => {
+          let todo = self.find_doc("todos", &todo_id).await.filter(is_live);
+          todos.insert(todo_id, todo.clone());
+          todo
+        }
+      };
+      if let Some(todo) = todo {
+        candidates.push((task, todo));
+      }
+    }
+    candidates
+  }
+  /// Inserts the claim where every device sees it first, MongoDB when reachable. Only the
+  /// device whose insert succeeds may fire; an insert that finds the claim taken loses.
+  async fn claim(&self, claim_id: &str, user_id: &str) -> bool {
+    if let Ok(Some(_)) = self.json_provider.find_by_id(CLAIMS_TABLE, claim_id).await {
+      return false;
+    }
+    let now = Utc::now();
+    let Ok(value) = serde_json::to_value(ReminderClaimEntity {
+      id: Some(claim_id.to_string()),
+      user_id: user_id.to_string(),
+      created_at: Some(now),
+      updated_at: Some(now),
+    }) else {
+      return false;
+    };
+    if let Some(mongo) = &self.mongodb_provider {
+      match mongo.insert(CLAIMS_TABLE, value.clone()).await {
+        Ok(_) => {}
+        Err(OrmError::Duplicate(_)) => return false,
+        // Unreachable: the local claim below still keeps this device from firing twice.
+        Err(_) => {}
+      }
+    }
+    self.json_provider.insert(CLAIMS_TABLE, value).await.is_ok()
+  }
+  async fn fire(
+    &self,
+    id: &str,
+    task: &Value,
+    user_id: &str,
+    trigger: ReminderTrigger,
+    due_at: &str,
+    refire: bool,
+  ) -> bool {
+    let task_id = str_field(task, "id");
+    let kind = match trigger {
+      ReminderTrigger::Overdue => NotificationKind::Overdue,
+      ReminderTrigger::Before(_) => NotificationKind::DueSoon,
+    };
+    let notification = self
+      .notification_service
+      .notify(
+        user_id,
+        kind,
+        None,
+        NotificationTarget::new("tasks", task_id),
+        json!({
+          "reminder_id": id,
+          "task_title": task.get("title"),
+          "todo_id": task.get("todo_id"),
+          "due_at": due_at,
+          "offset": trigger.offset(),
+          "snoozed": refire,
+        }),
+      )
+      .await;
+    let now = Utc::now();
+    let state = ReminderStateEntity {
+      id: Some(id.to_string()),
+      task_id: task_id.to_string(),
+      user_id: user_id.to_string(),
+      offset: trigger.offset(),
+      due_at: due_at.to_string(),
+      status: ReminderStatus::Fired,
+      fired_at: now.to_rfc3339(),
+      snoozed_until: None,
+      notification_id: notification
+        .as_ref()
+        .and_then(|n| n.get("id"))
+        .and_then(|v| v.as_str())
+        .map(String::from),
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let Ok(value) = serde_json::to_value(&state) else {
+      return false;
+    };
+    self.store(STATES_TABLE, value).await.is_ok()
+  }
+  async fn set_status(
+    &self,
+    reminder_id: &str,
+    user_id: &str,
+    status: ReminderStatus,
+    snoozed_until: Option<String>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let mut state = self
+      .find_doc(STATES_TABLE, reminder_id)
+      .await
+      .ok_or_else(|| err_response("Reminder not found"))?;
+    if str_field(&state, "user_id") != user_id {
+      return Err(ResponseModel::forbidden(
+        "This reminder belongs to someone else",
+      ));
+    }
+    state["status"] = json!(status);
+    state["snoozed_until"] = json!(snoozed_until);
+    state["updated_at"] = json!(Utc::now().to_rfc3339());
+    Ok(success_response(self.store(STATES_TABLE, state).await?))
+  }
+  /// The task owner and the todo's assignees who can still see the todo, as user ids.
+  async fn recipients(&self, task: &Value, todo: &Value) -> Vec<String> {
+    let mut candidates = vec![str_field(task, "user_id").to_string()];
+    candidates.extend(string_list(todo, "assignees"));
+    let mut resolved: Vec<(String, Option<String>)> = Vec::new();
+    for candidate in candidates {
+      let user_id = self.notification_service.resolve_user_id(&candidate).await;
+      let profile_id = profile::find_profile_id(
+        &self.json_provider,
+        self.mongodb_provider.as_ref(),
+        &user_id,
+      )
+      .await;
+      resolved.push((user_id, profile_id));
+    }
+    visible_recipients(todo, &resolved)
+  }
+  async fn settings_for(&self, user_id: &str) -> (Vec<i64>, bool) {
+    self
+      .find_doc(SETTINGS_TABLE, user_id)
+      .await
+      .and_then(|doc| serde_json::from_value::<ReminderSettingsEntity>(doc).ok())
+      .map(|s| (s.default_offsets, s.overdue))
+      .unwrap_or_else(|| (reminders::DEFAULT_OFFSETS.to_vec(), true))
+  }
+  async fn task_offsets(&self, task_id: &str) -> Vec<i64> {
+    self
+      .find_doc(TASK_REMINDERS_TABLE, task_id)
+      .await
+      .and_then(|doc| serde_json::from_value::<TaskReminderEntity>(doc).ok())
+      .map(|r| r.offsets)
+      .unwrap_or_default()
+  }
+  async fn find_task(&self, task_id: &str) -> Result<(Value, Value), ResponseModel> {
+    let task = self
+      .find_doc("tasks", task_id)
+      .await
+      .filter(is_live)
+      .ok_or_else(|| err_response("Task not found"))?;
+    let todo = self
+      .find_doc("todos", str_field(&task, "todo_id"))
+      .await
+      .filter(is_live)
+      .ok_or_else(|| err_response("Todo not found"))?;
+    Ok((task, todo))
+  }
+  /// The most recently updated of the local and cloud copies.
+  async fn find_doc(&self, table: &str, id: &str) -> Option<Value> {
+    if id.is_empty() {
+      return None;
+    }
+    let local = self
+      .json_provider
+      .find_by_id(table, id)
+      .await
+      .ok()
+      .flatten();
+    let cloud = match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    };
+    match (local, cloud) {
+      (Some(local), Some(cloud)) => Some(
+        if str_field(&cloud, "updated_at") > str_field(&local, "updated_at") {
+          cloud
+        } else {
+          local
+        },
+      ),
+      (local, cloud) => local.or(cloud),
+    }
+  }
+  /// Matches from the local store and MongoDB, de-duplicated by id.
+  async fn find_all(&self, table: &str, filter: &Filter) -> Vec<Value> {
+    let mut docs = self
+      .json_provider
+      .find_many(table, Some(filter), None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      for doc in mongo
+        .find_many(table, Some(filter), None, None, None, true)
+        .await
+        .unwrap_or_default()
+      {
+        if !docs.iter().any(|d| d.get("id") == doc.get("id")) {
+          docs.push(doc);
+        }
+      }
+    }
+    docs
+  }
+  /// Upserts locally and, when reachable, in MongoDB so other devices see the same state.
+  async fn store(&self, table: &str, doc: Value) -> Result<Value, ResponseModel> {
+    let id = str_field(&doc, "id").to_string();
+    let saved = match self.json_provider.find_by_id(table, &id).await? {
+      Some(_) => self.json_provider.update(table, &id, doc.clone()).await?,
+      None => self.json_provider.insert(table, doc.clone()).await?,
+    };
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = match mongo.find_by_id(table, &id).await {
+        Ok(Some(_)) => mongo.update(table, &id, doc).await,
+        Ok(None) => mongo.insert(table, doc).await,
+        Err(e) => Err(e),
+      };
+    }
+    Ok(saved)
+  }
+}
+/// De-duplicated user ids, from `(user id, profile id)` pairs, of those who can see the todo.
+fn visible_recipients(todo: &Value, candidates: &[(String, Option<String>)]) -> Vec<String> {
+  let mut users: Vec<String> = Vec::new();
+  for (user_id, profile_id) in candidates {
+    if !user_id.is_empty()
+      && !users.contains(user_id)
+      && PermissionService::can_view_todo_with_profile(todo, user_id, profile_id.as_deref())
+    {
+      users.push(user_id.clone());
+    }
+  }
+  users
+}
+fn snooze_ended(state: &Value, now: &str) -> bool {
+  state.get("status") == Some(&json!(ReminderStatus::Snoozed))
+    && state
+      .get("snoozed_until")
+      .and_then(|v| v.as_str())
+      .map(|until| until <= now)
+      .unwrap_or(false)
+}
+fn is_live(doc: &Value) -> bool {
+  doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true)
+}
+fn str_field<'a>(doc: &'a Value, field: &str) -> &'a str {
+  doc.get(field).and_then(|v| v.as_str()).unwrap_or("")
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn assignees_of_shared_todos_are_reminded_by_profile_id() {
+    let todo = json!({
+      "user_id": "owner",
+      "visibility": "shared",
+      "assignees": ["profile-b"],
+    });
+    let candidates = vec![
+      ("owner".to_string(), Some("profile-a".to_string())),
+      ("user-b".to_string(), Some("profile-b".to_string())),
+      ("user-c".to_string(), None),
+      ("owner".to_string(), Some("profile-a".to_string())),
+    ];
+    assert_eq!(
+      visible_recipients(&todo, &candidates),
+      vec!["owner".to_string(), "user-b".to_string()]
+    );
+    let private =
+      json!({ "user_id": "owner", "visibility": "private", "assignees": ["profile-b"] });
+    assert_eq!(
+      visible_recipients(&private, &candidates),
+      vec!["owner".to_string()]
+    );
+  }
+}
@@ -11,14 +11,19 @@ use crate::repositories::mongodb_provider::MongoProvider;
 use crate::services::activity_monitor_service::ActivityMonitorService;
 use crate::services::cascade::{CascadeService, CountService};
 use crate::services::entity_resolution_service::EntityResolutionService;
+use crate::services::mention_service::{self, MentionService};
+use crate::services::notification_service::NotificationService;
 use crate::services::permission_service::PermissionService;
 use crate::services::profile_service::ProfileService;
+use crate::services::watcher_service::WatcherService;
+use crate::services::webhook_service::WebhookService;
 use crate::utils::{
+  chat_search,
   load_param::parse_load_param,
   relation_stripper::strip_relation_fields,
   response_helper::{err_response, err_response_formatted, success_response},
   security::security_projection,
-  user_sync,
+  user_sync, webhooks,
 };
 use nosql_orm::cache::QueryCache;
 use nosql_orm::provider::DatabaseProvider;
@@ -37,6 +42,10 @@ pub struct RepositoryService {
   pub activity_monitor: ActivityMonitorService,
   pub profile_service: ProfileService,
   pub entity_resolution: Arc<EntityResolutionService>,
+  notification_service: Option<Arc<NotificationService>>,
+  mention_service: Option<Arc<MentionService>>,
+  watcher_service: Option<Arc<WatcherService>>,
+  webhook_service: Option<Arc<WebhookService>>,
   app_handle: tauri::AppHandle,
   spawned_handles: RwLock<Vec<tokio::task::JoinHandle<()>>>,
 }
@@ -69,6 +78,10 @@ impl RepositoryService {
       activity_monitor,
       profile_service,
       entity_resolution,
+      notification_service: None,
+      mention_service: None,
+      watcher_service: None,
+      webhook_service: None,
       app_handle,
       spawned_handles: RwLock::new(Vec::new()),
     }
@@ -78,6 +91,40 @@ impl RepositoryService {
     self.cache_service = new_cache_service;
     self
   }
+  pub fn with_notifications(mut self, notification_service: Arc<NotificationService>) -> Self {
+    self.notification_service = Some(notification_service);
+    self
+  }
+  pub fn with_mentions(mut self, mention_service: Arc<MentionService>) -> Self {
+    self.mention_service = Some(mention_service);
+    self
+  }
+  pub fn with_watchers(mut self, watcher_service: Arc<WatcherService>) -> Self {
+    self.watcher_service = Some(watcher_service);
+    self
+  }
+  pub fn with_webhooks(mut self, webhook_service: Arc<WebhookService>) -> Self {
+    self.webhook_service = Some(webhook_service);
+    self
+  }
+  /// Stores the comment's `@username` mentions, resolved against people who can see its todo.
+  async fn apply_comment_mentions(&self, comment: &mut Value) {
+    let Some(mentions) = &self.mention_service else {
+      return;
+    };
+    let content = comment
+      .get("content")
+      .and_then(|v| v.as_str())
+      .unwrap_or("")
+      .to_string();
+    let resolved = match mentions.todo_for_comment(comment).await {
+      Some(todo) => mentions.resolve_for_todo(&content, &todo).await,
+      None => Vec::new(),
+    };
+    let (mentions, ids) = mention_service::mention_fields(&resolved);
+    comment["mentions"] = mentions;
+    comment["mention_ids"] = ids;
+  }
   fn emit_db_change_event(&self, operation: &str, table: &str, data: &serde_json::Value) {
     let event_name = format!("db-change-{}", table);
     let payload = serde_json::json!({
@@ -86,6 +133,27 @@ impl RepositoryService {
     });
     if let Err(_e) = self.app_handle.emit(&event_name, payload) {}
   }
+  /// Queues and sends the webhook events a create (`old` is `None`) or update raised, in the
+  /// background so the change itself is not held up by slow receivers.
+  fn dispatch_webhooks(&self, table: &str, old: Option<&Value>, record: &Value) {
+    let Some(webhook_service) = self.webhook_service.clone() else {
+      return;
+    };
+    if webhooks::events_for(table, old, record).is_empty() {
+      return;
+    }
+    let table = table.to_string();
+    let old = old.cloned();
+    let record = record.clone();
+    let handle = tokio::spawn(async move {
+      webhook_service
+        .on_record_changed(&table, old.as_ref(), &record)
+        .await;
+    });
+    if let Ok(mut handles) = self.spawned_handles.write() {
+      handles.push(handle);
+    }
+  }
   pub async fn execute(
     &self,
     operation: String,
@@ -409,15 +477,19 @@ impl RepositoryService {
             }
           } else if table == "chats" {
             let uid = user_id.as_deref().unwrap_or("");
+            let rooms = provider
+              .find_many("rooms", None, None, None, None, true)
+              .await
+              .unwrap_or_default();
+            let groups = provider
+              .find_many("groups", None, None, None, None, true)
+              .await
+              .unwrap_or_default();
+            let room_ids = chat_search::room_ids_for_user(&rooms, &groups, uid);
             serde_json::json!({
               "$and": [
-                { "sender_id": uid },
-                {
-                  "$or": [
-                    { "title": search_regex },
-                    { "message": search_regex }
-                  ]
-                }
+                { "room_id": { "$in": room_ids } },
+                { "content": search_regex }
               ]
             })
           } else {
@@ -895,8 +967,11 @@ impl RepositoryService {
         }
       }
     }
-    let validated_data = validate_model(&table, &data_val, true, Some(visibility_str.clone()))
+    let mut validated_data = validate_model(&table, &data_val, true, Some(visibility_str.clone()))
       .map_err(|e| err_response_formatted("Validation failed", &e))?;
+    if table == "comments" {
+      self.apply_comment_mentions(&mut validated_data).await;
+    }
     let created_record = provider.insert(&table, validated_data).await?;
     self.cache_service.invalidate_collection(&table).await;
     if table == "profiles" {
@@ -979,6 +1054,14 @@ impl RepositoryService {
       .activity_monitor
       .log_action(&table, "create", &created_record, None)
       .await;
+    if let Some(watchers) = &self.watcher_service {
+      watchers.on_record_created(&table, &created_record).await;
+    }
+    if let Some(notifications) = &self.notification_service {
+      notifications
+        .on_record_created(&table, &created_record, user_id.as_deref())
+        .await;
+    }
     let should_publish_to_github = table == "tasks"
       && created_record
         .get("publish_to_github")
@@ -1005,6 +1088,7 @@ impl RepositoryService {
     let response_doc = projection.apply_recursive(&final_record);
     let _ = start.elapsed();
     self.emit_db_change_event("created", &table, &response_doc);
+    self.dispatch_webhooks(&table, None, &response_doc);
     Ok(success_response(serde_json::json!(response_doc)))
   }
   async fn handle_github_sync_comment(
@@ -1244,6 +1328,7 @@ impl RepositoryService {
       .ok_or_else(|| err_response("Data must be an array for updateAll"))?
       .clone();
     let mut validated_records: Vec<Value> = Vec::with_capacity(raw_records.len());
+    let mut existing_records: Vec<Option<Value>> = Vec::with_capacity(raw_records.len());
     let visibility_str = resolve_visibility_for_offline(visibility.clone());
     let provider = get_provider_for_table(
       &self.json_provider,
@@ -1259,16 +1344,24 @@ impl RepositoryService {
           let mut validated_with_immutable = validated;
           merge_immutable_fields(&existing, &mut validated_with_immutable);
           validated_records.push(validated_with_immutable);
+          existing_records.push(Some(existing));
         } else {
           validated_records.push(validated);
+          existing_records.push(None);
         }
       } else {
         validated_records.push(validated);
+        existing_records.push(None);
       }
     }
-    for record in &validated_records {
+    let projection = security_projection();
+    for (record, existing) in validated_records.iter().zip(&existing_records) {
       if let Some(id) = record.get("id").and_then(|v| v.as_str()) {
-        if let Err(_e) = provider.update(&table, id, record.clone()).await {}
+        if provider.update(&table, id, record.clone()).await.is_ok() {
+          if let Some(existing) = existing {
+            self.dispatch_webhooks(&table, Some(existing), &projection.apply_recursive(record));
+          }
+        }
       }
     }
     let projected_records = apply_projection_recursive(validated_records);
@@ -1411,6 +1504,16 @@ impl RepositoryService {
     } else {
       validated_data.clone()
     };
+    if table == "comments" {
+      if validated_data.get("content").is_some() {
+        self.apply_comment_mentions(&mut merged_data).await;
+      } else {
+        // Mentions only come from the content, never from the client
+        for field in ["mentions", "mention_ids"] {
+          merged_data[field] = existing_record.get(field).cloned().unwrap_or(json!([]));
+        }
+      }
+    }
     if table == "todos" {
       let is_sharing =
         new_visibility.as_deref() == Some("shared") || new_visibility.as_deref() == Some("public");
@@ -1570,8 +1673,24 @@ impl RepositoryService {
       .activity_monitor
       .log_action(&table, "update", &updated_record, None)
       .await;
+    if let Some(watchers) = &self.watcher_service {
+      watchers
+        .on_record_updated(&table, &existing_record, &updated_record)
+        .await;
+    }
+    if let Some(notifications) = &self.notification_service {
+      notifications
+        .on_record_updated(
+          &table,
+          &existing_record,
+          &updated_record,
+          user_id.as_deref(),
+        )
+        .await;
+    }
     let projection = security_projection();
     let response_doc = projection.apply_recursive(&updated_record);
+    self.dispatch_webhooks(&table, Some(&existing_record), &response_doc);
     Ok(success_response(serde_json::json!(response_doc)))
   }
   async fn handle_delete(
@@ -0,0 +1,448 @@
+/* sys lib */
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+/* nosql_orm */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::security_event_entity::SecurityEventEntity;
+use crate::models::response::{ResponseModel, ResponseStatus};
+/* helpers */
+use crate::utils::response_helper::{err_response, success_response};
+const SECURITY_EVENTS_TABLE: &str = "security_events";
+const EXPORT_LIMIT: usize = 10_000;
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SecurityEventQuery {
+  #[serde(default)]
+  pub user_id: Option<String>,
+  #[serde(default)]
+  pub event_types: Option<Vec<String>>,
+  /// RFC 3339 timestamp or `YYYY-MM-DD`
+  #[serde(default)]
+  pub from: Option<String>,
+  /// RFC 3339 timestamp or `YYYY-MM-DD` (inclusive)
+  #[serde(default)]
+  pub to: Option<String>,
+  #[serde(default)]
+  pub skip: Option<u64>,
+  #[serde(default)]
+  pub limit: Option<u64>,
+}
+pub struct SecurityAuditService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  device: String,
+}
+impl SecurityAuditService {
+  pub fn new(json_provider: JsonProvider, mongodb_provider: Option<Arc<MongoProvider>>) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+      device: Self::detect_device(),
+    }
+  }
+  fn detect_device() -> String {
+    let hostname = std::env::var("HOSTNAME")
+      .or_else(|_| std::env::var("COMPUTERNAME"))
+      .ok()
+      .or_else(|| {
+        std::fs::read_to_string("/etc/hostname")
+          .ok()
+          .map(|s| s.trim().to_string())
+      })
+      .filter(|s| !s.is_empty())
+      .unwrap_or_else(|| "unknown-host".to_string());
+    format!(
+      "{} ({}/{})",
+      hostname,
+      std::env::consts::OS,
+      std::env::consts::ARCH
+    )
+  }
+  /// Appends an event. Failures never propagate to the audited operation.
+  pub async fn record(&self, mut event: SecurityEventEntity) {
+    event.id = Some(nosql_orm::utils::generate_id());
+    event.device = self.device.clone();
+    let Ok(event_val) = serde_json::to_value(&event) else {
+      return;
+    };
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = mongo.insert(SECURITY_EVENTS_TABLE, event_val.clone()).await;
+    }
+    let _ = self
+      .json_provider
+      .insert(SECURITY_EVENTS_TABLE, event_val)
+      .await;
+  }
+  /// Records `event` with its success flag and error message taken from an operation result.
+  pub async fn record_outcome(
+    &self,
+    mut event: SecurityEventEntity,
+    result: &Result<ResponseModel, ResponseModel>,
+  ) {
+    event.success = result.is_ok();
+    if let Err(err) = result {
+      match event.details {
+        Value::Object(ref mut map) => {
+          map.insert("error".to_string(), json!(err.message));
+        }
+        _ => event.details = json!({ "error": err.message }),
+      }
+    }
+    self.record(event).await;
+  }
+  /// Looks up a user id by a unique field such as `username` or `email`.
+  pub async fn resolve_user_id(&self, field: &str, value: &str) -> Option<String> {
+    if value.is_empty() {
+      return None;
+    }
+    let filter = Filter::Eq(field.to_string(), json!(value));
+    if let Ok(users) = self
+      .json_provider
+      .find_many("users", Some(&filter), None, Some(1), None, true)
+      .await
+    {
+      if let Some(id) = users
+        .first()
+        .and_then(|u| u.get("id"))
+        .and_then(|v| v.as_str())
+      {
+        return Some(id.to_string());
+      }
+    }
+    if let Some(mongo) = &self.mongodb_provider {
+      if let Ok(users) = mongo
+        .find_many("users", Some(&filter), None, Some(1), None, true)
+        .await
+      {
+        return users
+          .first()
+          .and_then(|u| u.get("id"))
+          .and_then(|v| v.as_str())
+          .map(|s| s.to_string());
+      }
+    }
+    None
+  }
+  pub async fn query(
+    &self,
+    requester_id: &str,
+    is_admin: bool,
+    query: SecurityEventQuery,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let skip = query.skip.unwrap_or(0) as usize;
+    let limit = query.limit.unwrap_or(50) as usize;
+    let events = self.load_events(requester_id, is_admin, &query).await?;
+    let total = events.len();
+    let page: Vec<Value> = events.into_iter().skip(skip).take(limit).collect();
+    Ok(success_response(json!({
+      "events": page,
+      "total": total,
+      "skip": skip,
+      "limit": limit
+    })))
+  }
+  pub async fn export(
+    &self,
+    requester_id: &str,
+    is_admin: bool,
+    query: SecurityEventQuery,
+    format: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let mut events = self.load_events(requester_id, is_admin, &query).await?;
+    events.truncate(EXPORT_LIMIT);
+    let date = Utc::now().format("%Y%m%d-%H%M%S");
+    let (content, filename) = match format {
+      "csv" => (
+        Self::events_to_csv(&events),
+        format!("security-events-{}.csv", date),
+      ),
+      "json" => (
+        serde_json::to_string_pretty(&events)
+          .map_err(|e| err_response(&format!("Failed to serialize events: {}", e)))?,
+        format!("security-events-{}.json", date),
+      ),
+      other => {
+        return Err(err_response(&format!(
+          "Unsupported export format '{}'. Use 'json' or 'csv'",
+          other
+        )))
+      }
+    };
+    Ok(ResponseModel {
+      status: ResponseStatus::Success,
+      message: format!("{} security events exported", events.len()),
+      data: json!({
+        "filename": filename,
+        "format": format,
+        "count": events.len(),
+        "content": content
+      }),
+    })
+  }
+  async fn load_events(
+    &self,
+    requester_id: &str,
+    is_admin: bool,
+    query: &SecurityEventQuery,
+  ) -> Result<Vec<Value>, ResponseModel> {
+    let user_filter = match (&query.user_id, is_admin) {
+      (Some(uid), true) if !uid.is_empty() => Some(uid.clone()),
+      (_, true) => None,
+      (Some(uid), false) if !uid.is_empty() && uid != requester_id => {
+        return Err(ResponseModel::forbidden(
+          "Only admins can view other users' security events",
+        ));
+      }
+      (_, false) => Some(requester_id.to_string()),
+    };
+    let filter = Self::build_filter(user_filter, query)?;
+    let mut docs = self
+      .json_provider
+      .find_many(
+        SECURITY_EVENTS_TABLE,
+        filter.as_ref(),
+        None,
+        None,
+        None,
+        true,
+      )
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      if let Ok(cloud) = mongo
+        .find_many(
+          SECURITY_EVENTS_TABLE,
+          filter.as_ref(),
+          None,
+          None,
+          None,
+          true,
+        )
+        .await
+      {
+        docs.extend(cloud);
+      }
+    }
+    // Events recorded online live in both stores
+    let mut seen: HashSet<String> = HashSet::new();
+    docs.retain(|doc| match doc.get("id").and_then(|v| v.as_str()) {
+      Some(id) => seen.insert(id.to_string()),
+      None => true,
+    });
+    docs.sort_by_key(|doc| {
+      std::cmp::Reverse(doc.get("timestamp").and_then(|v| v.as_i64()).unwrap_or(0))
+    });
+    Ok(docs)
+  }
+  fn build_filter(
+    user_id: Option<String>,
+    query: &SecurityEventQuery,
+  ) -> Result<Option<Filter>, ResponseModel> {
+    let mut filters: Vec<Filter> = Vec::new();
+    if let Some(uid) = user_id {
+      filters.push(Filter::Eq("user_id".to_string(), json!(uid)));
+    }
+    if let Some(types) = query.event_types.as_ref().filter(|t| !t.is_empty()) {
+      filters.push(Filter::In(
+        "event_type".to_string(),
+        types.iter().map(|t| json!(t)).collect(),
+      ));
+    }
+    if let Some(from) = query.from.as_deref().filter(|s| !s.is_empty()) {
+      let from = parse_boundary(from, false)?;
+      filters.push(Filter::Gte("timestamp".to_string(), json!(from)));
+    }
+    if let Some(to) = query.to.as_deref().filter(|s| !s.is_empty()) {
+      let to = parse_boundary(to, true)?;
+      filters.push(Filter::Lte("timestamp".to_string(), json!(to)));
+    }
+    Ok(match filters.len() {
+      0 => None,
+      1 => filters.pop(),
+      _ => Some(Filter::And(filters)),
+    })
+  }
+  fn events_to_csv(events: &[Value]) -> String {
+    let columns = [
+      "timestamp",
+      "event_type",
+      "success",
+      "user_id",
+      "actor_id",
+      "target_type",
+      "target_id",
+      "device",
+      "details",
+    ];
+    let mut csv = columns.join(",");
+    csv.push('\n');
+    for event in events {
+      let row: Vec<String> = columns
+        .iter()
+        .map(|col| {
+          let value = match (*col, event.get(*col)) {
+            ("timestamp", Some(v)) => v
+              .as_i64()
+              .and_then(DateTime::<Utc>::from_timestamp_millis)
+              .map(|dt| dt.to_rfc3339())
+              .unwrap_or_default(),
+            (_, Some(Value::String(s))) => s.clone(),
+            (_, Some(Value::Null)) | (_, None) => String::new(),
+            (_, Some(v)) => v.to_string(),
+          };
+          csv_escape(&value)
+        })
+        .collect();
+      csv.push_str(&row.join(","));
+      csv.push('\n');
+    }
+    csv
+  }
+}
+/// Parses a date boundary into unix milliseconds. Date-only upper bounds cover the whole day.
+fn parse_boundary(value: &str, end_of_day: bool) -> Result<i64, ResponseModel> {
+  if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+    return Ok(dt.timestamp_millis());
+  }
+  let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+    .map_err(|_| err_response(&format!("Invalid date '{}'", value)))?;
+  let time = if end_of_day {
+    date.and_hms_milli_opt(23, 59, 59, 999)
+  } else {
+    date.and_hms_opt(0, 0, 0)
+  };
+  time
+    .map(|t| t.and_utc().timestamp_millis())
+    .ok_or_else(|| err_response(&format!("Invalid date '{}'", value)))
+}
+fn csv_escape(value: &str) -> String {
+  if value.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_string()
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::entities::security_event_entity::SecurityEventType;
+  async fn service() -> (SecurityAuditService, tempfile::TempDir) {
+    let dir = tempfile::tempdir().unwrap();
+    let json_provider = JsonProvider::new(dir.path()).await.unwrap();
+    (SecurityAuditService::new(json_provider, None), dir)
+  }
+  async fn record_at(
+    service: &SecurityAuditService,
+    event_type: SecurityEventType,
+    user_id: &str,
+    timestamp: &str,
+  ) {
+    let mut event = SecurityEventEntity::new(event_type).user(Some(user_id));
+    event.timestamp = DateTime::parse_from_rfc3339(timestamp)
+      .unwrap()
+      .timestamp_millis();
+    service.record(event).await;
+  }
+  async fn seeded() -> (SecurityAuditService, tempfile::TempDir) {
+    let (service, dir) = service().await;
+    record_at(
+      &service,
+      SecurityEventType::Login,
+      "alice",
+      "2026-03-01T09:00:00Z",
+    )
+    .await;
+    record_at(
+      &service,
+      SecurityEventType::PasswordChanged,
+      "alice",
+      "2026-03-05T09:00:00Z",
+    )
+    .await;
+    record_at(
+      &service,
+      SecurityEventType::Login,
+      "bob",
+      "2026-03-03T09:00:00Z",
+    )
+    .await;
+    (service, dir)
+  }
+  fn users(response: &ResponseModel) -> Vec<String> {
+    response.data["events"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|e| e["user_id"].as_str().unwrap_or("").to_string())
+      .collect()
+  }
+  #[tokio::test]
+  async fn users_only_see_their_own_events() {
+    let (service, _dir) = seeded().await;
+    let own = service
+      .query("alice", false, SecurityEventQuery::default())
+      .await
+      .unwrap();
+    assert_eq!(users(&own), vec!["alice", "alice"]);
+    let other = SecurityEventQuery {
+      user_id: Some("bob".to_string()),
+      ..Default::default()
+    };
+    assert!(service.query("alice", false, other).await.is_err());
+  }
+  #[tokio::test]
+  async fn admins_see_everyones_events_and_can_filter_by_user() {
+    let (service, _dir) = seeded().await;
+    let all = service
+      .query("admin", true, SecurityEventQuery::default())
+      .await
+      .unwrap();
+    assert_eq!(users(&all), vec!["alice", "bob", "alice"]);
+    let bob = SecurityEventQuery {
+      user_id: Some("bob".to_string()),
+      ..Default::default()
+    };
+    let filtered = service.query("admin", true, bob).await.unwrap();
+    assert_eq!(users(&filtered), vec!["bob"]);
+  }
+  #[tokio::test]
+  async fn filters_by_type_and_date_range() {
+    let (service, _dir) = seeded().await;
+    let logins = SecurityEventQuery {
+      event_types: Some(vec!["login".to_string()]),
+      ..Default::default()
+    };
+    let response = service.query("admin", true, logins).await.unwrap();
+    assert_eq!(users(&response), vec!["bob", "alice"]);
+    let early_march = SecurityEventQuery {
+      from: Some("2026-03-01".to_string()),
+      to: Some("2026-03-03".to_string()),
+      ..Default::default()
+    };
+    let response = service.query("admin", true, early_march).await.unwrap();
+    assert_eq!(users(&response), vec!["bob", "alice"]);
+  }
+  #[test]
+  fn csv_export_escapes_values() {
+    let events = vec![json!({
+      "timestamp": 0,
+      "event_type": "login",
+      "success": false,
+      "user_id": "alice",
+      "device": "desk, \"home\"",
+      "details": { "error": "line\nbreak" },
+    })];
+    let csv = SecurityAuditService::events_to_csv(&events);
+    let row = csv.lines().nth(1).unwrap();
+    assert!(row.starts_with("1970-01-01T00:00:00+00:00,login,false,alice,,,,"));
+    assert!(row.contains("\"desk, \"\"home\"\"\""));
+    assert!(csv.contains("\"{\"\"error\"\":\"\"line\\nbreak\"\"}\""));
+    assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+    assert_eq!(csv_escape("plain"), "plain");
+  }
+}
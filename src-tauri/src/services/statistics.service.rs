@@ -5,8 +5,11 @@ use serde_json::{json, Value};
 use nosql_orm::prelude::{DatabaseProvider, Filter};
 use nosql_orm::providers::JsonProvider;
 /* models */
+use crate::entities::daily_activity_entity::DailyActivityModel;
 use crate::entities::statistics_entity::StatisticsResponseModel;
 use crate::models::response::{ResponseModel, ResponseStatus};
+/* services */
+use crate::services::permission_service::PermissionService;
 /* helpers */
 use crate::utils::response_helper::err_response;
 /* statistics modules */
@@ -56,11 +59,7 @@ impl StatisticsService {
     let end_str = end_date.to_rfc3339();
     let prev_start_str = previous_start_date.to_rfc3339();
     let _prev_end_str = previous_end_date.to_rfc3339();
-    let all_tasks: Vec<Value> = self
-      .json_provider
-      .find_many("tasks", None, None, None, None, true)
-      .await
-      .unwrap_or_default();
+    let all_tasks = self.get_todo_tasks(&todo_ids).await;
     let current_tasks: Vec<Value> = all_tasks
       .iter()
       .filter(|task| {
@@ -143,6 +142,48 @@ impl StatisticsService {
       .unwrap(),
     })
   }
+  /// Todos the user owns or is assigned to, whatever their visibility.
+  pub async fn get_visible_todos(&self, user_id: &str, profile_id: Option<&str>) -> Vec<Value> {
+    let Ok(filter) = Filter::from_json(&PermissionService::get_todo_filter_for_user(
+      user_id,
+      profile_id,
+      Some("all"),
+    )) else {
+      return Vec::new();
+    };
+    self
+      .json_provider
+      .find_many("todos", Some(&filter), None, None, None, true)
+      .await
+      .unwrap_or_default()
+  }
+  pub async fn get_todo_tasks(&self, todo_ids: &[String]) -> Vec<Value> {
+    if todo_ids.is_empty() {
+      return Vec::new();
+    }
+    let filter = Filter::In(
+      "todo_id".to_string(),
+      todo_ids.iter().map(|id| json!(id)).collect(),
+    );
+    self
+      .json_provider
+      .find_many("tasks", Some(&filter), None, None, None, true)
+      .await
+      .unwrap_or_default()
+  }
+  /// The user's activity record for one day, if anything was logged.
+  pub async fn get_daily_activity(
+    &self,
+    user_id: &str,
+    date: &NaiveDate,
+  ) -> Option<DailyActivityModel> {
+    self
+      .get_daily_activities_filtered(user_id, date, date)
+      .await
+      .into_iter()
+      .next()
+      .and_then(|doc| serde_json::from_value(doc).ok())
+  }
   async fn get_daily_activities_filtered(
     &self,
     user_id: &str,
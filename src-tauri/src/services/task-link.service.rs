@@ -0,0 +1,252 @@
+/* sys lib */
+use serde_json::{json, Value};
+use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::chat_entity::ChatTaskRef;
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::permission_service::PermissionService;
+/* helpers */
+use crate::utils::profile;
+use crate::utils::response_helper::{err_response, success_response};
+use crate::utils::task_refs::{self, TaskRef};
+const BACKLINK_EXCERPT_CHARS: usize = 160;
+/// Resolves task references written in chat messages and serves their previews and backlinks.
+/// Every lookup is checked against the todo's permissions for the user asking.
+pub struct TaskLinkService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+}
+impl TaskLinkService {
+  pub fn new(json_provider: JsonProvider, mongodb_provider: Option<Arc<MongoProvider>>) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+    }
+  }
+  /// References in `content` that resolve to a task the sender can see.
+  pub async fn resolve(&self, content: &str, sender_id: &str) -> Vec<ChatTaskRef> {
+    let mut resolved: Vec<ChatTaskRef> = Vec::new();
+    for task_ref in task_refs::parse_task_refs(content) {
+      let task = match &task_ref {
+        TaskRef::Id { task_id, .. } => self.find_doc("tasks", task_id).await,
+        TaskRef::Slug { slug, position, .. } => self.find_by_slug(slug, *position, sender_id).await,
+      };
+      let Some(task) = task.filter(is_live) else {
+        continue;
+      };
+      let Some((task_id, todo_id)) = ids_of(&task) else {
+        continue;
+      };
+      if !self.can_view(&todo_id, sender_id).await {
+        continue;
+      }
+      if resolved.iter().any(|r| r.task_id == task_id) {
+        continue;
+      }
+      resolved.push(ChatTaskRef {
+        raw: task_ref.raw().to_string(),
+        task_id,
+        todo_id,
+      });
+    }
+    resolved
+  }
+  /// Current previews for a message's references. Tasks the viewer cannot see, or that
+  /// are gone, come back as `accessible: false` with no details.
+  pub async fn previews(&self, refs: &[ChatTaskRef], viewer_id: &str) -> Vec<Value> {
+    let mut previews = Vec::new();
+    for task_ref in refs {
+      let task = self
+        .find_doc("tasks", &task_ref.task_id)
+        .await
+        .filter(is_live);
+      let mut todo = self
+        .find_doc("todos", &task_ref.todo_id)
+        .await
+        .filter(is_live);
+      if let Some(found) = &todo {
+        if !self.can_view_todo(found, viewer_id).await {
+          todo = None;
+        }
+      }
+      let preview = match (task, todo) {
+        (Some(task), Some(todo)) => {
+          json!({
+            "raw": task_ref.raw,
+            "task_id": task_ref.task_id,
+            "todo_id": task_ref.todo_id,
+            "accessible": true,
+            "title": task.get("title"),
+            "status": task.get("status"),
+            "priority": task.get("priority"),
+            "due_date": task.get("end_date"),
+            "assignees": todo.get("assignees"),
+            "todo_title": todo.get("title"),
+          })
+        }
+        _ => json!({
+          "raw": task_ref.raw,
+          "task_id": task_ref.task_id,
+          "accessible": false,
+        }),
+      };
+      previews.push(preview);
+    }
+    previews
+  }
+  /// Chat messages that reference the task, limited to rooms the viewer belongs to.
+  pub async fn backlinks(
+    &self,
+    task_id: &str,
+    viewer_id: &str,
+    room_ids: &[String],
+  ) -> Result<ResponseModel, ResponseModel> {
+    let task = self
+      .find_doc("tasks", task_id)
+      .await
+      .ok_or_else(|| err_response("Task not found"))?;
+    let (_, todo_id) = ids_of(&task).ok_or_else(|| err_response("Task not found"))?;
+    if !self.can_view(&todo_id, viewer_id).await {
+      return Err(ResponseModel::forbidden(
+        "You do not have access to this task",
+      ));
+    }
+    let filter = Filter::In("task_ref_ids".to_string(), vec![json!(task_id)]);
+    let mut messages = self
+      .json_provider
+      .find_many("chats", Some(&filter), None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      for doc in mongo
+        .find_many("chats", Some(&filter), None, None, None, true)
+        .await
+        .unwrap_or_default()
+      {
+        if !messages.iter().any(|m| m.get("id") == doc.get("id")) {
+          messages.push(doc);
+        }
+      }
+    }
+    let mut links: Vec<Value> = messages
+      .iter()
+      .filter(|m| is_live(m))
+      .filter(|m| {
+        let room_id = m.get("room_id").and_then(|v| v.as_str()).unwrap_or("");
+        room_ids.iter().any(|id| id == room_id)
+      })
+      .map(|m| {
+        let excerpt: String = m
+          .get("content")
+          .and_then(|v| v.as_str())
+          .unwrap_or("")
+          .chars()
+          .take(BACKLINK_EXCERPT_CHARS)
+          .collect();
+        json!({
+          "message_id": m.get("id"),
+          "room_id": m.get("room_id"),
+          "sender_id": m.get("sender_id"),
+          "created_at": m.get("created_at"),
+          "excerpt": excerpt,
+        })
+      })
+      .collect();
+    links.sort_by(|a, b| {
+      b["created_at"]
+        .as_str()
+        .unwrap_or("")
+        .cmp(a["created_at"].as_str().unwrap_or(""))
+    });
+    Ok(success_response(
+      json!({ "task_id": task_id, "backlinks": links }),
+    ))
+  }
+  async fn can_view(&self, todo_id: &str, user_id: &str) -> bool {
+    match self.find_doc("todos", todo_id).await.filter(is_live) {
+      Some(todo) => self.can_view_todo(&todo, user_id).await,
+      None => false,
+    }
+  }
+  /// Checks the user's profile id too, since assignees are stored as profile ids.
+  async fn can_view_todo(&self, todo: &Value, user_id: &str) -> bool {
+    let profile_id = self.profile_id_of(user_id).await;
+    PermissionService::can_view_todo_with_profile(todo, user_id, profile_id.as_deref())
+  }
+  async fn profile_id_of(&self, user_id: &str) -> Option<String> {
+    profile::find_profile_id(&self.json_provider, self.mongodb_provider.as_ref(), user_id).await
+  }
+  /// The `position`-th task (1-based, by `order`) of the only todo visible to the user
+  /// whose title slugifies to `slug`. Ambiguous slugs resolve to nothing.
+  async fn find_by_slug(&self, slug: &str, position: usize, user_id: &str) -> Option<Value> {
+    let profile_id = self.profile_id_of(user_id).await;
+    let filter = Filter::from_json(&PermissionService::get_todo_filter_for_user(
+      user_id,
+      profile_id.as_deref(),
+      Some("all"),
+    ))
+    .ok()?;
+    let todos = self.find_all("todos", &filter).await;
+    let mut matching = todos.iter().filter(|todo| {
+      is_live(todo)
+        && task_refs::slugify(todo.get("title").and_then(|v| v.as_str()).unwrap_or("")) == slug
+    });
+    let todo = matching.next()?;
+    if matching.next().is_some() {
+      return None;
+    }
+    let todo_id = todo.get("id").and_then(|v| v.as_str())?;
+    let task_filter = Filter::Eq("todo_id".to_string(), json!(todo_id));
+    let mut tasks: Vec<Value> = self
+      .find_all("tasks", &task_filter)
+      .await
+      .into_iter()
+      .filter(is_live)
+      .collect();
+    tasks.sort_by_key(|task| task.get("order").and_then(|v| v.as_i64()).unwrap_or(0));
+    tasks.into_iter().nth(position - 1)
+  }
+  async fn find_doc(&self, table: &str, id: &str) -> Option<Value> {
+    if let Ok(Some(doc)) = self.json_provider.find_by_id(table, id).await {
+      return Some(doc);
+    }
+    match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    }
+  }
+  /// Matches from the local store and MongoDB, de-duplicated by id.
+  async fn find_all(&self, table: &str, filter: &Filter) -> Vec<Value> {
+    let mut docs = self
+      .json_provider
+      .find_many(table, Some(filter), None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      for doc in mongo
+        .find_many(table, Some(filter), None, None, None, true)
+        .await
+        .unwrap_or_default()
+      {
+        if !docs.iter().any(|d| d.get("id") == doc.get("id")) {
+          docs.push(doc);
+        }
+      }
+    }
+    docs
+  }
+}
+fn is_live(doc: &Value) -> bool {
+  doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true)
+}
+fn ids_of(task: &Value) -> Option<(String, String)> {
+  let task_id = task.get("id").and_then(|v| v.as_str())?;
+  let todo_id = task.get("todo_id").and_then(|v| v.as_str())?;
+  Some((task_id.to_string(), todo_id.to_string()))
+}
@@ -0,0 +1,314 @@
+/* sys lib */
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::watcher_entity::{WatchSource, WatcherEntity};
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::permission_service::PermissionService;
+/* helpers */
+use crate::utils::notification_triggers;
+use crate::utils::profile;
+use crate::utils::response_helper::{err_response, success_response};
+const WATCHERS_TABLE: &str = "watchers";
+/// Who follows which todos and tasks. Anyone who can view an item may watch it; commenting
+/// on a task or being assigned to a todo watches it automatically unless the user unwatched
+/// it before. Watcher lists only ever contain people who can still view the todo.
+pub struct WatcherService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+}
+impl WatcherService {
+  pub fn new(json_provider: JsonProvider, mongodb_provider: Option<Arc<MongoProvider>>) -> Self {
+    Self {
+      json_provider,
+      mongodb_provider,
+    }
+  }
+  pub async fn watch(
+    &self,
+    entity: &str,
+    target_id: &str,
+    user_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let todo = self.todo_of(entity, target_id).await?;
+    if !self.can_view(&todo, user_id).await {
+      return Err(ResponseModel::forbidden(
+        "You do not have access to this item",
+      ));
+    }
+    let doc = self
+      .set_watch(entity, target_id, user_id, WatchSource::Manual, true)
+      .await?;
+    Ok(success_response(doc))
+  }
+  /// Stops watching. The record is kept, inactive, so auto-watching leaves the user alone.
+  pub async fn unwatch(
+    &self,
+    entity: &str,
+    target_id: &str,
+    user_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    self.todo_of(entity, target_id).await?;
+    let source = self
+      .find_doc(&WatcherEntity::id_for(entity, target_id, user_id))
+      .await
+      .and_then(|doc| serde_json::from_value::<WatcherEntity>(doc).ok())
+      .map(|watch| watch.source)
+      .unwrap_or_default();
+    let doc = self
+      .set_watch(entity, target_id, user_id, source, false)
+      .await?;
+    Ok(success_response(doc))
+  }
+  /// The item's watchers and whether the viewer is one of them.
+  pub async fn get_watchers(
+    &self,
+    entity: &str,
+    target_id: &str,
+    viewer_id: &str,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let todo = self.todo_of(entity, target_id).await?;
+    if !self.can_view(&todo, viewer_id).await {
+      return Err(ResponseModel::forbidden(
+        "You do not have access to this item",
+      ));
+    }
+    let watchers = self.watchers_of(entity, target_id).await;
+    Ok(success_response(json!({
+      "entity": entity,
+      "target_id": target_id,
+      "watching": watchers.iter().any(|id| id == viewer_id),
+      "watchers": watchers,
+    })))
+  }
+  /// Everything the user actively watches and can still view.
+  pub async fn get_watched(&self, user_id: &str) -> Result<ResponseModel, ResponseModel> {
+    let filter = Filter::And(vec![
+      Filter::Eq("user_id".to_string(), json!(user_id)),
+      Filter::Eq("active".to_string(), json!(true)),
+    ]);
+    let mut watched = Vec::new();
+    for watch in self.find_all(&filter).await {
+      let entity = str_field(&watch, "entity");
+      let target_id = str_field(&watch, "target_id");
+      if let Ok(todo) = self.todo_of(entity, target_id).await {
+        if self.can_view(&todo, user_id).await {
+          watched.push(watch);
+        }
+      }
+    }
+    Ok(success_response(json!(watched)))
+  }
+  /// Active watchers of the item who can still view its todo.
+  pub async fn watchers_of(&self, entity: &str, target_id: &str) -> Vec<String> {
+    let Ok(todo) = self.todo_of(entity, target_id).await else {
+      return Vec::new();
+    };
+    let filter = Filter::And(vec![
+      Filter::Eq("target_id".to_string(), json!(target_id)),
+      Filter::Eq("entity".to_string(), json!(entity)),
+      Filter::Eq("active".to_string(), json!(true)),
+    ]);
+    let mut watchers: Vec<String> = Vec::new();
+    for watch in self.find_all(&filter).await {
+      let user_id = str_field(&watch, "user_id").to_string();
+      if !watchers.contains(&user_id) && self.can_view(&todo, &user_id).await {
+        watchers.push(user_id);
+      }
+    }
+    watchers
+  }
+  /// Auto-watches that follow from a new record: commenters watch the task.
+  pub async fn on_record_created(&self, table: &str, record: &Value) {
+    match table {
+      "comments" => {
+        if let Some(task_id) = self.comment_task_id(record).await {
+          self
+            .auto_watch(
+              "tasks",
+              &task_id,
+              str_field(record, "user_id"),
+              WatchSource::Commented,
+            )
+            .await;
+        }
+      }
+      "todos" => self.watch_new_assignees(None, record).await,
+      _ => {}
+    }
+  }
+  /// Auto-watches that follow from an update: newly added assignees watch the todo.
+  pub async fn on_record_updated(&self, table: &str, old: &Value, new: &Value) {
+    if table == "todos" {
+      self.watch_new_assignees(Some(old), new).await;
+    }
+  }
+  async fn watch_new_assignees(&self, old: Option<&Value>, todo: &Value) {
+    let todo_id = str_field(todo, "id");
+    for assignee in notification_triggers::added_ids(old, todo, "assignees") {
+      let user_id = self.resolve_user_id(&assignee).await;
+      self
+        .auto_watch("todos", todo_id, &user_id, WatchSource::Assigned)
+        .await;
+    }
+  }
+  /// Watches for the user unless they already watch the item or chose to unwatch it.
+  async fn auto_watch(&self, entity: &str, target_id: &str, user_id: &str, source: WatchSource) {
+    if user_id.is_empty() || target_id.is_empty() {
+      return;
+    }
+    let id = WatcherEntity::id_for(entity, target_id, user_id);
+    if self.find_doc(&id).await.is_some() {
+      return;
+    }
+    let _ = self
+      .set_watch(entity, target_id, user_id, source, true)
+      .await;
+  }
+  async fn set_watch(
+    &self,
+    entity: &str,
+    target_id: &str,
+    user_id: &str,
+    source: WatchSource,
+    active: bool,
+  ) -> Result<Value, ResponseModel> {
+    let id = WatcherEntity::id_for(entity, target_id, user_id);
+    let now = Utc::now();
+    let created_at = self
+      .find_doc(&id)
+      .await
+      .and_then(|doc| serde_json::from_value::<WatcherEntity>(doc).ok())
+      .and_then(|watch| watch.created_at)
+      .unwrap_or(now);
+    let watch = WatcherEntity {
+      id: Some(id),
+      user_id: user_id.to_string(),
+      entity: entity.to_string(),
+      target_id: target_id.to_string(),
+      source,
+      active,
+      created_at: Some(created_at),
+      updated_at: Some(now),
+    };
+    let value = serde_json::to_value(&watch)
+      .map_err(|e| err_response(&format!("Failed to save watcher: {}", e)))?;
+    self.store(value).await
+  }
+  /// Checks the user's profile id too, since assignees are stored as profile ids.
+  async fn can_view(&self, todo: &Value, user_id: &str) -> bool {
+    let profile_id =
+      profile::find_profile_id(&self.json_provider, self.mongodb_provider.as_ref(), user_id).await;
+    PermissionService::can_view_todo_with_profile(todo, user_id, profile_id.as_deref())
+  }
+  /// The todo that decides who may see the item: the todo itself, or the task's todo.
+  async fn todo_of(&self, entity: &str, target_id: &str) -> Result<Value, ResponseModel> {
+    let todo_id = match entity {
+      "todos" => target_id.to_string(),
+      "tasks" => {
+        let task = self
+          .find_record("tasks", target_id)
+          .await
+          .filter(is_live)
+          .ok_or_else(|| err_response("Task not found"))?;
+        str_field(&task, "todo_id").to_string()
+      }
+      _ => return Err(err_response("Only todos and tasks can be watched")),
+    };
+    self
+      .find_record("todos", &todo_id)
+      .await
+      .filter(is_live)
+      .ok_or_else(|| err_response("Todo not found"))
+  }
+  async fn comment_task_id(&self, comment: &Value) -> Option<String> {
+    if let Some(task_id) = comment.get("task_id").and_then(|v| v.as_str()) {
+      return Some(task_id.to_string());
+    }
+    let subtask = self
+      .find_record("subtasks", str_field(comment, "subtask_id"))
+      .await?;
+    Some(str_field(&subtask, "task_id").to_string())
+  }
+  /// Assignees may be stored as profile ids; watchers are always users.
+  async fn resolve_user_id(&self, id: &str) -> String {
+    match self.find_record("profiles", id).await {
+      Some(profile) => str_field(&profile, "user_id").to_string(),
+      None => id.to_string(),
+    }
+  }
+  async fn find_doc(&self, id: &str) -> Option<Value> {
+    self.find_record(WATCHERS_TABLE, id).await
+  }
+  async fn find_record(&self, table: &str, id: &str) -> Option<Value> {
+    if id.is_empty() {
+      return None;
+    }
+    if let Ok(Some(doc)) = self.json_provider.find_by_id(table, id).await {
+      return Some(doc);
+    }
+    match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    }
+  }
+  /// Matches from the local store and MongoDB, de-duplicated by id.
+  async fn find_all(&self, filter: &Filter) -> Vec<Value> {
+    let mut docs = self
+      .json_provider
+      .find_many(WATCHERS_TABLE, Some(filter), None, None, None, true)
+      .await
+      .unwrap_or_default();
+    if let Some(mongo) = &self.mongodb_provider {
+      for doc in mongo
+        .find_many(WATCHERS_TABLE, Some(filter), None, None, None, true)
+        .await
+        .unwrap_or_default()
+      {
+        if !docs.iter().any(|d| d.get("id") == doc.get("id")) {
+          docs.push(doc);
+        }
+      }
+    }
+    docs
+  }
+  /// Upserts locally and, when reachable, in MongoDB so other devices see the same state.
+  async fn store(&self, doc: Value) -> Result<Value, ResponseModel> {
+    let id = str_field(&doc, "id").to_string();
+    let saved = match self.json_provider.find_by_id(WATCHERS_TABLE, &id).await? {
+      Some(_) => {
+        self
+          .json_provider
+          .update(WATCHERS_TABLE, &id, doc.clone())
+          .await?
+      }
+      None => {
+        self
+          .json_provider
+          .insert(WATCHERS_TABLE, doc.clone())
+          .await?
+      }
+    };
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = match mongo.find_by_id(WATCHERS_TABLE, &id).await {
+        Ok(Some(_)) => mongo.update(WATCHERS_TABLE, &id, doc).await,
+        Ok(None) => mongo.insert(WATCHERS_TABLE, doc).await,
+        Err(e) => Err(e),
+      };
+    }
+    Ok(saved)
+  }
+}
+fn is_live(doc: &Value) -> bool {
+  doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true)
+}
+fn str_field<'a>(doc: &'a Value, field: &str) -> &'a str {
+  doc.get(field).and_then(|v| v.as_str()).unwrap_or("")
+}
@@ -0,0 +1,622 @@
+/* sys lib */
+use chrono::{Duration, Utc};
+use data_encoding::HEXLOWER;
+use rand::RngCore;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
+/* entities */
+use crate::entities::webhook_entity::{
+  WebhookDeliveryEntity, WebhookDeliveryStatus, WebhookEntity, WebhookEvent, WebhookInput,
+  WebhookSecretEntity,
+};
+/* models */
+use crate::models::response::ResponseModel;
+/* services */
+use crate::services::permission_service::PermissionService;
+/* helpers */
+use crate::utils::profile;
+use crate::utils::response_helper::{err_response, success_response};
+use crate::utils::signed_in;
+use crate::utils::webhooks::{self, MAX_ATTEMPTS};
+const WEBHOOKS_TABLE: &str = "webhooks";
+const DELIVERIES_TABLE: &str = "webhook_deliveries";
+const SECRETS_TABLE: &str = "webhook_secrets";
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const MIN_SECRET_CHARS: usize = 16;
+/// Finished deliveries are dropped from the log after this many days.
+const LOG_RETENTION_DAYS: i64 = 30;
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+/// Sends data-change events to configured webhooks. Each request is signed with the webhook's
+/// secret in `X-TaskFlow-Signature`; failed requests are retried with backoff and every
+/// attempt is kept in the delivery log.
+///
+/// Subscriptions and the delivery log are stored in MongoDB, so they follow their owner
+/// across devices. The device where a change is made only queues its deliveries; the
+/// webhook owner's device sends them, so receivers on the owner's own network are reached
+/// too. Deliveries queued offline stay local until MongoDB is reachable again. Secrets live
+/// in their own table, which only the owner's devices read.
+pub struct WebhookService {
+  json_provider: JsonProvider,
+  mongodb_provider: Option<Arc<MongoProvider>>,
+  http_client: reqwest::Client,
+  /// Held while deliveries are sent, so a delivery is never picked up twice at once
+  delivering: Mutex<()>,
+}
+impl WebhookService {
+  pub fn new(json_provider: JsonProvider, mongodb_provider: Option<Arc<MongoProvider>>) -> Self {
+    let http_client = reqwest::Client::builder()
+      .user_agent("TaskFlow-Webhooks/1.0")
+      .timeout(REQUEST_TIMEOUT)
+      .redirect(reqwest::redirect::Policy::none())
+      .build()
+      .expect("Failed to create HTTP client");
+    Self {
+      json_provider,
+      mongodb_provider,
+      http_client,
+      delivering: Mutex::new(()),
+    }
+  }
+  /// The user's webhooks and, for admins, every global one.
+  pub async fn list(&self, user_id: &str, is_admin: bool) -> Result<ResponseModel, ResponseModel> {
+    let hooks: Vec<Value> = self
+      .find_webhooks(None)
+      .await
+      .into_iter()
+      .filter(|hook| can_manage(hook, user_id, is_admin))
+      .collect();
+    Ok(success_response(json!(hooks)))
+  }
+  /// Creates a webhook. The response is the only place its secret is shown.
+  pub async fn create(
+    &self,
+    user_id: &str,
+    is_admin: bool,
+    input: WebhookInput,
+  ) -> Result<ResponseModel, ResponseModel> {
+    validate(&input, is_admin)?;
+    let now = Utc::now();
+    let id = nosql_orm::utils::generate_id();
+    let hook = WebhookEntity {
+      id: Some(id.clone()),
+      user_id: user_id.to_string(),
+      url: input.url.trim().to_string(),
+      events: dedup_events(input.events),
+      active: input.active.unwrap_or(true),
+      global: input.global,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let secret = input.secret.unwrap_or_else(generate_secret);
+    self.store_secret(&id, user_id, &secret).await?;
+    let value = serde_json::to_value(&hook)
+      .map_err(|e| err_response(&format!("Failed to save webhook: {}", e)))?;
+    let mut saved = self.store(WEBHOOKS_TABLE, value).await?;
+    saved["secret"] = json!(secret);
+    Ok(success_response(saved))
+  }
+  pub async fn update(
+    &self,
+    id: &str,
+    user_id: &str,
+    is_admin: bool,
+    input: WebhookInput,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let mut hook = self.find_managed(id, user_id, is_admin).await?;
+    validate(&input, is_admin)?;
+    hook.url = input.url.trim().to_string();
+    hook.events = dedup_events(input.events);
+    if let Some(secret) = input.secret {
+      self.store_secret(id, &hook.user_id, &secret).await?;
+    }
+    if let Some(active) = input.active {
+      hook.active = active;
+    }
+    hook.global = input.global;
+    hook.updated_at = Some(Utc::now());
+    let value = serde_json::to_value(&hook)
+      .map_err(|e| err_response(&format!("Failed to save webhook: {}", e)))?;
+    Ok(success_response(self.store(WEBHOOKS_TABLE, value).await?))
+  }
+  /// Removes the webhook together with its delivery log.
+  pub async fn delete(
+    &self,
+    id: &str,
+    user_id: &str,
+    is_admin: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    self.find_managed(id, user_id, is_admin).await?;
+    self.remove(WEBHOOKS_TABLE, id).await;
+    self.remove(SECRETS_TABLE, id).await;
+    let filter = Filter::Eq("webhook_id".to_string(), json!(id));
+    for delivery in self.find_deliveries(&filter).await {
+      if let Some(delivery_id) = &delivery.id {
+        self.remove(DELIVERIES_TABLE, delivery_id).await;
+      }
+    }
+    Ok(success_response(json!({ "id": id, "deleted": true })))
+  }
+  /// The webhook's delivery log, newest first.
+  pub async fn deliveries(
+    &self,
+    id: &str,
+    user_id: &str,
+    is_admin: bool,
+    skip: Option<usize>,
+    limit: Option<usize>,
+  ) -> Result<ResponseModel, ResponseModel> {
+    self.find_managed(id, user_id, is_admin).await?;
+    let filter = Filter::Eq("webhook_id".to_string(), json!(id));
+    let mut deliveries = self.find_deliveries(&filter).await;
+    deliveries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    let total = deliveries.len();
+    let page: Vec<WebhookDeliveryEntity> = deliveries
+      .into_iter()
+      .skip(skip.unwrap_or(0))
+      .take(limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE))
+      .collect();
+    Ok(success_response(
+      json!({ "deliveries": page, "total": total }),
+    ))
+  }
+  /// Sends a logged delivery again with a fresh set of attempts: right away on the owner's
+  /// device, otherwise as soon as the owner's device picks it up.
+  pub async fn redeliver(
+    &self,
+    delivery_id: &str,
+    user_id: &str,
+    is_admin: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let _guard = self.delivering.lock().await;
+    let mut delivery = self
+      .find_delivery(delivery_id)
+      .await
+      .ok_or_else(|| err_response("Delivery not found"))?;
+    self
+      .find_managed(&delivery.webhook_id, user_id, is_admin)
+      .await?;
+    delivery.status = WebhookDeliveryStatus::Pending;
+    delivery.attempts = 0;
+    delivery.next_attempt_at = None;
+    let delivery = self.send_or_queue(delivery).await?;
+    Ok(success_response(json!(delivery)))
+  }
+  /// Sends a `webhook.ping` to the webhook and returns the logged delivery, so a receiver can
+  /// be checked without waiting for a real event. Like `redeliver`, it is sent right away
+  /// only from the owner's device.
+  pub async fn test(
+    &self,
+    id: &str,
+    user_id: &str,
+    is_admin: bool,
+  ) -> Result<ResponseModel, ResponseModel> {
+    let _guard = self.delivering.lock().await;
+    let hook = self.find_managed(id, user_id, is_admin).await?;
+    let delivery = self
+      .enqueue(&hook, WebhookEvent::Ping, json!({ "webhook_id": id }))
+      .await?;
+    let delivery = self.send_or_queue(delivery).await?;
+    Ok(success_response(json!(delivery)))
+  }
+  /// Queues the events raised by a created (`old` is `None`) or updated record for every
+  /// subscribed webhook that may see it, then sends what is due from this device.
+  pub async fn on_record_changed(&self, table: &str, old: Option<&Value>, record: &Value) {
+    let events = webhooks::events_for(table, old, record);
+    if events.is_empty() {
+      return;
+    }
+    let hooks = self
+      .find_webhooks(Some(&Filter::Eq("active".to_string(), json!(true))))
+      .await;
+    let todo = self.todo_for(table, record).await;
+    for event in events {
+      for hook in &hooks {
+        let Ok(hook) = serde_json::from_value::<WebhookEntity>(hook.clone()) else {
+          continue;
+        };
+        if !hook.active || !hook.events.contains(&event) {
+          continue;
+        }
+        if !hook.global && !self.can_view(todo.as_ref(), &hook.user_id).await {
+          continue;
+        }
+        let _ = self.enqueue(&hook, event, record.clone()).await;
+      }
+    }
+    self.deliver_due().await;
+  }
+  /// Moves deliveries queued offline to MongoDB, then sends every pending delivery of the
+  /// signed-in user's webhooks whose retry time has come and returns how many succeeded.
+  /// Does nothing while another caller is already sending.
+  pub async fn deliver_due(&self) -> usize {
+    let Ok(_guard) = self.delivering.try_lock() else {
+      return 0;
+    };
+    self.hand_off().await;
+    let Some(user_id) = signed_in::signed_in_user() else {
+      return 0;
+    };
+    let now = Utc::now();
+    let filter = Filter::And(vec![
+      Filter::Eq("user_id".to_string(), json!(user_id)),
+      Filter::Eq("status".to_string(), json!(WebhookDeliveryStatus::Pending)),
+    ]);
+    let mut due: Vec<WebhookDeliveryEntity> = self
+      .find_deliveries(&filter)
+      .await
+      .into_iter()
+      .filter(|d| d.next_attempt_at.map(|at| at <= now).unwrap_or(true))
+      .collect();
+    due.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    let mut delivered = 0;
+    for delivery in due {
+      if self.attempt(delivery).await.status == WebhookDeliveryStatus::Delivered {
+        delivered += 1;
+      }
+    }
+    self.prune_log(&user_id, now).await;
+    delivered
+  }
+  /// Attempts the delivery when this device sends for its webhook, otherwise leaves it queued
+  /// for the owner's device.
+  async fn send_or_queue(
+    &self,
+    delivery: WebhookDeliveryEntity,
+  ) -> Result<WebhookDeliveryEntity, ResponseModel> {
+    if signed_in::is_signed_in_user(&delivery.user_id) {
+      return Ok(self.attempt(delivery).await);
+    }
+    self.save_delivery(&delivery).await?;
+    Ok(delivery)
+  }
+  /// Makes one attempt and records its outcome. Deliveries for removed or disabled webhooks
+  /// fail without being sent.
+  async fn attempt(&self, mut delivery: WebhookDeliveryEntity) -> WebhookDeliveryEntity {
+    let now = Utc::now();
+    delivery.attempts += 1;
+    let hook = self
+      .find_doc(WEBHOOKS_TABLE, &delivery.webhook_id)
+      .await
+      .and_then(|doc| serde_json::from_value::<WebhookEntity>(doc).ok())
+      .filter(|hook| hook.active);
+    let give_up = hook.is_none();
+    let result = match &hook {
+      Some(hook) => match self.find_secret(hook).await {
+        Some(secret) => self.send(hook, &secret, &delivery).await,
+        None => Err((None, "Webhook secret is not available".to_string())),
+      },
+      None => Err((None, "Webhook was removed or disabled".to_string())),
+    };
+    match result {
+      Ok(status) => {
+        delivery.status = WebhookDeliveryStatus::Delivered;
+        delivery.response_status = Some(status);
+        delivery.last_error = None;
+        delivery.delivered_at = Some(now);
+        delivery.next_attempt_at = None;
+      }
+      Err((status, error)) => {
+        delivery.response_status = status;
+        delivery.last_error = Some(error);
+        if give_up || delivery.attempts >= MAX_ATTEMPTS {
+          delivery.status = WebhookDeliveryStatus::Failed;
+          delivery.next_attempt_at = None;
+        } else {
+          delivery.status = WebhookDeliveryStatus::Pending;
+          delivery.next_attempt_at = Some(now + webhooks::retry_delay(delivery.attempts));
+        }
+      }
+    }
+    delivery.updated_at = Some(now);
+    let _ = self.save_delivery(&delivery).await;
+    delivery
+  }
+  /// Posts the event. Any 2xx answer counts as delivered.
+  async fn send(
+    &self,
+    hook: &WebhookEntity,
+    secret: &str,
+    delivery: &WebhookDeliveryEntity,
+  ) -> Result<u16, (Option<u16>, String)> {
+    let delivery_id = delivery.id.clone().unwrap_or_default();
+    let body = json!({
+      "id": delivery_id,
+      "event": delivery.event,
+      "webhook_id": hook.id,
+      "created_at": delivery.created_at,
+      "data": delivery.payload,
+    })
+    .to_string();
+    webhooks::post_signed(
+      &self.http_client,
+      &hook.url,
+      secret,
+      delivery.event.as_str(),
+      &delivery_id,
+      body,
+    )
+    .await
+  }
+  async fn enqueue(
+    &self,
+    hook: &WebhookEntity,
+    event: WebhookEvent,
+    payload: Value,
+  ) -> Result<WebhookDeliveryEntity, ResponseModel> {
+    let now = Utc::now();
+    let delivery = WebhookDeliveryEntity {
+      id: Some(nosql_orm::utils::generate_id()),
+      webhook_id: hook.id.clone().unwrap_or_default(),
+      user_id: hook.user_id.clone(),
+      event,
+      payload,
+      status: WebhookDeliveryStatus::Pending,
+      attempts: 0,
+      next_attempt_at: Some(now),
+      response_status: None,
+      last_error: None,
+      delivered_at: None,
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    self.save_delivery(&delivery).await?;
+    Ok(delivery)
+  }
+  /// Saves a delivery in MongoDB, where the owner's devices find it. Without a connection it
+  /// is kept locally until `hand_off` moves it.
+  async fn save_delivery(&self, delivery: &WebhookDeliveryEntity) -> Result<(), ResponseModel> {
+    let id = delivery.id.clone().unwrap_or_default();
+    let value = serde_json::to_value(delivery)
+      .map_err(|e| err_response(&format!("Failed to save delivery: {}", e)))?;
+    if let Some(mongo) = &self.mongodb_provider {
+      let saved = match mongo.find_by_id(DELIVERIES_TABLE, &id).await {
+        Ok(Some(_)) => mongo
+          .update(DELIVERIES_TABLE, &id, value.clone())
+          .await
+          .is_ok(),
+        Ok(None) => mongo.insert(DELIVERIES_TABLE, value.clone()).await.is_ok(),
+        Err(_) => false,
+      };
+      if saved {
+        let _ = self.json_provider.delete(DELIVERIES_TABLE, &id).await;
+        return Ok(());
+      }
+    }
+    self.store_local(DELIVERIES_TABLE, value).await.map(|_| ())
+  }
+  /// Moves deliveries kept locally while offline to MongoDB.
+  async fn hand_off(&self) {
+    if self.mongodb_provider.is_none() {
+      return;
+    }
+    for doc in self
+      .json_provider
+      .find_many(DELIVERIES_TABLE, None, None, None, None, true)
+      .await
+      .unwrap_or_default()
+    {
+      if let Ok(delivery) = serde_json::from_value::<WebhookDeliveryEntity>(doc) {
+        let _ = self.save_delivery(&delivery).await;
+      }
+    }
+  }
+  /// Drops the user's delivered and failed entries older than `LOG_RETENTION_DAYS`.
+  async fn prune_log(&self, user_id: &str, now: chrono::DateTime<Utc>) {
+    let cutoff = now - Duration::days(LOG_RETENTION_DAYS);
+    let filter = Filter::And(vec![
+      Filter::Eq("user_id".to_string(), json!(user_id)),
+      Filter::Ne("status".to_string(), json!(WebhookDeliveryStatus::Pending)),
+    ]);
+    for delivery in self.find_deliveries(&filter).await {
+      if let (Some(id), Some(updated_at)) = (&delivery.id, delivery.updated_at) {
+        if updated_at < cutoff {
+          self.remove(DELIVERIES_TABLE, id).await;
+        }
+      }
+    }
+  }
+  /// Whether the user can see `todo`, by user id or by profile id as assignees are stored.
+  async fn can_view(&self, todo: Option<&Value>, user_id: &str) -> bool {
+    let Some(todo) = todo else {
+      return false;
+    };
+    let profile_id =
+      profile::find_profile_id(&self.json_provider, self.mongodb_provider.as_ref(), user_id).await;
+    PermissionService::can_view_todo_with_profile(todo, user_id, profile_id.as_deref())
+  }
+  /// The todo that decides which users' webhooks may see the record.
+  async fn todo_for(&self, table: &str, record: &Value) -> Option<Value> {
+    let task = match table {
+      "todos" => return Some(record.clone()),
+      "tasks" => record.clone(),
+      "comments" => {
+        let task_id = match record.get("task_id").and_then(|v| v.as_str()) {
+          Some(task_id) => task_id.to_string(),
+          None => {
+            let subtask = self
+              .find_doc("subtasks", str_field(record, "subtask_id"))
+              .await?;
+            str_field(&subtask, "task_id").to_string()
+          }
+        };
+        self.find_doc("tasks", &task_id).await?
+      }
+      _ => return None,
+    };
+    self.find_doc("todos", str_field(&task, "todo_id")).await
+  }
+  async fn find_managed(
+    &self,
+    id: &str,
+    user_id: &str,
+    is_admin: bool,
+  ) -> Result<WebhookEntity, ResponseModel> {
+    let doc = self
+      .find_doc(WEBHOOKS_TABLE, id)
+      .await
+      .ok_or_else(|| err_response("Webhook not found"))?;
+    if !can_manage(&doc, user_id, is_admin) {
+      return Err(ResponseModel::forbidden(
+        "You do not have permission to manage this webhook",
+      ));
+    }
+    serde_json::from_value(doc).map_err(|e| err_response(&format!("Invalid webhook: {}", e)))
+  }
+  async fn find_delivery(&self, id: &str) -> Option<WebhookDeliveryEntity> {
+    self
+      .find_doc(DELIVERIES_TABLE, id)
+      .await
+      .and_then(|doc| serde_json::from_value(doc).ok())
+  }
+  /// Matches from MongoDB and those still queued locally.
+  async fn find_deliveries(&self, filter: &Filter) -> Vec<WebhookDeliveryEntity> {
+    self
+      .find_all(DELIVERIES_TABLE, Some(filter))
+      .await
+      .into_iter()
+      .filter_map(|doc| serde_json::from_value(doc).ok())
+      .collect()
+  }
+  /// The webhook's secret, loaded only on its owner's device.
+  async fn find_secret(&self, hook: &WebhookEntity) -> Option<String> {
+    if !signed_in::is_signed_in_user(&hook.user_id) {
+      return None;
+    }
+    self
+      .find_doc(SECRETS_TABLE, hook.id.as_deref().unwrap_or(""))
+      .await
+      .and_then(|doc| serde_json::from_value::<WebhookSecretEntity>(doc).ok())
+      .map(|entry| entry.secret)
+  }
+  async fn store_secret(
+    &self,
+    webhook_id: &str,
+    user_id: &str,
+    secret: &str,
+  ) -> Result<(), ResponseModel> {
+    let now = Utc::now();
+    let entry = WebhookSecretEntity {
+      id: Some(webhook_id.to_string()),
+      user_id: user_id.to_string(),
+      secret: secret.to_string(),
+      created_at: Some(now),
+      updated_at: Some(now),
+    };
+    let value = serde_json::to_value(&entry)
+      .map_err(|e| err_response(&format!("Failed to save webhook secret: {}", e)))?;
+    self.store(SECRETS_TABLE, value).await.map(|_| ())
+  }
+  async fn find_doc(&self, table: &str, id: &str) -> Option<Value> {
+    if id.is_empty() {
+      return None;
+    }
+    if let Ok(Some(doc)) = self.json_provider.find_by_id(table, id).await {
+      return Some(doc);
+    }
+    match &self.mongodb_provider {
+      Some(mongo) => mongo.find_by_id(table, id).await.ok().flatten(),
+      None => None,
+    }
+  }
+  async fn find_webhooks(&self, filter: Option<&Filter>) -> Vec<Value> {
+    self.find_all(WEBHOOKS_TABLE, filter).await
+  }
+  /// Matches from MongoDB and the local store, de-duplicated by id with MongoDB's copy first.
+  async fn find_all(&self, table: &str, filter: Option<&Filter>) -> Vec<Value> {
+    let mut docs = match &self.mongodb_provider {
+      Some(mongo) => mongo
+        .find_many(table, filter, None, None, None, true)
+        .await
+        .unwrap_or_default(),
+      None => Vec::new(),
+    };
+    for doc in self
+      .json_provider
+      .find_many(table, filter, None, None, None, true)
+      .await
+      .unwrap_or_default()
+    {
+      if !docs.iter().any(|d| d.get("id") == doc.get("id")) {
+        docs.push(doc);
+      }
+    }
+    docs
+  }
+  /// Upserts locally and, when reachable, in MongoDB so other devices see the same state.
+  async fn store(&self, table: &str, doc: Value) -> Result<Value, ResponseModel> {
+    let id = str_field(&doc, "id").to_string();
+    let saved = self.store_local(table, doc.clone()).await?;
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = match mongo.find_by_id(table, &id).await {
+        Ok(Some(_)) => mongo.update(table, &id, doc).await,
+        Ok(None) => mongo.insert(table, doc).await,
+        Err(e) => Err(e),
+      };
+    }
+    Ok(saved)
+  }
+  async fn store_local(&self, table: &str, doc: Value) -> Result<Value, ResponseModel> {
+    let id = str_field(&doc, "id").to_string();
+    Ok(match self.json_provider.find_by_id(table, &id).await? {
+      Some(_) => self.json_provider.update(table, &id, doc).await?,
+      None => self.json_provider.insert(table, doc).await?,
+    })
+  }
+  async fn remove(&self, table: &str, id: &str) {
+    let _ = self.json_provider.delete(table, id).await;
+    if let Some(mongo) = &self.mongodb_provider {
+      let _ = mongo.delete(table, id).await;
+    }
+  }
+}
+fn validate(input: &WebhookInput, is_admin: bool) -> Result<(), ResponseModel> {
+  let url = reqwest::Url::parse(input.url.trim())
+    .map_err(|e| err_response(&format!("Invalid webhook URL: {}", e)))?;
+  if !matches!(url.scheme(), "http" | "https") {
+    return Err(err_response("Webhook URL must use http or https"));
+  }
+  if input.events.is_empty() {
+    return Err(err_response("Choose at least one event"));
+  }
+  if let Some(secret) = &input.secret {
+    if secret.chars().count() < MIN_SECRET_CHARS {
+      return Err(err_response(
+        "Webhook secret must be at least 16 characters",
+      ));
+    }
+  }
+  if input.global && !is_admin {
+    return Err(ResponseModel::forbidden(
+      "Only admins can create webhooks for every todo",
+    ));
+  }
+  Ok(())
+}
+/// Redirects are not followed, so a receiver cannot bounce a delivery to another address.
+fn can_manage(hook: &Value, user_id: &str, is_admin: bool) -> bool {
+  let global = hook.get("global").and_then(|v| v.as_bool()) == Some(true);
+  str_field(hook, "user_id") == user_id || (global && is_admin)
+}
+fn dedup_events(events: Vec<WebhookEvent>) -> Vec<WebhookEvent> {
+  let mut unique: Vec<WebhookEvent> = Vec::new();
+  for event in events {
+    if !unique.contains(&event) {
+      unique.push(event);
+    }
+  }
+  unique
+}
+fn generate_secret() -> String {
+  let mut bytes = [0u8; 32];
+  rand::thread_rng().fill_bytes(&mut bytes);
+  HEXLOWER.encode(&bytes)
+}
+fn str_field<'a>(doc: &'a Value, field: &str) -> &'a str {
+  doc.get(field).and_then(|v| v.as_str()).unwrap_or("")
+}
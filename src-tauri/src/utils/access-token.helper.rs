@@ -0,0 +1,237 @@
+/* sys lib */
+use chrono::Utc;
+use data_encoding::{BASE64URL_NOPAD, HEXLOWER};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+/* models */
+use crate::models::response::ResponseModel;
+pub const ACCESS_TOKEN_PREFIX: &str = "tfp_";
+const ACCESS_TOKEN_BYTES: usize = 32;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+  ReadTodos,
+  WriteTasks,
+  Chat,
+  /// Full account access, including admin commands when the owner is an admin
+  Admin,
+}
+impl TokenScope {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      TokenScope::ReadTodos => "read_todos",
+      TokenScope::WriteTasks => "write_tasks",
+      TokenScope::Chat => "chat",
+      TokenScope::Admin => "admin",
+    }
+  }
+}
+/// Scope a token needs to run `operation` on `table` through `crud_route!`.
+pub fn required_scope(table: &str, operation: &str) -> TokenScope {
+  let read = matches!(operation, "get" | "getAll");
+  match table {
+    "todos" | "categories" if read => TokenScope::ReadTodos,
+    "tasks" | "subtasks" if read => TokenScope::ReadTodos,
+    "tasks" | "subtasks" => TokenScope::WriteTasks,
+    "chats" | "comments" | "rooms" | "groups" => TokenScope::Chat,
+    _ => TokenScope::Admin,
+  }
+}
+/// Scope a token needs to run a dedicated command that authenticates through
+/// `extract_user_for_command`. Commands not listed here need `admin`.
+pub fn command_scope(command: &str) -> TokenScope {
+  match command {
+    "search_data"
+    | "get_all_archive_data"
+    | "get_all_archive_paginated"
+    | "get_todo_permissions"
+    | "get_notification_preferences"
+    | "get_security_events"
+    | "export_security_events"
+    | "get_webhooks"
+    | "get_webhook_deliveries" => TokenScope::ReadTodos,
+    "set_todo_overdue_policy"
+    | "set_notification_preferences"
+    | "set_notification_mute"
+    | "create_webhook"
+    | "update_webhook"
+    | "delete_webhook"
+    | "redeliver_webhook"
+    | "test_webhook" => TokenScope::WriteTasks,
+    _ => TokenScope::Admin,
+  }
+}
+/// Tables holding credentials or tokens. Personal access tokens may never write them,
+/// whatever their scope, so a leaked token cannot take over the account.
+const CREDENTIAL_TABLES: [&str; 4] = [
+  "users",
+  "passkeys",
+  "oauth_identities",
+  "personal_access_tokens",
+];
+pub fn is_credential_write(table: &str, operation: &str) -> bool {
+  CREDENTIAL_TABLES.contains(&table) && !matches!(operation, "get" | "getAll")
+}
+/// In-memory view of a stored token, keyed by its hash.
+#[derive(Debug, Clone)]
+pub struct AccessTokenGrant {
+  pub id: String,
+  pub user_id: String,
+  pub profile_id: Option<String>,
+  pub scopes: Vec<TokenScope>,
+  /// Unix milliseconds
+  pub expires_at: Option<i64>,
+}
+impl AccessTokenGrant {
+  pub fn allows(&self, scope: TokenScope) -> bool {
+    self
+      .scopes
+      .iter()
+      .any(|s| *s == scope || *s == TokenScope::Admin)
+  }
+  fn is_expired(&self) -> bool {
+    self
+      .expires_at
+      .map(|exp| exp <= Utc::now().timestamp_millis())
+      .unwrap_or(false)
+  }
+}
+fn grants() -> &'static RwLock<HashMap<String, AccessTokenGrant>> {
+  static GRANTS: OnceLock<RwLock<HashMap<String, AccessTokenGrant>>> = OnceLock::new();
+  GRANTS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+pub fn is_access_token(token: &str) -> bool {
+  token.starts_with(ACCESS_TOKEN_PREFIX)
+}
+pub fn generate_access_token() -> String {
+  let mut bytes = [0u8; ACCESS_TOKEN_BYTES];
+  rand::thread_rng().fill_bytes(&mut bytes);
+  format!("{}{}", ACCESS_TOKEN_PREFIX, BASE64URL_NOPAD.encode(&bytes))
+}
+pub fn hash_access_token(token: &str) -> String {
+  HEXLOWER.encode(&Sha256::digest(token.as_bytes()))
+}
+pub fn register_grant(token_hash: String, grant: AccessTokenGrant) {
+  if let Ok(mut map) = grants().write() {
+    map.insert(token_hash, grant);
+  }
+}
+/// Swaps in a freshly loaded set of grants, dropping tokens revoked or deleted elsewhere.
+pub fn replace_grants(fresh: HashMap<String, AccessTokenGrant>) {
+  if let Ok(mut map) = grants().write() {
+    *map = fresh;
+  }
+}
+pub fn revoke_grant(token_id: &str) {
+  if let Ok(mut map) = grants().write() {
+    map.retain(|_, grant| grant.id != token_id);
+  }
+}
+/// Resolves a live (known, unexpired) personal access token.
+pub fn lookup_access_token(token: &str) -> Result<AccessTokenGrant, ResponseModel> {
+  let token_hash = hash_access_token(token);
+  let grant = grants()
+    .read()
+    .ok()
+    .and_then(|map| map.get(&token_hash).cloned())
+    .ok_or_else(|| ResponseModel::unauthorized("Invalid token"))?;
+  if grant.is_expired() {
+    return Err(ResponseModel::unauthorized("Access token has expired"));
+  }
+  Ok(grant)
+}
+/// Resolves a personal access token and checks it carries `scope`.
+pub fn authorize_access_token(
+  token: &str,
+  scope: TokenScope,
+) -> Result<AccessTokenGrant, ResponseModel> {
+  let grant = lookup_access_token(token)?;
+  if !grant.allows(scope) {
+    return Err(ResponseModel::forbidden(format!(
+      "Access token is missing the '{}' scope",
+      scope.as_str()
+    )));
+  }
+  Ok(grant)
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  fn grant(id: &str, scopes: Vec<TokenScope>, expires_at: Option<i64>) -> AccessTokenGrant {
+    AccessTokenGrant {
+      id: id.to_string(),
+      user_id: "user-1".to_string(),
+      profile_id: None,
+      scopes,
+      expires_at,
+    }
+  }
+  #[test]
+  fn maps_crud_routes_to_scopes() {
+    assert_eq!(required_scope("todos", "getAll"), TokenScope::ReadTodos);
+    assert_eq!(required_scope("todos", "update"), TokenScope::Admin);
+    assert_eq!(required_scope("tasks", "create"), TokenScope::WriteTasks);
+    assert_eq!(required_scope("comments", "create"), TokenScope::Chat);
+    assert_eq!(required_scope("profiles", "get"), TokenScope::Admin);
+  }
+  #[test]
+  fn enforces_scope_expiry_and_revocation() {
+    let token = generate_access_token();
+    assert!(is_access_token(&token));
+    register_grant(
+      hash_access_token(&token),
+      grant("pat-scope", vec![TokenScope::ReadTodos], None),
+    );
+    assert!(authorize_access_token(&token, TokenScope::ReadTodos).is_ok());
+    assert!(authorize_access_token(&token, TokenScope::WriteTasks).is_err());
+    revoke_grant("pat-scope");
+    assert!(authorize_access_token(&token, TokenScope::ReadTodos).is_err());
+    let expired = generate_access_token();
+    register_grant(
+      hash_access_token(&expired),
+      grant("pat-expired", vec![TokenScope::Admin], Some(0)),
+    );
+    assert!(authorize_access_token(&expired, TokenScope::ReadTodos).is_err());
+  }
+  #[test]
+  fn maps_dedicated_commands_to_scopes() {
+    assert_eq!(
+      command_scope("get_notification_preferences"),
+      TokenScope::ReadTodos
+    );
+    assert_eq!(
+      command_scope("set_todo_overdue_policy"),
+      TokenScope::WriteTasks
+    );
+    assert_eq!(command_scope("transfer_todo_ownership"), TokenScope::Admin);
+  }
+  #[test]
+  fn scoped_tokens_reach_dedicated_commands() {
+    let token = generate_access_token();
+    register_grant(
+      hash_access_token(&token),
+      grant("pat-command", vec![TokenScope::ReadTodos], None),
+    );
+    let reach = |command| crate::utils::auth::extract_user_for_command(&token, "secret", command);
+    assert_eq!(reach("get_todo_permissions").unwrap(), "user-1");
+    assert_eq!(reach("get_webhooks").unwrap(), "user-1");
+    assert!(reach("set_notification_mute").is_err());
+    assert!(reach("update_todo_permissions").is_err());
+  }
+  #[test]
+  fn credential_tables_are_read_only_for_tokens() {
+    assert!(is_credential_write("users", "update"));
+    assert!(is_credential_write("passkeys", "delete"));
+    assert!(!is_credential_write("users", "get"));
+    assert!(!is_credential_write("tasks", "update"));
+  }
+  #[test]
+  fn admin_scope_grants_everything() {
+    let g = grant("pat-admin", vec![TokenScope::Admin], None);
+    assert!(g.allows(TokenScope::Chat));
+    assert!(g.allows(TokenScope::WriteTasks));
+  }
+}
@@ -7,6 +7,9 @@ use nosql_orm::providers::{JsonProvider, MongoProvider};
 /* models */
 use crate::entities::{table_entity::TableModelType, user_entity::UserEntity};
 use crate::models::response::{ResponseModel, ResponseStatus};
+use crate::utils::access_token::{
+  authorize_access_token, command_scope, is_access_token, lookup_access_token, TokenScope,
+};
 use crate::utils::response_helper::err_response;
 use bcrypt::{hash, DEFAULT_COST};
 use jsonwebtoken::{decode, DecodingKey, Validation};
@@ -33,7 +36,38 @@ impl Claims {
 pub fn hash_password(password: &str) -> Result<String, ResponseModel> {
   hash(password, DEFAULT_COST).map_err(|e| err_response(&format!("Error hashing password: {}", e)))
 }
+/// Accepts session JWTs and personal access tokens; the latter need full (`admin`) scope here.
+/// Dedicated commands open to narrower tokens use `extract_user_for_command` instead.
 pub fn extract_user_from_token(token: &str, jwt_secret: &str) -> Result<String, ResponseModel> {
+  extract_user_with_scope(token, jwt_secret, TokenScope::Admin)
+}
+/// Like `extract_user_with_scope`, with the scope `command` needs, see [`command_scope`].
+pub fn extract_user_for_command(
+  token: &str,
+  jwt_secret: &str,
+  command: &str,
+) -> Result<String, ResponseModel> {
+  extract_user_with_scope(token, jwt_secret, command_scope(command))
+}
+/// Like `extract_user_from_token`, but refuses personal access tokens whatever their scope.
+/// Used by commands that change credentials or manage tokens.
+pub fn extract_session_user(token: &str, jwt_secret: &str) -> Result<String, ResponseModel> {
+  if is_access_token(token) {
+    return Err(ResponseModel::forbidden(
+      "Credentials and access tokens can only be managed from a signed-in session",
+    ));
+  }
+  extract_user_from_token(token, jwt_secret)
+}
+/// Like `extract_user_from_token`, but a personal access token only needs `scope`.
+pub fn extract_user_with_scope(
+  token: &str,
+  jwt_secret: &str,
+  scope: TokenScope,
+) -> Result<String, ResponseModel> {
+  if is_access_token(token) {
+    return authorize_access_token(token, scope).map(|grant| grant.user_id);
+  }
   let token_data = decode::<Claims>(
     token,
     &DecodingKey::from_secret(jwt_secret.as_ref()),
@@ -46,6 +80,11 @@ pub fn extract_user_from_token(token: &str, jwt_secret: &str) -> Result<String,
   Ok(token_data.claims.id)
 }
 pub fn extract_profile_from_token(token: &str, jwt_secret: &str) -> Result<String, ResponseModel> {
+  if is_access_token(token) {
+    return lookup_access_token(token)?
+      .profile_id
+      .ok_or_else(|| err_response("Profile ID not found in token"));
+  }
   let token_data = decode::<Claims>(
     token,
     &DecodingKey::from_secret(jwt_secret.as_ref()),
@@ -169,3 +208,25 @@ pub async fn find_user_by_username(
   serde_json::from_value::<UserEntity>(user_val)
     .map_err(|e| err_response(&format!("Failed to parse user: {}", e)))
 }
+/// The user from the local store, falling back to MongoDB for accounts not synced here yet.
+pub async fn find_user_by_id(
+  json_provider: &JsonProvider,
+  mongodb_provider: Option<&Arc<MongoProvider>>,
+  user_id: &str,
+) -> Result<UserEntity, ResponseModel> {
+  let table_name = TableModelType::User.table_name();
+  let user_val = match json_provider.find_by_id(table_name, user_id).await {
+    Ok(Some(user)) => user,
+    _ => {
+      let mongo =
+        mongodb_provider.ok_or_else(|| err_response("User not found and MongoDB unavailable"))?;
+      mongo
+        .find_by_id(table_name, user_id)
+        .await
+        .map_err(|e| err_response(&format!("Database error: {}", e)))?
+        .ok_or_else(|| err_response("User not found"))?
+    }
+  };
+  serde_json::from_value::<UserEntity>(user_val)
+    .map_err(|e| err_response(&format!("Failed to parse user: {}", e)))
+}
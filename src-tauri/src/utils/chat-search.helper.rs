@@ -0,0 +1,137 @@
+/* sys lib */
+use serde_json::Value;
+/// Characters of surrounding text kept on each side of the first hit in a snippet.
+const SNIPPET_RADIUS: usize = 60;
+/// Room ids (`RoomEntity.room` / `GroupEntity.room_id`) a user can read.
+pub fn room_ids_for_user(rooms: &[Value], groups: &[Value], user_id: &str) -> Vec<String> {
+  let has_member = |doc: &Value, field: &str| {
+    doc
+      .get(field)
+      .and_then(|v| v.as_array())
+      .map(|ids| ids.iter().any(|id| id.as_str() == Some(user_id)))
+      .unwrap_or(false)
+  };
+  let live = |doc: &&Value| doc.get("deleted_at").map(|v| v.is_null()).unwrap_or(true);
+  let mut ids: Vec<String> = rooms
+    .iter()
+    .filter(live)
+    .filter(|room| has_member(room, "participant_ids"))
+    .filter_map(|room| room.get("room").and_then(|v| v.as_str()))
+    .chain(
+      groups
+        .iter()
+        .filter(live)
+        .filter(|group| has_member(group, "member_ids"))
+        .filter_map(|group| group.get("room_id").and_then(|v| v.as_str())),
+    )
+    .map(|s| s.to_string())
+    .collect();
+  ids.sort();
+  ids.dedup();
+  ids
+}
+/// Lower-cased search terms; a message matches when it contains every term.
+pub fn parse_terms(query: &str) -> Vec<String> {
+  query
+    .split_whitespace()
+    .map(|term| term.to_lowercase())
+    .collect()
+}
+pub fn matches_all(content: &str, terms: &[String]) -> bool {
+  let haystack = content.to_lowercase();
+  !terms.is_empty() && terms.iter().all(|term| haystack.contains(term.as_str()))
+}
+/// Excerpt around the first hit plus the `[start, end)` char ranges of every hit inside it.
+pub fn snippet(content: &str, terms: &[String]) -> (String, Vec<(usize, usize)>) {
+  let chars: Vec<char> = content.chars().collect();
+  // Lower-cased like the terms, which can turn one char into several (e.g. 'İ'), so each
+  // lowered char remembers the original char it came from
+  let lower: Vec<char> = content.to_lowercase().chars().collect();
+  let origin: Vec<usize> = chars
+    .iter()
+    .enumerate()
+    .flat_map(|(i, c)| std::iter::repeat(i).take(c.to_lowercase().count()))
+    .collect();
+  let term_chars: Vec<Vec<char>> = terms.iter().map(|t| t.chars().collect()).collect();
+  let mut hits: Vec<(usize, usize)> = Vec::new();
+  for term in term_chars.iter().filter(|t| !t.is_empty()) {
+    let mut i = 0;
+    while i + term.len() <= lower.len() {
+      if lower[i..i + term.len()] == term[..] {
+        if let (Some(&s), Some(&e)) = (origin.get(i), origin.get(i + term.len() - 1)) {
+          hits.push((s, e + 1));
+        }
+        i += term.len();
+      } else {
+        i += 1;
+      }
+    }
+  }
+  hits.sort();
+  let first = hits.first().map(|h| h.0).unwrap_or(0);
+  let start = first.saturating_sub(SNIPPET_RADIUS);
+  let end = (first + SNIPPET_RADIUS * 2).min(chars.len()).max(start);
+  let mut text: String = chars[start..end].iter().collect();
+  let offset = if start > 0 {
+    text.insert(0, '…');
+    1
+  } else {
+    0
+  };
+  if end < chars.len() {
+    text.push('…');
+  }
+  let ranges = hits
+    .into_iter()
+    .filter(|(s, e)| *s >= start && *e <= end)
+    .map(|(s, e)| (s - start + offset, e - start + offset))
+    .collect();
+  (text, ranges)
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  #[test]
+  fn collects_rooms_from_participants_and_groups() {
+    let rooms = vec![
+      json!({ "room": "dm-1", "participant_ids": ["u1", "u2"] }),
+      json!({ "room": "dm-2", "participant_ids": ["u3"] }),
+      json!({ "room": "old", "participant_ids": ["u1"], "deleted_at": "2024-01-01T00:00:00Z" }),
+    ];
+    let groups = vec![json!({ "room_id": "team", "member_ids": ["u1"] })];
+    assert_eq!(
+      room_ids_for_user(&rooms, &groups, "u1"),
+      vec!["dm-1", "team"]
+    );
+  }
+  #[test]
+  fn matches_every_term_case_insensitively() {
+    let terms = parse_terms("Deploy  FRIDAY");
+    assert!(matches_all("we deploy on friday", &terms));
+    assert!(!matches_all("we deploy on monday", &terms));
+    assert!(!matches_all("anything", &[]));
+  }
+  #[test]
+  fn snippet_marks_hits_relative_to_excerpt() {
+    let content = format!("{}Release notes for the release", "x".repeat(100));
+    let (text, ranges) = snippet(&content, &parse_terms("release"));
+    assert!(text.starts_with('…'));
+    let chars: Vec<char> = text.chars().collect();
+    for (s, e) in &ranges {
+      let hit: String = chars[*s..*e].iter().collect();
+      assert_eq!(hit.to_lowercase(), "release");
+    }
+    assert_eq!(ranges.len(), 2);
+  }
+  #[test]
+  fn highlights_line_up_when_lowercasing_changes_length() {
+    let content = "Visit İstanbul";
+    let terms = parse_terms("İSTANBUL");
+    assert!(matches_all(content, &terms));
+    let (text, ranges) = snippet(content, &terms);
+    let chars: Vec<char> = text.chars().collect();
+    assert_eq!(ranges, vec![(6, 14)]);
+    assert_eq!(chars[6..14].iter().collect::<String>(), "İstanbul");
+  }
+}
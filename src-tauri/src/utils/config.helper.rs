@@ -2,6 +2,23 @@
 use std::env;
 use std::path::Path;
 const ENV_CONTENT: &str = include_str!("../../.env");
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OAuthProviderKind {
+  Oidc,
+  Github,
+}
+#[derive(Debug, Clone)]
+pub struct OAuthProviderConfig {
+  pub id: String,
+  pub display_name: String,
+  pub kind: OAuthProviderKind,
+  /// OIDC issuer URL used for discovery; unused for GitHub
+  pub issuer: String,
+  pub client_id: String,
+  pub client_secret: String,
+  pub redirect_uri: String,
+  pub scopes: Vec<String>,
+}
 #[derive(Debug, Clone)]
 pub struct ConfigHelper {
   pub name_app: String,
@@ -20,6 +37,10 @@ pub struct ConfigHelper {
   pub client_id_github: String,
   pub client_secret_github: String,
   pub callback_url_github: String,
+  pub oauth_providers: Vec<OAuthProviderConfig>,
+  /// Bind address for the realtime chat WebSocket; empty disables it
+  pub chat_ws_addr: String,
+  pub attachment_quota_mb: u64,
 }
 fn parse_env_content(content: &str) -> Vec<(String, String)> {
   let mut vars = Vec::new();
@@ -60,6 +81,45 @@ fn load_embedded_env() {
     env::set_var(key, value);
   }
 }
+/// Reads `OAUTH_PROVIDERS=gitlab,google` plus `OAUTH_<ID>_ISSUER`, `_CLIENT_ID`,
+/// `_CLIENT_SECRET`, `_REDIRECT_URI`, `_SCOPES` and `_NAME` for each listed id. Ids without
+/// an issuer or client id are skipped.
+fn load_oauth_providers(
+  rp_domain: &str,
+  github: Option<OAuthProviderConfig>,
+) -> Vec<OAuthProviderConfig> {
+  let mut providers: Vec<OAuthProviderConfig> = github.into_iter().collect();
+  let ids = env::var("OAUTH_PROVIDERS").unwrap_or_default();
+  for id in ids
+    .split(',')
+    .map(|s| s.trim().to_lowercase())
+    .filter(|s| !s.is_empty())
+  {
+    let var = |suffix: &str| env::var(format!("OAUTH_{}_{}", id.to_uppercase(), suffix)).ok();
+    let (Some(issuer), Some(client_id)) = (var("ISSUER"), var("CLIENT_ID")) else {
+      continue;
+    };
+    if providers.iter().any(|p| p.id == id) {
+      continue;
+    }
+    providers.push(OAuthProviderConfig {
+      display_name: var("NAME").unwrap_or_else(|| id.clone()),
+      kind: OAuthProviderKind::Oidc,
+      issuer: issuer.trim_end_matches('/').to_string(),
+      client_id,
+      client_secret: var("CLIENT_SECRET").unwrap_or_default(),
+      redirect_uri: var("REDIRECT_URI")
+        .unwrap_or_else(|| format!("https://{}/oauth/{}/callback", rp_domain, id)),
+      scopes: var("SCOPES")
+        .unwrap_or_else(|| "openid email profile".to_string())
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect(),
+      id,
+    });
+  }
+  providers
+}
 impl ConfigHelper {
   pub fn new() -> Self {
     dotenvy::dotenv().ok();
@@ -74,6 +134,25 @@ impl ConfigHelper {
         break;
       }
     }
+    let rp_domain = env::var("RP_DOMAIN").unwrap_or_else(|_| "taskflow.tcs.com".to_string());
+    let client_id_github = env::var("CLIENT_ID_GITHUB").unwrap_or_else(|_| "".to_string());
+    let client_secret_github = env::var("CLIENT_SECRET_GITHUB").unwrap_or_else(|_| "".to_string());
+    let callback_url_github = env::var("CALLBACK_URL_GITHUB").unwrap_or_else(|_| "".to_string());
+    let github_provider = (!client_id_github.is_empty()).then(|| OAuthProviderConfig {
+      id: "github".to_string(),
+      display_name: "GitHub".to_string(),
+      kind: OAuthProviderKind::Github,
+      issuer: String::new(),
+      client_id: client_id_github.clone(),
+      client_secret: client_secret_github.clone(),
+      redirect_uri: if callback_url_github.is_empty() {
+        format!("https://{}/github/callback", rp_domain)
+      } else {
+        callback_url_github.clone()
+      },
+      scopes: vec!["read:user".to_string(), "user:email".to_string()],
+    });
+    let oauth_providers = load_oauth_providers(&rp_domain, github_provider);
     Self {
       name_app: env::var("NAME_APP").unwrap_or_else(|_| "TaskFlow".to_string()),
       app_home_folder: env::var("APP_HOME_FOLDER").unwrap_or_else(|_| ".taskflow".to_string()),
@@ -93,13 +172,19 @@ impl ConfigHelper {
         .ok()
         .and_then(|s| s.parse::<u64>().ok())
         .unwrap_or(1),
-      rp_domain: env::var("RP_DOMAIN").unwrap_or_else(|_| "taskflow.tcs.com".to_string()),
+      rp_domain,
       enable_query_logging: env::var("ENABLE_QUERY_LOGGING")
         .map(|s| s.to_lowercase() == "true")
         .unwrap_or(false),
-      client_id_github: env::var("CLIENT_ID_GITHUB").unwrap_or_else(|_| "".to_string()),
-      client_secret_github: env::var("CLIENT_SECRET_GITHUB").unwrap_or_else(|_| "".to_string()),
-      callback_url_github: env::var("CALLBACK_URL_GITHUB").unwrap_or_else(|_| "".to_string()),
+      client_id_github,
+      client_secret_github,
+      callback_url_github,
+      oauth_providers,
+      attachment_quota_mb: env::var("ATTACHMENT_QUOTA_MB")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(500),
+      chat_ws_addr: env::var("CHAT_WS_ADDR").unwrap_or_else(|_| "127.0.0.1:0".to_string()),
     }
   }
 }
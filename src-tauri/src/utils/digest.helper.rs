@@ -0,0 +1,190 @@
+/* sys lib */
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+use chrono_tz::Tz;
+/* entities */
+use crate::entities::digest_entity::{
+  DigestFrequency, DigestSettingsEntity, DigestSummary, DigestTask,
+};
+/* helpers */
+use crate::utils::notification_prefs::parse_timezone;
+/// Where a pending task falls relative to the digest's day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DueBucket {
+  Overdue,
+  Today,
+  /// Due within the six days after today.
+  ThisWeek,
+}
+/// The settings' zone, falling back to UTC for values saved before validation existed.
+pub fn timezone(settings: &DigestSettingsEntity) -> Tz {
+  parse_timezone(&settings.timezone).unwrap_or(Tz::UTC)
+}
+/// The local date to send a digest for, or `None` when none is due at `now`: the digest is
+/// off, it is before the chosen hour or on the wrong weekday, or one went out already.
+pub fn due_on(settings: &DigestSettingsEntity, now: DateTime<Utc>) -> Option<NaiveDate> {
+  if settings.frequency == DigestFrequency::Off {
+    return None;
+  }
+  let local = now.with_timezone(&timezone(settings));
+  if local.hour() < settings.hour {
+    return None;
+  }
+  if settings.frequency == DigestFrequency::Weekly
+    && local.weekday().num_days_from_monday() != settings.weekday
+  {
+    return None;
+  }
+  let date = local.date_naive();
+  (settings.last_sent_on.as_deref() != Some(date.format("%Y-%m-%d").to_string().as_str()))
+    .then_some(date)
+}
+/// Start of the window "new since the last digest" covers: the previous send, or one
+/// period back for a first digest.
+pub fn window_start(settings: &DigestSettingsEntity, now: DateTime<Utc>) -> DateTime<Utc> {
+  let previous = settings
+    .last_sent_at
+    .as_deref()
+    .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+    .map(|at| at.with_timezone(&Utc));
+  previous.unwrap_or_else(|| match settings.frequency {
+    DigestFrequency::Weekly => now - Duration::days(7),
+    _ => now - Duration::days(1),
+  })
+}
+/// Buckets a due date against `today` in the digest's timezone. Tasks due after this week
+/// are left out.
+pub fn bucket(
+  due: DateTime<Utc>,
+  today: NaiveDate,
+  zone: Tz,
+  now: DateTime<Utc>,
+) -> Option<DueBucket> {
+  if due < now {
+    return Some(DueBucket::Overdue);
+  }
+  let due_date = due.with_timezone(&zone).date_naive();
+  if due_date <= today {
+    Some(DueBucket::Today)
+  } else if due_date <= today + Duration::days(6) {
+    Some(DueBucket::ThisWeek)
+  } else {
+    None
+  }
+}
+pub fn subject(summary: &DigestSummary) -> String {
+  let period = match summary.frequency {
+    DigestFrequency::Weekly => "Weekly",
+    _ => "Daily",
+  };
+  format!("{} digest for {}", period, summary.date)
+}
+/// Plain-text body used for the email copy.
+pub fn render_text(summary: &DigestSummary) -> String {
+  let mut out = format!("{}\n", subject(summary));
+  section(
+    &mut out,
+    "Overdue",
+    &summary.overdue,
+    summary.counts.overdue,
+  );
+  section(
+    &mut out,
+    "Due today",
+    &summary.due_today,
+    summary.counts.due_today,
+  );
+  section(
+    &mut out,
+    "Due this week",
+    &summary.due_this_week,
+    summary.counts.due_this_week,
+  );
+  out.push_str(&format!(
+    "\nNewly assigned: {}\nUnread comments: {}\nOther updates: {}\n",
+    summary.counts.newly_assigned, summary.counts.unread_comments, summary.counts.other_updates
+  ));
+  if let Some(day) = &summary.yesterday {
+    out.push_str(&format!(
+      "\nYesterday: {} of {} tasks completed, {} created, productivity {}%\n",
+      day.tasks_completed, day.total_tasks, day.tasks_created, day.productivity_score
+    ));
+  }
+  out
+}
+fn section(out: &mut String, title: &str, tasks: &[DigestTask], total: usize) {
+  if total == 0 {
+    return;
+  }
+  out.push_str(&format!("\n{} ({})\n", title, total));
+  for task in tasks {
+    out.push_str(&format!(
+      "- {} [{}] due {}\n",
+      task.title, task.todo_title, task.end_date
+    ));
+  }
+  if total > tasks.len() {
+    out.push_str(&format!("  and {} more\n", total - tasks.len()));
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  fn at(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+      .unwrap()
+      .with_timezone(&Utc)
+  }
+  fn settings(frequency: DigestFrequency) -> DigestSettingsEntity {
+    DigestSettingsEntity {
+      id: Some("u1".to_string()),
+      user_id: "u1".to_string(),
+      frequency,
+      hour: 8,
+      weekday: 0,
+      timezone: "+02:00".to_string(),
+      email: false,
+      last_sent_on: None,
+      last_sent_at: None,
+      last_email_error: None,
+      created_at: None,
+      updated_at: None,
+    }
+  }
+  #[test]
+  fn sends_once_per_local_day_after_the_hour() {
+    let mut daily = settings(DigestFrequency::Daily);
+    // 05:30 UTC is 07:30 local
+    assert_eq!(due_on(&daily, at("2024-05-01T05:30:00Z")), None);
+    let date = due_on(&daily, at("2024-05-01T06:00:00Z")).unwrap();
+    assert_eq!(date.to_string(), "2024-05-01");
+    daily.last_sent_on = Some("2024-05-01".to_string());
+    assert_eq!(due_on(&daily, at("2024-05-01T20:00:00Z")), None);
+    assert!(due_on(&settings(DigestFrequency::Off), at("2024-05-01T06:00:00Z")).is_none());
+  }
+  #[test]
+  fn weekly_digests_wait_for_their_weekday() {
+    let weekly = settings(DigestFrequency::Weekly);
+    // 2024-05-01 is a Wednesday, 2024-05-06 a Monday
+    assert_eq!(due_on(&weekly, at("2024-05-01T09:00:00Z")), None);
+    assert!(due_on(&weekly, at("2024-05-06T09:00:00Z")).is_some());
+  }
+  #[test]
+  fn buckets_by_local_date() {
+    let zone = Tz::Europe__Berlin;
+    let now = at("2024-05-01T06:00:00Z");
+    let today = now.with_timezone(&zone).date_naive();
+    assert_eq!(
+      bucket(at("2024-05-01T05:00:00Z"), today, zone, now),
+      Some(DueBucket::Overdue)
+    );
+    assert_eq!(
+      bucket(at("2024-05-01T21:00:00Z"), today, zone, now),
+      Some(DueBucket::Today)
+    );
+    assert_eq!(
+      bucket(at("2024-05-01T23:00:00Z"), today, zone, now),
+      Some(DueBucket::ThisWeek)
+    );
+    assert_eq!(bucket(at("2024-05-08T12:00:00Z"), today, zone, now), None);
+  }
+}
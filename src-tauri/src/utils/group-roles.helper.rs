@@ -0,0 +1,113 @@
+/* sys lib */
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+/* entities */
+use crate::entities::group_entity::GroupRole;
+pub fn string_list(doc: &Value, field: &str) -> Vec<String> {
+  doc
+    .get(field)
+    .and_then(|v| v.as_array())
+    .map(|ids| {
+      ids
+        .iter()
+        .filter_map(|id| id.as_str().map(|s| s.to_string()))
+        .collect()
+    })
+    .unwrap_or_default()
+}
+/// Role of the user in a group document, or `None` when they are not a member.
+pub fn role_of(group: &Value, user_id: &str) -> Option<GroupRole> {
+  if group.get("owner_id").and_then(|v| v.as_str()) == Some(user_id) {
+    return Some(GroupRole::Owner);
+  }
+  if !string_list(group, "member_ids")
+    .iter()
+    .any(|id| id == user_id)
+  {
+    return None;
+  }
+  if string_list(group, "admin_ids")
+    .iter()
+    .any(|id| id == user_id)
+  {
+    Some(GroupRole::Admin)
+  } else {
+    Some(GroupRole::Member)
+  }
+}
+pub fn can_moderate(role: Option<GroupRole>) -> bool {
+  matches!(role, Some(GroupRole::Owner) | Some(GroupRole::Admin))
+}
+/// Moderators act only on members ranked below them; the owner can never be removed or muted.
+pub fn outranks(actor: Option<GroupRole>, target: Option<GroupRole>) -> bool {
+  match (actor, target) {
+    (Some(actor), Some(target)) => can_moderate(Some(actor)) && actor > target,
+    (Some(actor), None) => can_moderate(Some(actor)),
+    _ => false,
+  }
+}
+pub fn is_muted(group: &Value, user_id: &str, now: DateTime<Utc>) -> bool {
+  group
+    .get("muted")
+    .and_then(|v| v.as_array())
+    .map(|mutes| {
+      mutes.iter().any(|mute| {
+        mute.get("user_id").and_then(|v| v.as_str()) == Some(user_id)
+          && mute
+            .get("until")
+            .and_then(|v| v.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|until| until.with_timezone(&Utc) > now)
+            .unwrap_or(true)
+      })
+    })
+    .unwrap_or(false)
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  fn group() -> Value {
+    json!({
+      "owner_id": "owner",
+      "member_ids": ["owner", "admin", "member"],
+      "admin_ids": ["admin"],
+      "muted": [
+        { "user_id": "member", "muted_by": "admin", "until": "2030-01-01T00:00:00Z" }
+      ]
+    })
+  }
+  #[test]
+  fn resolves_roles() {
+    let group = group();
+    assert_eq!(role_of(&group, "owner"), Some(GroupRole::Owner));
+    assert_eq!(role_of(&group, "admin"), Some(GroupRole::Admin));
+    assert_eq!(role_of(&group, "member"), Some(GroupRole::Member));
+    assert_eq!(role_of(&group, "stranger"), None);
+  }
+  #[test]
+  fn only_higher_roles_moderate() {
+    let owner = Some(GroupRole::Owner);
+    let admin = Some(GroupRole::Admin);
+    let member = Some(GroupRole::Member);
+    assert!(outranks(owner, admin));
+    assert!(outranks(admin, member));
+    assert!(outranks(admin, None));
+    assert!(!outranks(admin, admin));
+    assert!(!outranks(admin, owner));
+    assert!(!outranks(member, None));
+  }
+  #[test]
+  fn mutes_expire() {
+    let group = group();
+    let before = DateTime::parse_from_rfc3339("2029-12-31T00:00:00Z")
+      .unwrap()
+      .with_timezone(&Utc);
+    let after = DateTime::parse_from_rfc3339("2030-01-02T00:00:00Z")
+      .unwrap()
+      .with_timezone(&Utc);
+    assert!(is_muted(&group, "member", before));
+    assert!(!is_muted(&group, "member", after));
+    assert!(!is_muted(&group, "admin", before));
+  }
+}
@@ -0,0 +1,41 @@
+/* sys lib */
+use regex::Regex;
+use std::sync::LazyLock;
+/// `@username`, not preceded by anything that would make it part of an email address or word
+static MENTION: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?:^|[^A-Za-z0-9_.@-])@([A-Za-z0-9_][A-Za-z0-9_.-]*)")
+    .expect("valid mention pattern")
+});
+/// Most mentions resolved per comment or message; anything beyond is left as plain text.
+pub const MAX_MENTIONS: usize = 20;
+/// Usernames mentioned in `content`, in the order they appear and without duplicates.
+/// Trailing dots and dashes are treated as punctuation, so `@alex.` mentions `alex`.
+pub fn parse_mentions(content: &str) -> Vec<String> {
+  let mut names: Vec<String> = Vec::new();
+  for caps in MENTION.captures_iter(content) {
+    let name = caps[1].trim_end_matches(['.', '-']);
+    if name.is_empty() || names.iter().any(|n| n == name) {
+      continue;
+    }
+    names.push(name.to_string());
+    if names.len() == MAX_MENTIONS {
+      break;
+    }
+  }
+  names
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn parses_mentions_in_order_without_duplicates() {
+    assert_eq!(
+      parse_mentions("@alex can you ask @sam.lee? cc @alex."),
+      vec!["alex".to_string(), "sam.lee".to_string()]
+    );
+  }
+  #[test]
+  fn ignores_email_addresses() {
+    assert!(parse_mentions("mail me at alex@example.com or @@").is_empty());
+  }
+}
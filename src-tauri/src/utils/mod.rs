@@ -1,3 +1,5 @@
+#[path = "access-token.helper.rs"]
+pub mod access_token;
 pub mod activity;
 #[path = "activity_log.helper.rs"]
 pub mod activity_log;
@@ -5,14 +7,32 @@ pub mod activity_log;
 pub mod auth;
 #[path = "cascade.helper.rs"]
 pub mod cascade;
+#[path = "chat-search.helper.rs"]
+pub mod chat_search;
 #[path = "collection_metadata.helper.rs"]
 pub mod collection_metadata;
 #[path = "common.helper.rs"]
 pub mod common;
 #[path = "config.helper.rs"]
 pub mod config;
+#[path = "digest.helper.rs"]
+pub mod digest;
+#[path = "group-roles.helper.rs"]
+pub mod group_roles;
 #[path = "load_param.helper.rs"]
 pub mod load_param;
+#[path = "mentions.helper.rs"]
+pub mod mentions;
+#[path = "notification-groups.helper.rs"]
+pub mod notification_groups;
+#[path = "notification-prefs.helper.rs"]
+pub mod notification_prefs;
+#[path = "notification-sync.helper.rs"]
+pub mod notification_sync;
+#[path = "notification-triggers.helper.rs"]
+pub mod notification_triggers;
+#[path = "overdue.helper.rs"]
+pub mod overdue;
 #[path = "percentage.helper.rs"]
 pub mod percentage;
 #[path = "profile.helper.rs"]
@@ -21,15 +41,25 @@ pub mod profile;
 pub mod qr;
 #[path = "relation-stripper.helper.rs"]
 pub mod relation_stripper;
+#[path = "reminders.helper.rs"]
+pub mod reminders;
 #[path = "response.utils.rs"]
 pub mod response;
 #[path = "response.helper.rs"]
 pub mod response_helper;
 #[path = "security.helper.rs"]
 pub mod security;
+#[path = "signed-in.helper.rs"]
+pub mod signed_in;
+#[path = "task-refs.helper.rs"]
+pub mod task_refs;
 #[path = "timestamp.helper.rs"]
 pub mod timestamp;
 #[path = "user-sync.helper.rs"]
 pub mod user_sync;
 #[path = "visibility.helper.rs"]
 pub mod visibility;
+#[path = "webauthn.helper.rs"]
+pub mod webauthn;
+#[path = "webhooks.helper.rs"]
+pub mod webhooks;
@@ -0,0 +1,122 @@
+/* sys lib */
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+/* entities */
+use crate::entities::notification_entity::{
+  NotificationEntity, NotificationKind, NotificationTarget,
+};
+/// How long an unread group keeps absorbing new events, counted from its first one.
+pub const GROUP_WINDOW_MINUTES: i64 = 60;
+pub fn group_key(user_id: &str, kind: NotificationKind, target: &NotificationTarget) -> String {
+  let kind = serde_json::to_value(kind)
+    .ok()
+    .and_then(|v| v.as_str().map(String::from))
+    .unwrap_or_default();
+  format!("{}:{}:{}:{}", user_id, kind, target.entity, target.id)
+}
+/// The patch that folds `incoming` into `group`, or `None` when it repeats the group's
+/// latest event (same actor and payload) and should be dropped.
+pub fn merge(group: &Value, incoming: &NotificationEntity, now: DateTime<Utc>) -> Option<Value> {
+  let same_actor = group.get("actor_id").and_then(|v| v.as_str()) == incoming.actor_id.as_deref();
+  if same_actor && group.get("payload") == Some(&incoming.payload) {
+    return None;
+  }
+  let mut actor_ids: Vec<String> = group
+    .get("actor_ids")
+    .and_then(|v| v.as_array())
+    .map(|ids| {
+      ids
+        .iter()
+        .filter_map(|id| id.as_str().map(String::from))
+        .collect()
+    })
+    .unwrap_or_default();
+  if let Some(actor) = &incoming.actor_id {
+    if !actor_ids.contains(actor) {
+      actor_ids.push(actor.clone());
+    }
+  }
+  let count = group.get("count").and_then(|v| v.as_u64()).unwrap_or(1) + 1;
+  Some(json!({
+    "count": count,
+    "actor_id": incoming.actor_id,
+    "actor_ids": actor_ids,
+    "payload": incoming.payload,
+    "desktop": incoming.desktop,
+    "email_digest": incoming.email_digest,
+    "updated_at": now.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+  }))
+}
+/// Opaque cursor pointing just past `doc` in newest-first order.
+pub fn encode_cursor(doc: &Value) -> String {
+  let created_at = doc.get("created_at").and_then(|v| v.as_str()).unwrap_or("");
+  let id = doc.get("id").and_then(|v| v.as_str()).unwrap_or("");
+  URL_SAFE_NO_PAD.encode(format!("{}|{}", created_at, id))
+}
+/// `(created_at, id)` of the last notification on the previous page.
+pub fn decode_cursor(cursor: &str) -> Option<(String, String)> {
+  let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+  let (created_at, id) = raw.split_once('|')?;
+  Some((created_at.to_string(), id.to_string()))
+}
+/// Newest-first ordering key: most recently created first, ties broken by id. Creation time
+/// never changes, so events grouped into a notification do not move it between pages.
+pub fn sort_key(doc: &Value) -> (String, String) {
+  (
+    doc
+      .get("created_at")
+      .and_then(|v| v.as_str())
+      .unwrap_or("")
+      .to_string(),
+    doc
+      .get("id")
+      .and_then(|v| v.as_str())
+      .unwrap_or("")
+      .to_string(),
+  )
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::entities::notification_entity::NotificationCreateModel;
+  fn comment(actor: &str, comment_id: &str) -> NotificationEntity {
+    NotificationEntity::from(NotificationCreateModel {
+      user_id: "owner".to_string(),
+      kind: NotificationKind::Commented,
+      actor_id: Some(actor.to_string()),
+      target: NotificationTarget::new("tasks", "t1"),
+      payload: json!({ "comment_id": comment_id }),
+    })
+  }
+  #[test]
+  fn keys_by_recipient_kind_and_target() {
+    assert_eq!(
+      group_key(
+        "owner",
+        NotificationKind::Commented,
+        &NotificationTarget::new("tasks", "t1")
+      ),
+      "owner:commented:tasks:t1"
+    );
+  }
+  #[test]
+  fn merging_counts_events_and_collects_actors() {
+    let now = Utc::now();
+    let group = serde_json::to_value(comment("alice", "c1")).unwrap();
+    let patch = merge(&group, &comment("bob", "c2"), now).unwrap();
+    assert_eq!(patch["count"], json!(2));
+    assert_eq!(patch["actor_ids"], json!(["alice", "bob"]));
+    assert_eq!(patch["actor_id"], json!("bob"));
+    assert!(merge(&group, &comment("alice", "c1"), now).is_none());
+  }
+  #[test]
+  fn cursors_round_trip() {
+    let doc = json!({ "id": "n1", "created_at": "2024-05-01T12:00:00Z" });
+    assert_eq!(
+      decode_cursor(&encode_cursor(&doc)),
+      Some(("2024-05-01T12:00:00Z".to_string(), "n1".to_string()))
+    );
+    assert_eq!(decode_cursor("not a cursor"), None);
+  }
+}
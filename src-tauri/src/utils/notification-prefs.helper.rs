@@ -0,0 +1,223 @@
+/* sys lib */
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde_json::Value;
+/* entities */
+use crate::entities::notification_entity::{NotificationKind, NotificationTarget};
+use crate::entities::notification_preferences_entity::{
+  NotificationChannels, NotificationPreferencesEntity, QuietHours,
+};
+/// Parses an IANA zone name such as `Europe/Berlin` or `UTC`, so local times follow DST.
+/// Whole-hour UTC offsets saved before zone names were required (`+02:00`) still parse, as
+/// the matching fixed `Etc/GMT` zone.
+pub fn parse_timezone(timezone: &str) -> Option<Tz> {
+  if let Ok(zone) = timezone.trim().parse::<Tz>() {
+    return Some(zone);
+  }
+  let seconds = parse_fixed_offset(timezone)?.local_minus_utc();
+  if seconds % 3600 != 0 {
+    return None;
+  }
+  // `Etc/GMT` zones use POSIX signs: `Etc/GMT-2` is two hours ahead of UTC
+  match seconds / 3600 {
+    0 => Some(Tz::UTC),
+    hours => format!("Etc/GMT{:+}", -hours).parse().ok(),
+  }
+}
+/// Whether `timezone` is an IANA zone name, which is what new settings must use.
+pub fn is_zone_name(timezone: &str) -> bool {
+  timezone.trim().parse::<Tz>().is_ok()
+}
+fn parse_fixed_offset(timezone: &str) -> Option<FixedOffset> {
+  let tz = timezone.trim();
+  if tz.eq_ignore_ascii_case("utc") || tz == "Z" {
+    return FixedOffset::east_opt(0);
+  }
+  let sign = match tz.chars().next()? {
+    '+' => 1,
+    '-' => -1,
+    _ => return None,
+  };
+  let digits: String = tz[1..].chars().filter(|c| *c != ':').collect();
+  if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+  let hours: i32 = digits[..2].parse().ok()?;
+  let minutes: i32 = digits[2..].parse().ok()?;
+  if hours > 14 || minutes > 59 {
+    return None;
+  }
+  FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+pub fn parse_clock(value: &str) -> Option<NaiveTime> {
+  NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+/// Whether `now` falls inside the quiet window. Windows may wrap past midnight; an
+/// unparseable window is treated as no window at all.
+pub fn in_quiet_hours(quiet: &QuietHours, now: DateTime<Utc>) -> bool {
+  let (Some(start), Some(end), Some(zone)) = (
+    parse_clock(&quiet.start),
+    parse_clock(&quiet.end),
+    parse_timezone(&quiet.timezone),
+  ) else {
+    return false;
+  };
+  let local = now.with_timezone(&zone).time();
+  if start <= end {
+    start <= local && local < end
+  } else {
+    local >= start || local < end
+  }
+}
+/// Whether the notification comes from a todo or room the user muted. Task notifications
+/// carry their todo in `payload.todo_id`, chat and group ones their room in `payload.room_id`.
+pub fn is_muted(
+  prefs: &NotificationPreferencesEntity,
+  target: &NotificationTarget,
+  payload: &Value,
+) -> bool {
+  let todo_id = if target.entity == "todos" {
+    Some(target.id.as_str())
+  } else {
+    payload.get("todo_id").and_then(|v| v.as_str())
+  };
+  let room_id = payload.get("room_id").and_then(|v| v.as_str());
+  todo_id
+    .map(|id| prefs.muted_todos.iter().any(|m| m == id))
+    .unwrap_or(false)
+    || room_id
+      .map(|id| prefs.muted_rooms.iter().any(|m| m == id))
+      .unwrap_or(false)
+}
+/// Channels a notification should go out on, or `None` when it should not be stored at all.
+pub fn delivery(
+  prefs: Option<&NotificationPreferencesEntity>,
+  kind: NotificationKind,
+  target: &NotificationTarget,
+  payload: &Value,
+  now: DateTime<Utc>,
+) -> Option<NotificationChannels> {
+  let Some(prefs) = prefs else {
+    return Some(NotificationChannels::default());
+  };
+  if is_muted(prefs, target, payload) {
+    return None;
+  }
+  let mut channels = prefs.kinds.get(&kind).copied().unwrap_or_default();
+  if let Some(quiet) = &prefs.quiet_hours {
+    if in_quiet_hours(quiet, now) {
+      channels.desktop = false;
+    }
+  }
+  if !channels.in_app && !channels.email_digest {
+    // A toast alone has nothing to open, so it is only sent alongside the in-app copy
+    return None;
+  }
+  if !channels.in_app {
+    channels.desktop = false;
+  }
+  Some(channels)
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use std::collections::HashMap;
+  fn prefs() -> NotificationPreferencesEntity {
+    NotificationPreferencesEntity {
+      id: Some("u1".to_string()),
+      user_id: "u1".to_string(),
+      kinds: HashMap::new(),
+      quiet_hours: None,
+      muted_todos: vec!["todo-1".to_string()],
+      muted_rooms: vec!["room-1".to_string()],
+      created_at: None,
+      updated_at: None,
+    }
+  }
+  fn at(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+      .unwrap()
+      .with_timezone(&Utc)
+  }
+  #[test]
+  fn parses_zone_names_and_legacy_offsets() {
+    assert_eq!(parse_timezone("UTC"), Some(Tz::UTC));
+    assert_eq!(parse_timezone("Europe/Kyiv"), Some(Tz::Europe__Kyiv));
+    assert_eq!(parse_timezone("+02:00"), Some(Tz::Etc__GMTMinus2));
+    assert_eq!(parse_timezone("-0500"), Some(Tz::Etc__GMTPlus5));
+    assert_eq!(parse_timezone("-0530"), None);
+    assert_eq!(parse_timezone("Mars/Base"), None);
+    assert!(is_zone_name("America/New_York"));
+    assert!(!is_zone_name("+02:00"));
+  }
+  #[test]
+  fn quiet_hours_follow_daylight_saving() {
+    let quiet = QuietHours {
+      start: "22:00".to_string(),
+      end: "07:00".to_string(),
+      timezone: "Europe/Berlin".to_string(),
+    };
+    // 20:30 UTC is 22:30 in summer (UTC+2) but only 21:30 in winter (UTC+1)
+    assert!(in_quiet_hours(&quiet, at("2024-07-15T20:30:00Z")));
+    assert!(!in_quiet_hours(&quiet, at("2024-01-15T20:30:00Z")));
+    assert!(in_quiet_hours(&quiet, at("2024-01-15T21:30:00Z")));
+    // 05:30 UTC is 06:30 in winter but 07:30 in summer, after the window
+    assert!(in_quiet_hours(&quiet, at("2024-01-15T05:30:00Z")));
+    assert!(!in_quiet_hours(&quiet, at("2024-07-15T05:30:00Z")));
+  }
+  #[test]
+  fn quiet_hours_wrap_midnight_in_local_time() {
+    let quiet = QuietHours {
+      start: "22:00".to_string(),
+      end: "07:00".to_string(),
+      timezone: "+02:00".to_string(),
+    };
+    assert!(in_quiet_hours(&quiet, at("2024-05-01T21:30:00Z")));
+    assert!(in_quiet_hours(&quiet, at("2024-05-01T04:00:00Z")));
+    assert!(!in_quiet_hours(&quiet, at("2024-05-01T05:00:00Z")));
+    assert!(!in_quiet_hours(&quiet, at("2024-05-01T19:00:00Z")));
+  }
+  #[test]
+  fn muted_sources_and_disabled_kinds_are_dropped() {
+    let mut prefs = prefs();
+    let now = at("2024-05-01T12:00:00Z");
+    let task = NotificationTarget::new("tasks", "task-1");
+    assert!(delivery(
+      Some(&prefs),
+      NotificationKind::Commented,
+      &task,
+      &json!({ "todo_id": "todo-1" }),
+      now
+    )
+    .is_none());
+    assert!(delivery(
+      Some(&prefs),
+      NotificationKind::Replied,
+      &NotificationTarget::new("chats", "m1"),
+      &json!({ "room_id": "room-1" }),
+      now
+    )
+    .is_none());
+    prefs.kinds.insert(
+      NotificationKind::Commented,
+      NotificationChannels {
+        in_app: false,
+        desktop: true,
+        email_digest: false,
+      },
+    );
+    assert!(delivery(
+      Some(&prefs),
+      NotificationKind::Commented,
+      &task,
+      &json!({ "todo_id": "todo-2" }),
+      now
+    )
+    .is_none());
+    assert_eq!(
+      delivery(None, NotificationKind::Commented, &task, &json!({}), now),
+      Some(NotificationChannels::default())
+    );
+  }
+}
@@ -0,0 +1,65 @@
+/* sys lib */
+use serde_json::{json, Value};
+fn text<'a>(doc: &'a Value, field: &str) -> &'a str {
+  doc.get(field).and_then(|v| v.as_str()).unwrap_or("")
+}
+/// Whether a read made at `read_at` still covers `doc`, i.e. nothing was grouped into it
+/// afterwards.
+pub fn read_covers(doc: &Value, read_at: &str) -> bool {
+  !read_at.is_empty() && text(doc, "updated_at") <= read_at
+}
+/// Merges the local and cloud copies of one notification. The most recently updated copy
+/// wins, but a read recorded on the other copy after that update still marks it read, so
+/// reads made offline survive and new grouped events reopen a read group.
+pub fn reconcile(local: Option<Value>, cloud: Option<Value>) -> Option<Value> {
+  let (mut newest, other) = match (local, cloud) {
+    (Some(local), Some(cloud)) => {
+      if text(&cloud, "updated_at") > text(&local, "updated_at") {
+        (cloud, Some(local))
+      } else {
+        (local, Some(cloud))
+      }
+    }
+    (local, cloud) => (local.or(cloud)?, None),
+  };
+  if let Some(other) = other {
+    let read = other.get("read").and_then(|v| v.as_bool()) == Some(true);
+    let read_at = text(&other, "read_at");
+    if read && read_covers(&newest, read_at) && read_at > text(&newest, "read_at") {
+      newest["read"] = json!(true);
+      newest["read_at"] = json!(read_at);
+    }
+  }
+  Some(newest)
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn offline_read_survives_reconcile() {
+    let cloud = json!({ "id": "n1", "read": false, "updated_at": "2024-05-01T10:00:00Z" });
+    let local = json!({
+      "id": "n1",
+      "read": true,
+      "read_at": "2024-05-01T11:00:00Z",
+      "updated_at": "2024-05-01T10:00:00Z",
+    });
+    let merged = reconcile(Some(local), Some(cloud)).unwrap();
+    assert_eq!(merged["read"], json!(true));
+  }
+  #[test]
+  fn later_grouped_event_reopens_the_group() {
+    let cloud =
+      json!({ "id": "n1", "read": false, "count": 3, "updated_at": "2024-05-01T12:00:00Z" });
+    let local = json!({
+      "id": "n1",
+      "read": true,
+      "read_at": "2024-05-01T11:00:00Z",
+      "updated_at": "2024-05-01T10:00:00Z",
+    });
+    let merged = reconcile(Some(local), Some(cloud)).unwrap();
+    assert_eq!(merged["read"], json!(false));
+    assert_eq!(merged["count"], json!(3));
+    assert!(reconcile(None, None).is_none());
+  }
+}
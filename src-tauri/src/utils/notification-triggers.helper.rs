@@ -0,0 +1,94 @@
+/* sys lib */
+use serde_json::Value;
+/* entities */
+use crate::entities::permission_entity::ASSIGNEE_DEFAULT_ROLE;
+/* helpers */
+use crate::utils::group_roles::string_list;
+/// A change to one assignee's entry in a todo's `assignee_roles`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleChange {
+  pub user_id: String,
+  pub from: String,
+  pub to: String,
+}
+/// Ids listed under `field` in `new` that were not there in `old`.
+pub fn added_ids(old: Option<&Value>, new: &Value, field: &str) -> Vec<String> {
+  let before = old.map(|doc| string_list(doc, field)).unwrap_or_default();
+  let mut added: Vec<String> = Vec::new();
+  for id in string_list(new, field) {
+    if !before.contains(&id) && !added.contains(&id) {
+      added.push(id);
+    }
+  }
+  added
+}
+/// Role changes for people who were assignees both before and after the update.
+/// Newly added assignees are reported as assignments, not role changes.
+pub fn role_changes(old: &Value, new: &Value) -> Vec<RoleChange> {
+  let before = string_list(old, "assignees");
+  string_list(new, "assignees")
+    .into_iter()
+    .filter(|id| before.contains(id))
+    .filter_map(|id| {
+      let from = role_in(old, &id);
+      let to = role_in(new, &id);
+      (from != to).then(|| RoleChange {
+        user_id: id,
+        from,
+        to,
+      })
+    })
+    .collect()
+}
+/// Whether the update moved a task or subtask into `completed`.
+pub fn became_completed(old: &Value, new: &Value) -> bool {
+  let status = |doc: &Value| doc.get("status").and_then(|v| v.as_str()).map(String::from);
+  status(new).as_deref() == Some("completed") && status(old).as_deref() != Some("completed")
+}
+pub fn excerpt(text: &str, max_chars: usize) -> String {
+  text.chars().take(max_chars).collect()
+}
+fn role_in(todo: &Value, user_id: &str) -> String {
+  todo
+    .get("assignee_roles")
+    .and_then(|roles| roles.get(user_id))
+    .and_then(|v| v.as_str())
+    .unwrap_or(ASSIGNEE_DEFAULT_ROLE)
+    .to_string()
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  #[test]
+  fn reports_only_new_assignees() {
+    let old = json!({ "assignees": ["a", "b"] });
+    let new = json!({ "assignees": ["b", "c", "c"] });
+    assert_eq!(added_ids(Some(&old), &new, "assignees"), vec!["c"]);
+    assert_eq!(added_ids(None, &new, "assignees"), vec!["b", "c"]);
+  }
+  #[test]
+  fn role_changes_skip_new_assignees_and_defaults() {
+    let old = json!({ "assignees": ["a", "b"], "assignee_roles": { "a": "viewer" } });
+    let new = json!({
+      "assignees": ["a", "b", "c"],
+      "assignee_roles": { "a": "editor", "b": "viewer", "c": "editor" },
+    });
+    assert_eq!(
+      role_changes(&old, &new),
+      vec![RoleChange {
+        user_id: "a".to_string(),
+        from: "viewer".to_string(),
+        to: "editor".to_string(),
+      }]
+    );
+  }
+  #[test]
+  fn completion_is_a_transition() {
+    let pending = json!({ "status": "pending" });
+    let done = json!({ "status": "completed" });
+    assert!(became_completed(&pending, &done));
+    assert!(!became_completed(&done, &done));
+    assert!(!became_completed(&done, &pending));
+  }
+}
@@ -0,0 +1,50 @@
+/* sys lib */
+use chrono::{DateTime, Duration, Utc};
+/* helpers */
+use crate::utils::reminders::parse_due;
+/// Whether a task due at `due` is still late once the grace period is over.
+pub fn past_grace(due: DateTime<Utc>, grace_hours: i64, now: DateTime<Utc>) -> bool {
+  now >= due + Duration::hours(grace_hours.max(0))
+}
+/// Smallest number of `step_days` steps that moves `due` far enough forward that it is no
+/// longer past its grace period.
+pub fn roll_over_steps(
+  due: DateTime<Utc>,
+  step_days: i64,
+  grace_hours: i64,
+  now: DateTime<Utc>,
+) -> i64 {
+  let step = Duration::days(step_days.max(1));
+  let late_by = now - (due + Duration::hours(grace_hours.max(0)));
+  late_by.num_seconds() / step.num_seconds() + 1
+}
+/// `date` moved forward by `days`, in the format tasks store dates in. Values that do not
+/// parse are returned as `None`.
+pub fn shift_date(date: &str, days: i64) -> Option<String> {
+  let shifted = parse_due(date)? + Duration::days(days);
+  Some(shifted.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  fn at(value: &str) -> DateTime<Utc> {
+    parse_due(value).unwrap()
+  }
+  #[test]
+  fn grace_period_delays_the_policy() {
+    let due = at("2024-05-01T12:00:00Z");
+    assert!(!past_grace(due, 24, at("2024-05-02T11:00:00Z")));
+    assert!(past_grace(due, 24, at("2024-05-02T12:00:00Z")));
+  }
+  #[test]
+  fn rolls_over_until_back_in_grace() {
+    let due = at("2024-05-01T12:00:00Z");
+    assert_eq!(roll_over_steps(due, 1, 0, at("2024-05-01T12:00:00Z")), 1);
+    assert_eq!(roll_over_steps(due, 1, 0, at("2024-05-03T13:00:00Z")), 3);
+    assert_eq!(roll_over_steps(due, 7, 0, at("2024-05-03T13:00:00Z")), 1);
+    assert_eq!(
+      shift_date("2024-05-01T12:00:00Z", 3).as_deref(),
+      Some("2024-05-04T12:00:00Z")
+    );
+  }
+}
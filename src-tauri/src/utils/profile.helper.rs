@@ -1,13 +1,17 @@
-/* providers */
-use nosql_orm::providers::MongoProvider;
+/* sys lib */
+use serde_json::json;
 use std::sync::Arc;
+/* providers */
+use nosql_orm::provider::DatabaseProvider;
+use nosql_orm::providers::{JsonProvider, MongoProvider};
+use nosql_orm::query::Filter;
 /* services */
 use crate::services::profile::profile_sync_unified::ProfileSyncUnifiedService;
 /* models */
 use crate::entities::profile_entity::ProfileEntity;
 use crate::models::response::ResponseModel;
 pub async fn check_profile_exists(
-  json_provider: &nosql_orm::providers::JsonProvider,
+  json_provider: &JsonProvider,
   mongodb_provider: Option<Arc<MongoProvider>>,
   user_id: &str,
 ) -> Result<Option<ProfileEntity>, ResponseModel> {
@@ -15,3 +19,50 @@ pub async fn check_profile_exists(
     ProfileSyncUnifiedService::new(json_provider.clone(), mongodb_provider);
   profile_sync_service.get_profile(user_id).await
 }
+/// The user's profile id, from the user record or else the profile that points at the user.
+/// Todo assignees are stored as profile ids, so access checks need it next to the user id.
+pub async fn find_profile_id(
+  json_provider: &JsonProvider,
+  mongodb_provider: Option<&Arc<MongoProvider>>,
+  user_id: &str,
+) -> Option<String> {
+  if user_id.is_empty() {
+    return None;
+  }
+  let mut user = json_provider
+    .find_by_id("users", user_id)
+    .await
+    .ok()
+    .flatten();
+  if user.is_none() {
+    if let Some(mongo) = mongodb_provider {
+      user = mongo.find_by_id("users", user_id).await.ok().flatten();
+    }
+  }
+  if let Some(profile_id) = user
+    .as_ref()
+    .and_then(|u| u.get("profile_id"))
+    .and_then(|v| v.as_str())
+    .filter(|id| !id.is_empty())
+  {
+    return Some(profile_id.to_string());
+  }
+  let filter = Filter::Eq("user_id".to_string(), json!(user_id));
+  let mut profiles = json_provider
+    .find_many("profiles", Some(&filter), None, None, None, true)
+    .await
+    .unwrap_or_default();
+  if profiles.is_empty() {
+    if let Some(mongo) = mongodb_provider {
+      profiles = mongo
+        .find_many("profiles", Some(&filter), None, None, None, true)
+        .await
+        .unwrap_or_default();
+    }
+  }
+  profiles
+    .first()
+    .and_then(|p| p.get("id"))
+    .and_then(|v| v.as_str())
+    .map(String::from)
+}
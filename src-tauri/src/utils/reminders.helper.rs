@@ -0,0 +1,128 @@
+/* sys lib */
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+/// Offsets used when neither the task nor the user chose any: a day and an hour before.
+pub const DEFAULT_OFFSETS: [i64; 2] = [24 * 60, 60];
+/// Longest offset accepted, 30 days.
+pub const MAX_OFFSET_MINUTES: i64 = 30 * 24 * 60;
+/// What a reminder fires for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReminderTrigger {
+  /// `n` minutes before the due date.
+  Before(i64),
+  Overdue,
+}
+impl ReminderTrigger {
+  pub fn offset(&self) -> Option<i64> {
+    match self {
+      ReminderTrigger::Before(minutes) => Some(*minutes),
+      ReminderTrigger::Overdue => None,
+    }
+  }
+}
+/// Parses a task `end_date`. Date-only values are due at the end of that day (UTC).
+pub fn parse_due(end_date: &str) -> Option<DateTime<Utc>> {
+  if let Ok(dt) = DateTime::parse_from_rfc3339(end_date) {
+    return Some(dt.with_timezone(&Utc));
+  }
+  let date = NaiveDate::parse_from_str(end_date.get(..10)?, "%Y-%m-%d").ok()?;
+  Some(date.and_hms_opt(23, 59, 59)?.and_utc())
+}
+/// The reminder that should currently be showing for a task due at `due`.
+///
+/// Once the task is overdue that is the overdue reminder (when enabled). Before that it is
+/// the smallest offset whose moment has passed, so offsets that were already behind us when
+/// the task was created or the app was closed are skipped instead of firing in a burst.
+pub fn current_trigger(
+  due: DateTime<Utc>,
+  offsets: &[i64],
+  overdue: bool,
+  now: DateTime<Utc>,
+) -> Option<ReminderTrigger> {
+  if now >= due {
+    return overdue.then_some(ReminderTrigger::Overdue);
+  }
+  offsets
+    .iter()
+    .copied()
+    .filter(|minutes| due - Duration::minutes(*minutes) <= now)
+    .min()
+    .map(ReminderTrigger::Before)
+}
+/// Stable id of a reminder. The due date is part of it, so moving the due date re-arms
+/// every reminder for the task.
+pub fn reminder_id(task_id: &str, user_id: &str, trigger: ReminderTrigger, due: &str) -> String {
+  let key = match trigger {
+    ReminderTrigger::Before(minutes) => format!("before-{}", minutes),
+    ReminderTrigger::Overdue => "overdue".to_string(),
+  };
+  format!("{}:{}:{}:{}", task_id, user_id, key, due)
+}
+/// Id of the claim for one firing of a reminder. A snoozed reminder fires again under a new
+/// claim for each snooze.
+pub fn claim_id(reminder_id: &str, snoozed_until: Option<&str>) -> String {
+  match snoozed_until {
+    Some(until) => format!("{}@{}", reminder_id, until),
+    None => reminder_id.to_string(),
+  }
+}
+/// Positive, de-duplicated offsets within `MAX_OFFSET_MINUTES`, largest first.
+pub fn normalize_offsets(offsets: &[i64]) -> Vec<i64> {
+  let mut cleaned: Vec<i64> = offsets
+    .iter()
+    .copied()
+    .filter(|minutes| *minutes > 0 && *minutes <= MAX_OFFSET_MINUTES)
+    .collect();
+  cleaned.sort_unstable_by(|a, b| b.cmp(a));
+  cleaned.dedup();
+  cleaned
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  fn at(value: &str) -> DateTime<Utc> {
+    parse_due(value).unwrap()
+  }
+  #[test]
+  fn date_only_due_dates_end_the_day() {
+    assert_eq!(parse_due("2024-05-01"), Some(at("2024-05-01T23:59:59Z")));
+    assert_eq!(parse_due("soon"), None);
+  }
+  #[test]
+  fn picks_the_closest_passed_offset() {
+    let due = at("2024-05-02T12:00:00Z");
+    let offsets = [1440, 60];
+    assert_eq!(
+      current_trigger(due, &offsets, true, at("2024-05-01T11:00:00Z")),
+      None
+    );
+    assert_eq!(
+      current_trigger(due, &offsets, true, at("2024-05-01T13:00:00Z")),
+      Some(ReminderTrigger::Before(1440))
+    );
+    assert_eq!(
+      current_trigger(due, &offsets, true, at("2024-05-02T11:30:00Z")),
+      Some(ReminderTrigger::Before(60))
+    );
+    assert_eq!(
+      current_trigger(due, &offsets, true, at("2024-05-02T12:00:00Z")),
+      Some(ReminderTrigger::Overdue)
+    );
+    assert_eq!(
+      current_trigger(due, &offsets, false, at("2024-05-03T12:00:00Z")),
+      None
+    );
+  }
+  #[test]
+  fn snoozes_get_their_own_claims() {
+    let id = reminder_id("t1", "u1", ReminderTrigger::Overdue, "2024-05-01");
+    assert_eq!(claim_id(&id, None), id);
+    assert_ne!(
+      claim_id(&id, Some("2024-05-02T10:00:00Z")),
+      claim_id(&id, Some("2024-05-02T11:00:00Z"))
+    );
+  }
+  #[test]
+  fn offsets_are_cleaned() {
+    assert_eq!(normalize_offsets(&[60, -5, 1440, 60, 0]), vec![1440, 60]);
+  }
+}
@@ -10,6 +10,7 @@ macro_rules! taskflow_excluded_fields {
       "recovery_codes".to_string(),
       "reset_token".to_string(),
       "temporary_code".to_string(),
+      "token_hash".to_string(),
       "code_expires_at".to_string(),
     ]
   };
@@ -0,0 +1,34 @@
+/* sys lib */
+use std::sync::{OnceLock, RwLock};
+/// The user signed in on this device. The sign-in commands set it, `check_token` refreshes it
+/// and `logout` clears it. Background jobs (digests, reminders, overdue sweeps, webhook
+/// deliveries) only act for this user, so every device handles its own user's work and never
+/// another user's.
+fn signed_in() -> &'static RwLock<Option<String>> {
+  static SIGNED_IN: OnceLock<RwLock<Option<String>>> = OnceLock::new();
+  SIGNED_IN.get_or_init(|| RwLock::new(None))
+}
+/// Returns true when a different user is signed in than before.
+pub fn set_signed_in_user(user_id: &str) -> bool {
+  if user_id.is_empty() || is_signed_in_user(user_id) {
+    return false;
+  }
+  match signed_in().write() {
+    Ok(mut current) => {
+      *current = Some(user_id.to_string());
+      true
+    }
+    Err(_) => false,
+  }
+}
+pub fn clear_signed_in_user() {
+  if let Ok(mut current) = signed_in().write() {
+    *current = None;
+  }
+}
+pub fn signed_in_user() -> Option<String> {
+  signed_in().read().ok().and_then(|current| current.clone())
+}
+pub fn is_signed_in_user(user_id: &str) -> bool {
+  !user_id.is_empty() && signed_in_user().as_deref() == Some(user_id)
+}
@@ -0,0 +1,115 @@
+/* sys lib */
+use regex::Regex;
+use std::sync::LazyLock;
+/// `#task:<id>`
+static TASK_ID_REF: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"#task:([A-Za-z0-9_-]+)").expect("valid task id pattern"));
+/// `<todo-slug>#<n>`, where `n` is the task's 1-based position in the todo
+static TODO_SLUG_REF: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?:^|[^A-Za-z0-9_#:-])([a-z0-9]+(?:-[a-z0-9]+)*)#([0-9]{1,6})\b")
+    .expect("valid todo slug pattern")
+});
+/// Most references resolved per message; anything beyond is left as plain text.
+pub const MAX_TASK_REFS: usize = 10;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskRef {
+  Id {
+    raw: String,
+    task_id: String,
+  },
+  Slug {
+    raw: String,
+    slug: String,
+    position: usize,
+  },
+}
+impl TaskRef {
+  pub fn raw(&self) -> &str {
+    match self {
+      TaskRef::Id { raw, .. } | TaskRef::Slug { raw, .. } => raw,
+    }
+  }
+}
+/// Task references in the order they appear, without duplicates.
+pub fn parse_task_refs(content: &str) -> Vec<TaskRef> {
+  let mut found: Vec<(usize, TaskRef)> = Vec::new();
+  for caps in TASK_ID_REF.captures_iter(content) {
+    let whole = caps.get(0).expect("match");
+    found.push((
+      whole.start(),
+      TaskRef::Id {
+        raw: whole.as_str().to_string(),
+        task_id: caps[1].to_string(),
+      },
+    ));
+  }
+  for caps in TODO_SLUG_REF.captures_iter(content) {
+    let slug = caps.get(1).expect("slug");
+    let Ok(position) = caps[2].parse::<usize>() else {
+      continue;
+    };
+    if position == 0 {
+      continue;
+    }
+    found.push((
+      slug.start(),
+      TaskRef::Slug {
+        raw: format!("{}#{}", slug.as_str(), position),
+        slug: slug.as_str().to_string(),
+        position,
+      },
+    ));
+  }
+  found.sort_by_key(|(start, _)| *start);
+  let mut refs: Vec<TaskRef> = Vec::new();
+  for (_, task_ref) in found {
+    if !refs.iter().any(|r| r.raw() == task_ref.raw()) {
+      refs.push(task_ref);
+    }
+  }
+  refs.truncate(MAX_TASK_REFS);
+  refs
+}
+/// Lower-case, dash-separated form of a todo title used in `slug#n` references.
+pub fn slugify(title: &str) -> String {
+  let mut slug = String::new();
+  for c in title.chars() {
+    if c.is_ascii_alphanumeric() {
+      slug.push(c.to_ascii_lowercase());
+    } else if !slug.is_empty() && !slug.ends_with('-') {
+      slug.push('-');
+    }
+  }
+  slug.trim_end_matches('-').to_string()
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn parses_both_reference_styles() {
+    let refs = parse_task_refs("see #task:abc-123 and release-plan#12, also #task:abc-123 again");
+    assert_eq!(
+      refs,
+      vec![
+        TaskRef::Id {
+          raw: "#task:abc-123".to_string(),
+          task_id: "abc-123".to_string()
+        },
+        TaskRef::Slug {
+          raw: "release-plan#12".to_string(),
+          slug: "release-plan".to_string(),
+          position: 12
+        },
+      ]
+    );
+  }
+  #[test]
+  fn ignores_hashtags_and_zero_positions() {
+    assert!(parse_task_refs("#12 is not a task, nor is plan#0").is_empty());
+  }
+  #[test]
+  fn slugifies_titles() {
+    assert_eq!(slugify("Release Plan: Q3!"), "release-plan-q3");
+    assert_eq!(slugify("  Hello   World "), "hello-world");
+  }
+}
@@ -0,0 +1,348 @@
+/* sys lib */
+use data_encoding::BASE64URL_NOPAD;
+use p256::ecdsa::{signature::Verifier, DerSignature, VerifyingKey};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+/* models */
+use crate::models::response::ResponseModel;
+/* helpers */
+use crate::utils::response_helper::err_response;
+pub const FLAG_USER_PRESENT: u8 = 0x01;
+pub const FLAG_USER_VERIFIED: u8 = 0x04;
+pub const FLAG_ATTESTED_DATA: u8 = 0x40;
+const COSE_ALG_ES256: i64 = -7;
+const COSE_KTY_EC2: i64 = 2;
+const COSE_CRV_P256: i64 = 1;
+/// Subset of CBOR needed to read attestation objects and COSE keys. Arrays and simple
+/// values are consumed but never inspected, so they decode to `Skipped`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CborValue {
+  Int(i64),
+  Bytes(Vec<u8>),
+  Text(String),
+  Map(BTreeMap<CborKey, CborValue>),
+  Skipped,
+}
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CborKey {
+  Int(i64),
+  Text(String),
+}
+impl CborValue {
+  pub fn get(&self, key: &CborKey) -> Option<&CborValue> {
+    match self {
+      CborValue::Map(map) => map.get(key),
+      _ => None,
+    }
+  }
+  pub fn get_text(&self, key: &str) -> Option<&CborValue> {
+    self.get(&CborKey::Text(key.to_string()))
+  }
+  pub fn get_int(&self, key: i64) -> Option<&CborValue> {
+    self.get(&CborKey::Int(key))
+  }
+  pub fn as_bytes(&self) -> Option<&[u8]> {
+    match self {
+      CborValue::Bytes(b) => Some(b),
+      _ => None,
+    }
+  }
+  pub fn as_i64(&self) -> Option<i64> {
+    match self {
+      CborValue::Int(i) => Some(*i),
+      _ => None,
+    }
+  }
+}
+struct CborReader<'a> {
+  data: &'a [u8],
+  pos: usize,
+}
+impl<'a> CborReader<'a> {
+  fn take(&mut self, len: usize) -> Result<&'a [u8], ResponseModel> {
+    let end = self
+      .pos
+      .checked_add(len)
+      .filter(|end| *end <= self.data.len())
+      .ok_or_else(|| err_response("Malformed CBOR: unexpected end of data"))?;
+    let slice = &self.data[self.pos..end];
+    self.pos = end;
+    Ok(slice)
+  }
+  fn read_argument(&mut self, info: u8) -> Result<u64, ResponseModel> {
+    match info {
+      0..=23 => Ok(info as u64),
+      24 => Ok(self.take(1)?[0] as u64),
+      25 => Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64),
+      26 => Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64),
+      27 => Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap())),
+      _ => Err(err_response("Malformed CBOR: unsupported length encoding")),
+    }
+  }
+  fn read_value(&mut self, depth: usize) -> Result<CborValue, ResponseModel> {
+    if depth > 16 {
+      return Err(err_response("Malformed CBOR: nesting too deep"));
+    }
+    let initial = self.take(1)?[0];
+    let major = initial >> 5;
+    let info = initial & 0x1f;
+    match major {
+      0 => {
+        let value = self.read_argument(info)?;
+        i64::try_from(value)
+          .map(CborValue::Int)
+          .map_err(|_| err_response("Malformed CBOR: integer overflow"))
+      }
+      1 => {
+        let value = self.read_argument(info)?;
+        i64::try_from(value)
+          .map(|v| CborValue::Int(-1 - v))
+          .map_err(|_| err_response("Malformed CBOR: integer overflow"))
+      }
+      2 => {
+        let len = self.read_argument(info)? as usize;
+        Ok(CborValue::Bytes(self.take(len)?.to_vec()))
+      }
+      3 => {
+        let len = self.read_argument(info)? as usize;
+        let text = std::str::from_utf8(self.take(len)?)
+          .map_err(|_| err_response("Malformed CBOR: invalid UTF-8 text"))?;
+        Ok(CborValue::Text(text.to_string()))
+      }
+      4 => {
+        let len = self.read_argument(info)? as usize;
+        for _ in 0..len {
+          self.read_value(depth + 1)?;
+        }
+        Ok(CborValue::Skipped)
+      }
+      5 => {
+        let len = self.read_argument(info)? as usize;
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+          let key = match self.read_value(depth + 1)? {
+            CborValue::Int(i) => CborKey::Int(i),
+            CborValue::Text(s) => CborKey::Text(s),
+            _ => return Err(err_response("Malformed CBOR: unsupported map key")),
+          };
+          let value = self.read_value(depth + 1)?;
+          map.insert(key, value);
+        }
+        Ok(CborValue::Map(map))
+      }
+      7 => match info {
+        20..=23 => Ok(CborValue::Skipped),
+        _ => Err(err_response("Malformed CBOR: unsupported simple value")),
+      },
+      _ => Err(err_response("Malformed CBOR: unsupported major type")),
+    }
+  }
+}
+/// Decodes a single CBOR item and returns it with the number of bytes consumed.
+pub fn decode_cbor(data: &[u8]) -> Result<(CborValue, usize), ResponseModel> {
+  let mut reader = CborReader { data, pos: 0 };
+  let value = reader.read_value(0)?;
+  Ok((value, reader.pos))
+}
+pub fn decode_base64url(value: &str) -> Result<Vec<u8>, ResponseModel> {
+  BASE64URL_NOPAD
+    .decode(value.trim_end_matches('=').as_bytes())
+    .map_err(|_| err_response("Invalid base64url value"))
+}
+pub fn encode_base64url(bytes: &[u8]) -> String {
+  BASE64URL_NOPAD.encode(bytes)
+}
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+  Sha256::digest(data).to_vec()
+}
+#[derive(Debug, Clone)]
+pub struct AttestedCredential {
+  pub aaguid: Vec<u8>,
+  pub credential_id: Vec<u8>,
+  /// SEC1 uncompressed P-256 point
+  pub public_key: Vec<u8>,
+}
+#[derive(Debug, Clone)]
+pub struct AuthenticatorData {
+  pub rp_id_hash: Vec<u8>,
+  pub flags: u8,
+  pub sign_count: u32,
+  pub attested_credential: Option<AttestedCredential>,
+}
+impl AuthenticatorData {
+  pub fn user_present(&self) -> bool {
+    self.flags & FLAG_USER_PRESENT != 0
+  }
+  pub fn user_verified(&self) -> bool {
+    self.flags & FLAG_USER_VERIFIED != 0
+  }
+}
+pub fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData, ResponseModel> {
+  if data.len() < 37 {
+    return Err(err_response("Authenticator data is too short"));
+  }
+  let rp_id_hash = data[0..32].to_vec();
+  let flags = data[32];
+  let sign_count = u32::from_be_bytes(data[33..37].try_into().unwrap());
+  let attested_credential = if flags & FLAG_ATTESTED_DATA != 0 {
+    if data.len() < 55 {
+      return Err(err_response("Attested credential data is too short"));
+    }
+    let aaguid = data[37..53].to_vec();
+    let id_len = u16::from_be_bytes(data[53..55].try_into().unwrap()) as usize;
+    let id_end = 55 + id_len;
+    if data.len() < id_end {
+      return Err(err_response("Credential id exceeds authenticator data"));
+    }
+    let credential_id = data[55..id_end].to_vec();
+    let (cose_key, _) = decode_cbor(&data[id_end..])?;
+    Some(AttestedCredential {
+      aaguid,
+      credential_id,
+      public_key: cose_key_to_sec1(&cose_key)?,
+    })
+  } else {
+    None
+  };
+  Ok(AuthenticatorData {
+    rp_id_hash,
+    flags,
+    sign_count,
+    attested_credential,
+  })
+}
+/// Converts an ES256 COSE key into a SEC1 uncompressed point.
+pub fn cose_key_to_sec1(key: &CborValue) -> Result<Vec<u8>, ResponseModel> {
+  let kty = key.get_int(1).and_then(|v| v.as_i64());
+  let alg = key.get_int(3).and_then(|v| v.as_i64());
+  let crv = key.get_int(-1).and_then(|v| v.as_i64());
+  if kty != Some(COSE_KTY_EC2) || alg != Some(COSE_ALG_ES256) || crv != Some(COSE_CRV_P256) {
+    return Err(err_response(
+      "Unsupported passkey algorithm. Only ES256 (P-256) is supported",
+    ));
+  }
+  let x = key.get_int(-2).and_then(|v| v.as_bytes());
+  let y = key.get_int(-3).and_then(|v| v.as_bytes());
+  match (x, y) {
+    (Some(x), Some(y)) if x.len() == 32 && y.len() == 32 => {
+      let mut point = Vec::with_capacity(65);
+      point.push(0x04);
+      point.extend_from_slice(x);
+      point.extend_from_slice(y);
+      Ok(point)
+    }
+    _ => Err(err_response("Invalid passkey public key coordinates")),
+  }
+}
+pub fn verify_es256_signature(
+  public_key: &[u8],
+  message: &[u8],
+  signature: &[u8],
+) -> Result<(), ResponseModel> {
+  let key = VerifyingKey::from_sec1_bytes(public_key)
+    .map_err(|_| err_response("Stored passkey public key is invalid"))?;
+  let signature =
+    DerSignature::try_from(signature).map_err(|_| err_response("Malformed passkey signature"))?;
+  key
+    .verify(message, &signature)
+    .map_err(|_| err_response("Passkey signature verification failed"))
+}
+/// Checks `clientDataJSON` against the ceremony type, challenge and relying party domain.
+pub fn verify_client_data(
+  client_data_json: &[u8],
+  expected_type: &str,
+  expected_challenge: &str,
+  rp_domain: &str,
+) -> Result<(), ResponseModel> {
+  let client_data: serde_json::Value =
+    serde_json::from_slice(client_data_json).map_err(|_| err_response("Malformed client data"))?;
+  if client_data.get("type").and_then(|v| v.as_str()) != Some(expected_type) {
+    return Err(err_response("Unexpected WebAuthn ceremony type"));
+  }
+  let challenge = client_data
+    .get("challenge")
+    .and_then(|v| v.as_str())
+    .unwrap_or("");
+  if challenge.trim_end_matches('=') != expected_challenge {
+    return Err(err_response("Passkey challenge mismatch"));
+  }
+  let origin = client_data
+    .get("origin")
+    .and_then(|v| v.as_str())
+    .unwrap_or("");
+  if !origin_matches_rp(origin, rp_domain) {
+    return Err(err_response(&format!(
+      "Origin '{}' is not allowed for {}",
+      origin, rp_domain
+    )));
+  }
+  Ok(())
+}
+fn origin_matches_rp(origin: &str, rp_domain: &str) -> bool {
+  let host = match origin.strip_prefix("https://") {
+    Some(rest) => rest.split(['/', ':']).next().unwrap_or(""),
+    None => return false,
+  };
+  host == rp_domain || host.ends_with(&format!(".{}", rp_domain))
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn test_decode_cbor_map_with_int_and_text_keys() {
+    // {1: 2, "fmt": "none", -2: h'0102'}
+    let data = [
+      0xa3, 0x01, 0x02, 0x63, b'f', b'm', b't', 0x64, b'n', b'o', b'n', b'e', 0x21, 0x42, 0x01,
+      0x02,
+    ];
+    let (value, consumed) = decode_cbor(&data).unwrap();
+    assert_eq!(consumed, data.len());
+    assert_eq!(value.get_int(1).and_then(|v| v.as_i64()), Some(2));
+    assert_eq!(
+      value.get_text("fmt"),
+      Some(&CborValue::Text("none".to_string()))
+    );
+    assert_eq!(
+      value.get_int(-2).and_then(|v| v.as_bytes()),
+      Some(&[0x01, 0x02][..])
+    );
+  }
+  #[test]
+  fn test_decode_cbor_rejects_truncated_input() {
+    assert!(decode_cbor(&[0x43, 0x01]).is_err());
+  }
+  #[test]
+  fn test_origin_matches_rp() {
+    assert!(origin_matches_rp(
+      "https://taskflow.tcs.com",
+      "taskflow.tcs.com"
+    ));
+    assert!(origin_matches_rp(
+      "https://app.taskflow.tcs.com:443",
+      "taskflow.tcs.com"
+    ));
+    assert!(!origin_matches_rp(
+      "http://taskflow.tcs.com",
+      "taskflow.tcs.com"
+    ));
+    assert!(!origin_matches_rp(
+      "https://eviltaskflow.tcs.com",
+      "taskflow.tcs.com"
+    ));
+  }
+  #[test]
+  fn test_verify_es256_signature_roundtrip() {
+    use p256::ecdsa::{signature::Signer, SigningKey};
+    let signing_key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+    let public_key = signing_key
+      .verifying_key()
+      .to_encoded_point(false)
+      .as_bytes()
+      .to_vec();
+    let message = b"authenticator-data-and-client-hash";
+    let signature: p256::ecdsa::Signature = signing_key.sign(message);
+    let der = signature.to_der();
+    assert!(verify_es256_signature(&public_key, message, der.as_bytes()).is_ok());
+    assert!(verify_es256_signature(&public_key, b"tampered", der.as_bytes()).is_err());
+  }
+}
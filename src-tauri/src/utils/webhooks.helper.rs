@@ -0,0 +1,224 @@
+/* sys lib */
+use chrono::{Duration, Utc};
+use data_encoding::HEXLOWER;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+/* entities */
+use crate::entities::webhook_entity::WebhookEvent;
+/* helpers */
+use crate::utils::notification_triggers;
+/// Attempts made before a delivery is given up as failed.
+pub const MAX_ATTEMPTS: u32 = 6;
+const FIRST_RETRY_SECONDS: i64 = 30;
+const MAX_RETRY_SECONDS: i64 = 60 * 60;
+const HMAC_BLOCK_SIZE: usize = 64;
+/// HMAC-SHA256 (RFC 2104).
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+  let mut block = [0u8; HMAC_BLOCK_SIZE];
+  if key.len() > HMAC_BLOCK_SIZE {
+    block[..32].copy_from_slice(&Sha256::digest(key));
+  } else {
+    block[..key.len()].copy_from_slice(key);
+  }
+  let mut inner = Sha256::new();
+  inner.update(block.map(|b| b ^ 0x36));
+  inner.update(message);
+  let mut outer = Sha256::new();
+  outer.update(block.map(|b| b ^ 0x5c));
+  outer.update(inner.finalize());
+  let mut mac = [0u8; 32];
+  mac.copy_from_slice(&outer.finalize());
+  mac
+}
+/// Value of the `X-TaskFlow-Signature` header: `sha256=` and the hex HMAC of
+/// `<timestamp>.<body>`, so receivers can reject replayed requests by their timestamp.
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+  let signed = format!("{}.{}", timestamp, body);
+  format!(
+    "sha256={}",
+    HEXLOWER.encode(&hmac_sha256(secret.as_bytes(), signed.as_bytes()))
+  )
+}
+/// Wait before the next attempt once `attempts` have failed: 30 seconds, doubling each time,
+/// at most an hour.
+pub fn retry_delay(attempts: u32) -> Duration {
+  let exponent = attempts.saturating_sub(1).min(16);
+  Duration::seconds((FIRST_RETRY_SECONDS << exponent).min(MAX_RETRY_SECONDS))
+}
+/// Posts a signed event body. Returns the status of a 2xx answer, or the status (when the
+/// receiver answered) and an error to log.
+pub async fn post_signed(
+  client: &reqwest::Client,
+  url: &str,
+  secret: &str,
+  event: &str,
+  delivery_id: &str,
+  body: String,
+) -> Result<u16, (Option<u16>, String)> {
+  let timestamp = Utc::now().timestamp();
+  let response = client
+    .post(url)
+    .header("Content-Type", "application/json")
+    .header("X-TaskFlow-Event", event)
+    .header("X-TaskFlow-Delivery", delivery_id)
+    .header("X-TaskFlow-Timestamp", timestamp.to_string())
+    .header("X-TaskFlow-Signature", signature(secret, timestamp, &body))
+    .body(body)
+    .send()
+    .await
+    .map_err(|e| (None, e.to_string()))?;
+  let status = response.status();
+  if status.is_success() {
+    Ok(status.as_u16())
+  } else {
+    Err((
+      Some(status.as_u16()),
+      format!("Receiver answered {}", status),
+    ))
+  }
+}
+/// Events raised by a record being created (`old` is `None`) or updated.
+pub fn events_for(table: &str, old: Option<&Value>, new: &Value) -> Vec<WebhookEvent> {
+  match (table, old) {
+    ("tasks", None) => vec![WebhookEvent::TaskCreated],
+    ("tasks", Some(old)) if notification_triggers::became_completed(old, new) => {
+      vec![WebhookEvent::TaskCompleted]
+    }
+    ("comments", None) => vec![WebhookEvent::CommentCreated],
+    ("todos", old) if is_shared(new) && !old.map(is_shared).unwrap_or(false) => {
+      vec![WebhookEvent::TodoShared]
+    }
+    _ => Vec::new(),
+  }
+}
+fn is_shared(todo: &Value) -> bool {
+  matches!(
+    todo.get("visibility").and_then(|v| v.as_str()),
+    Some("shared") | Some("public")
+  )
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+  use tokio::net::TcpListener;
+  /// Accepts one request, answers with `status` and returns its head and body.
+  async fn receive_one(listener: TcpListener, status: &str) -> (String, String) {
+    let (mut socket, _) = listener.accept().await.unwrap();
+    let mut raw = Vec::new();
+    let mut buf = [0u8; 4096];
+    let (head, body) = loop {
+      let n = socket.read(&mut buf).await.unwrap();
+      assert!(n > 0, "connection closed before the request was complete");
+      raw.extend_from_slice(&buf[..n]);
+      let text = String::from_utf8_lossy(&raw).to_string();
+      if let Some((head, body)) = text.split_once("\r\n\r\n") {
+        let length = head
+          .lines()
+          .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name
+              .eq_ignore_ascii_case("content-length")
+              .then(|| value.trim().parse::<usize>().ok())
+              .flatten()
+          })
+          .unwrap_or(0);
+        if body.len() >= length {
+          break (head.to_string(), body.to_string());
+        }
+      }
+    };
+    let reply = format!(
+      "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+      status
+    );
+    socket.write_all(reply.as_bytes()).await.unwrap();
+    (head, body)
+  }
+  fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().find_map(|line| {
+      let (key, value) = line.split_once(':')?;
+      key.eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+  }
+  #[tokio::test]
+  async fn posts_signed_events_to_a_local_receiver() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let receiver = tokio::spawn(receive_one(listener, "200 OK"));
+    let client = reqwest::Client::new();
+    let body = json!({ "event": "task.created" }).to_string();
+    let sent = post_signed(&client, &url, "secret", "task.created", "d1", body.clone()).await;
+    assert_eq!(sent, Ok(200));
+    let (head, received) = receiver.await.unwrap();
+    assert_eq!(received, body);
+    assert_eq!(header(&head, "x-taskflow-event"), Some("task.created"));
+    let timestamp: i64 = header(&head, "x-taskflow-timestamp")
+      .unwrap()
+      .parse()
+      .unwrap();
+    assert_eq!(
+      header(&head, "x-taskflow-signature"),
+      Some(signature("secret", timestamp, &body).as_str())
+    );
+  }
+  #[tokio::test]
+  async fn non_success_answers_are_errors() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let receiver = tokio::spawn(receive_one(listener, "503 Service Unavailable"));
+    let sent = post_signed(
+      &reqwest::Client::new(),
+      &url,
+      "s",
+      "webhook.ping",
+      "d2",
+      "{}".to_string(),
+    )
+    .await;
+    receiver.await.unwrap();
+    assert_eq!(sent.map_err(|(status, _)| status), Err(Some(503)));
+  }
+  #[test]
+  fn hmac_matches_rfc_4231() {
+    assert_eq!(
+      HEXLOWER.encode(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+      "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    assert_eq!(
+      HEXLOWER.encode(&hmac_sha256(
+        &[0xaa; 131],
+        b"Test Using Larger Than Block-Size Key - Hash Key First"
+      )),
+      "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+    );
+  }
+  #[test]
+  fn retries_back_off_up_to_an_hour() {
+    assert_eq!(retry_delay(1), Duration::seconds(30));
+    assert_eq!(retry_delay(3), Duration::seconds(120));
+    assert_eq!(retry_delay(20), Duration::seconds(3600));
+  }
+  #[test]
+  fn maps_changes_to_events() {
+    let pending = json!({ "status": "pending" });
+    let done = json!({ "status": "completed" });
+    assert_eq!(
+      events_for("tasks", None, &pending),
+      vec![WebhookEvent::TaskCreated]
+    );
+    assert_eq!(
+      events_for("tasks", Some(&pending), &done),
+      vec![WebhookEvent::TaskCompleted]
+    );
+    assert!(events_for("tasks", Some(&done), &done).is_empty());
+    let private = json!({ "visibility": "private" });
+    let shared = json!({ "visibility": "shared" });
+    assert_eq!(
+      events_for("todos", Some(&private), &shared),
+      vec![WebhookEvent::TodoShared]
+    );
+    assert!(events_for("todos", Some(&shared), &shared).is_empty());
+  }
+}
@@ -123,7 +123,7 @@ export class AuthService {
   logout() {
     this.jwtTokenService.clearToken();
     this.storageService.clear();
-    window.location.reload();
+    this.signOutBackend();
   }
   /**
    * Full logout - clear all local user data
@@ -131,7 +131,16 @@ export class AuthService {
   logoutAll() {
     this.jwtTokenService.clearToken();
     this.storageService.clear();
-    window.location.reload();
+    this.signOutBackend();
+  }
+  /**
+   * Stop background jobs acting for this user, then reload
+   */
+  private signOutBackend() {
+    this.requestService.invokeCommand<void>("logout").subscribe({
+      complete: () => window.location.reload(),
+      error: () => window.location.reload(),
+    });
   }
   /**
    * Single source of truth for auth status.